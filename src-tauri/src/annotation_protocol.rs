@@ -0,0 +1,120 @@
+//! The `annotation://` protocol, which serves annotation images straight to
+//! the webview so previews can use an `<img>` URL instead of moving the
+//! bytes through a command. `annotation://localhost/<key>?modified=<dateModified>`
+//! goes through the app's image cache and is revalidated by `ETag`; without
+//! `modified` the image is loaded fresh and never cached by the webview.
+
+use reqwest::Url;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, UriSchemeContext, UriSchemeResponder, Wry};
+
+use crate::error::AppError;
+use crate::{
+    app_data_dir, check_item_key, image_cache_stamp, load_annotation_image, open_zotero_connection, read_settings,
+    resolve_annotation_image,
+};
+
+pub const SCHEME: &str = "annotation";
+
+/// Answers a request off the webview's thread, since a cache miss may
+/// render from the PDF.
+pub fn handle(ctx: UriSchemeContext<'_, Wry>, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || responder.respond(serve(&app, &request)));
+}
+
+fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    // Windows and Android address the scheme as `http://annotation.localhost/…`.
+    let Ok(url) = Url::parse(&request.uri().to_string()) else {
+        return error(StatusCode::BAD_REQUEST, format!("{} is not a valid annotation URL.", request.uri()));
+    };
+    let annotation_key = url.path().trim_matches('/').to_string();
+    if let Err(err) = check_item_key(&annotation_key) {
+        return error(StatusCode::BAD_REQUEST, err);
+    }
+    let modified = url
+        .query_pairs()
+        .find(|(key, _)| key == "modified")
+        .map(|(_, value)| value.to_string());
+
+    match modified {
+        Some(modified) => serve_cached(app, request, &annotation_key, &modified),
+        None => serve_fresh(app, &annotation_key),
+    }
+    .unwrap_or_else(|err| {
+        tracing::warn!(annotation_key, "failed to serve annotation image: {}", err.message);
+        error(StatusCode::NOT_FOUND, err.message)
+    })
+}
+
+/// The image from the app's image cache, or `304 Not Modified` without
+/// touching the database when the webview already holds this version.
+fn serve_cached(
+    app: &AppHandle,
+    request: &Request<Vec<u8>>,
+    annotation_key: &str,
+    modified: &str,
+) -> Result<Response<Vec<u8>>, AppError> {
+    let image_settings = read_settings(app)?.image_settings;
+    let Some(stamp) = image_cache_stamp(&image_settings, modified) else {
+        return serve_fresh(app, annotation_key);
+    };
+    let etag = format!("\"{}\"", stamp.replace('"', ""));
+    let unchanged = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if unchanged {
+        return Ok(base_response(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Vec::new())
+            .unwrap_or_default());
+    }
+
+    let bytes = resolve_annotation_image(
+        &*open_zotero_connection(app)?,
+        &image_settings,
+        &app_data_dir(app)?,
+        annotation_key,
+        modified,
+    )?;
+    Ok(image(bytes, "no-cache", Some(etag)))
+}
+
+fn serve_fresh(app: &AppHandle, annotation_key: &str) -> Result<Response<Vec<u8>>, AppError> {
+    let render_dpi = read_settings(app)?.image_settings.render_dpi;
+    let bytes = load_annotation_image(&*open_zotero_connection(app)?, annotation_key, render_dpi)?;
+    Ok(image(bytes, "no-store", None))
+}
+
+fn base_response(status: StatusCode) -> tauri::http::response::Builder {
+    let response = Response::builder().status(status);
+    // The dev server loads the UI from another origin; release builds
+    // serve it from the app's own.
+    #[cfg(debug_assertions)]
+    let response = response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    response
+}
+
+fn image(bytes: Vec<u8>, cache_control: &str, etag: Option<String>) -> Response<Vec<u8>> {
+    let content_type = image::guess_format(&bytes)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("application/octet-stream");
+    let mut response = base_response(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, cache_control);
+    if let Some(etag) = etag {
+        response = response.header(header::ETAG, etag);
+    }
+    response.body(bytes).unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    base_response(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(message.into_bytes())
+        .unwrap_or_default()
+}
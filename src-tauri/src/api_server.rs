@@ -0,0 +1,379 @@
+//! Optional HTTP server on 127.0.0.1, so scripts, editor plugins and other
+//! tools can search the library, render notes and run exports without the
+//! window. Every request needs `Authorization: Bearer <apiServer.token>`.
+//!
+//! - `GET /items/search?q=…&limit=…` lists matching items.
+//! - `GET /items/<key>/note` renders the item's note without writing it.
+//! - `POST /export/<key>?dryRun=true` exports the item and returns the report.
+//!
+//! Responses are JSON; errors carry the same `code` and `message` as
+//! command errors.
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Listener};
+
+use crate::error::{AppError, ErrorCode};
+use crate::{
+    check_item_key, export_with_settings, open_zotero_connection, read_settings, search_items, validation,
+    with_dry_run_exporter, SETTINGS_CHANGED_EVENT,
+};
+
+/// Requests answered at once; further connections are closed unanswered
+/// until one finishes.
+const MAX_CONNECTIONS: usize = 8;
+/// Pause after a failed accept, e.g. when the process is out of file
+/// descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request line and headers accepted; requests have no body.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ApiServerSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Bearer token every request must send.
+    pub token: String,
+}
+
+impl Default for ApiServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 23121,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerStatus {
+    pub running: bool,
+    /// `http://127.0.0.1:<port>` while running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Why the server could not start, e.g. the port is taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+static STATUS: Mutex<ApiServerStatus> = Mutex::new(ApiServerStatus {
+    running: false,
+    address: None,
+    error: None,
+});
+
+pub fn status() -> ApiServerStatus {
+    STATUS.lock().map(|status| status.clone()).unwrap_or_default()
+}
+
+fn set_status(status: ApiServerStatus) {
+    if let Ok(mut current) = STATUS.lock() {
+        *current = status;
+    }
+}
+
+struct Request {
+    method: String,
+    target: String,
+    authorization: Option<String>,
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream.take(MAX_HEAD_BYTES as u64));
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|err| format!("failed to read request: {err}"))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line.".to_string());
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        authorization: None,
+    };
+
+    loop {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| format!("failed to read request headers: {err}"))?;
+        let header = line.trim_end();
+        if read == 0 || header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                request.authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+    Ok(request)
+}
+
+/// Compares in constant time, so response timing doesn't leak the token.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(given.trim(), token))
+}
+
+fn error_body(status: u16, message: impl Into<String>) -> (u16, Value) {
+    (status, json!(AppError::new(ErrorCode::Other, message)))
+}
+
+fn app_error(err: AppError) -> (u16, Value) {
+    let status = match err.code {
+        ErrorCode::ZoteroNotFound | ErrorCode::DatabaseLocked => 503,
+        ErrorCode::InvalidSettings => 409,
+        _ => 500,
+    };
+    (status, json!(err))
+}
+
+fn to_json<T: Serialize>(result: Result<T, AppError>) -> (u16, Value) {
+    match result.and_then(|value| {
+        serde_json::to_value(value).map_err(|err| AppError::from(format!("failed to serialize response: {err}")))
+    }) {
+        Ok(value) => (200, value),
+        Err(err) => app_error(err),
+    }
+}
+
+fn search(app: &AppHandle, url: &Url) -> (u16, Value) {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let query = param("q").unwrap_or_default();
+    let limit = match param("limit").map(|limit| limit.parse::<usize>()) {
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) => return error_body(400, "limit must be a number."),
+        None => None,
+    };
+    to_json((|| {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => read_settings(app)?.search_limit,
+        };
+        let limit = limit.clamp(1, validation::MAX_SEARCH_LIMIT);
+        Ok(search_items(&*open_zotero_connection(app)?, &query, limit)?)
+    })())
+}
+
+fn note(app: &AppHandle, item_key: &str) -> (u16, Value) {
+    to_json(with_dry_run_exporter(app, |exporter| {
+        let rendered = exporter.render_item(item_key)?;
+        Ok(json!({
+            "itemKey": item_key,
+            "citeKey": rendered.cite_key,
+            "path": rendered.markdown_path.to_string_lossy(),
+            "markdown": rendered.markdown,
+        }))
+    }))
+}
+
+fn export(app: &AppHandle, item_key: &str, url: &Url) -> (u16, Value) {
+    let dry_run = url
+        .query_pairs()
+        .any(|(key, value)| key == "dryRun" && matches!(value.as_ref(), "true" | "1"));
+    to_json(export_with_settings(app, vec![item_key.to_string()], dry_run, &mut |_, _, _| {}))
+}
+
+fn route(app: &AppHandle, token: &str, request: &Request) -> (u16, Value) {
+    if !authorized(request, token) {
+        return error_body(401, "Send the API token as Authorization: Bearer <token>.");
+    }
+    let Ok(url) = Url::parse(&format!("http://127.0.0.1{}", request.target)) else {
+        return error_body(400, format!("{:?} is not a valid request path.", request.target));
+    };
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let item_key = match segments.as_slice() {
+        ["items", key, "note"] | ["export", key] => Some(*key),
+        _ => None,
+    };
+    if let Some(Err(err)) = item_key.map(check_item_key) {
+        return error_body(400, err);
+    }
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["items", "search"]) => search(app, &url),
+        ("GET", ["items", key, "note"]) => note(app, key),
+        ("POST", ["export", key]) => export(app, key, &url),
+        (_, ["items", "search"] | ["items", _, "note"] | ["export", _]) => {
+            error_body(405, format!("{} is not allowed here.", request.method))
+        }
+        _ => error_body(404, format!("There is no endpoint at {}.", url.path())),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn handle_connection(app: &AppHandle, token: &str, mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let (status, body) = match read_request(&stream) {
+        Ok(request) => route(app, token, &request),
+        Err(err) => error_body(400, err),
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    );
+    if let Err(err) = stream.write_all(response.as_bytes()) {
+        tracing::warn!("failed to answer API request: {err}");
+    }
+}
+
+/// Frees a connection slot when its handler finishes, even by panicking.
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answers each connection on its own thread, at most [`MAX_CONNECTIONS`]
+/// at a time, until `stop` is set.
+fn accept_loop(app: &AppHandle, token: &str, listener: TcpListener, stop: &AtomicBool) {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                tracing::warn!("failed to accept an API connection: {err}");
+                std::thread::sleep(ACCEPT_BACKOFF);
+                continue;
+            }
+        };
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            tracing::warn!("closed an API connection: {MAX_CONNECTIONS} requests are already being answered");
+            continue;
+        }
+        let slot = Slot(active.clone());
+        let (app, token) = (app.clone(), token.to_string());
+        std::thread::spawn(move || {
+            let _slot = slot;
+            handle_connection(&app, &token, stream);
+        });
+    }
+}
+
+/// Serves with `settings` until they change; returns when the server should
+/// be restarted or stopped.
+fn serve(app: &AppHandle, settings: &ApiServerSettings, woken: &mpsc::Receiver<()>) {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, settings.port));
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::warn!("failed to start the API server on {address}: {err}");
+            set_status(ApiServerStatus {
+                error: Some(format!("failed to listen on {address}: {err}")),
+                ..ApiServerStatus::default()
+            });
+            let _ = woken.recv();
+            return;
+        }
+    };
+    tracing::info!("API server listening on {address}");
+    set_status(ApiServerStatus {
+        running: true,
+        address: Some(format!("http://{address}")),
+        error: None,
+    });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let acceptor = {
+        let (app, token, stop) = (app.clone(), settings.token.trim().to_string(), stop.clone());
+        std::thread::spawn(move || accept_loop(&app, &token, listener, &stop))
+    };
+
+    loop {
+        if woken.recv().is_err() {
+            // Settings can no longer change; serve until the app exits.
+            let _ = acceptor.join();
+            return;
+        }
+        let current = read_settings(app).map(|settings| settings.api_server).unwrap_or_default();
+        if current != *settings {
+            break;
+        }
+    }
+
+    stop.store(true, Ordering::SeqCst);
+    // The accept loop is blocked in accept(); a connection of our own wakes
+    // it to see the stop flag.
+    match TcpStream::connect(address) {
+        Ok(_) => {
+            let _ = acceptor.join();
+        }
+        Err(err) => tracing::warn!("failed to wake the API server on {address}: {err}"),
+    }
+    tracing::info!("API server on {address} stopped");
+}
+
+/// Runs the API server while `apiServer.enabled` is set. Saved settings wake
+/// the loop, so enabling it or changing the port or token applies at once.
+pub fn spawn(app: AppHandle) {
+    let (wake, woken) = mpsc::channel::<()>();
+    app.listen(SETTINGS_CHANGED_EVENT, move |_| {
+        let _ = wake.send(());
+    });
+
+    std::thread::spawn(move || loop {
+        let settings = read_settings(&app).map(|settings| settings.api_server).unwrap_or_default();
+        if settings.enabled && !settings.token.trim().is_empty() {
+            serve(&app, &settings, &woken);
+            continue;
+        }
+        set_status(ApiServerStatus::default());
+        if woken.recv().is_err() {
+            return;
+        }
+    });
+}
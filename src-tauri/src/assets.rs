@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// How image references written into notes are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageLinkStyle {
+    /// Relative to `attachment_base_dir`, which vault tools resolve by name.
+    #[default]
+    AttachmentRelative,
+    /// Relative to the directory of the note file.
+    NoteRelative,
+    /// Absolute filesystem path.
+    Absolute,
+}
+
+/// Values substituted into asset path templates.
+pub struct AssetContext<'a> {
+    pub cite_key: &'a str,
+    pub item_key: &'a str,
+}
+
+/// Characters that would escape or break a single path segment.
+pub fn sanitize_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            other => other,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Expands `{{citekey}}` / `{{itemKey}}` placeholders in `template` and
+/// returns the resulting relative directory. Absolute paths and `..`
+/// components are dropped so the folder always stays under the base dir.
+pub fn render_asset_dir(template: &str, context: &AssetContext) -> PathBuf {
+    let expanded = template
+        .replace("{{citekey}}", &sanitize_segment(context.cite_key))
+        .replace("{{itemKey}}", &sanitize_segment(context.item_key));
+
+    Path::new(expanded.trim())
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Joins path components with `/` regardless of platform, as expected by
+/// markdown links.
+pub fn to_link_path(path: &Path) -> String {
+    let joined = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.has_root() {
+        format!("/{joined}")
+    } else {
+        joined
+    }
+}
+
+/// Lexically normalizes `path`, resolving `.` and `..` without touching the
+/// filesystem (the note directory may not exist yet).
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Computes the path of `target` relative to the directory `base`. Returns
+/// `None` when the two share no common root (e.g. different drives).
+pub fn relative_to(target: &Path, base: &Path) -> Option<PathBuf> {
+    let target = normalize(target);
+    let base = normalize(base);
+
+    let target_components = target.components().collect::<Vec<_>>();
+    let base_components = base.components().collect::<Vec<_>>();
+    let shared = target_components
+        .iter()
+        .zip(base_components.iter())
+        .take_while(|(left, right)| left == right)
+        .count();
+
+    if shared == 0 {
+        return None;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in shared..base_components.len() {
+        relative.push("..");
+    }
+    for component in &target_components[shared..] {
+        relative.push(component.as_os_str());
+    }
+    Some(relative)
+}
+
+/// Builds the link to embed for an asset written to `destination`.
+/// `attachment_relative` is the same file relative to the attachment base
+/// directory; `note_dir` is the directory the note will be written to.
+pub fn image_link(
+    style: ImageLinkStyle,
+    destination: &Path,
+    attachment_relative: &Path,
+    note_dir: &Path,
+) -> String {
+    match style {
+        ImageLinkStyle::AttachmentRelative => to_link_path(attachment_relative),
+        ImageLinkStyle::NoteRelative => relative_to(destination, note_dir)
+            .map(|relative| to_link_path(&relative))
+            .unwrap_or_else(|| destination.to_string_lossy().replace('\\', "/")),
+        ImageLinkStyle::Absolute => destination.to_string_lossy().replace('\\', "/"),
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Listener};
+
+use crate::history::SyncTrigger;
+use crate::progress::Progress;
+use crate::report::ExportReport;
+use crate::{read_settings, sync_changed, SETTINGS_CHANGED_EVENT};
+
+pub const AUTO_SYNC_PROGRESS_EVENT: &str = "auto-sync-progress";
+pub const AUTO_SYNC_COMPLETED_EVENT: &str = "auto-sync-completed";
+pub const AUTO_SYNC_FAILED_EVENT: &str = "auto-sync-failed";
+
+/// How often settings are re-read when no `settings-changed` event arrives.
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct AutoSyncSettings {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    /// Sync once shortly after launch, independently of `enabled`.
+    pub on_startup: bool,
+    /// Wait before the startup sync, so Zotero can finish starting too.
+    pub startup_delay_seconds: u32,
+}
+
+impl Default for AutoSyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 30,
+            on_startup: false,
+            startup_delay_seconds: 10,
+        }
+    }
+}
+
+impl AutoSyncSettings {
+    fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_minutes.max(1)) * 60)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AutoSyncProgress {
+    current: usize,
+    total: usize,
+    cite_key: String,
+}
+
+fn run_once(app: &AppHandle, trigger: SyncTrigger) {
+    let progress = Progress::new(app, "auto-sync", None);
+    let mut on_progress = |current: usize, total: usize, cite_key: &str| {
+        progress.report(current as u64, total as u64, &format!("@{cite_key}"));
+        let _ = app.emit(
+            AUTO_SYNC_PROGRESS_EVENT,
+            AutoSyncProgress {
+                current,
+                total,
+                cite_key: cite_key.to_string(),
+            },
+        );
+    };
+
+    match sync_changed(app, trigger, false, &mut on_progress) {
+        Ok(report) => {
+            let _ = app.emit::<ExportReport>(AUTO_SYNC_COMPLETED_EVENT, report);
+        }
+        Err(err) => {
+            let _ = app.emit(AUTO_SYNC_FAILED_EVENT, err);
+        }
+    }
+}
+
+/// Runs the incremental sync on the configured interval while the app is
+/// open, and once after launch when `autoSync.onStartup` is set. Interval
+/// syncs are disabled unless `autoSync.enabled` is set. Saved settings wake
+/// the loop, so toggling auto-sync or changing the interval applies at once.
+pub fn spawn(app: AppHandle) {
+    let (wake, woken) = mpsc::channel::<()>();
+    app.listen(SETTINGS_CHANGED_EVENT, move |_| {
+        let _ = wake.send(());
+    });
+
+    std::thread::spawn(move || {
+        if let Ok(settings) = read_settings(&app) {
+            if settings.auto_sync.on_startup {
+                std::thread::sleep(Duration::from_secs(u64::from(
+                    settings.auto_sync.startup_delay_seconds,
+                )));
+                run_once(&app, SyncTrigger::Startup);
+            }
+        }
+
+        let mut last_run = Instant::now();
+
+        loop {
+            if woken.recv_timeout(TICK) == Err(mpsc::RecvTimeoutError::Disconnected) {
+                std::thread::sleep(TICK);
+            }
+
+            let Ok(settings) = read_settings(&app) else {
+                continue;
+            };
+            if !settings.auto_sync.enabled || last_run.elapsed() < settings.auto_sync.interval() {
+                continue;
+            }
+
+            last_run = Instant::now();
+            run_once(&app, SyncTrigger::Scheduled);
+        }
+    });
+}
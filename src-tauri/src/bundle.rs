@@ -0,0 +1,83 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::report::{format_timestamp, now_seconds};
+
+/// Bumped whenever the bundle layout changes incompatibly.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Annotation as stored by Zotero, including its raw reader position.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleAnnotation {
+    pub key: String,
+    pub attachment_key: String,
+    pub attachment_title: String,
+    pub annotation_type: String,
+    pub color_hex: String,
+    pub text: String,
+    pub comment: String,
+    pub page_label: String,
+    /// Zotero's sort key, e.g. `00003|001234|00567`.
+    pub sort_index: String,
+    /// Parsed `position` JSON (page index and rects/paths).
+    pub position: Value,
+    pub date_added: String,
+    pub date_modified: String,
+    /// Cached render of image annotations in the Zotero profile, if present.
+    pub image_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleNote {
+    pub key: String,
+    pub html: String,
+}
+
+/// Everything ZotNotes knows about an item, for downstream scripts and for
+/// debugging template output.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemBundle {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub item_key: String,
+    pub cite_key: Option<String>,
+    pub item: Value,
+    pub annotations: Vec<BundleAnnotation>,
+    pub child_notes: Vec<BundleNote>,
+}
+
+impl ItemBundle {
+    pub fn new(
+        item_key: String,
+        cite_key: Option<String>,
+        item: Value,
+        annotations: Vec<BundleAnnotation>,
+        child_notes: Vec<BundleNote>,
+    ) -> Self {
+        Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            exported_at: format_timestamp(now_seconds()),
+            item_key,
+            cite_key,
+            item,
+            annotations,
+            child_notes,
+        }
+    }
+}
+
+/// Zotero's numeric `itemAnnotations.type` as a name.
+pub fn annotation_type_name(value: i64) -> &'static str {
+    match value {
+        1 => "highlight",
+        2 => "note",
+        3 => "image",
+        4 => "ink",
+        5 => "underline",
+        6 => "text",
+        _ => "unknown",
+    }
+}
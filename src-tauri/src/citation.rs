@@ -0,0 +1,122 @@
+//! Plain-text references for the clipboard, in an APA-like style: authors,
+//! year, title, container, volume and issue, pages, and the DOI or URL.
+//! Good enough for a quick paste; use Zotero itself for a specific style.
+
+use serde_json::Value;
+
+use crate::extract_year;
+
+fn field(item: &Value, key: &str) -> String {
+    item.get("data")
+        .and_then(|data| data.get(key))
+        .and_then(Value::as_str)
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// `Last, F. M.` for a person, or the single-field name of an organisation.
+fn creator_name(creator: &Value) -> Option<String> {
+    let text = |key: &str| creator.get(key).and_then(Value::as_str).unwrap_or_default().trim();
+    let (last, first) = (text("lastName"), text("firstName"));
+    if last.is_empty() {
+        return Some(text("name")).filter(|name| !name.is_empty()).map(str::to_string);
+    }
+    let initials = first
+        .split([' ', '-'])
+        .filter_map(|part| part.chars().next())
+        .map(|initial| format!("{initial}."))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(if initials.is_empty() {
+        last.to_string()
+    } else {
+        format!("{last}, {initials}")
+    })
+}
+
+/// Authors joined APA-style: `A, B, & C`. Falls back to every creator when
+/// the item has no authors, e.g. an edited book.
+fn authors(item: &Value) -> String {
+    let creators = item
+        .pointer("/data/creators")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let is_author = |creator: &&Value| creator.get("creatorType").and_then(Value::as_str) == Some("author");
+    let chosen = if creators.iter().any(|creator| is_author(&creator)) {
+        creators.iter().filter(is_author).collect::<Vec<_>>()
+    } else {
+        creators.iter().collect()
+    };
+    let names = chosen.into_iter().filter_map(creator_name).collect::<Vec<_>>();
+    match names.as_slice() {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{}, & {last}", rest.join(", ")),
+    }
+}
+
+/// Appends `.` unless `text` already ends in sentence punctuation.
+fn sentence(text: &str) -> String {
+    if text.ends_with(['.', '?', '!']) {
+        text.to_string()
+    } else {
+        format!("{text}.")
+    }
+}
+
+/// The item as one line of plain text.
+pub fn format_reference(item: &Value) -> String {
+    let mut parts = Vec::new();
+
+    let year = extract_year(&field(item, "date"));
+    let year = if year.is_empty() { "n.d.".to_string() } else { year };
+    let authors = authors(item);
+    if authors.is_empty() {
+        parts.push(format!("({year})."));
+    } else {
+        parts.push(format!("{} ({year}).", authors.trim_end_matches('.')));
+    }
+
+    let title = field(item, "title");
+    if !title.is_empty() {
+        parts.push(sentence(&title));
+    }
+
+    let container = ["publicationTitle", "bookTitle", "proceedingsTitle", "websiteTitle"]
+        .iter()
+        .map(|key| field(item, key))
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let mut source = container;
+    let (volume, issue, pages) = (field(item, "volume"), field(item, "issue"), field(item, "pages"));
+    if !volume.is_empty() {
+        source.push_str(&format!(", {volume}"));
+        if !issue.is_empty() {
+            source.push_str(&format!("({issue})"));
+        }
+    }
+    if !pages.is_empty() {
+        source.push_str(&format!(", {pages}"));
+    }
+    let source = source.trim_start_matches(", ");
+    if !source.is_empty() {
+        parts.push(sentence(source));
+    }
+
+    let publisher = field(item, "publisher");
+    if !publisher.is_empty() && field(item, "publicationTitle").is_empty() {
+        parts.push(sentence(&publisher));
+    }
+
+    let doi = field(item, "DOI");
+    let url = field(item, "url");
+    if !doi.is_empty() {
+        let doi = doi.trim_start_matches("https://doi.org/");
+        parts.push(format!("https://doi.org/{doi}"));
+    } else if !url.is_empty() {
+        parts.push(url);
+    }
+
+    parts.join(" ")
+}
@@ -0,0 +1,118 @@
+//! Better BibTeX citation keys, memoized. The first lookup reads the whole
+//! `citationkey` table into memory, and later ones are hash lookups until
+//! better-bibtex.sqlite changes on disk. Exports also save each key they
+//! look up in the app's state database, so after a restart, or in a
+//! command-line export, items exported before are not read again while the
+//! Better BibTeX database is unchanged.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::state_db::StateDb;
+use crate::zotero_db;
+
+struct Memo {
+    fingerprint: String,
+    keys: HashMap<String, String>,
+}
+
+static MEMO: Mutex<Option<Memo>> = Mutex::new(None);
+
+/// The database's path with the size and modification time of it and its
+/// WAL and journal files; any write to it changes the fingerprint.
+fn fingerprint(path: &Path) -> String {
+    let files = zotero_db::stamp(path)
+        .into_iter()
+        .map(|file| match file {
+            Some((len, modified)) => {
+                let nanos = modified
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_nanos())
+                    .unwrap_or_default();
+                format!("{len}:{nanos}")
+            }
+            None => "-".to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{}|{}", path.display(), files.join(","))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn read_one(conn: &Connection, item_key: &str) -> Result<Option<String>, String> {
+    let citation_key = conn
+        .prepare_cached(
+            r#"
+            SELECT citationKey
+            FROM citationkey
+            WHERE itemKey = ?1
+            LIMIT 1
+            "#,
+        )
+        .and_then(|mut stmt| stmt.query_row(params![item_key], |row| row.get::<_, String>(0)))
+        .optional()
+        .map_err(|err| format!("failed to read Better BibTeX citation key: {err}"))?;
+    Ok(citation_key.and_then(non_empty))
+}
+
+fn read_all(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let read_err = |err: rusqlite::Error| format!("failed to read Better BibTeX citation keys: {err}");
+    let mut stmt = conn
+        .prepare("SELECT itemKey, citationKey FROM citationkey")
+        .map_err(read_err)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(read_err)?;
+    let mut keys = HashMap::new();
+    for row in rows {
+        let (item_key, citation_key) = row.map_err(read_err)?;
+        if let Some(citation_key) = non_empty(citation_key) {
+            keys.entry(item_key).or_insert(citation_key);
+        }
+    }
+    Ok(keys)
+}
+
+/// The citation key of `item_key` in the Better BibTeX database `conn` at
+/// `fingerprint`. The memo is checked and loaded under one lock, so
+/// parallel export workers read the database once between them.
+fn memoized(conn: &Connection, state: Option<&StateDb>, fingerprint: String, item_key: &str) -> Result<Option<String>, String> {
+    let mut memo = MEMO
+        .lock()
+        .map_err(|_| "citation key cache was poisoned by a failed lookup.".to_string())?;
+    let citation_key = match memo.as_ref().filter(|memo| memo.fingerprint == fingerprint) {
+        Some(memo) => memo.keys.get(item_key).cloned(),
+        None => {
+            if let Some(saved) = state.map(|state| state.citation_key(&fingerprint, item_key)).transpose()?.flatten() {
+                return Ok(Some(saved));
+            }
+            let keys = read_all(conn)?;
+            let citation_key = keys.get(item_key).cloned();
+            *memo = Some(Memo {
+                fingerprint: fingerprint.clone(),
+                keys,
+            });
+            citation_key
+        }
+    };
+    if let (Some(state), Some(citation_key)) = (state, &citation_key) {
+        if let Err(err) = state.save_citation_key(&fingerprint, item_key, citation_key) {
+            tracing::warn!("failed to save citation key of {item_key}: {err}");
+        }
+    }
+    Ok(citation_key)
+}
+
+/// The citation key of `item_key` in the Better BibTeX database `conn`.
+/// `state`, when given, keeps the keys looked up across restarts.
+pub fn lookup(conn: &Connection, state: Option<&StateDb>, item_key: &str) -> Result<Option<String>, String> {
+    let Some(path) = conn.path().filter(|path| !path.is_empty()) else {
+        return read_one(conn, item_key);
+    };
+    memoized(conn, state, fingerprint(Path::new(path)), item_key)
+}
@@ -0,0 +1,202 @@
+//! Finds `@citekey` mentions in the Markdown folder whose item has no
+//! literature note yet, so the notes can be created in one batch. Mentions
+//! follow Pandoc's citation syntax (`[@key]`, `@key`, `[see @key, p. 4]`);
+//! frontmatter, code blocks and inline code are not scanned.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::export::Exporter;
+use crate::load_sqlite_item;
+use crate::note_import::item_for_cite_key;
+use crate::note_search::markdown_files;
+
+/// Punctuation Pandoc allows inside a cite key, but not at its end.
+const KEY_PUNCTUATION: &str = ":.#$%&-+?<>~/";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationMention {
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlinkedCitation {
+    pub cite_key: String,
+    pub item_key: String,
+    pub title: String,
+    pub mentions: Vec<CitationMention>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationScan {
+    /// Cited items without a literature note, by cite key.
+    pub unlinked: Vec<UnlinkedCitation>,
+    /// Cited keys that match no item in the library.
+    pub unknown_keys: Vec<String>,
+}
+
+/// The cite key starting right after an `@` in `rest`, if any.
+fn cite_key_at(rest: &str) -> Option<&str> {
+    if let Some(braced) = rest.strip_prefix('{') {
+        return braced.split_once('}').map(|(key, _)| key.trim()).filter(|key| !key.is_empty());
+    }
+    let end = rest
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || KEY_PUNCTUATION.contains(ch)))
+        .unwrap_or(rest.len());
+    let key = rest[..end].trim_end_matches(|ch: char| KEY_PUNCTUATION.contains(ch));
+    key.starts_with(|ch: char| ch.is_alphanumeric() || ch == '_').then_some(key)
+}
+
+/// Cite keys mentioned on `line`, outside inline code.
+fn line_cite_keys(line: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    for (index, segment) in line.split('`').enumerate() {
+        if index % 2 == 1 {
+            continue;
+        }
+        let mut previous = None::<char>;
+        for (at, ch) in segment.char_indices() {
+            // A word character before the `@` means an email address or handle.
+            let starts_mention = ch == '@' && !previous.is_some_and(|prev| prev.is_alphanumeric() || prev == '_');
+            if starts_mention {
+                keys.extend(cite_key_at(&segment[at + 1..]));
+            }
+            previous = Some(ch);
+        }
+    }
+    keys
+}
+
+/// `(line number, cite key)` for every mention in `content`.
+fn cite_keys_in(content: &str) -> Vec<(usize, String)> {
+    let mut mentions = Vec::new();
+    let mut lines = content.lines().enumerate().peekable();
+    if lines.peek().is_some_and(|(_, line)| line.trim_end() == "---") {
+        lines.next();
+        for (_, line) in lines.by_ref() {
+            if matches!(line.trim_end(), "---" | "...") {
+                break;
+            }
+        }
+    }
+    let mut fence = None::<&str>;
+    for (index, line) in lines {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(open) = ["```", "~~~"].into_iter().find(|open| trimmed.starts_with(open)) {
+            fence = Some(open);
+            continue;
+        }
+        mentions.extend(line_cite_keys(line).into_iter().map(|key| (index + 1, key.to_string())));
+    }
+    mentions
+}
+
+/// Scans the notes under `dir` for cited items that have no note: no
+/// exported note that still exists, and no file named `key.md` or `@key.md`.
+pub fn scan(exporter: &Exporter, dir: &Path) -> Result<CitationScan, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not an existing folder.", dir.display()));
+    }
+    let files = markdown_files(dir);
+    let note_stems = files
+        .iter()
+        .filter_map(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().trim_start_matches('@').to_string())
+        .collect::<HashSet<_>>();
+    let exported = exporter
+        .state
+        .items()?
+        .into_iter()
+        .filter(|record| Path::new(&record.markdown_path).is_file())
+        .map(|record| record.item_key)
+        .collect::<HashSet<_>>();
+
+    let mut mentions = BTreeMap::<String, Vec<CitationMention>>::new();
+    for path in &files {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for (line, cite_key) in cite_keys_in(&content) {
+            if note_stems.contains(&cite_key) {
+                continue;
+            }
+            mentions.entry(cite_key).or_default().push(CitationMention {
+                path: path.to_string_lossy().to_string(),
+                line,
+            });
+        }
+    }
+
+    let mut scan = CitationScan::default();
+    for (cite_key, mentions) in mentions {
+        let Some(item_key) = item_for_cite_key(exporter.zotero, exporter.citations, &cite_key)? else {
+            scan.unknown_keys.push(cite_key);
+            continue;
+        };
+        if exported.contains(&item_key) || scan.unlinked.iter().any(|cited| cited.item_key == item_key) {
+            continue;
+        }
+        let item = load_sqlite_item(exporter.zotero, &item_key)?;
+        let title = item
+            .pointer("/data/title")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        scan.unlinked.push(UnlinkedCitation {
+            cite_key,
+            item_key,
+            title,
+            mentions,
+        });
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cite_key_at_reads_plain_and_braced_keys() {
+        assert_eq!(cite_key_at("smith2020, p. 4"), Some("smith2020"));
+        assert_eq!(cite_key_at("doe:2021.] says"), Some("doe:2021"));
+        assert_eq!(cite_key_at("{Smith 2020}]"), Some("Smith 2020"));
+        assert_eq!(cite_key_at("_key-x"), Some("_key-x"));
+    }
+
+    #[test]
+    fn cite_key_at_rejects_empty_and_punctuation_keys() {
+        assert_eq!(cite_key_at(""), None);
+        assert_eq!(cite_key_at(" smith"), None);
+        assert_eq!(cite_key_at("{ }"), None);
+        assert_eq!(cite_key_at("{unclosed"), None);
+        assert_eq!(cite_key_at("-key"), None);
+    }
+
+    #[test]
+    fn line_cite_keys_skip_emails_and_inline_code() {
+        let line = "See [@smith2020; @doe2021] and mail me@example.org, not `@code`.";
+        assert_eq!(line_cite_keys(line), ["smith2020", "doe2021"]);
+    }
+
+    #[test]
+    fn cite_keys_in_skip_frontmatter_and_fences() {
+        let content = "---\nauthor: @notakey\n---\nText @first.\n```\n@fenced\n```\n~~~\n@tilde\n~~~\nLast @second";
+        assert_eq!(
+            cite_keys_in(content),
+            [(4, "first".to_string()), (11, "second".to_string())]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Headless exports for cron jobs and CI: `zotnotes export …` runs the same
+//! export code as the app, with the same settings and export-state
+//! database, without opening a window. `zotnotes mcp` starts the
+//! assistant server in [`crate::mcp`].
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::history::{SyncRun, SyncTrigger};
+use crate::report::{now_seconds, ExportReport};
+use crate::state_db::StateDb;
+use crate::zotero_db::{self, ZoteroDb};
+use crate::{
+    export, export_changed, export_keys, home_dir, logging, mcp, open_better_bibtex_file, profiles,
+    read_settings_file, resolve_better_bibtex_sqlite_path, resolve_zotero_sqlite_path, sync_lock,
+    AppSettings,
+};
+
+/// Must match `identifier` in `tauri.conf.json`, which names the app's
+/// config and data directories.
+const APP_IDENTIFIER: &str = "com.zotnotes.desktop";
+
+const USAGE: &str = "\
+Usage: zotnotes export [options]
+
+Exports Zotero annotations to Markdown with the app's saved settings.
+Without --collection, re-exports the items changed since their last export.
+
+Options:
+  --collection <name|key>  Export every item in this Zotero collection
+  --out <dir>              Write notes here instead of the Markdown folder
+  --profile <name>         Use this settings profile instead of the active one
+  --dry-run                Report what would change without writing anything
+  --json                   Print the export report as JSON
+  -h, --help               Show this help";
+
+#[derive(Debug, Default)]
+struct ExportArgs {
+    collection: Option<String>,
+    out: Option<PathBuf>,
+    profile: Option<String>,
+    dry_run: bool,
+    json: bool,
+}
+
+fn parse_export_args(args: &[String]) -> Result<Option<ExportArgs>, String> {
+    let mut parsed = ExportArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{name} needs a value."))
+        };
+        match arg.as_str() {
+            "--collection" => parsed.collection = Some(value(arg)?),
+            "--out" => parsed.out = Some(PathBuf::from(value(arg)?)),
+            "--profile" => parsed.profile = Some(profiles::validate_name(&value(arg)?)?),
+            "--dry-run" => parsed.dry_run = true,
+            "--json" => parsed.json = true,
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown option {other}.")),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// The app's config and data directories, where Tauri puts them for
+/// [`APP_IDENTIFIER`].
+fn app_dirs() -> Result<(PathBuf, PathBuf), String> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+    let (config, data) = if cfg!(target_os = "windows") {
+        let roaming = env_dir("APPDATA").ok_or("APPDATA environment variable is not set.")?;
+        (roaming.clone(), roaming)
+    } else if cfg!(target_os = "macos") {
+        let support = home_dir()?.join("Library").join("Application Support");
+        (support.clone(), support)
+    } else {
+        let home = home_dir()?;
+        (
+            env_dir("XDG_CONFIG_HOME").unwrap_or_else(|| home.join(".config")),
+            env_dir("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local").join("share")),
+        )
+    };
+    let (config, data) = (config.join(APP_IDENTIFIER), data.join(APP_IDENTIFIER));
+    for dir in [&config, &data] {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| format!("failed to create app directory {}: {err}", dir.display()))?;
+    }
+    Ok((config, data))
+}
+
+/// Keys of the regular items in the collection with the given name or key.
+fn collection_item_keys(zotero: &Connection, collection: &str) -> Result<Vec<String>, String> {
+    let ids = zotero
+        .prepare("SELECT collectionID, key FROM collections WHERE key = ?1 OR collectionName = ?1")
+        .and_then(|mut stmt| {
+            stmt.query_map([collection], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|err| format!("failed to look up collection {collection:?}: {err}"))?;
+    let id = match ids.as_slice() {
+        [] => return Err(format!("no Zotero collection is named {collection:?}.")),
+        [(id, _)] => *id,
+        several => {
+            let keys = several.iter().map(|(_, key)| key.as_str()).collect::<Vec<_>>();
+            return Err(format!(
+                "several collections are named {collection:?}; pass one of their keys instead: {}.",
+                keys.join(", ")
+            ));
+        }
+    };
+
+    zotero
+        .prepare(
+            "SELECT i.key FROM collectionItems ci
+             JOIN items i ON i.itemID = ci.itemID
+             JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
+             WHERE ci.collectionID = ?1
+               AND it.typeName NOT IN ('attachment', 'note', 'annotation')
+               AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+             ORDER BY ci.orderIndex",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(params![id], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()
+        })
+        .map_err(|err| format!("failed to read the items of collection {collection:?}: {err}"))
+}
+
+/// The saved settings, export-state database and Zotero connections of a
+/// profile, for running without a window.
+pub struct Headless {
+    pub settings: AppSettings,
+    pub state: StateDb,
+    /// Where `state` lives, for export workers that open their own handle.
+    pub state_path: PathBuf,
+    pub zotero: ZoteroDb,
+    pub citations: Option<Connection>,
+    pub data_dir: PathBuf,
+}
+
+impl Headless {
+    /// Opens `profile`, or the active profile when `None`.
+    pub fn open(profile: Option<&str>) -> Result<Self, AppError> {
+        let (config_dir, data_dir) = app_dirs()?;
+        let profile = profile
+            .map(str::to_string)
+            .unwrap_or_else(|| profiles::active_profile(&config_dir));
+        let settings_file = profiles::settings_file(&config_dir, &profile);
+        if profile != profiles::DEFAULT_PROFILE && !settings_file.exists() {
+            return Err(format!("there is no profile named {profile:?}.").into());
+        }
+        let settings = read_settings_file(&settings_file)?;
+        let _ = logging::init(&data_dir, settings.log_level);
+
+        let state_path = data_dir.join(profiles::state_db_file_name(&profile));
+        Ok(Self {
+            state: StateDb::open(&state_path)?,
+            state_path,
+            zotero: zotero_db::open(&resolve_zotero_sqlite_path()?)?,
+            citations: resolve_better_bibtex_sqlite_path().and_then(|path| open_better_bibtex_file(&path).ok()),
+            settings,
+            data_dir,
+        })
+    }
+
+    pub fn exporter(&self, dry_run: bool) -> export::Exporter<'_> {
+        export::Exporter {
+            settings: &self.settings,
+            zotero: &self.zotero,
+            citations: self.citations.as_ref(),
+            state: &self.state,
+            data_dir: &self.data_dir,
+            dry_run,
+        }
+    }
+}
+
+/// Opens an export worker's own connections, like [`Headless::open`], and
+/// runs `run` with an exporter on them.
+fn worker_exporter(
+    settings: &AppSettings,
+    data_dir: &Path,
+    state_path: &Path,
+    dry_run: bool,
+    run: &mut dyn FnMut(&export::Exporter),
+) -> Result<(), String> {
+    let zotero = zotero_db::open(&resolve_zotero_sqlite_path()?)?;
+    let citations = resolve_better_bibtex_sqlite_path().and_then(|path| open_better_bibtex_file(&path).ok());
+    let state = StateDb::open(state_path)?;
+    run(&export::Exporter {
+        settings,
+        zotero: &zotero,
+        citations: citations.as_ref(),
+        state: &state,
+        data_dir,
+        dry_run,
+    });
+    Ok(())
+}
+
+fn run_export(args: &ExportArgs) -> Result<ExportReport, AppError> {
+    let mut headless = Headless::open(args.profile.as_deref())?;
+    // Waits for an export or sync running in the app.
+    let _guard = sync_lock::acquire(&headless.data_dir)?;
+    if let Some(out) = &args.out {
+        headless.settings.markdown_dir = out.to_string_lossy().to_string();
+    }
+    let exporter = headless.exporter(args.dry_run);
+    let zotero = &headless.zotero;
+    let (settings, data_dir, state_path) = (&headless.settings, &headless.data_dir, &headless.state_path);
+    let open_worker = |run: &mut dyn FnMut(&export::Exporter)| {
+        worker_exporter(settings, data_dir, state_path, args.dry_run, run)
+    };
+
+    let mut on_progress = |current: usize, total: usize, key: &str| {
+        if !args.json {
+            eprintln!("[{current}/{total}] {key}");
+        }
+    };
+    match &args.collection {
+        Some(collection) => {
+            let keys = collection_item_keys(zotero, collection)?;
+            export_keys(&exporter, zotero, keys, &open_worker, &mut on_progress)
+        }
+        None => {
+            let started_at = now_seconds();
+            let result = export_changed(&exporter, zotero, &open_worker, &mut on_progress);
+            if !args.dry_run {
+                let run = SyncRun::new(SyncTrigger::Cli, started_at, result.as_ref().map_err(|err| &err.message));
+                let _ = headless.state.record_sync_run(&run);
+            }
+            result
+        }
+    }
+}
+
+fn print_report(report: &ExportReport, json: bool) {
+    if json {
+        match serde_json::to_string_pretty(report) {
+            Ok(json) => println!("{json}"),
+            Err(err) => eprintln!("zotnotes: failed to serialize the report: {err}"),
+        }
+        return;
+    }
+    for item in &report.items {
+        let status = format!("{:?}", item.status).to_lowercase();
+        println!("{status}\t@{}\t{}", item.cite_key, item.path);
+    }
+    for failure in &report.failed {
+        eprintln!("failed: {failure}");
+    }
+    println!(
+        "{}{} exported, {} failed, {} excluded.",
+        if report.dry_run { "Dry run: " } else { "" },
+        report.items.len(),
+        report.failed.len(),
+        report.excluded.len()
+    );
+}
+
+/// Runs the command line in `args` (without the program name) and returns
+/// its exit code, or `None` when `args` is not a CLI command and the app
+/// should start normally. `export` exits with 1 when the export failed or
+/// any item failed, and 2 on a usage error; `mcp` is handled by [`mcp::run`].
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    match command.as_str() {
+        "export" => {}
+        "mcp" => return Some(mcp::run(rest)),
+        _ => return None,
+    }
+    let args = match parse_export_args(rest) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return Some(0);
+        }
+        Err(err) => {
+            eprintln!("zotnotes: {err}\n\n{USAGE}");
+            return Some(2);
+        }
+    };
+
+    match run_export(&args) {
+        Ok(report) => {
+            print_report(&report, args.json);
+            Some(if report.failed.is_empty() { 0 } else { 1 })
+        }
+        Err(err) => {
+            eprintln!("zotnotes: {err}");
+            Some(1)
+        }
+    }
+}
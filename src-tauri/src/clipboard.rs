@@ -0,0 +1,68 @@
+//! Copies text to the system clipboard through the platform's own tool:
+//! `pbcopy` on macOS, `clip` on Windows, and `wl-copy`, `xclip` or `xsel`
+//! on Linux, whichever is installed.
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Clipboard tools to try, in order, with their arguments.
+fn candidates() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        vec![("clip", &[])]
+    } else {
+        let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.push(("wl-copy", &[]));
+        }
+        tools.push(("xclip", &["-selection", "clipboard"]));
+        tools.push(("xsel", &["--clipboard", "--input"]));
+        tools
+    }
+}
+
+/// `clip` reads the console code page unless the input is UTF-16 with a
+/// byte-order mark.
+fn encode(text: &str) -> Vec<u8> {
+    if cfg!(target_os = "windows") {
+        std::iter::once(0xFEFF)
+            .chain(text.encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect()
+    } else {
+        text.as_bytes().to_vec()
+    }
+}
+
+pub fn copy_text(text: &str) -> Result<(), String> {
+    for (program, args) in candidates() {
+        // The X11 tools keep running to serve the selection, so their output
+        // must not be tied to a pipe we wait on.
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("failed to start {program}: {err}")),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(&encode(text))
+                .map_err(|err| format!("failed to write to {program}: {err}"))?;
+        }
+        let status = child
+            .wait()
+            .map_err(|err| format!("failed to wait for {program}: {err}"))?;
+        return if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{program} exited with {status}"))
+        };
+    }
+    Err("no clipboard tool was found; install wl-clipboard, xclip or xsel.".to_string())
+}
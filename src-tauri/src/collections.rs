@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::assets::{render_asset_dir, sanitize_segment, AssetContext};
+use crate::extract_year;
+use crate::render::TemplateSettings;
+
+/// Export settings applied to items in a specific Zotero collection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct CollectionOverride {
+    /// Collection key, or collection name (case-insensitive).
+    pub collection: String,
+    /// Replaces the global template settings when set.
+    pub template_settings: Option<TemplateSettings>,
+    /// Note file name without extension, e.g. `{{year}} {{title}}`; empty
+    /// keeps the default `@{{citekey}}`.
+    pub filename_pattern: String,
+    /// Folder under `markdown_dir` for notes in this collection.
+    pub output_subfolder: String,
+}
+
+/// A collection the item belongs to, directly or through a subcollection.
+#[derive(Debug, Clone)]
+pub struct CollectionRef {
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTarget {
+    pub markdown_path: String,
+    pub template_settings: TemplateSettings,
+    /// The override that applied, if any.
+    pub collection: Option<String>,
+}
+
+pub const DEFAULT_FILENAME_PATTERN: &str = "@{{citekey}}";
+
+impl CollectionOverride {
+    fn matches(&self, collection: &CollectionRef) -> bool {
+        let wanted = self.collection.trim();
+        !wanted.is_empty()
+            && (collection.key == wanted || collection.name.trim().eq_ignore_ascii_case(wanted))
+    }
+}
+
+/// Collection keys listed on a Zotero item (`data.collections`).
+pub fn item_collection_keys(item: &Value) -> Vec<String> {
+    item.get("data")
+        .and_then(|data| data.get("collections"))
+        .and_then(Value::as_array)
+        .map(|keys| {
+            keys.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the first override, in settings order, matching any of the
+/// item's collections.
+pub fn select_override<'a>(
+    overrides: &'a [CollectionOverride],
+    collections: &[CollectionRef],
+) -> Option<&'a CollectionOverride> {
+    overrides.iter().find(|candidate| {
+        collections
+            .iter()
+            .any(|collection| candidate.matches(collection))
+    })
+}
+
+/// Expands `{{citekey}}`, `{{itemKey}}`, `{{title}}` and `{{year}}` in the
+/// file name pattern and appends `.md`.
+pub fn render_file_name(pattern: &str, cite_key: &str, item: &Value) -> String {
+    let data = item.get("data");
+    let field = |key: &str| {
+        data.and_then(|data| data.get(key))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    };
+    let item_key = item.get("key").and_then(Value::as_str).unwrap_or_default();
+
+    let pattern = if pattern.trim().is_empty() {
+        DEFAULT_FILENAME_PATTERN
+    } else {
+        pattern.trim()
+    };
+    let expanded = pattern
+        .replace("{{citekey}}", cite_key)
+        .replace("{{itemKey}}", item_key)
+        .replace("{{title}}", &field("title"))
+        .replace("{{year}}", &extract_year(&field("date")));
+
+    let name = sanitize_segment(&expanded);
+    if name.is_empty() {
+        format!("@{}.md", sanitize_segment(cite_key))
+    } else {
+        format!("{name}.md")
+    }
+}
+
+/// Builds the note path and template for `item`, applying the matching
+/// collection override on top of the global settings.
+pub fn resolve_target(
+    markdown_dir: &str,
+    overrides: &[CollectionOverride],
+    collections: &[CollectionRef],
+    item: &Value,
+    cite_key: &str,
+    template_settings: TemplateSettings,
+) -> ExportTarget {
+    let selected = select_override(overrides, collections);
+    let item_key = item.get("key").and_then(Value::as_str).unwrap_or_default();
+
+    let mut directory = PathBuf::from(markdown_dir.trim());
+    let mut file_name = render_file_name(DEFAULT_FILENAME_PATTERN, cite_key, item);
+    if let Some(selected) = selected {
+        let context = AssetContext { cite_key, item_key };
+        directory.push(render_asset_dir(&selected.output_subfolder, &context));
+        file_name = render_file_name(&selected.filename_pattern, cite_key, item);
+    }
+
+    ExportTarget {
+        markdown_path: directory.join(file_name).to_string_lossy().to_string(),
+        template_settings: selected
+            .and_then(|selected| selected.template_settings.clone())
+            .unwrap_or(template_settings),
+        collection: selected.map(|selected| selected.collection.clone()),
+    }
+}
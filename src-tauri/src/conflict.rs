@@ -0,0 +1,60 @@
+//! Handling of notes edited locally while their Zotero item also changed.
+//!
+//! The state database stores the hash of the note as last written. A note
+//! whose file no longer matches that hash was edited outside ZotNotes; if the
+//! newly rendered note differs from it as well, both sides changed and the
+//! configured strategy decides what is written.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictStrategy {
+    /// Leave the edited note as it is.
+    KeepLocal,
+    /// Overwrite the note with the freshly rendered one.
+    KeepZotero,
+    /// Leave the note and write the rendered one next to it as
+    /// `<name>.conflict.md`.
+    #[default]
+    ConflictCopy,
+}
+
+/// Which sides changed since the note was last written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Divergence {
+    None,
+    /// Only the file was edited; the rendered note matches the last export.
+    LocalOnly,
+    Both,
+}
+
+/// Compares the note on disk and the new render with the recorded hash.
+/// Without a record (first export, or a note not written by ZotNotes) there
+/// is nothing to compare against and the note is written as usual.
+pub fn divergence(existing_hash: &str, rendered_hash: &str, recorded_hash: Option<&str>) -> Divergence {
+    let Some(recorded) = recorded_hash else {
+        return Divergence::None;
+    };
+    if existing_hash == recorded || existing_hash == rendered_hash {
+        Divergence::None
+    } else if rendered_hash == recorded {
+        Divergence::LocalOnly
+    } else {
+        Divergence::Both
+    }
+}
+
+/// `notes/@key.md` becomes `notes/@key.conflict.md`.
+pub fn conflict_copy_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}.conflict.{}", extension.to_string_lossy()),
+        None => format!("{stem}.conflict"),
+    };
+    path.with_file_name(file_name)
+}
@@ -0,0 +1,163 @@
+//! Checks every data source ZotNotes talks to, for the settings "Test"
+//! button: zotero.sqlite, Better BibTeX, the local Zotero API and the web
+//! API key.
+
+use reqwest::header::HeaderMap;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{apply_api_key, sqlite_file_uri, AppSettings};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tables the exporter reads from zotero.sqlite.
+const REQUIRED_TABLES: [&str; 6] = [
+    "items",
+    "itemData",
+    "itemAnnotations",
+    "itemAttachments",
+    "collections",
+    "deletedItems",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl From<Result<String, String>> for ConnectionStatus {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(message) => Self { ok: true, message },
+            Err(message) => Self { ok: false, message },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionReport {
+    pub zotero_database: ConnectionStatus,
+    pub better_bibtex: ConnectionStatus,
+    pub local_api: ConnectionStatus,
+    pub web_api: ConnectionStatus,
+}
+
+/// Opens `file_name` in a data directory that is not saved in the settings
+/// yet, the same way the configured databases are opened.
+pub fn open_in_data_dir(dir: &str, file_name: &str) -> Result<Connection, String> {
+    let path = Path::new(dir).join(file_name);
+    if !path.exists() {
+        return Err(format!("{dir} does not contain {file_name}."));
+    }
+    Connection::open_with_flags(
+        sqlite_file_uri(&path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .map_err(|err| format!("failed to open {}: {err}", path.display()))
+}
+
+/// Checks that zotero.sqlite has the tables the exporter needs and reports
+/// its schema version.
+pub fn check_zotero_database(conn: &Connection) -> Result<String, String> {
+    for table in REQUIRED_TABLES {
+        let exists = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get::<_, bool>(0),
+            )
+            .map_err(|err| format!("failed to read Zotero schema: {err}"))?;
+        if !exists {
+            return Err(format!("zotero.sqlite has no {table} table; is this a Zotero 7 database?"));
+        }
+    }
+
+    let schema = conn
+        .query_row("SELECT version FROM version WHERE schema = 'userdata'", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map_err(|err| format!("failed to read Zotero schema version: {err}"))?;
+    let items = conn
+        .query_row("SELECT COUNT(*) FROM items", [], |row| row.get::<_, i64>(0))
+        .map_err(|err| format!("failed to count Zotero items: {err}"))?;
+    Ok(format!("Schema version {schema}, {items} items."))
+}
+
+pub fn check_better_bibtex(conn: &Connection) -> Result<String, String> {
+    let keys = conn
+        .query_row("SELECT COUNT(*) FROM citationkey", [], |row| row.get::<_, i64>(0))
+        .map_err(|err| format!("failed to read Better BibTeX citation keys: {err}"))?;
+    Ok(format!("{keys} citation keys."))
+}
+
+/// Pings the connector endpoint of the Zotero desktop app.
+pub async fn check_local_api(client: &reqwest::Client, settings: &AppSettings) -> Result<String, String> {
+    let base = settings.zotero_base_url.trim().trim_end_matches('/');
+    if base.is_empty() {
+        return Err("No Zotero base URL is set.".to_string());
+    }
+
+    let url = format!("{base}/connector/ping");
+    let response = client
+        .get(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("Zotero is not reachable at {base}: {err}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Zotero HTTP {status} for {url}"));
+    }
+    Ok(format!("Zotero is running at {base}."))
+}
+
+/// Looks up the API key on the web API and reports its user and access.
+pub async fn check_web_api(client: &reqwest::Client, settings: &AppSettings) -> Result<String, String> {
+    let key = settings.zotero_api_key.trim();
+    if key.is_empty() {
+        return Err("No Zotero API key is set.".to_string());
+    }
+
+    let response = client
+        .get("https://api.zotero.org/keys/current")
+        .headers(apply_api_key(HeaderMap::new(), Some(key.to_string())))
+        .header("Zotero-API-Version", "3")
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("api.zotero.org is not reachable: {err}"))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN {
+        return Err("Zotero rejected the API key.".to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("Zotero HTTP {status} for the API key lookup"));
+    }
+
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|err| format!("failed to parse API key details: {err}"))?;
+    let user_id = body.get("userID").map(Value::to_string).unwrap_or_default();
+    let can_write = body
+        .pointer("/access/user/write")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let configured = settings.zotero_user_id.trim();
+    if !configured.is_empty() && configured != user_id {
+        return Err(format!(
+            "The API key belongs to user {user_id}, but the Zotero user ID setting is {configured}."
+        ));
+    }
+    Ok(format!(
+        "Key for user {user_id}, {}.",
+        if can_write { "with write access" } else { "read-only" }
+    ))
+}
@@ -0,0 +1,135 @@
+use serde_json::Value;
+
+use crate::render::{field_values, NoteInput};
+
+/// Columns written when none are configured.
+pub const DEFAULT_COLUMNS: [&str; 7] = [
+    "citekey",
+    "title",
+    "author",
+    "year",
+    "itemType",
+    "publicationTitle",
+    "DOI",
+];
+
+/// One row of the CSV: an item payload and its citation key.
+pub struct CsvItem {
+    pub item: Value,
+    pub cite_key: String,
+}
+
+/// Quotes a field when it contains separators, quotes or line breaks. A
+/// field that a spreadsheet would read as a formula (starting with `=`,
+/// `+`, `-`, `@`, a tab or a carriage return) gets a leading `'`.
+pub fn escape_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn string_list(item: &Value, key: &str, entry_key: Option<&str>) -> Vec<String> {
+    item.get("data")
+        .and_then(|data| data.get(key))
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| match entry_key {
+                    Some(entry_key) => entry.get(entry_key).and_then(Value::as_str),
+                    None => entry.as_str(),
+                })
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Resolves a column against the item. Besides Zotero field names and the
+/// derived `author`/`year`/`company`/`citekey` fields, `itemKey`, `tags` and
+/// `collections` are supported. Multiple values are joined with `; `.
+fn column_value(row: &CsvItem, column: &str) -> String {
+    let values = match column {
+        "itemKey" => vec![row
+            .item
+            .get("key")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()],
+        "tags" => string_list(&row.item, "tags", Some("tag")),
+        "collections" => string_list(&row.item, "collections", None),
+        other => {
+            let input = NoteInput {
+                item: row.item.clone(),
+                cite_key: row.cite_key.clone(),
+                grouped_annotations: Vec::new(),
+                child_notes: Vec::new(),
+            };
+            field_values(&input, other)
+        }
+    };
+
+    values
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Renders a header row followed by one row per item.
+pub fn items_to_csv(rows: &[CsvItem], columns: &[String]) -> String {
+    let columns = if columns.is_empty() {
+        DEFAULT_COLUMNS.iter().map(|column| column.to_string()).collect()
+    } else {
+        columns.to_vec()
+    };
+
+    let mut lines = vec![columns
+        .iter()
+        .map(|column| escape_field(column))
+        .collect::<Vec<_>>()
+        .join(",")];
+    for row in rows {
+        lines.push(
+            columns
+                .iter()
+                .map(|column| escape_field(&column_value(row, column)))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+
+    let mut csv = lines.join("\r\n");
+    csv.push_str("\r\n");
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_with_separators_are_quoted() {
+        assert_eq!(escape_field("plain"), "plain");
+        assert_eq!(escape_field("a, b"), "\"a, b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn formula_fields_are_neutralized() {
+        assert_eq!(escape_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(escape_field("+1"), "'+1");
+        assert_eq!(escape_field("-2"), "'-2");
+        assert_eq!(escape_field("@sum"), "'@sum");
+        assert_eq!(escape_field("\tcell"), "'\tcell");
+        assert_eq!(escape_field("a=b"), "a=b");
+    }
+}
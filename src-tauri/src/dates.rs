@@ -0,0 +1,280 @@
+//! Date formatting for note output. A pattern such as `D MMMM YYYY` is
+//! applied to Zotero timestamps (which are UTC) with month names from the
+//! configured locale; the presets `short` and `long` pick the locale's
+//! usual order. Locales without month names here fall back to English.
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::{civil_from_days, parse_zotero_timestamp};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct DateFormat {
+    /// `short`, `long`, `iso`, or a pattern built from `YYYY`, `YY`,
+    /// `MMMM`, `MMM`, `MM`, `M`, `DD`, `D`, `HH`, `mm` and `ss`; text in
+    /// `[brackets]` is copied as is. Empty keeps Zotero's value.
+    pub pattern: String,
+    /// BCP 47 language tag for month names and presets, e.g. `de` or
+    /// `en-GB`; empty means `en-US`.
+    pub locale: String,
+}
+
+struct Names {
+    months: [&'static str; 12],
+    short_months: [&'static str; 12],
+}
+
+const ENGLISH: Names = Names {
+    months: [
+        "January", "February", "March", "April", "May", "June", "July", "August", "September",
+        "October", "November", "December",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+};
+
+const LOCALES: [(&str, Names); 6] = [
+    (
+        "de",
+        Names {
+            months: [
+                "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September",
+                "Oktober", "November", "Dezember",
+            ],
+            short_months: [
+                "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.",
+                "Nov.", "Dez.",
+            ],
+        },
+    ),
+    (
+        "fr",
+        Names {
+            months: [
+                "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août",
+                "septembre", "octobre", "novembre", "décembre",
+            ],
+            short_months: [
+                "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+                "nov.", "déc.",
+            ],
+        },
+    ),
+    (
+        "es",
+        Names {
+            months: [
+                "enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto",
+                "septiembre", "octubre", "noviembre", "diciembre",
+            ],
+            short_months: [
+                "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+            ],
+        },
+    ),
+    (
+        "it",
+        Names {
+            months: [
+                "gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto",
+                "settembre", "ottobre", "novembre", "dicembre",
+            ],
+            short_months: [
+                "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+            ],
+        },
+    ),
+    (
+        "pt",
+        Names {
+            months: [
+                "janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto",
+                "setembro", "outubro", "novembro", "dezembro",
+            ],
+            short_months: [
+                "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+            ],
+        },
+    ),
+    (
+        "nl",
+        Names {
+            months: [
+                "januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus",
+                "september", "oktober", "november", "december",
+            ],
+            short_months: [
+                "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+            ],
+        },
+    ),
+];
+
+/// Language and region of a tag like `en-GB` or `pt_BR`, lowercased.
+fn split_locale(locale: &str) -> (String, String) {
+    let mut parts = locale.trim().split(['-', '_']);
+    let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+    let region = parts.next().unwrap_or_default().to_ascii_lowercase();
+    (language, region)
+}
+
+fn names(language: &str) -> &'static Names {
+    LOCALES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or(&ENGLISH, |(_, names)| names)
+}
+
+/// Pattern for a preset in the given locale; other patterns are returned
+/// unchanged.
+fn preset(pattern: &str, language: &str, region: &str) -> String {
+    let month_first = language.is_empty() || (language == "en" && matches!(region, "" | "us"));
+    let pattern = match (pattern, language) {
+        ("iso", _) => "YYYY-MM-DD",
+        ("short", _) if month_first => "M/D/YYYY",
+        ("short", "de") => "DD.MM.YYYY",
+        ("short", "nl") => "DD-MM-YYYY",
+        ("short", _) => "DD/MM/YYYY",
+        ("long", _) if month_first => "MMMM D, YYYY",
+        ("long", "de") => "D. MMMM YYYY",
+        ("long", "es" | "pt") => "D [de] MMMM [de] YYYY",
+        ("long", _) => "D MMMM YYYY",
+        (other, _) => other,
+    };
+    pattern.to_string()
+}
+
+const TOKENS: [&str; 11] = ["YYYY", "YY", "MMMM", "MMM", "MM", "M", "DD", "D", "HH", "mm", "ss"];
+
+/// Formats unix seconds (UTC) with `format`; `None` when no pattern is set.
+pub fn format_seconds(seconds: i64, format: &DateFormat) -> Option<String> {
+    let pattern = format.pattern.trim();
+    if pattern.is_empty() {
+        return None;
+    }
+    let (language, region) = split_locale(&format.locale);
+    let pattern = preset(pattern, &language, &region);
+    let names = names(&language);
+
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    let month_index = (month - 1).clamp(0, 11) as usize;
+
+    let mut output = String::new();
+    let mut rest = pattern.as_str();
+    while let Some(ch) = rest.chars().next() {
+        if ch == '[' {
+            let literal = &rest[1..];
+            let end = literal.find(']').unwrap_or(literal.len());
+            output.push_str(&literal[..end]);
+            rest = literal.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        let Some(token) = TOKENS.iter().find(|token| rest.starts_with(*token)) else {
+            output.push(ch);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        };
+        let value = match *token {
+            "YYYY" => format!("{year:04}"),
+            "YY" => format!("{:02}", year.rem_euclid(100)),
+            "MMMM" => names.months[month_index].to_string(),
+            "MMM" => names.short_months[month_index].to_string(),
+            "MM" => format!("{month:02}"),
+            "M" => month.to_string(),
+            "DD" => format!("{day:02}"),
+            "D" => day.to_string(),
+            "HH" => format!("{:02}", time / 3_600),
+            "mm" => format!("{:02}", (time % 3_600) / 60),
+            _ => format!("{:02}", time % 60),
+        };
+        output.push_str(&value);
+        rest = &rest[token.len()..];
+    }
+    Some(output)
+}
+
+/// Formats a Zotero timestamp, or returns it unchanged when no pattern is
+/// set or it cannot be parsed.
+pub fn format_zotero_date(raw: &str, format: &DateFormat) -> String {
+    parse_zotero_timestamp(raw)
+        .and_then(|seconds| format_seconds(seconds, format))
+        .unwrap_or_else(|| raw.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-07 09:05:03 UTC.
+    const SECONDS: i64 = 1_709_802_303;
+
+    fn format(pattern: &str, locale: &str) -> DateFormat {
+        DateFormat {
+            pattern: pattern.to_string(),
+            locale: locale.to_string(),
+        }
+    }
+
+    fn formatted(seconds: i64, pattern: &str, locale: &str) -> String {
+        format_seconds(seconds, &format(pattern, locale)).unwrap()
+    }
+
+    #[test]
+    fn presets_follow_the_locale() {
+        let cases = [
+            ("", "3/7/2024", "March 7, 2024"),
+            ("en-US", "3/7/2024", "March 7, 2024"),
+            ("en_GB", "07/03/2024", "7 March 2024"),
+            ("de", "07.03.2024", "7. März 2024"),
+            ("es", "07/03/2024", "7 de marzo de 2024"),
+            ("nl-NL", "07-03-2024", "7 maart 2024"),
+        ];
+        for (locale, short, long) in cases {
+            assert_eq!(formatted(SECONDS, "short", locale), short, "short in {locale:?}");
+            assert_eq!(formatted(SECONDS, "long", locale), long, "long in {locale:?}");
+            assert_eq!(formatted(SECONDS, "iso", locale), "2024-03-07", "iso in {locale:?}");
+        }
+    }
+
+    #[test]
+    fn unknown_locales_use_english_names() {
+        assert_eq!(formatted(SECONDS, "long", "sv-SE"), "7 March 2024");
+        assert_eq!(formatted(SECONDS, "MMM", "zz"), "Mar");
+    }
+
+    #[test]
+    fn patterns_fill_every_token() {
+        assert_eq!(formatted(SECONDS, "YYYY-MM-DD HH:mm:ss", ""), "2024-03-07 09:05:03");
+        assert_eq!(formatted(SECONDS, "YY/M/D", ""), "24/3/7");
+        assert_eq!(formatted(SECONDS, "D MMM YYYY", "de"), "7 März 2024");
+        assert!(format_seconds(SECONDS, &format("  ", "de")).is_none());
+    }
+
+    #[test]
+    fn bracketed_text_is_copied() {
+        assert_eq!(formatted(SECONDS, "[Day] D [of] MMMM", ""), "Day 7 of March");
+        assert_eq!(formatted(SECONDS, "D [at HH:mm", ""), "7 at HH:mm");
+        assert_eq!(formatted(SECONDS, "[]YYYY", ""), "2024");
+    }
+
+    #[test]
+    fn times_before_1970_are_formatted() {
+        assert_eq!(formatted(-1, "YYYY-MM-DD HH:mm:ss", ""), "1969-12-31 23:59:59");
+        assert_eq!(formatted(-86_400 * 365, "long", "en-GB"), "1 January 1969");
+        assert_eq!(formatted(-2_208_988_800, "iso", ""), "1900-01-01");
+    }
+
+    #[test]
+    fn zotero_dates_are_formatted_or_kept() {
+        let long = format("long", "");
+        assert_eq!(format_zotero_date("2024-03-07 09:05:03", &long), "March 7, 2024");
+        assert_eq!(format_zotero_date("2024-03-07T23:59:59Z", &long), "March 7, 2024");
+        assert_eq!(format_zotero_date("2024-03-07", &long), "March 7, 2024");
+        assert_eq!(format_zotero_date("2024-03-07", &format("HH:mm", "")), "00:00");
+        assert_eq!(format_zotero_date("2024-03-07", &DateFormat::default()), "2024-03-07");
+        assert_eq!(format_zotero_date("n.d.", &long), "n.d.");
+    }
+}
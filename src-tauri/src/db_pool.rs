@@ -0,0 +1,105 @@
+//! Long-lived read-only connections to zotero.sqlite and better-bibtex.sqlite,
+//! kept in Tauri managed state so commands skip the open, schema parse and
+//! probe on every call. An idle connection is only handed out again while
+//! its file is unchanged (an `immutable=1` connection would keep serving
+//! cached pages), its path still matches the settings, and it answers a
+//! health check; otherwise it is dropped and a fresh one opened.
+
+use rusqlite::Connection;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::AppError;
+use crate::zotero_db::{self, Stamp, ZoteroDb};
+
+/// Idle connections kept per database; more are opened when commands run
+/// concurrently, and the extras are closed when they are returned.
+const MAX_IDLE: usize = 4;
+
+struct Idle<T> {
+    conn: T,
+    path: PathBuf,
+    stamp: Stamp,
+}
+
+type IdleList<T> = Mutex<Vec<Idle<T>>>;
+
+#[derive(Default)]
+pub struct DbPool {
+    zotero: IdleList<ZoteroDb>,
+    better_bibtex: IdleList<Connection>,
+}
+
+/// A connection checked out of the pool; it goes back when dropped.
+pub struct Pooled<'a, T> {
+    idle: &'a IdleList<T>,
+    entry: Option<Idle<T>>,
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Only `drop` takes the entry out.
+        &self.entry.as_ref().expect("pooled connection is present").conn
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        let (Some(entry), Ok(mut idle)) = (self.entry.take(), self.idle.lock()) else {
+            return;
+        };
+        if idle.len() < MAX_IDLE {
+            idle.push(entry);
+        }
+    }
+}
+
+fn healthy(conn: &Connection) -> bool {
+    conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)).is_ok()
+}
+
+fn checkout<'a, T>(
+    idle: &'a IdleList<T>,
+    path: &Path,
+    connection: impl Fn(&T) -> &Connection,
+    open: impl FnOnce(&Path) -> Result<T, AppError>,
+) -> Result<Pooled<'a, T>, AppError> {
+    let stamp = zotero_db::stamp(path);
+    let reused = {
+        let mut entries = idle
+            .lock()
+            .map_err(|_| AppError::from("database pool lock was poisoned.".to_string()))?;
+        entries.retain(|entry| entry.path == path && entry.stamp == stamp);
+        std::iter::from_fn(|| entries.pop()).find(|entry| healthy(connection(&entry.conn)))
+    };
+
+    let entry = match reused {
+        Some(entry) => entry,
+        None => Idle {
+            conn: open(path)?,
+            path: path.to_path_buf(),
+            stamp,
+        },
+    };
+    Ok(Pooled {
+        idle,
+        entry: Some(entry),
+    })
+}
+
+impl DbPool {
+    pub fn zotero(&self, path: &Path) -> Result<Pooled<'_, ZoteroDb>, AppError> {
+        checkout(&self.zotero, path, |db| db, zotero_db::open)
+    }
+
+    pub fn better_bibtex(
+        &self,
+        path: &Path,
+        open: impl FnOnce(&Path) -> Result<Connection, AppError>,
+    ) -> Result<Pooled<'_, Connection>, AppError> {
+        checkout(&self.better_bibtex, path, |conn| conn, open)
+    }
+}
@@ -0,0 +1,73 @@
+//! Debug dumps of Zotero responses, written to a `zotnotes-debug` folder in
+//! the temp directory. Secret fields and the configured API key are
+//! redacted before anything is written, and old dumps are pruned after each
+//! write so the folder doesn't grow forever.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::diagnostics::{redact_fields, REDACTED};
+use crate::report::now_seconds;
+
+const DUMP_DIR: &str = "zotnotes-debug";
+const MAX_DUMPS: usize = 20;
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Redacts secret fields when `content` is JSON, then every occurrence of
+/// the given secrets, e.g. an API key embedded in a URL.
+fn redact(content: String, secrets: &[&str]) -> String {
+    let mut content = match serde_json::from_str::<Value>(&content) {
+        Ok(mut value) => {
+            redact_fields(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or(content)
+        }
+        Err(_) => content,
+    };
+    for secret in secrets.iter().map(|secret| secret.trim()).filter(|secret| !secret.is_empty()) {
+        content = content.replace(secret, REDACTED);
+    }
+    content
+}
+
+/// Keeps the newest [`MAX_DUMPS`] dumps that are less than [`MAX_AGE`] old.
+fn prune(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut dumps = entries
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect::<Vec<_>>();
+    dumps.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    let now = SystemTime::now();
+    for (index, (modified, path)) in dumps.into_iter().enumerate() {
+        let expired = now.duration_since(modified).is_ok_and(|age| age > MAX_AGE);
+        if index >= MAX_DUMPS || expired {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Writes a redacted dump named after `prefix` and returns its path.
+pub fn write(prefix: &str, content: String, secrets: &[&str]) -> Result<PathBuf, String> {
+    let sanitized_prefix = prefix
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric() || *ch == '-' || *ch == '_')
+        .collect::<String>();
+    let timestamp = now_seconds();
+    let file_name = if sanitized_prefix.is_empty() {
+        format!("zotero-debug-{timestamp}.json")
+    } else {
+        format!("{sanitized_prefix}-{timestamp}.json")
+    };
+
+    let dir = std::env::temp_dir().join(DUMP_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create debug dump folder {}: {err}", dir.display()))?;
+    let path = dir.join(file_name);
+    std::fs::write(&path, redact(content, secrets))
+        .map_err(|err| format!("failed to write debug dump {}: {err}", path.display()))?;
+    prune(&dir);
+    Ok(path)
+}
@@ -0,0 +1,215 @@
+//! Diagnostics bundle for bug reports: one zip with the redacted settings,
+//! recent logs, sync history, environment details and the Zotero schema
+//! version.
+
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::io::Write;
+
+use crate::report::{civil_from_days, now_seconds};
+
+pub const REDACTED: &str = "<redacted>";
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Minimal zip writer: deflated entries, no zip64, so it only suits small
+/// archives like this one.
+pub struct ZipArchive {
+    buffer: Vec<u8>,
+    entries: Vec<ZipEntry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+/// UTF-8 file names (general purpose flag bit 11).
+const UTF8_FLAG: u16 = 0x0800;
+const DEFLATE: u16 = 8;
+const ZIP_VERSION: u16 = 20;
+
+impl ZipArchive {
+    pub fn new() -> Self {
+        let now = now_seconds();
+        let (year, month, day) = civil_from_days(now.div_euclid(86_400));
+        let seconds = now.rem_euclid(86_400);
+        let dos_date = ((year.clamp(1980, 2107) - 1980) << 9 | month << 5 | day) as u16;
+        let dos_time = ((seconds / 3_600) << 11 | (seconds % 3_600 / 60) << 5 | ((seconds % 60) / 2)) as u16;
+
+        Self {
+            buffer: Vec::new(),
+            entries: Vec::new(),
+            dos_time,
+            dos_date,
+        }
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .map_err(|err| format!("failed to compress {name}: {err}"))?;
+        let compressed = encoder
+            .finish()
+            .map_err(|err| format!("failed to compress {name}: {err}"))?;
+
+        let entry = ZipEntry {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            compressed_size: compressed.len() as u32,
+            size: data.len() as u32,
+            offset: self.buffer.len() as u32,
+        };
+        self.put_u32(0x0403_4b50);
+        self.put_u16(ZIP_VERSION);
+        self.put_u16(UTF8_FLAG);
+        self.put_u16(DEFLATE);
+        self.put_u16(self.dos_time);
+        self.put_u16(self.dos_date);
+        self.put_u32(entry.crc);
+        self.put_u32(entry.compressed_size);
+        self.put_u32(entry.size);
+        self.put_u16(name.len() as u16);
+        self.put_u16(0);
+        self.buffer.extend_from_slice(name.as_bytes());
+        self.buffer.extend_from_slice(&compressed);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the archive bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.buffer.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.put_u32(0x0201_4b50);
+            self.put_u16(ZIP_VERSION);
+            self.put_u16(ZIP_VERSION);
+            self.put_u16(UTF8_FLAG);
+            self.put_u16(DEFLATE);
+            self.put_u16(self.dos_time);
+            self.put_u16(self.dos_date);
+            self.put_u32(entry.crc);
+            self.put_u32(entry.compressed_size);
+            self.put_u32(entry.size);
+            self.put_u16(entry.name.len() as u16);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u32(0);
+            self.put_u32(entry.offset);
+            self.buffer.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.buffer.len() as u32 - directory_offset;
+
+        self.put_u32(0x0605_4b50);
+        self.put_u16(0);
+        self.put_u16(0);
+        self.put_u16(entries.len() as u16);
+        self.put_u16(entries.len() as u16);
+        self.put_u32(directory_size);
+        self.put_u32(directory_offset);
+        self.put_u16(0);
+        self.buffer
+    }
+}
+
+/// Field names, lowercased without separators, whose values are secrets;
+/// matched as suffixes so `zoteroApiKey` and `Zotero-API-Key` are covered.
+const SECRET_SUFFIXES: [&str; 5] = ["apikey", "authorization", "password", "secret", "token"];
+
+fn is_secret_field(name: &str) -> bool {
+    let normalized = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    SECRET_SUFFIXES.iter().any(|suffix| normalized.ends_with(suffix))
+}
+
+/// Calls `on_secret` with every non-empty string under a secret field name.
+fn visit_secrets(value: &mut Value, on_secret: &mut dyn FnMut(&mut Value)) {
+    match value {
+        Value::Object(map) => {
+            for (name, field) in map.iter_mut() {
+                if is_secret_field(name) && field.as_str().is_some_and(|text| !text.trim().is_empty()) {
+                    on_secret(field);
+                } else {
+                    visit_secrets(field, on_secret);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| visit_secrets(item, on_secret)),
+        _ => {}
+    }
+}
+
+/// Replaces the value of every secret field, at any depth.
+pub fn redact_fields(value: &mut Value) {
+    visit_secrets(value, &mut |field| *field = Value::String(REDACTED.to_string()));
+}
+
+/// The values of every secret field, e.g. to scrub them from free text.
+pub fn secret_values(value: &Value) -> Vec<String> {
+    let mut secrets = Vec::new();
+    visit_secrets(&mut value.clone(), &mut |field| {
+        secrets.extend(field.as_str().map(|secret| secret.trim().to_string()));
+    });
+    secrets
+}
+
+/// Settings as JSON with the API key, tokens and passwords replaced.
+pub fn redacted_settings(settings: &Value) -> Value {
+    let mut settings = settings.clone();
+    redact_fields(&mut settings);
+    settings
+}
+
+/// Every row of zotero.sqlite's `version` table, e.g. `userdata` and
+/// `system` schema versions.
+pub fn zotero_schema_versions(conn: &Connection) -> Result<Value, String> {
+    let mut stmt = conn
+        .prepare("SELECT schema, version FROM version ORDER BY schema")
+        .map_err(|err| format!("failed to prepare Zotero schema query: {err}"))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|err| format!("failed to execute Zotero schema query: {err}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to read Zotero schema row: {err}"))?;
+
+    Ok(Value::Object(
+        rows.into_iter()
+            .map(|(schema, version)| (schema, json!(version)))
+            .collect(),
+    ))
+}
+
+/// App, platform and path details.
+pub fn environment(extra: Value) -> Value {
+    let mut environment = json!({
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "createdAt": now_seconds(),
+    });
+    if let (Some(environment), Value::Object(extra)) = (environment.as_object_mut(), extra) {
+        environment.extend(extra);
+    }
+    environment
+}
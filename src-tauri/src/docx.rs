@@ -0,0 +1,641 @@
+//! Word (.docx) output of rendered notes, for readers who don't use a
+//! Markdown editor. The Markdown is converted block by block: headings,
+//! paragraphs, quotes and callouts, lists, tables, code blocks and rules,
+//! with bold, italic, highlight, strikethrough, code and links inside them.
+//! Images are embedded: annotation images from memory, anything else read
+//! relative to the note. Frontmatter, HTML tags and block IDs are dropped.
+
+use image::ImageFormat;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::diagnostics::ZipArchive;
+use crate::export::NoteImage;
+
+/// Widest an image may be: 6 inches, in EMU.
+const MAX_IMAGE_WIDTH: u64 = 6 * 914_400;
+/// EMU per pixel at 96 DPI.
+const EMU_PER_PIXEL: u64 = 9_525;
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Default Extension="png" ContentType="image/png"/><Default Extension="jpeg" ContentType="image/jpeg"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/><Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/></Types>"#;
+
+const PACKAGE_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Calibri" w:hAnsi="Calibri" w:eastAsia="Calibri" w:cs="Calibri"/><w:sz w:val="22"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="120" w:line="264" w:lineRule="auto"/></w:pPr></w:pPrDefault></w:docDefaults><w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/></w:style><w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="360" w:after="120"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:b/><w:sz w:val="36"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="300" w:after="120"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:b/><w:sz w:val="30"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading3"><w:name w:val="heading 3"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="80"/><w:outlineLvl w:val="2"/></w:pPr><w:rPr><w:b/><w:sz w:val="26"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading4"><w:name w:val="heading 4"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="3"/></w:pPr><w:rPr><w:b/><w:i/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading5"><w:name w:val="heading 5"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="4"/></w:pPr><w:rPr><w:b/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Heading6"><w:name w:val="heading 6"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:pPr><w:keepNext/><w:outlineLvl w:val="5"/></w:pPr><w:rPr><w:i/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Quote"><w:name w:val="Quote"/><w:basedOn w:val="Normal"/><w:pPr><w:pBdr><w:left w:val="single" w:sz="18" w:space="8" w:color="A0A0A0"/></w:pBdr><w:ind w:left="360"/></w:pPr><w:rPr><w:color w:val="404040"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="Code"><w:name w:val="Code"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="0"/><w:shd w:val="clear" w:color="auto" w:fill="F2F2F2"/></w:pPr><w:rPr><w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/><w:sz w:val="20"/></w:rPr></w:style><w:style w:type="paragraph" w:styleId="ListParagraph"><w:name w:val="List Paragraph"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="40"/></w:pPr></w:style><w:style w:type="character" w:styleId="Hyperlink"><w:name w:val="Hyperlink"/><w:rPr><w:color w:val="0563C1"/><w:u w:val="single"/></w:rPr></w:style></w:styles>"#;
+
+const DOCUMENT_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture"><w:body>"#;
+
+const DOCUMENT_END: &str = r#"<w:sectPr><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="708" w:footer="708" w:gutter="0"/></w:sectPr></w:body></w:document>"#;
+
+/// A rendered note to convert.
+pub struct DocxNote<'a> {
+    pub markdown: &'a str,
+    /// Folder that relative image links are resolved against.
+    pub base_dir: &'a Path,
+    /// Annotation images kept in memory, matched by their link.
+    pub images: &'a [NoteImage],
+}
+
+#[derive(Clone, Copy, Default)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+    highlight: bool,
+    strike: bool,
+    code: bool,
+    link: bool,
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Control characters are not allowed in XML 1.0.
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Decodes `%XX` escapes in a Markdown link target.
+fn percent_decode(target: &str) -> String {
+    let bytes = target.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn is_image_target(target: &str) -> bool {
+    Path::new(target)
+        .extension()
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Drops a trailing `^block-id`.
+fn strip_block_id(line: &str) -> &str {
+    let trimmed = line.trim_end();
+    match trimmed.rsplit_once(' ') {
+        Some((rest, id))
+            if id.len() > 1
+                && id.starts_with('^')
+                && id[1..].chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-') =>
+        {
+            rest
+        }
+        _ => trimmed,
+    }
+}
+
+/// `- item`, `- [ ] task` or `1. item`: the marker to show and the text.
+fn list_item(trimmed: &str) -> Option<(String, &str)> {
+    for (marker, shown) in [("- [ ] ", "☐"), ("- [x] ", "☑"), ("- ", "•"), ("* ", "•"), ("+ ", "•")] {
+        if let Some(text) = trimmed.strip_prefix(marker) {
+            return Some((shown.to_string(), text));
+        }
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let text = trimmed[digits..].strip_prefix(". ").filter(|_| digits > 0)?;
+    Some((trimmed[..=digits].to_string(), text))
+}
+
+fn is_rule(trimmed: &str) -> bool {
+    let compact = trimmed.chars().filter(|ch| !ch.is_whitespace()).collect::<String>();
+    compact.len() >= 3 && ['-', '*', '_'].iter().any(|mark| compact.chars().all(|ch| ch == *mark))
+}
+
+struct Document<'a> {
+    body: String,
+    /// `(id, type, target, external)` of document relationships.
+    relationships: Vec<(String, &'static str, String, bool)>,
+    media: Vec<(String, Vec<u8>)>,
+    drawings: usize,
+    /// Images and link base of the note being converted.
+    images: &'a [NoteImage],
+    base_dir: &'a Path,
+}
+
+impl Document<'_> {
+    fn relationship(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        let id = format!("rId{}", self.relationships.len() + 2);
+        self.relationships.push((id.clone(), kind, target, external));
+        id
+    }
+
+    fn image_bytes(&self, target: &str) -> Option<Vec<u8>> {
+        let target = percent_decode(target.trim());
+        let in_memory = self.images.iter().find(|image| {
+            image.link == target || Path::new(&image.link).file_name() == Path::new(&target).file_name()
+        });
+        if let Some(image) = in_memory {
+            return Some(image.bytes.clone());
+        }
+        let path = Path::new(&target);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.base_dir.join(path)
+        };
+        std::fs::read(path).ok()
+    }
+
+    /// An inline picture run, or `None` when the image can't be read.
+    fn image_run(&mut self, target: &str, name: &str) -> Option<String> {
+        let bytes = self.image_bytes(target)?;
+        let decoded = image::load_from_memory(&bytes).ok()?;
+        let (extension, bytes) = match image::guess_format(&bytes).ok()? {
+            ImageFormat::Png => ("png", bytes),
+            ImageFormat::Jpeg => ("jpeg", bytes),
+            // Word doesn't read WebP; everything else becomes PNG too.
+            _ => {
+                let mut png = Cursor::new(Vec::new());
+                decoded.write_to(&mut png, ImageFormat::Png).ok()?;
+                ("png", png.into_inner())
+            }
+        };
+        let file_name = format!("image{}.{extension}", self.media.len() + 1);
+        self.media.push((file_name.clone(), bytes));
+        let id = self.relationship(
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image",
+            format!("media/{file_name}"),
+            false,
+        );
+
+        let width = u64::from(decoded.width()).max(1) * EMU_PER_PIXEL;
+        let height = u64::from(decoded.height()).max(1) * EMU_PER_PIXEL;
+        let (cx, cy) = if width > MAX_IMAGE_WIDTH {
+            (MAX_IMAGE_WIDTH, height * MAX_IMAGE_WIDTH / width)
+        } else {
+            (width, height)
+        };
+        self.drawings += 1;
+        let n = self.drawings;
+        let name = escape_xml(name);
+        Some(format!(
+            r#"<w:r><w:drawing><wp:inline distT="0" distB="0" distL="0" distR="0"><wp:extent cx="{cx}" cy="{cy}"/><wp:docPr id="{n}" name="Picture {n}" descr="{name}"/><a:graphic><a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture"><pic:pic><pic:nvPicPr><pic:cNvPr id="{n}" name="{name}"/><pic:cNvPicPr/></pic:nvPicPr><pic:blipFill><a:blip r:embed="{id}"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill><pic:spPr><a:xfrm><a:off x="0" y="0"/><a:ext cx="{cx}" cy="{cy}"/></a:xfrm><a:prstGeom prst="rect"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing></w:r>"#
+        ))
+    }
+
+    fn image_or_placeholder(&mut self, target: &str, name: &str) -> String {
+        self.image_run(target, name).unwrap_or_else(|| {
+            let style = RunStyle {
+                italic: true,
+                ..RunStyle::default()
+            };
+            text_run(&format!("[image missing: {target}]"), style)
+        })
+    }
+
+    /// Converts the inline Markdown of `text` to runs.
+    fn runs(&mut self, text: &str, base: RunStyle) -> String {
+        let mut out = String::new();
+        let mut style = base;
+        let mut pending = String::new();
+        let chars = text.char_indices().collect::<Vec<_>>();
+        let mut index = 0;
+        let flush = |pending: &mut String, out: &mut String, style: RunStyle| {
+            if !pending.is_empty() {
+                out.push_str(&text_run(pending, style));
+                pending.clear();
+            }
+        };
+
+        while index < chars.len() {
+            let (at, ch) = chars[index];
+            let rest = &text[at..];
+
+            if ch == '\\' && index + 1 < chars.len() && chars[index + 1].1.is_ascii_punctuation() {
+                pending.push(chars[index + 1].1);
+                index += 2;
+                continue;
+            }
+            if ch == '`' {
+                if let Some(end) = rest[1..].find('`') {
+                    flush(&mut pending, &mut out, style);
+                    let code = RunStyle { code: true, ..style };
+                    out.push_str(&text_run(&rest[1..1 + end], code));
+                    index += rest[..end + 2].chars().count();
+                    continue;
+                }
+            }
+            // `![[image]]`, `[[image]]` and `[[note|alias]]`.
+            let opener = ["![[", "[["].into_iter().find(|opener| rest.starts_with(opener)).map_or(0, str::len);
+            if let Some((inner, _)) = rest[opener..].split_once("]]").filter(|_| opener > 0) {
+                flush(&mut pending, &mut out, style);
+                let (target, alias) = inner.split_once('|').unwrap_or((inner, ""));
+                if is_image_target(target) {
+                    out.push_str(&self.image_or_placeholder(target, alias));
+                } else {
+                    let label = if alias.is_empty() { target.split('#').next().unwrap_or(target) } else { alias };
+                    out.push_str(&text_run(label, style));
+                }
+                index += rest[..opener + inner.len() + 2].chars().count();
+                continue;
+            }
+            // `![alt](src)` and `[text](url)`.
+            let is_image = rest.starts_with("![");
+            if is_image || ch == '[' {
+                let label_start = if is_image { 2 } else { 1 };
+                let parsed = rest[label_start..].split_once("](").and_then(|(label, after)| {
+                    let (destination, _) = after.split_once(')')?;
+                    (!label.contains(']')).then_some((label, destination))
+                });
+                if let Some((label, destination)) = parsed {
+                    flush(&mut pending, &mut out, style);
+                    let url = destination
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .trim_matches(['<', '>']);
+                    if is_image && !url.contains("://") {
+                        out.push_str(&self.image_or_placeholder(url, label));
+                    } else if !is_image && url.contains(':') && !url.starts_with('#') {
+                        let id = self.relationship(
+                            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink",
+                            url.to_string(),
+                            true,
+                        );
+                        let label = self.runs(label, RunStyle { link: true, ..style });
+                        out.push_str(&format!(r#"<w:hyperlink r:id="{id}">{label}</w:hyperlink>"#));
+                    } else {
+                        let runs = self.runs(label, style);
+                        out.push_str(&runs);
+                    }
+                    let consumed = label_start + label.len() + 2 + destination.len() + 1;
+                    index += rest[..consumed].chars().count();
+                    continue;
+                }
+            }
+            // HTML tags: `<br>` breaks the line, others are dropped.
+            if ch == '<' && rest[1..].starts_with(|next: char| next.is_ascii_alphabetic() || next == '/' || next == '!') {
+                if let Some(end) = rest.find('>') {
+                    flush(&mut pending, &mut out, style);
+                    if rest[..end].to_ascii_lowercase().starts_with("<br") {
+                        out.push_str("<w:r><w:br/></w:r>");
+                    }
+                    index += rest[..=end].chars().count();
+                    continue;
+                }
+            }
+
+            let toggle = [("**", 0), ("__", 0), ("==", 1), ("~~", 2)]
+                .into_iter()
+                .find(|(marker, _)| rest.starts_with(marker));
+            if let Some((_, which)) = toggle {
+                flush(&mut pending, &mut out, style);
+                match which {
+                    0 => style.bold = !style.bold,
+                    1 => style.highlight = !style.highlight,
+                    _ => style.strike = !style.strike,
+                }
+                index += 2;
+                continue;
+            }
+            let previous = index.checked_sub(1).map(|previous| chars[previous].1);
+            let next = chars.get(index + 1).map(|(_, next)| *next);
+            let opens = !style.italic && next.is_some_and(|next| !next.is_whitespace());
+            let closes = style.italic && previous.is_some_and(|previous| !previous.is_whitespace());
+            // `_` only marks emphasis at word edges, so snake_case stays intact.
+            let word_edge = ch == '*'
+                || (opens && !previous.is_some_and(char::is_alphanumeric))
+                || (closes && !next.is_some_and(char::is_alphanumeric));
+            if (ch == '*' || ch == '_') && (opens || closes) && word_edge {
+                flush(&mut pending, &mut out, style);
+                style.italic = !style.italic;
+                index += 1;
+                continue;
+            }
+
+            pending.push(ch);
+            index += 1;
+        }
+        flush(&mut pending, &mut out, style);
+        out
+    }
+
+    fn paragraph(&mut self, properties: &str, lines: &[&str], base: RunStyle) {
+        let mut runs = String::new();
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 {
+                runs.push_str("<w:r><w:br/></w:r>");
+            }
+            runs.push_str(&self.runs(strip_block_id(line), base));
+        }
+        self.body.push_str(&format!("<w:p><w:pPr>{properties}</w:pPr>{runs}</w:p>"));
+    }
+
+    fn table(&mut self, rows: &[&str]) {
+        let cells = |row: &str| {
+            row.trim()
+                .trim_start_matches('|')
+                .trim_end_matches('|')
+                .split('|')
+                .map(|cell| cell.trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        let border = r#"w:val="single" w:sz="4" w:space="0" w:color="A0A0A0""#;
+        let mut xml = format!(
+            r#"<w:tbl><w:tblPr><w:tblW w:w="0" w:type="auto"/><w:tblBorders><w:top {border}/><w:left {border}/><w:bottom {border}/><w:right {border}/><w:insideH {border}/><w:insideV {border}/></w:tblBorders></w:tblPr>"#
+        );
+        for (index, row) in rows.iter().enumerate() {
+            let is_separator = row.chars().all(|ch| matches!(ch, '|' | '-' | ':' | ' '));
+            if is_separator {
+                continue;
+            }
+            let header = RunStyle {
+                bold: index == 0,
+                ..RunStyle::default()
+            };
+            xml.push_str("<w:tr>");
+            for cell in cells(row) {
+                let runs = self.runs(&cell, header);
+                xml.push_str(&format!(r#"<w:tc><w:p><w:pPr><w:spacing w:after="0"/></w:pPr>{runs}</w:p></w:tc>"#));
+            }
+            xml.push_str("</w:tr>");
+        }
+        xml.push_str("</w:tbl><w:p/>");
+        self.body.push_str(&xml);
+    }
+
+    /// Quote lines, with nested `>` removed and a callout's `[!type]` marker
+    /// turned into a bold title.
+    fn quote(&mut self, lines: &[&str]) {
+        let mut paragraph = Vec::<String>::new();
+        let mut paragraphs = Vec::<Vec<String>>::new();
+        for line in lines {
+            let mut content = *line;
+            while let Some(rest) = content.trim_start().strip_prefix('>') {
+                content = rest.strip_prefix(' ').unwrap_or(rest);
+            }
+            if content.trim().is_empty() {
+                paragraphs.extend((!paragraph.is_empty()).then(|| std::mem::take(&mut paragraph)));
+                continue;
+            }
+            if let Some(callout) = content.trim_start().strip_prefix("[!") {
+                if let Some((kind, title)) = callout.split_once(']') {
+                    let title = title.trim_start_matches(['-', '+']).trim();
+                    let title = if title.is_empty() { kind.to_string() } else { title.to_string() };
+                    paragraph.push(format!("**{title}**"));
+                    continue;
+                }
+            }
+            paragraph.push(content.to_string());
+        }
+        paragraphs.extend((!paragraph.is_empty()).then_some(paragraph));
+        for paragraph in paragraphs {
+            let lines = paragraph.iter().map(String::as_str).collect::<Vec<_>>();
+            self.paragraph(r#"<w:pStyle w:val="Quote"/>"#, &lines, RunStyle::default());
+        }
+    }
+
+    fn convert(&mut self, markdown: &str) {
+        let mut lines = markdown.lines().peekable();
+        if lines.peek().is_some_and(|line| line.trim_end() == "---") {
+            lines.next();
+            for line in lines.by_ref() {
+                if matches!(line.trim_end(), "---" | "...") {
+                    break;
+                }
+            }
+        }
+        let lines = lines.collect::<Vec<_>>();
+
+        let mut index = 0;
+        while index < lines.len() {
+            let line = lines[index];
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if trimmed.is_empty() {
+                index += 1;
+                continue;
+            }
+            if let Some(fence) = ["```", "~~~"].into_iter().find(|fence| trimmed.starts_with(fence)) {
+                index += 1;
+                while index < lines.len() && !lines[index].trim_start().starts_with(fence) {
+                    let code = escape_xml(lines[index]);
+                    self.body.push_str(&format!(
+                        r#"<w:p><w:pPr><w:pStyle w:val="Code"/></w:pPr><w:r><w:t xml:space="preserve">{code}</w:t></w:r></w:p>"#
+                    ));
+                    index += 1;
+                }
+                index += 1;
+                continue;
+            }
+            let hashes = trimmed.chars().take_while(|ch| *ch == '#').count();
+            if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+                let properties = format!(r#"<w:pStyle w:val="Heading{hashes}"/>"#);
+                self.paragraph(&properties, &[trimmed[hashes..].trim()], RunStyle::default());
+                index += 1;
+                continue;
+            }
+            if is_rule(trimmed) {
+                self.body.push_str(
+                    r#"<w:p><w:pPr><w:pBdr><w:bottom w:val="single" w:sz="6" w:space="1" w:color="A0A0A0"/></w:pBdr></w:pPr></w:p>"#,
+                );
+                index += 1;
+                continue;
+            }
+            if trimmed.starts_with('>') {
+                let start = index;
+                while index < lines.len() && lines[index].trim_start().starts_with('>') {
+                    index += 1;
+                }
+                self.quote(&lines[start..index]);
+                continue;
+            }
+            if trimmed.starts_with('|') {
+                let start = index;
+                while index < lines.len() && lines[index].trim_start().starts_with('|') {
+                    index += 1;
+                }
+                self.table(&lines[start..index]);
+                continue;
+            }
+
+            if let Some((marker, text)) = list_item(trimmed) {
+                let left = 360 * (indent / 2 + 1);
+                let properties =
+                    format!(r#"<w:pStyle w:val="ListParagraph"/><w:ind w:left="{left}" w:hanging="360"/>"#);
+                let runs = self.runs(strip_block_id(text), RunStyle::default());
+                self.body.push_str(&format!(
+                    r#"<w:p><w:pPr>{properties}</w:pPr>{}<w:r><w:tab/></w:r>{runs}</w:p>"#,
+                    text_run(&marker, RunStyle::default())
+                ));
+                index += 1;
+                continue;
+            }
+
+            // A paragraph runs to the next blank line or block.
+            let start = index;
+            index += 1;
+            while index < lines.len() {
+                let next = lines[index].trim_start();
+                let starts_block = next.is_empty()
+                    || next.starts_with(['#', '>', '|'])
+                    || next.starts_with("```")
+                    || next.starts_with("~~~")
+                    || list_item(next).is_some();
+                if starts_block {
+                    break;
+                }
+                index += 1;
+            }
+            self.paragraph("", &lines[start..index], RunStyle::default());
+        }
+    }
+}
+
+fn text_run(text: &str, style: RunStyle) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut properties = String::new();
+    if style.link {
+        properties.push_str(r#"<w:rStyle w:val="Hyperlink"/>"#);
+    }
+    if style.code {
+        properties.push_str(r#"<w:rFonts w:ascii="Consolas" w:hAnsi="Consolas" w:cs="Consolas"/>"#);
+    }
+    if style.bold {
+        properties.push_str("<w:b/>");
+    }
+    if style.italic {
+        properties.push_str("<w:i/>");
+    }
+    if style.strike {
+        properties.push_str("<w:strike/>");
+    }
+    if style.highlight {
+        properties.push_str(r#"<w:highlight w:val="yellow"/>"#);
+    }
+    if style.code {
+        properties.push_str(r#"<w:shd w:val="clear" w:color="auto" w:fill="F2F2F2"/>"#);
+    }
+    let properties = if properties.is_empty() {
+        String::new()
+    } else {
+        format!("<w:rPr>{properties}</w:rPr>")
+    };
+    format!(r#"<w:r>{properties}<w:t xml:space="preserve">{}</w:t></w:r>"#, escape_xml(text))
+}
+
+/// Builds a .docx file from `notes`, each starting on a new page.
+pub fn notes_to_docx(notes: &[DocxNote<'_>]) -> Result<Vec<u8>, String> {
+    let mut document = Document {
+        body: String::new(),
+        relationships: Vec::new(),
+        media: Vec::new(),
+        drawings: 0,
+        images: &[],
+        base_dir: Path::new(""),
+    };
+    for (index, note) in notes.iter().enumerate() {
+        if index > 0 {
+            document.body.push_str(r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#);
+        }
+        document.images = note.images;
+        document.base_dir = note.base_dir;
+        document.convert(note.markdown);
+    }
+    let Document {
+        body,
+        relationships,
+        media,
+        ..
+    } = document;
+
+    let mut rels = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+    );
+    for (id, kind, target, external) in &relationships {
+        let mode = if *external { r#" TargetMode="External""# } else { "" };
+        rels.push_str(&format!(
+            r#"<Relationship Id="{id}" Type="{kind}" Target="{}"{mode}/>"#,
+            escape_xml(target)
+        ));
+    }
+    rels.push_str("</Relationships>");
+
+    let mut archive = ZipArchive::new();
+    archive.add("[Content_Types].xml", CONTENT_TYPES.as_bytes())?;
+    archive.add("_rels/.rels", PACKAGE_RELS.as_bytes())?;
+    archive.add("word/document.xml", format!("{DOCUMENT_START}{body}{DOCUMENT_END}").as_bytes())?;
+    archive.add("word/styles.xml", STYLES.as_bytes())?;
+    archive.add("word/_rels/document.xml.rels", rels.as_bytes())?;
+    for (name, bytes) in &media {
+        archive.add(&format!("word/media/{name}"), bytes)?;
+    }
+    Ok(archive.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_items_keep_their_markers() {
+        assert_eq!(list_item("- item"), Some(("•".to_string(), "item")));
+        assert_eq!(list_item("- [ ] task"), Some(("☐".to_string(), "task")));
+        assert_eq!(list_item("- [x] done"), Some(("☑".to_string(), "done")));
+        assert_eq!(list_item("12. twelfth"), Some(("12.".to_string(), "twelfth")));
+        assert_eq!(list_item("2020. was a year"), Some(("2020.".to_string(), "was a year")));
+        assert_eq!(list_item(". nothing"), None);
+        assert_eq!(list_item("-no space"), None);
+    }
+
+    #[test]
+    fn block_ids_are_dropped() {
+        assert_eq!(strip_block_id("Quote ^zot-ab12  "), "Quote");
+        assert_eq!(strip_block_id("x ^"), "x ^");
+        assert_eq!(strip_block_id("a ^b_c"), "a ^b_c");
+        assert_eq!(strip_block_id("^only"), "^only");
+    }
+
+    #[test]
+    fn text_is_escaped_and_decoded() {
+        assert_eq!(escape_xml("a < b & \"c\"\u{1}\t"), "a &lt; b &amp; &quot;c&quot;\t");
+        assert_eq!(percent_decode("My%20Figure%2.png"), "My Figure%2.png");
+        assert!(is_image_target("images/Fig.PNG"));
+        assert!(!is_image_target("paper.pdf"));
+    }
+
+    #[test]
+    fn documents_are_zip_packages() {
+        let note = DocxNote {
+            markdown: "# Title\n\nBody",
+            base_dir: Path::new(""),
+            images: &[],
+        };
+        let bytes = notes_to_docx(&[note]).unwrap();
+        assert!(bytes.starts_with(&[0x50, 0x4b, 0x03, 0x04]));
+        assert!(bytes.windows(17).any(|window| window == b"word/document.xml"));
+    }
+}
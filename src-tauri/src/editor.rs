@@ -0,0 +1,178 @@
+use std::path::Path;
+use std::process::Command;
+
+#[cfg(not(target_os = "windows"))]
+use crate::render::encode_uri_component;
+
+/// Splits a command line into arguments, honouring single and double quotes.
+fn split_command(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut has_token = false;
+
+    for ch in command.chars() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some(_), ch) => current.push(ch),
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                has_token = true;
+            }
+            (None, ch) if ch.is_whitespace() => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            (None, ch) => {
+                current.push(ch);
+                has_token = true;
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    args
+}
+
+/// The platform's default handler for files and URLs. On Windows this
+/// avoids `cmd /C start`, which would split paths and URIs at `&`.
+fn system_opener(target: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(target);
+        command
+    } else if cfg!(target_os = "windows") {
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler").arg(target);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(target);
+        command
+    }
+}
+
+/// Builds the process that opens `path`.
+///
+/// `editor_command` may be empty (system default) or a command line where
+/// `{path}` is replaced by the file path. Without a `{path}` placeholder the
+/// path is appended as the last argument. `obsidian` is handled by the
+/// caller, through [`crate::obsidian::note_uri`].
+fn editor_process(editor_command: &str, path: &Path) -> Result<Command, String> {
+    let path_text = path.to_string_lossy().to_string();
+    let trimmed = editor_command.trim();
+
+    if trimmed.is_empty() {
+        return Ok(system_opener(&path_text));
+    }
+
+    let mut args = split_command(trimmed);
+    if args.is_empty() {
+        return Err("editor command is empty.".to_string());
+    }
+
+    if args.iter().any(|arg| arg.contains("{path}")) {
+        for arg in &mut args {
+            *arg = arg.replace("{path}", &path_text);
+        }
+    } else {
+        args.push(path_text);
+    }
+
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]);
+    Ok(command)
+}
+
+pub fn open_in_editor(editor_command: &str, path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("file does not exist: {}", path.display()));
+    }
+
+    editor_process(editor_command, path)?
+        .spawn()
+        .map(|_| ())
+        .map_err(|err| format!("failed to launch editor for {}: {err}", path.display()))
+}
+
+/// Opens `uri` (e.g. `zotero://select/...`) with its registered handler.
+pub fn open_uri(uri: &str) -> Result<(), String> {
+    system_opener(uri)
+        .spawn()
+        .map(|_| ())
+        .map_err(|err| format!("failed to open {uri}: {err}"))
+}
+
+/// `file://` URI of an absolute path, for the file manager D-Bus interface.
+#[cfg(not(target_os = "windows"))]
+fn file_uri(path: &Path) -> String {
+    let encoded = path
+        .to_string_lossy()
+        .split('/')
+        .map(encode_uri_component)
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("file://{encoded}")
+}
+
+/// Shows `path` in the system file manager with it selected. On Linux the
+/// selection needs a file manager implementing `org.freedesktop.FileManager1`
+/// (Nautilus, Dolphin, Nemo, …); otherwise the containing folder is opened.
+pub fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("file does not exist: {}", path.display()));
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|err| format!("failed to resolve {}: {err}", path.display()))?;
+    let launch_error = |err: std::io::Error| format!("failed to open the file manager for {}: {err}", path.display());
+
+    if cfg!(target_os = "macos") {
+        return Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()
+            .map(|_| ())
+            .map_err(launch_error);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // Explorer only accepts the path quoted right after the comma.
+        let path_text = path.to_string_lossy();
+        let path_text = path_text.strip_prefix(r"\\?\").unwrap_or(&path_text);
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{path_text}\""))
+            .spawn()
+            .map(|_| ())
+            .map_err(launch_error)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", file_uri(&path)))
+            .arg("string:")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if shown {
+            return Ok(());
+        }
+        let folder = path.parent().unwrap_or(&path);
+        system_opener(&folder.to_string_lossy())
+            .spawn()
+            .map(|_| ())
+            .map_err(launch_error)
+    }
+}
@@ -0,0 +1,115 @@
+//! Error type returned by every command: a machine-readable code the
+//! frontend can branch on (to suggest starting Zotero, fixing a path, ...),
+//! the message to show, and optional context such as the path or URL
+//! involved. Internal helpers still use `String` errors; those arrive here
+//! as [`ErrorCode::Other`].
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::validation::FieldError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// zotero.sqlite or better-bibtex.sqlite could not be found.
+    ZoteroNotFound,
+    /// Zotero holds the database and neither the live file nor a snapshot
+    /// could be read.
+    DatabaseLocked,
+    /// Any other SQLite failure.
+    Database,
+    /// A request did not reach the server.
+    Network,
+    /// The Zotero API answered with an error status.
+    ZoteroApi,
+    /// Settings are missing or invalid; `fields` lists them when known.
+    InvalidSettings,
+    /// A write outside the markdown and attachment folders was refused.
+    PathNotAllowed,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Settings rejected by validation, summarized in one message.
+    pub fn invalid_settings(fields: Vec<FieldError>) -> Self {
+        let message = match fields.as_slice() {
+            [only] => only.message.clone(),
+            _ => format!("{} settings need attention.", fields.len()),
+        };
+        Self {
+            fields,
+            ..Self::new(ErrorCode::InvalidSettings, message)
+        }
+    }
+
+    /// Classifies a SQLite error; `what` describes the database, e.g.
+    /// "failed to open Zotero database /path".
+    pub fn sqlite(what: &str, err: &rusqlite::Error) -> Self {
+        let code = match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                ErrorCode::DatabaseLocked
+            }
+            _ => ErrorCode::Database,
+        };
+        Self::new(code, format!("{what}: {err}"))
+    }
+
+    /// Classifies a failed HTTP request to `url`.
+    pub fn request(url: &str, err: &reqwest::Error) -> Self {
+        Self::new(ErrorCode::Network, format!("request to {url} failed: {err}")).with_context(url)
+    }
+
+    pub fn http_status(url: &str, status: reqwest::StatusCode, body: &str) -> Self {
+        let message = if body.trim().is_empty() {
+            format!("Zotero HTTP {status}")
+        } else {
+            format!("Zotero HTTP {status}: {}", body.trim())
+        };
+        Self::new(ErrorCode::ZoteroApi, message).with_context(url)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Other, message)
+    }
+}
+
+/// For helpers that still return `String` errors.
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.message
+    }
+}
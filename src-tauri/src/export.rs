@@ -0,0 +1,550 @@
+//! Backend export pipeline: loads an item from zotero.sqlite, renders its
+//! note, writes the note and images, and records the result in the state
+//! database. Mirrors the frontend export flow so sync can run without the UI.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::citation_keys;
+use crate::collections::{item_collection_keys, resolve_target};
+use crate::conflict::ConflictStrategy;
+use crate::images;
+use crate::joplin::{self, JoplinClient, OutputTarget};
+use crate::managed::merge_with_previous;
+use crate::ocr;
+use crate::render::{render_note, ColorGroup, NoteInput, RenderAnnotation};
+use crate::report::{plan_note, write_note, AnnotationStamp, FileChange, ItemReport, WriteStatus};
+use crate::state_db::{content_hash, AnnotationActivity, ItemExportRecord, StateDb};
+use crate::{
+    ensure_parent, load_annotations, load_child_notes, load_sqlite_item, resolve_annotation_image, write_item_asset,
+    zotero_collection_refs, AppSettings,
+};
+
+const HEX_TO_COLOR: [(&str, &str); 8] = [
+    ("#ffd400", "Yellow"),
+    ("#fff5ad", "Yellow"),
+    ("#5fb236", "Green"),
+    ("#2ea8e5", "Blue"),
+    ("#a28ae5", "Purple"),
+    ("#e56eee", "Pink"),
+    ("#f19837", "Orange"),
+    ("#aaaaaa", "Gray"),
+];
+
+/// Same names as the frontend's `colorNameFromHex`.
+pub fn color_name_from_hex(hex: &str) -> String {
+    let normalized = hex.trim().to_lowercase();
+    if normalized.is_empty() {
+        return "Unknown".to_string();
+    }
+
+    HEX_TO_COLOR
+        .iter()
+        .find(|(candidate, _)| *candidate == normalized)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("Unknown ({normalized})"))
+}
+
+/// Citation key stored on the item itself (`citationKey` field or a
+/// `Citation Key:` line in Extra), as the frontend's `resolveCiteKey` reads it.
+pub fn cite_key_from_item(item: &Value) -> Option<String> {
+    let data = item.get("data")?;
+    let field = |key: &str| {
+        data.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    if let Some(direct) = field("citationKey")
+        .or_else(|| field("citekey"))
+        .or_else(|| field("bibtexKey"))
+    {
+        return Some(direct);
+    }
+
+    let extra = field("extra")?;
+    extra.lines().find_map(|line| {
+        let (label, value) = line.trim().split_once(':')?;
+        let label = label.trim().to_lowercase();
+        let value = value.trim();
+        let known = ["citation key", "citekey", "bbt citation key"];
+        (known.contains(&label.as_str()) && !value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Version and annotation activity of an item as currently stored in Zotero,
+/// or `None` when the item no longer exists.
+pub fn current_activity(
+    zotero: &Connection,
+    item_key: &str,
+) -> Result<Option<(i64, AnnotationActivity)>, String> {
+    let version = zotero
+        .query_row(
+            r#"
+            SELECT version
+            FROM items
+            WHERE key = ?1
+              AND itemID NOT IN (SELECT itemID FROM deletedItems)
+            "#,
+            params![item_key],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|err| format!("failed to read Zotero item version: {err}"))?;
+    let Some(version) = version else {
+        return Ok(None);
+    };
+
+    let (count, latest): (i64, String) = zotero
+        .query_row(
+            r#"
+            SELECT COUNT(anno.itemID), COALESCE(MAX(anno.dateModified), '')
+            FROM items root
+            JOIN itemAttachments iatt ON iatt.parentItemID = root.itemID
+            JOIN itemAnnotations ia ON ia.parentItemID = iatt.itemID
+            JOIN items anno ON anno.itemID = ia.itemID
+            WHERE root.key = ?1
+              AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
+            "#,
+            params![item_key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|err| format!("failed to read Zotero annotation activity: {err}"))?;
+
+    Ok(Some((
+        version,
+        AnnotationActivity {
+            latest_modified: crate::report::parse_zotero_timestamp(&latest).unwrap_or(0),
+            count: usize::try_from(count).unwrap_or(0),
+        },
+    )))
+}
+
+/// Keys of the items tagged `tag` in Zotero (case-insensitive). An empty
+/// tag excludes nothing.
+pub fn excluded_item_keys(zotero: &Connection, tag: &str) -> Result<HashSet<String>, String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let mut stmt = zotero
+        .prepare(
+            r#"
+            SELECT DISTINCT i.key
+            FROM items i
+            JOIN itemTags itag ON itag.itemID = i.itemID
+            JOIN tags t ON t.tagID = itag.tagID
+            WHERE t.name = ?1 COLLATE NOCASE
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero excluded tag query: {err}"))?;
+    let keys = stmt
+        .query_map(params![tag], |row| row.get::<_, String>(0))
+        .map_err(|err| format!("failed to execute Zotero excluded tag query: {err}"))?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|err| format!("failed to read Zotero excluded tag row: {err}"))?;
+    Ok(keys)
+}
+
+/// Splits `item_keys` into the keys to export and the keys skipped because
+/// the item carries the exclusion tag.
+pub fn without_excluded(
+    zotero: &Connection,
+    item_keys: Vec<String>,
+    exclude_tag: &str,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let excluded = excluded_item_keys(zotero, exclude_tag)?;
+    Ok(item_keys.into_iter().partition(|key| !excluded.contains(key)))
+}
+
+/// Exported items whose Zotero version or annotations changed since their
+/// last export. Items deleted from Zotero or tagged `exclude_tag` are
+/// skipped.
+pub fn changed_records(
+    zotero: &Connection,
+    state: &StateDb,
+    exclude_tag: &str,
+) -> Result<Vec<ItemExportRecord>, String> {
+    let excluded = excluded_item_keys(zotero, exclude_tag)?;
+    let mut changed = Vec::new();
+    for record in state.items()? {
+        if excluded.contains(&record.item_key) {
+            continue;
+        }
+        let Some((version, activity)) = current_activity(zotero, &record.item_key)? else {
+            continue;
+        };
+        if version != record.item_version || record.annotations_changed(&activity) {
+            changed.push(record);
+        }
+    }
+    Ok(changed)
+}
+
+pub struct Exporter<'a> {
+    pub settings: &'a AppSettings,
+    pub zotero: &'a Connection,
+    pub citations: Option<&'a Connection>,
+    pub state: &'a StateDb,
+    /// App data directory, home of the image cache.
+    pub data_dir: &'a Path,
+    /// Plan the writes without touching files or the state database.
+    pub dry_run: bool,
+}
+
+/// An item's note rendered as it would be exported, before it is written.
+pub struct RenderedNote {
+    pub cite_key: String,
+    pub markdown_path: PathBuf,
+    /// The note, merged with the managed parts of the existing file.
+    pub markdown: String,
+    item: Value,
+    stamps: Vec<AnnotationStamp>,
+    /// Image files written, or planned in a dry run, while rendering.
+    changes: Vec<FileChange>,
+    /// Images kept in memory instead of written, for Joplin and DOCX.
+    pub images: Vec<NoteImage>,
+}
+
+/// An annotation image that is not written to disk, e.g. for Joplin, where
+/// it becomes a resource.
+pub struct NoteImage {
+    /// The image's link in the rendered note.
+    pub link: String,
+    /// The image, encoded in the configured format.
+    pub bytes: Vec<u8>,
+}
+
+impl Exporter<'_> {
+    /// OCR text of an area annotation's image, when OCR is enabled.
+    fn recognize(&self, annotation_key: &str, bytes: &[u8]) -> Option<String> {
+        if !self.settings.ocr.enabled {
+            return None;
+        }
+        match ocr::recognize(bytes, &self.settings.ocr) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::warn!(annotation = %annotation_key, "OCR failed: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn resolve_cite_key(&self, item: &Value, item_key: &str) -> Result<String, String> {
+        if let Some(cite_key) = cite_key_from_item(item) {
+            return Ok(cite_key);
+        }
+        if let Some(citations) = self.citations {
+            // A dry run leaves the state database alone, saved keys included.
+            let state = (!self.dry_run).then_some(self.state);
+            if let Some(cite_key) = citation_keys::lookup(citations, state, item_key)? {
+                return Ok(cite_key);
+            }
+        }
+        Err("Better BibTeX cite key is missing for this item.".to_string())
+    }
+
+    /// Renders one item's note and writes its images; in a dry run the
+    /// images are only planned.
+    pub fn render_item(&self, item_key: &str) -> Result<RenderedNote, String> {
+        self.render(item_key, self.settings.output_target == OutputTarget::Joplin)
+    }
+
+    /// Renders one item's note with its annotation images in
+    /// [`RenderedNote::images`] instead of on disk, for documents that
+    /// embed them.
+    pub fn render_item_in_memory(&self, item_key: &str) -> Result<RenderedNote, String> {
+        self.render(item_key, true)
+    }
+
+    fn render(&self, item_key: &str, images_in_memory: bool) -> Result<RenderedNote, String> {
+        let item = load_sqlite_item(self.zotero, item_key)?;
+        let cite_key = self.resolve_cite_key(&item, item_key)?;
+        let annotations = load_annotations(self.zotero, item_key)?;
+        let child_notes = load_child_notes(self.zotero, item_key)?
+            .into_iter()
+            .map(|note| note.note)
+            .collect();
+
+        let collections = zotero_collection_refs(Some(self.zotero), &item_collection_keys(&item));
+        let mut target = resolve_target(
+            &self.settings.markdown_dir,
+            &self.settings.collection_overrides,
+            &collections,
+            &item,
+            &cite_key,
+            self.settings.template_settings.clone(),
+        );
+        if let Some(imported) = self.state.imported_note_path(item_key)? {
+            target.markdown_path = imported;
+        }
+        let markdown_path = Path::new(&target.markdown_path);
+        let note_dir = markdown_path.parent().unwrap_or(Path::new(""));
+
+        let mut changes = Vec::<FileChange>::new();
+        let mut note_images = Vec::<NoteImage>::new();
+        let mut groups = Vec::<ColorGroup>::new();
+        let mut image_counter = 0;
+        for annotation in &annotations {
+            let mut render = RenderAnnotation {
+                key: annotation.key.clone(),
+                text: annotation.text.clone(),
+                comment: annotation.comment.clone(),
+                page_label: annotation.page_label.clone(),
+                image_markdown_path: None,
+                missing_image_message: None,
+                image_text: None,
+                attachment_key: annotation.attachment_key.clone(),
+                attachment_title: annotation.attachment_title.clone(),
+                sort_index: annotation.sort_index,
+                date_added: annotation.date_added.clone(),
+            };
+
+            if annotation.is_image_selection {
+                image_counter += 1;
+                let file_name = format!("@{cite_key}_{image_counter}.png");
+                match resolve_annotation_image(
+                    self.zotero,
+                    &self.settings.image_settings,
+                    self.data_dir,
+                    &annotation.key,
+                    &annotation.date_modified,
+                ) {
+                    Ok(bytes) if images_in_memory => {
+                        // Joplin resources and DOCX files embed the images.
+                        let format = self.settings.image_settings.format;
+                        let link = images::with_format_extension(Path::new(&file_name), format)
+                            .to_string_lossy()
+                            .to_string();
+                        render.image_markdown_path = Some(link.clone());
+                        render.image_text = self.recognize(&annotation.key, &bytes);
+                        note_images.push(NoteImage {
+                            link,
+                            bytes: images::transcode(&bytes, &self.settings.image_settings)?,
+                        });
+                    }
+                    Ok(bytes) => {
+                        let (saved, hash, change) = write_item_asset(
+                            self.settings,
+                            item_key,
+                            &cite_key,
+                            &file_name,
+                            &bytes,
+                            note_dir,
+                            self.dry_run,
+                        )?;
+                        changes.extend(change);
+                        if !self.dry_run {
+                            self.state
+                                .record_image(item_key, &annotation.key, &saved.path, &hash)?;
+                        }
+                        render.image_markdown_path = Some(saved.link);
+                        render.image_text = self.recognize(&annotation.key, &bytes);
+                    }
+                    Err(err) => {
+                        tracing::warn!(annotation = %annotation.key, "image unavailable: {err}");
+                        render.missing_image_message = Some(format!(
+                            "@{cite_key}: selected-area image missing for annotation {}.",
+                            annotation.key
+                        ));
+                    }
+                }
+            }
+
+            let color_name = color_name_from_hex(&annotation.color_hex);
+            match groups.iter_mut().find(|group| group.color_name == color_name) {
+                Some(group) => group.annotations.push(render),
+                None => groups.push(ColorGroup {
+                    color_name,
+                    annotations: vec![render],
+                }),
+            }
+        }
+
+        let input = NoteInput {
+            item: item.clone(),
+            cite_key: cite_key.clone(),
+            grouped_annotations: groups,
+            child_notes,
+        };
+        let keys = annotations
+            .iter()
+            .map(|annotation| annotation.key.clone())
+            .collect::<Vec<_>>();
+        let markdown = merge_with_previous(
+            markdown_path,
+            render_note(&input, &target.template_settings),
+            &keys,
+            self.settings.deleted_annotations,
+        );
+
+        let stamps = annotations
+            .iter()
+            .map(|annotation| AnnotationStamp {
+                key: annotation.key.clone(),
+                date_added: annotation.date_added.clone(),
+                date_modified: annotation.date_modified.clone(),
+            })
+            .collect::<Vec<_>>();
+        Ok(RenderedNote {
+            cite_key,
+            markdown_path: markdown_path.to_path_buf(),
+            markdown,
+            item,
+            stamps,
+            changes,
+            images: note_images,
+        })
+    }
+
+    /// Renders and writes one item's note and images.
+    pub fn export_item(&self, item_key: &str) -> Result<ItemReport, String> {
+        let rendered = self.render_item(item_key)?;
+        if self.settings.output_target == OutputTarget::Joplin {
+            return self.send_to_joplin(item_key, rendered);
+        }
+        let RenderedNote {
+            cite_key,
+            markdown_path,
+            markdown,
+            item,
+            stamps,
+            mut changes,
+            images: _,
+        } = rendered;
+        let markdown_path = markdown_path.as_path();
+
+        let recorded = self.state.item(item_key)?;
+        let recorded_hash = recorded.as_ref().map(|record| record.content_hash.as_str());
+        let strategy = self.settings.conflict_strategy;
+        let written = if self.dry_run {
+            plan_note(markdown_path, &markdown, &stamps, recorded_hash, strategy)
+        } else {
+            ensure_parent(markdown_path)?;
+            let written = write_note(markdown_path, &markdown, &stamps, recorded_hash, strategy)?;
+            let item_version = item.get("version").and_then(Value::as_i64).unwrap_or(0);
+            self.state.record_item(
+                item_key,
+                &cite_key,
+                &written.path,
+                item_version,
+                &written.content_hash,
+                &stamps,
+            )?;
+            written
+        };
+        changes.extend(written.changes);
+
+        Ok(ItemReport {
+            item_key: item_key.to_string(),
+            cite_key,
+            path: written.path,
+            status: written.status,
+            annotation_count: stamps.len(),
+            new_annotations: written.new_annotation_keys.len(),
+            conflict_path: written.conflict_path,
+            changes,
+        })
+    }
+
+    /// Creates or updates the item's note in Joplin. A note edited in Joplin
+    /// since the last export is handled per `conflict_strategy`: kept as it
+    /// is, overwritten, or kept with the new version added as a second note.
+    fn send_to_joplin(&self, item_key: &str, rendered: RenderedNote) -> Result<ItemReport, String> {
+        let title = rendered
+            .markdown_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("@{}", rendered.cite_key));
+        let content_hash = content_hash(rendered.markdown.as_bytes());
+        let recorded = self.state.item(item_key)?;
+        let recorded_id = recorded
+            .as_ref()
+            .and_then(|record| joplin::note_id(&record.markdown_path))
+            .map(str::to_string);
+        let report = |id: Option<&str>, status: WriteStatus, conflict_path: Option<String>| ItemReport {
+            item_key: item_key.to_string(),
+            cite_key: rendered.cite_key.clone(),
+            path: id.map(joplin::note_uri).unwrap_or_default(),
+            status,
+            annotation_count: rendered.stamps.len(),
+            new_annotations: 0,
+            conflict_path,
+            changes: Vec::new(),
+        };
+
+        let unchanged = recorded.as_ref().is_some_and(|record| record.content_hash == content_hash);
+        if unchanged && recorded_id.is_some() {
+            return Ok(report(recorded_id.as_deref(), WriteStatus::Unchanged, None));
+        }
+        if self.dry_run {
+            let status = if recorded_id.is_some() { WriteStatus::Updated } else { WriteStatus::Created };
+            return Ok(report(recorded_id.as_deref(), status, None));
+        }
+
+        let client = JoplinClient::new(&self.settings.joplin)?;
+        // A note deleted in Joplin is created again.
+        let remote = match &recorded_id {
+            Some(id) => client.note(id)?.map(|note| (id.clone(), note)),
+            None => None,
+        };
+        let edited = match (&remote, &recorded) {
+            (Some((_, note)), Some(record)) => note.updated_at > record.last_exported_at,
+            _ => false,
+        };
+        let strategy = self.settings.conflict_strategy;
+        if edited && strategy == ConflictStrategy::KeepLocal {
+            return Ok(report(recorded_id.as_deref(), WriteStatus::Conflict, None));
+        }
+
+        let mime = self.settings.image_settings.format.mime_type();
+        let mut body = rendered.markdown.clone();
+        for image in &rendered.images {
+            let resource = client.upload_resource(&image.link, mime, &image.bytes)?;
+            body = joplin::replace_image_link(&body, &image.link, &resource);
+        }
+
+        let (id, status, conflict_path) = match remote {
+            Some((id, _)) if edited && strategy == ConflictStrategy::ConflictCopy => {
+                let notebook = client.notebook_id()?;
+                let copy = client.create_note(&format!("{title} (conflict)"), &body, notebook.as_deref())?;
+                (id, WriteStatus::Conflict, Some(joplin::note_uri(&copy)))
+            }
+            Some((id, note)) => {
+                client.update_note(&id, &title, &body)?;
+                // The images of the previous export were replaced by new resources.
+                let prefix = format!("@{}_", rendered.cite_key);
+                for (resource, resource_title) in &note.resources {
+                    if resource_title.starts_with(&prefix) {
+                        if let Err(err) = client.delete_resource(resource) {
+                            tracing::warn!(item_key, "failed to delete old Joplin resource: {err}");
+                        }
+                    }
+                }
+                (id, WriteStatus::Updated, None)
+            }
+            None => {
+                let notebook = client.notebook_id()?;
+                (client.create_note(&title, &body, notebook.as_deref())?, WriteStatus::Created, None)
+            }
+        };
+
+        if status != WriteStatus::Conflict {
+            let item_version = rendered.item.get("version").and_then(Value::as_i64).unwrap_or(0);
+            self.state.record_item(
+                item_key,
+                &rendered.cite_key,
+                &joplin::note_uri(&id),
+                item_version,
+                &content_hash,
+                &rendered.stamps,
+            )?;
+        }
+        Ok(report(Some(&id), status, conflict_path))
+    }
+}
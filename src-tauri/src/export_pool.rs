@@ -0,0 +1,64 @@
+//! Batch exports spread over a bounded pool of worker threads. Each worker
+//! opens its own read connections and export-state handle, since SQLite
+//! connections cannot be shared between threads, and takes the next item
+//! until none are left. Results come back in the order of the item keys.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use crate::export::Exporter;
+use crate::report::ItemReport;
+
+/// Items exported at once. Renders inside an export are further limited by
+/// the image-render slots, so extra workers only wait on reads and writes.
+pub const EXPORT_WORKERS: usize = 4;
+
+/// Opens one worker's connections, builds an exporter on them and runs the
+/// callback with it.
+pub type OpenWorker<'a> = dyn Fn(&mut dyn FnMut(&Exporter)) -> Result<(), String> + Sync + 'a;
+
+/// Exports `item_keys` on up to [`EXPORT_WORKERS`] threads. `on_done`
+/// receives the number of finished items and the index of the one that just
+/// finished, on the calling thread.
+pub fn export_all(
+    item_keys: &[String],
+    open_worker: &OpenWorker,
+    on_done: &mut dyn FnMut(usize, usize),
+) -> Vec<Result<ItemReport, String>> {
+    let next = AtomicUsize::new(0);
+    let mut results = item_keys.iter().map(|_| None).collect::<Vec<_>>();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..EXPORT_WORKERS.min(item_keys.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                let claim = || {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    item_keys.get(index).map(|key| (index, key))
+                };
+                let opened = open_worker(&mut |exporter| {
+                    while let Some((index, key)) = claim() {
+                        let _ = sender.send((index, exporter.export_item(key)));
+                    }
+                });
+                // Without connections the worker fails what is left, so the
+                // batch still finishes when every worker does.
+                if let Err(err) = opened {
+                    while let Some((index, _)) = claim() {
+                        let _ = sender.send((index, Err(err.clone())));
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (done, (index, result)) in receiver.iter().enumerate() {
+            results[index] = Some(result);
+            on_done(done + 1, index);
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err("export worker stopped before the item was exported".to_string())))
+        .collect()
+}
@@ -0,0 +1,142 @@
+//! Optional Git history of the Markdown folder: after an export that wrote
+//! notes, the files it changed are staged and committed with a message
+//! listing the exported items. Only those files are committed, so other
+//! staged work in the repository is left alone.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::report::{ExportReport, WriteStatus};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct GitSettings {
+    /// Commit the changed notes after each export. The Markdown folder is
+    /// made a repository when it is not inside one.
+    pub auto_commit: bool,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output, String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|err| format!("failed to run git; install Git or turn off auto-commit: {err}"))
+}
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Subject and body of the commit for `report`.
+fn commit_message(report: &ExportReport) -> (String, String) {
+    let written = report
+        .items
+        .iter()
+        .filter(|item| matches!(item.status, WriteStatus::Created | WriteStatus::Updated))
+        .collect::<Vec<_>>();
+    let annotations = written.iter().map(|item| item.annotation_count).sum::<usize>();
+    let subject = format!(
+        "Export {} note{} from Zotero ({annotations} annotation{})",
+        written.len(),
+        if written.len() == 1 { "" } else { "s" },
+        if annotations == 1 { "" } else { "s" },
+    );
+    let body = report
+        .items
+        .iter()
+        .filter(|item| item.status != WriteStatus::Unchanged)
+        .map(|item| {
+            let status = match item.status {
+                WriteStatus::Created => "created",
+                WriteStatus::Updated => "updated",
+                WriteStatus::Conflict => "conflict copy",
+                WriteStatus::Unchanged => "unchanged",
+            };
+            let new = if item.new_annotations > 0 {
+                format!(", {} new", item.new_annotations)
+            } else {
+                String::new()
+            };
+            format!("- @{}: {status}, {} annotations{new}", item.cite_key, item.annotation_count)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (subject, body)
+}
+
+/// Files the export wrote: notes, conflict copies, images and the changelog.
+fn changed_paths(report: &ExportReport, changelog: Option<&str>) -> Vec<PathBuf> {
+    let mut paths = report
+        .items
+        .iter()
+        .filter(|item| item.status != WriteStatus::Unchanged)
+        .flat_map(|item| {
+            std::iter::once(item.path.as_str())
+                .chain(item.conflict_path.as_deref())
+                .chain(item.changes.iter().map(|change| change.path.as_str()))
+        })
+        .chain(changelog)
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Commits the files `report` changed under `markdown_dir`. Returns the
+/// short hash of the new commit, or `None` when there was nothing to commit.
+pub fn commit_report(markdown_dir: &Path, report: &ExportReport, changelog: Option<&str>) -> Result<Option<String>, String> {
+    let paths = changed_paths(report, changelog)
+        .into_iter()
+        .filter(|path| path.starts_with(markdown_dir))
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    if !git(markdown_dir, &["rev-parse", "--is-inside-work-tree"])?.status.success() {
+        git_ok(markdown_dir, &["init"])?;
+        tracing::info!("initialized a Git repository in {}", markdown_dir.display());
+    }
+
+    let paths = paths.iter().map(|path| path.to_string_lossy()).collect::<Vec<_>>();
+    let mut add = vec!["add", "--"];
+    add.extend(paths.iter().map(|path| path.as_ref()));
+    git_ok(markdown_dir, &add)?;
+
+    let mut staged = vec!["diff", "--cached", "--quiet", "--"];
+    staged.extend(paths.iter().map(|path| path.as_ref()));
+    if git(markdown_dir, &staged)?.status.success() {
+        return Ok(None);
+    }
+
+    let (subject, body) = commit_message(report);
+    let has_identity = git(markdown_dir, &["config", "user.email"])?.status.success();
+    let mut commit = Vec::<&str>::new();
+    if !has_identity {
+        // Without a configured identity Git refuses to commit.
+        commit.extend(["-c", "user.name=ZotNotes", "-c", "user.email=zotnotes@localhost"]);
+    }
+    commit.extend(["commit", "--quiet", "-m", &subject]);
+    if !body.is_empty() {
+        commit.extend(["-m", &body]);
+    }
+    commit.push("--");
+    commit.extend(paths.iter().map(|path| path.as_ref()));
+    git_ok(markdown_dir, &commit)?;
+
+    Ok(Some(git_ok(markdown_dir, &["rev-parse", "--short", "HEAD"])?))
+}
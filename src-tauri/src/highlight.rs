@@ -0,0 +1,129 @@
+//! Where a search result matched, so the UI can highlight it. Ranges are
+//! `[start, end)` offsets in UTF-16 code units, the unit JavaScript strings
+//! are indexed in.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    /// Name of the result field, e.g. `title` or `snippet`.
+    pub field: String,
+    pub ranges: Vec<[usize; 2]>,
+}
+
+/// Collects the non-empty ranges of each `(field, text)` pair.
+pub fn field_matches<'a>(
+    fields: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ranges_for: impl Fn(&str) -> Vec<[usize; 2]>,
+) -> Vec<FieldMatch> {
+    fields
+        .into_iter()
+        .filter_map(|(field, text)| {
+            let ranges = ranges_for(text);
+            (!ranges.is_empty()).then(|| FieldMatch {
+                field: field.to_string(),
+                ranges,
+            })
+        })
+        .collect()
+}
+
+/// Ranges of the words of `text` (runs of letters and digits) for which
+/// `is_match` holds on the lowercased word.
+pub fn word_ranges(text: &str, is_match: impl Fn(&str) -> bool) -> Vec<[usize; 2]> {
+    let mut ranges = Vec::new();
+    let mut word = String::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for ch in text.chars().chain(std::iter::once(' ')) {
+        if ch.is_alphanumeric() {
+            if word.is_empty() {
+                start = offset;
+            }
+            word.extend(ch.to_lowercase());
+        } else if !word.is_empty() {
+            if is_match(&word) {
+                ranges.push([start, offset]);
+            }
+            word.clear();
+        }
+        offset += ch.len_utf16();
+    }
+    ranges
+}
+
+/// Ranges where any of `needles` (already lowercased) occurs in `text`,
+/// ignoring case. Overlapping matches are merged.
+pub fn substring_ranges(text: &str, needles: &[String]) -> Vec<[usize; 2]> {
+    // Each lowercased char keeps the span of the char it came from, since
+    // lowercasing can turn one char into several.
+    let mut lowered = Vec::<(char, [usize; 2])>::new();
+    let mut offset = 0;
+    for ch in text.chars() {
+        let span = [offset, offset + ch.len_utf16()];
+        lowered.extend(ch.to_lowercase().map(|lower| (lower, span)));
+        offset = span[1];
+    }
+
+    let mut ranges = Vec::<[usize; 2]>::new();
+    for needle in needles.iter().filter(|needle| !needle.is_empty()) {
+        let needle = needle.chars().collect::<Vec<_>>();
+        for start in 0..lowered.len() {
+            let window = lowered.get(start..start + needle.len());
+            if window.is_some_and(|window| window.iter().map(|(ch, _)| *ch).eq(needle.iter().copied())) {
+                ranges.push([lowered[start].1[0], lowered[start + needle.len() - 1].1[1]]);
+            }
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged = Vec::<[usize; 2]>::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range[0] <= last[1] => last[1] = last[1].max(range[1]),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_ranges_cover_matching_words() {
+        let ranges = word_ranges("Shannon's theory, 1948", |word| word == "shannon" || word == "1948");
+        assert_eq!(ranges, vec![[0, 7], [18, 22]]);
+    }
+
+    #[test]
+    fn ranges_count_utf16_units() {
+        // "𝔸" takes two UTF-16 units, so the word after it starts at 3.
+        assert_eq!(word_ranges("𝔸 Über", |word| word == "über"), vec![[3, 7]]);
+        assert_eq!(substring_ranges("𝔸 Über", &["be".to_string()]), vec![[4, 6]]);
+    }
+
+    #[test]
+    fn substring_ranges_ignore_case_and_merge_overlaps() {
+        let needles = ["ana".to_string(), "nan".to_string()];
+        assert_eq!(substring_ranges("BANANA", &needles), vec![[1, 6]]);
+        assert_eq!(substring_ranges("banana", &["x".to_string()]), Vec::<[usize; 2]>::new());
+    }
+
+    #[test]
+    fn substring_ranges_skip_empty_needles() {
+        assert!(substring_ranges("text", &[String::new()]).is_empty());
+    }
+
+    #[test]
+    fn field_matches_leave_out_fields_without_ranges() {
+        let matches = field_matches([("title", "Deep learning"), ("creators", "Smith")], |text| {
+            word_ranges(text, |word| word == "learning")
+        });
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].field, "title");
+        assert_eq!(matches[0].ranges, vec![[5, 13]]);
+    }
+}
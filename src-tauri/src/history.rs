@@ -0,0 +1,100 @@
+//! Log of sync runs, kept in the state database so users can audit what was
+//! synced and when something went wrong.
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::open_state_db;
+use crate::report::{now_seconds, ExportReport, WriteStatus};
+
+/// What started a sync run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncTrigger {
+    Manual,
+    Scheduled,
+    Startup,
+    Job,
+    /// `zotnotes export` on the command line.
+    Cli,
+}
+
+impl SyncTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Manual => "manual",
+            Self::Scheduled => "scheduled",
+            Self::Startup => "startup",
+            Self::Job => "job",
+            Self::Cli => "cli",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "scheduled" => Self::Scheduled,
+            "startup" => Self::Startup,
+            "job" => Self::Job,
+            "cli" => Self::Cli,
+            _ => Self::Manual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRun {
+    pub id: i64,
+    pub trigger: SyncTrigger,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub conflicts: usize,
+    /// Per-item failures (`<item key>: <error>`).
+    pub failures: Vec<String>,
+    /// Set when the run as a whole failed, e.g. zotero.sqlite was unreadable.
+    pub error: Option<String>,
+}
+
+impl SyncRun {
+    pub fn new(trigger: SyncTrigger, started_at: i64, result: Result<&ExportReport, &String>) -> Self {
+        let mut run = Self {
+            id: 0,
+            trigger,
+            started_at,
+            finished_at: now_seconds(),
+            created: 0,
+            updated: 0,
+            unchanged: 0,
+            conflicts: 0,
+            failures: Vec::new(),
+            error: None,
+        };
+
+        match result {
+            Ok(report) => {
+                for item in &report.items {
+                    match item.status {
+                        WriteStatus::Created => run.created += 1,
+                        WriteStatus::Updated => run.updated += 1,
+                        WriteStatus::Unchanged => run.unchanged += 1,
+                        WriteStatus::Conflict => run.conflicts += 1,
+                    }
+                }
+                run.failures = report.failed.clone();
+            }
+            Err(err) => run.error = Some(err.clone()),
+        }
+        run
+    }
+}
+
+/// Records a finished sync run. Failing to write the log never fails the
+/// sync itself.
+pub fn record(app: &AppHandle, trigger: SyncTrigger, started_at: i64, result: Result<&ExportReport, &String>) {
+    if let Ok(state) = open_state_db(app) {
+        let _ = state.record_sync_run(&SyncRun::new(trigger, started_at, result));
+    }
+}
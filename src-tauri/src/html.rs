@@ -0,0 +1,576 @@
+//! Minimal HTML to Markdown conversion for Zotero notes and rich-text
+//! annotation comments. Zotero's note editor emits a small, well-formed
+//! subset of HTML, so a tolerant tokenizer is enough here.
+
+use std::collections::HashMap;
+
+enum Token {
+    Text(String),
+    Open {
+        name: String,
+        attrs: HashMap<String, String>,
+        self_closing: bool,
+    },
+    Close(String),
+}
+
+fn decode_entities(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let Some(end) = candidate.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &candidate[1..];
+            continue;
+        };
+
+        let entity = &candidate[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse::<u32>().ok()))
+                .and_then(char::from_u32),
+        };
+
+        match replacement {
+            Some(ch) => {
+                decoded.push(ch);
+                rest = &candidate[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &candidate[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+fn parse_attrs(raw: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let chars = raw.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        while idx < chars.len() && (chars[idx].is_whitespace() || chars[idx] == '/') {
+            idx += 1;
+        }
+        let name_start = idx;
+        while idx < chars.len() && !chars[idx].is_whitespace() && chars[idx] != '=' && chars[idx] != '/' {
+            idx += 1;
+        }
+        let name = chars[name_start..idx].iter().collect::<String>().to_lowercase();
+        if name.is_empty() {
+            idx += 1;
+            continue;
+        }
+
+        while idx < chars.len() && chars[idx].is_whitespace() {
+            idx += 1;
+        }
+        let mut value = String::new();
+        if idx < chars.len() && chars[idx] == '=' {
+            idx += 1;
+            while idx < chars.len() && chars[idx].is_whitespace() {
+                idx += 1;
+            }
+            if idx < chars.len() && (chars[idx] == '"' || chars[idx] == '\'') {
+                let quote = chars[idx];
+                idx += 1;
+                let value_start = idx;
+                while idx < chars.len() && chars[idx] != quote {
+                    idx += 1;
+                }
+                value = chars[value_start..idx].iter().collect();
+                idx += 1;
+            } else {
+                let value_start = idx;
+                while idx < chars.len() && !chars[idx].is_whitespace() {
+                    idx += 1;
+                }
+                value = chars[value_start..idx].iter().collect();
+            }
+        }
+
+        attrs.insert(name, decode_entities(&value));
+    }
+
+    attrs
+}
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            tokens.push(Token::Text(decode_entities(rest)));
+            break;
+        };
+        let inner = rest[1..end].trim();
+        rest = &rest[end + 1..];
+
+        if inner.starts_with('!') || inner.starts_with('?') {
+            continue;
+        }
+        if let Some(name) = inner.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_lowercase()));
+            continue;
+        }
+
+        let self_closing = inner.ends_with('/');
+        let inner = inner.trim_end_matches('/');
+        let (name, attrs) = match inner.find(char::is_whitespace) {
+            Some(split) => (&inner[..split], parse_attrs(&inner[split..])),
+            None => (inner, HashMap::new()),
+        };
+        let name = name.to_lowercase();
+
+        // Script/style bodies are never note content.
+        if name == "script" || name == "style" {
+            let closing = format!("</{name}");
+            rest = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .and_then(|pos| rest[pos..].find('>').map(|end| &rest[pos + end + 1..]))
+                .unwrap_or("");
+            continue;
+        }
+
+        tokens.push(Token::Open {
+            name,
+            attrs,
+            self_closing,
+        });
+    }
+
+    tokens
+}
+
+enum Frame {
+    Root,
+    Blockquote,
+    Link(String),
+    ListItem { marker: String },
+    Heading(usize),
+    Pre,
+    Cell,
+}
+
+struct Converter {
+    buffers: Vec<(Frame, String)>,
+    lists: Vec<(bool, usize)>,
+    /// Rows emitted and cells in the current row of the innermost table.
+    table_rows: usize,
+    row_cells: usize,
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div" | "section" | "article" | "header" | "footer" | "ul" | "ol" | "table" | "hr"
+            | "figure" | "figcaption"
+    )
+}
+
+impl Converter {
+    fn new() -> Self {
+        Self {
+            buffers: vec![(Frame::Root, String::new())],
+            lists: Vec::new(),
+            table_rows: 0,
+            row_cells: 0,
+        }
+    }
+
+    fn out(&mut self) -> &mut String {
+        &mut self.buffers.last_mut().expect("root buffer").1
+    }
+
+    fn in_pre(&self) -> bool {
+        self.buffers.iter().any(|(frame, _)| matches!(frame, Frame::Pre))
+    }
+
+    fn block_break(&mut self) {
+        let out = self.out();
+        let trimmed_len = out.trim_end_matches([' ', '\t']).len();
+        out.truncate(trimmed_len);
+        if out.is_empty() || out.ends_with("\n\n") {
+            return;
+        }
+        if out.ends_with('\n') {
+            out.push('\n');
+        } else {
+            out.push_str("\n\n");
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.in_pre() {
+            self.out().push_str(text);
+            return;
+        }
+
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let leading = text.starts_with(char::is_whitespace);
+        let trailing = text.ends_with(char::is_whitespace) && !collapsed.is_empty();
+        let out = self.out();
+        let at_line_start = out.is_empty() || out.ends_with('\n') || out.ends_with(' ');
+
+        if leading && !at_line_start {
+            out.push(' ');
+        }
+        out.push_str(&collapsed);
+        if trailing {
+            out.push(' ');
+        }
+    }
+
+    fn open(&mut self, name: &str, attrs: &HashMap<String, String>) {
+        match name {
+            "br" => {
+                let out = self.out();
+                let trimmed_len = out.trim_end_matches(' ').len();
+                out.truncate(trimmed_len);
+                out.push('\n');
+            }
+            "hr" => {
+                self.block_break();
+                self.out().push_str("---");
+                self.block_break();
+            }
+            "strong" | "b" => self.out().push_str("**"),
+            "em" | "i" => self.out().push('*'),
+            "s" | "del" | "strike" => self.out().push_str("~~"),
+            "sup" => self.out().push_str("<sup>"),
+            "sub" => self.out().push_str("<sub>"),
+            "code" if !self.in_pre() => self.out().push('`'),
+            "img" => {
+                let alt = attrs.get("alt").cloned().unwrap_or_default();
+                if let Some(src) = attrs.get("src").filter(|src| !src.starts_with("data:")) {
+                    self.out().push_str(&format!("![{alt}]({src})"));
+                } else if !alt.is_empty() {
+                    self.push_text(&alt);
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.buffers.push((Frame::Heading(level), String::new()));
+            }
+            "blockquote" => {
+                self.block_break();
+                self.buffers.push((Frame::Blockquote, String::new()));
+            }
+            "pre" => {
+                self.block_break();
+                self.buffers.push((Frame::Pre, String::new()));
+            }
+            "a" => {
+                let href = attrs.get("href").cloned().unwrap_or_default();
+                self.buffers.push((Frame::Link(href), String::new()));
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+                let start = attrs
+                    .get("start")
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .unwrap_or(1);
+                self.lists.push((name == "ol", start));
+            }
+            "li" => {
+                let marker = match self.lists.last_mut() {
+                    Some((true, counter)) => {
+                        let marker = format!("{counter}. ");
+                        *counter += 1;
+                        marker
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.buffers.push((Frame::ListItem { marker }, String::new()));
+            }
+            "table" => {
+                self.block_break();
+                self.table_rows = 0;
+            }
+            "tr" => self.row_cells = 0,
+            "td" | "th" => self.buffers.push((Frame::Cell, String::new())),
+            other if is_block(other) => self.block_break(),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "strong" | "b" => self.out().push_str("**"),
+            "em" | "i" => self.out().push('*'),
+            "s" | "del" | "strike" => self.out().push_str("~~"),
+            "sup" => self.out().push_str("</sup>"),
+            "sub" => self.out().push_str("</sub>"),
+            "code" if !self.in_pre() => self.out().push('`'),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                if let Some((Frame::Heading(level), text)) =
+                    self.pop_frame(|frame| matches!(frame, Frame::Heading(_)))
+                {
+                    let content = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !content.is_empty() {
+                        self.out().push_str(&format!("{} {content}", "#".repeat(level.clamp(1, 6))));
+                    }
+                }
+                self.block_break();
+            }
+            "blockquote" => {
+                if let Some((_, text)) = self.pop_frame(|frame| matches!(frame, Frame::Blockquote)) {
+                    let quoted = text
+                        .trim()
+                        .lines()
+                        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    self.out().push_str(&quoted);
+                }
+                self.block_break();
+            }
+            "pre" => {
+                if let Some((_, text)) = self.pop_frame(|frame| matches!(frame, Frame::Pre)) {
+                    let code = text.trim_matches('\n');
+                    self.out().push_str(&format!("```\n{code}\n```"));
+                }
+                self.block_break();
+            }
+            "a" => {
+                if let Some((Frame::Link(href), text)) = self.pop_frame(|frame| matches!(frame, Frame::Link(_))) {
+                    let label = text.trim();
+                    let rendered = match (label.is_empty(), href.is_empty()) {
+                        (_, true) => label.to_string(),
+                        (true, false) => format!("<{href}>"),
+                        (false, false) => format!("[{label}]({href})"),
+                    };
+                    self.out().push_str(&rendered);
+                }
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+            }
+            "li" => {
+                if let Some((Frame::ListItem { marker }, text)) =
+                    self.pop_frame(|frame| matches!(frame, Frame::ListItem { .. }))
+                {
+                    let indent = " ".repeat(marker.len());
+                    let body = text
+                        .trim()
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .enumerate()
+                        .map(|(index, line)| {
+                            if index == 0 {
+                                format!("{marker}{line}")
+                            } else {
+                                format!("{indent}{line}")
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let out = self.out();
+                    if !out.is_empty() && !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    out.push_str(&body);
+                    out.push('\n');
+                }
+            }
+            "td" | "th" => {
+                if let Some((_, text)) = self.pop_frame(|frame| matches!(frame, Frame::Cell)) {
+                    let cell = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    let out = self.out();
+                    if out.is_empty() || out.ends_with('\n') {
+                        out.push('|');
+                    }
+                    out.push_str(&format!(" {cell} |"));
+                    self.row_cells += 1;
+                }
+            }
+            "tr" if self.row_cells > 0 => {
+                let separator = if self.table_rows == 0 {
+                    format!("\n|{}", " --- |".repeat(self.row_cells))
+                } else {
+                    String::new()
+                };
+                self.out().push_str(&separator);
+                self.out().push('\n');
+                self.table_rows += 1;
+            }
+            other if is_block(other) => self.block_break(),
+            _ => {}
+        }
+    }
+
+    /// Pops the innermost frame matching `predicate`, flattening any frames
+    /// left open above it (unclosed tags) into their parents.
+    fn pop_frame(&mut self, predicate: impl Fn(&Frame) -> bool) -> Option<(Frame, String)> {
+        let position = self.buffers.iter().rposition(|(frame, _)| predicate(frame))?;
+        if position == 0 {
+            return None;
+        }
+        while self.buffers.len() > position + 1 {
+            let (_, text) = self.buffers.pop().expect("frame above target");
+            self.out().push_str(&text);
+        }
+        self.buffers.pop()
+    }
+
+    fn finish(mut self) -> String {
+        while self.buffers.len() > 1 {
+            let (_, text) = self.buffers.pop().expect("non-root frame");
+            self.out().push_str(&text);
+        }
+        let (_, text) = self.buffers.pop().expect("root buffer");
+
+        let mut collapsed = String::with_capacity(text.len());
+        let mut newline_run = 0;
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                newline_run += 1;
+                if newline_run > 1 {
+                    continue;
+                }
+            } else {
+                newline_run = 0;
+            }
+            collapsed.push_str(line);
+            collapsed.push('\n');
+        }
+
+        collapsed.trim().to_string()
+    }
+}
+
+/// Converts an HTML fragment (Zotero note or rich-text comment) to Markdown.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut converter = Converter::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => converter.push_text(&text),
+            Token::Open {
+                name,
+                attrs,
+                self_closing,
+            } => {
+                converter.open(&name, &attrs);
+                if self_closing && !matches!(name.as_str(), "br" | "hr" | "img") {
+                    converter.close(&name);
+                }
+            }
+            Token::Close(name) => converter.close(&name),
+        }
+    }
+
+    converter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(decode_entities("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;"), "a & b <c> \"d\" 'e'");
+        assert_eq!(decode_entities("&#233;t&#xE9; &#X263A;"), "été ☺");
+        assert_eq!(decode_entities("&nbsp;"), " ");
+    }
+
+    #[test]
+    fn unknown_entities_and_bare_ampersands_are_kept() {
+        assert_eq!(decode_entities("&bogus; &#xZZ; &#1114112;"), "&bogus; &#xZZ; &#1114112;");
+        assert_eq!(decode_entities("Smith & Wesson"), "Smith & Wesson");
+        assert_eq!(decode_entities("a&b and a very long run;"), "a&b and a very long run;");
+        assert_eq!(decode_entities("trailing &"), "trailing &");
+    }
+
+    #[test]
+    fn attributes_may_be_quoted_or_bare() {
+        let attrs = parse_attrs(r#" HREF="https://example.org/?a=1&amp;b=2" title='It is' data-x=bare checked/"#);
+        assert_eq!(attrs["href"], "https://example.org/?a=1&b=2");
+        assert_eq!(attrs["title"], "It is");
+        assert_eq!(attrs["data-x"], "bare");
+        assert_eq!(attrs["checked"], "");
+        assert_eq!(parse_attrs(r#"alt = "spaced""#)["alt"], "spaced");
+    }
+
+    #[test]
+    fn scripts_styles_and_comments_are_dropped() {
+        let html = "<p>Keep<!-- hidden --> this</p><script>alert('<p>no</p>')</script>\
+                    <STYLE>p { color: red }</STYLE><p>and this</p><!-- unclosed";
+        assert_eq!(html_to_markdown(html), "Keep this\n\nand this");
+    }
+
+    #[test]
+    fn headings_keep_their_level() {
+        let html = (1..=6).map(|level| format!("<h{level}>Level  {level}</h{level}>")).collect::<String>();
+        assert_eq!(
+            html_to_markdown(&html),
+            "# Level 1\n\n## Level 2\n\n### Level 3\n\n#### Level 4\n\n##### Level 5\n\n###### Level 6"
+        );
+        assert_eq!(html_to_markdown("<h2> </h2><p>Body</p>"), "Body");
+    }
+
+    #[test]
+    fn lists_are_numbered_and_nested() {
+        assert_eq!(html_to_markdown("<ul><li>one</li><li>two</li></ul>"), "- one\n- two");
+        assert_eq!(html_to_markdown("<ol start=\"3\"><li>three</li><li>four</li></ol>"), "3. three\n4. four");
+        assert_eq!(
+            html_to_markdown("<ul><li>outer<ol><li>inner</li></ol></li></ul>"),
+            "- outer\n  1. inner"
+        );
+    }
+
+    #[test]
+    fn links_use_their_label_or_target() {
+        assert_eq!(
+            html_to_markdown(r#"<p>See <a href="https://example.org">the <b>site</b></a>.</p>"#),
+            "See [the **site**](https://example.org)."
+        );
+        assert_eq!(html_to_markdown(r#"<a href="https://example.org"></a>"#), "<https://example.org>");
+        assert_eq!(html_to_markdown("<a>plain</a>"), "plain");
+    }
+
+    #[test]
+    fn unterminated_tags_stay_text() {
+        assert_eq!(html_to_markdown("a < b"), "a < b");
+        assert_eq!(html_to_markdown("<p>x &lt; y <b"), "x < y <b");
+    }
+}
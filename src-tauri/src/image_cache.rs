@@ -0,0 +1,71 @@
+//! App-side cache of annotation images in the app data directory, so
+//! repeated exports and previews don't go back to Zotero or the PDF renderer
+//! for every image. Entries are keyed by annotation key plus a stamp (the
+//! annotation's `dateModified` and the render resolution); storing a new
+//! stamp for a key removes the older entries, so edits in Zotero invalidate
+//! the cached image.
+
+use std::path::{Path, PathBuf};
+
+const CACHE_DIR: &str = "image-cache";
+
+fn cache_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(CACHE_DIR)
+}
+
+/// Annotation keys are alphanumeric; anything else is dropped so a key can't
+/// point outside the cache folder.
+fn key_prefix(annotation_key: &str) -> String {
+    let key = annotation_key
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>();
+    format!("{key}-")
+}
+
+fn file_name(annotation_key: &str, stamp: &str) -> String {
+    format!("{}{:08x}.img", key_prefix(annotation_key), crc32fast::hash(stamp.as_bytes()))
+}
+
+pub fn get(data_dir: &Path, annotation_key: &str, stamp: &str) -> Option<Vec<u8>> {
+    std::fs::read(cache_dir(data_dir).join(file_name(annotation_key, stamp)))
+        .ok()
+        .filter(|bytes| !bytes.is_empty())
+}
+
+/// Stores `bytes` for the key and stamp and drops entries with other stamps.
+pub fn put(data_dir: &Path, annotation_key: &str, stamp: &str, bytes: &[u8]) -> Result<(), String> {
+    let dir = cache_dir(data_dir);
+    std::fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create image cache {}: {err}", dir.display()))?;
+
+    let name = file_name(annotation_key, stamp);
+    let prefix = key_prefix(annotation_key);
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            if entry_name.starts_with(&prefix) && entry_name != name {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    let path = dir.join(name);
+    std::fs::write(&path, bytes)
+        .map_err(|err| format!("failed to write cached image {}: {err}", path.display()))
+}
+
+/// Removes every cached image and returns how many were removed.
+pub fn clear(data_dir: &Path) -> Result<usize, String> {
+    let dir = cache_dir(data_dir);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        std::fs::remove_file(entry.path())
+            .map_err(|err| format!("failed to remove cached image {}: {err}", entry.path().display()))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
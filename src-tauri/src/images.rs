@@ -0,0 +1,208 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageOutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        self.image_format().to_mime_type()
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Self::Png => ImageFormat::Png,
+            Self::Jpeg => ImageFormat::Jpeg,
+            Self::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+/// Handling of images for dark themes, where white PDF crops glare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DarkImageMode {
+    #[default]
+    Off,
+    /// Save images with their lightness inverted: white pages turn black,
+    /// while hues stay recognizable.
+    Invert,
+    /// Keep the image as is and save an inverted copy next to it with a
+    /// `-dark` suffix.
+    Variant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ImageSettings {
+    pub format: ImageOutputFormat,
+    /// Lossy encoder quality (1-100); ignored for PNG.
+    pub quality: u8,
+    /// Upper bounds for exported images; `None` leaves that axis unconstrained.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Resolution for area annotations rendered from the PDF. `None` uses
+    /// Zotero's cached image and renders only when it is missing; a value
+    /// renders every area annotation at that DPI.
+    pub render_dpi: Option<u32>,
+    pub dark_mode: DarkImageMode,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self {
+            format: ImageOutputFormat::Png,
+            quality: 85,
+            max_width: None,
+            max_height: None,
+            render_dpi: None,
+            dark_mode: DarkImageMode::Off,
+        }
+    }
+}
+
+/// Re-encodes `bytes` into the configured output format, downscaling to the
+/// configured bounds and inverting for [`DarkImageMode::Invert`]. PNG input
+/// that needs none of that is passed through untouched.
+pub fn transcode(bytes: &[u8], settings: &ImageSettings) -> Result<Vec<u8>, String> {
+    let source_format = image::guess_format(bytes).ok();
+    let mut decoded = image::load_from_memory(bytes)
+        .map_err(|err| format!("failed to decode annotation image: {err}"))?;
+
+    let inverted = settings.dark_mode == DarkImageMode::Invert;
+    if inverted {
+        decoded = invert_lightness(&decoded);
+    }
+    let resized = constrain_dimensions(&decoded, settings);
+    if resized.is_none()
+        && !inverted
+        && settings.format == ImageOutputFormat::Png
+        && source_format == Some(ImageFormat::Png)
+    {
+        return Ok(bytes.to_vec());
+    }
+
+    encode(resized.as_ref().unwrap_or(&decoded), settings)
+}
+
+/// Re-encodes `bytes` as `format` at the configured quality, without
+/// resizing or inverting. Bytes already in `format` are passed through.
+pub fn convert(bytes: &[u8], format: ImageOutputFormat, settings: &ImageSettings) -> Result<Vec<u8>, String> {
+    if image::guess_format(bytes).ok() == Some(format.image_format()) {
+        return Ok(bytes.to_vec());
+    }
+    let decoded = image::load_from_memory(bytes)
+        .map_err(|err| format!("failed to decode annotation image: {err}"))?;
+    encode(&decoded, &ImageSettings { format, ..settings.clone() })
+}
+
+/// Inverts lightness but keeps hues, like CSS `invert(1) hue-rotate(180deg)`:
+/// the colors are inverted, then rotated halfway round the color wheel so a
+/// red highlight stays red on the dark background.
+pub fn invert_lightness(image: &DynamicImage) -> DynamicImage {
+    // CSS hue-rotate(180deg) matrix.
+    const HUE_ROTATE: [[f32; 3]; 3] = [
+        [-0.574, 1.430, 0.144],
+        [0.426, 0.430, 0.144],
+        [0.426, 1.430, -0.856],
+    ];
+    let mut rgba = image.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let inverted = [
+            f32::from(255 - pixel[0]),
+            f32::from(255 - pixel[1]),
+            f32::from(255 - pixel[2]),
+        ];
+        for (channel, row) in HUE_ROTATE.iter().enumerate() {
+            let value = row[0] * inverted[0] + row[1] * inverted[1] + row[2] * inverted[2];
+            pixel[channel] = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Settings that produce the `-dark` copy for [`DarkImageMode::Variant`],
+/// or `None` when no copy is wanted.
+pub fn dark_variant_settings(settings: &ImageSettings) -> Option<ImageSettings> {
+    (settings.dark_mode == DarkImageMode::Variant).then(|| ImageSettings {
+        dark_mode: DarkImageMode::Invert,
+        ..settings.clone()
+    })
+}
+
+/// `figure.png` becomes `figure-dark.png`.
+pub fn dark_variant_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-dark.{}", extension.to_string_lossy()),
+        None => format!("{stem}-dark"),
+    };
+    path.with_file_name(name)
+}
+
+/// Returns a downscaled copy when `image` exceeds the configured bounds. The
+/// aspect ratio is preserved and images are never upscaled.
+pub fn constrain_dimensions(image: &DynamicImage, settings: &ImageSettings) -> Option<DynamicImage> {
+    let (width, height) = image.dimensions();
+    let max_width = settings.max_width.filter(|value| *value > 0).unwrap_or(u32::MAX);
+    let max_height = settings.max_height.filter(|value| *value > 0).unwrap_or(u32::MAX);
+
+    if width <= max_width && height <= max_height {
+        return None;
+    }
+
+    Some(image.resize(max_width.min(width), max_height.min(height), FilterType::Lanczos3))
+}
+
+pub fn encode(image: &DynamicImage, settings: &ImageSettings) -> Result<Vec<u8>, String> {
+    let quality = settings.quality.clamp(1, 100);
+
+    match settings.format {
+        ImageOutputFormat::Png => {
+            let mut buffer = Cursor::new(Vec::new());
+            image
+                .write_to(&mut buffer, ImageFormat::Png)
+                .map_err(|err| format!("failed to encode png: {err}"))?;
+            Ok(buffer.into_inner())
+        }
+        ImageOutputFormat::Jpeg => {
+            // JPEG has no alpha channel; flatten before encoding.
+            let rgb = image.to_rgb8();
+            let mut buffer = Vec::new();
+            JpegEncoder::new_with_quality(&mut buffer, quality)
+                .encode_image(&rgb)
+                .map_err(|err| format!("failed to encode jpeg: {err}"))?;
+            Ok(buffer)
+        }
+        ImageOutputFormat::Webp => {
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode(f32::from(quality));
+            Ok(encoded.to_vec())
+        }
+    }
+}
+
+/// Swaps the extension of `path` for the one matching `format`.
+pub fn with_format_extension(path: &Path, format: ImageOutputFormat) -> PathBuf {
+    path.with_extension(format.extension())
+}
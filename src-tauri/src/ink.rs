@@ -0,0 +1,157 @@
+//! Turns Zotero ink annotations (freehand strokes drawn in the reader) into
+//! images. The strokes are written as an SVG of round-capped paths, which is
+//! then rasterized so ink flows through the same image pipeline as area
+//! annotations.
+//!
+//! Zotero stores ink as `{"pageIndex", "width", "paths"}` where each path is
+//! a flat list of `x, y` pairs in PDF points, measured from the bottom-left
+//! of the page.
+
+use resvg::{tiny_skia, usvg};
+use serde::Deserialize;
+
+use crate::pdf_render::{MAX_DPI, MIN_DPI};
+
+const POINTS_PER_INCH: f32 = 72.0;
+const DEFAULT_COLOR: &str = "#000000";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InkPosition {
+    #[serde(default = "default_width")]
+    width: f32,
+    #[serde(default)]
+    paths: Vec<Vec<f32>>,
+}
+
+fn default_width() -> f32 {
+    2.0
+}
+
+/// `#rgb` or `#rrggbb`; anything else is drawn in black.
+fn stroke_color(color: &str) -> &str {
+    let color = color.trim();
+    let digits = color.strip_prefix('#').unwrap_or_default();
+    if matches!(digits.len(), 3 | 6) && digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        color
+    } else {
+        DEFAULT_COLOR
+    }
+}
+
+/// Builds an SVG of the strokes in `position` (Zotero's raw position JSON),
+/// cropped to the strokes plus the pen width and sized in PDF points. The
+/// background is white so the image survives conversion to JPEG.
+pub fn to_svg(position: &str, color: &str) -> Result<String, String> {
+    let ink: InkPosition = serde_json::from_str(position)
+        .map_err(|err| format!("failed to parse ink annotation position: {err}"))?;
+    let strokes = ink
+        .paths
+        .iter()
+        .map(|path| path.chunks_exact(2).map(|point| (point[0], point[1])).collect::<Vec<_>>())
+        .filter(|points| !points.is_empty())
+        .collect::<Vec<_>>();
+    let points = strokes.iter().flatten();
+    let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+        points.clone().map(|(x, _)| *x).reduce(f32::min),
+        points.clone().map(|(x, _)| *x).reduce(f32::max),
+        points.clone().map(|(_, y)| *y).reduce(f32::min),
+        points.map(|(_, y)| *y).reduce(f32::max),
+    ) else {
+        return Err("ink annotation has no strokes to render.".to_string());
+    };
+
+    let width = ink.width.max(0.1);
+    let pad = width;
+    let view_width = max_x - min_x + 2.0 * pad;
+    let view_height = max_y - min_y + 2.0 * pad;
+    let project = |(x, y): (f32, f32)| (x - min_x + pad, max_y - y + pad);
+    let paths = strokes
+        .iter()
+        .map(|points| {
+            let data = match points.as_slice() {
+                // A lone point still needs a segment to draw a round cap.
+                [point] => {
+                    let (x, y) = project(*point);
+                    format!("M{x:.2} {y:.2} L{x:.2} {y:.2}")
+                }
+                _ => points
+                    .iter()
+                    .enumerate()
+                    .map(|(index, point)| {
+                        let (x, y) = project(*point);
+                        format!("{}{x:.2} {y:.2}", if index == 0 { 'M' } else { 'L' })
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+            format!("  <path d=\"{data}\"/>")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{view_width:.2}\" height=\"{view_height:.2}\" viewBox=\"0 0 {view_width:.2} {view_height:.2}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n\
+         <g fill=\"none\" stroke=\"{}\" stroke-width=\"{width:.2}\" stroke-linecap=\"round\" stroke-linejoin=\"round\">\n\
+         {paths}\n\
+         </g>\n\
+         </svg>\n",
+        stroke_color(color)
+    ))
+}
+
+/// Rasterizes an ink annotation to PNG at `dpi` (clamped to the same range
+/// as PDF renders).
+pub fn render_png(position: &str, color: &str, dpi: u32) -> Result<Vec<u8>, String> {
+    let svg = to_svg(position, color)?;
+    let tree = usvg::Tree::from_str(&svg, &usvg::Options::default())
+        .map_err(|err| format!("failed to parse ink annotation SVG: {err}"))?;
+
+    let scale = dpi.clamp(MIN_DPI, MAX_DPI) as f32 / POINTS_PER_INCH;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or_else(|| "ink annotation is too small to render.".to_string())?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| "ink annotation is too small to render.".to_string())?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|err| format!("failed to encode ink annotation: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_is_cropped_to_the_strokes_and_flipped() {
+        let svg = to_svg(r#"{"width": 2, "paths": [[10, 20, 30, 40]]}"#, "#ff0000").unwrap();
+        assert!(svg.contains("width=\"24.00\" height=\"24.00\""));
+        assert!(svg.contains("<path d=\"M2.00 22.00 L22.00 2.00\"/>"));
+        assert!(svg.contains("stroke=\"#ff0000\""));
+    }
+
+    #[test]
+    fn a_lone_point_draws_a_dot() {
+        let svg = to_svg(r#"{"paths": [[5, 5]]}"#, "").unwrap();
+        assert!(svg.contains("<path d=\"M2.00 2.00 L2.00 2.00\"/>"));
+        assert!(svg.contains("stroke-width=\"2.00\""));
+    }
+
+    #[test]
+    fn invalid_colors_are_drawn_in_black() {
+        assert_eq!(stroke_color(" #abc "), "#abc");
+        assert_eq!(stroke_color("red"), DEFAULT_COLOR);
+        assert_eq!(stroke_color("#12345"), DEFAULT_COLOR);
+        assert_eq!(stroke_color("#ggg"), DEFAULT_COLOR);
+    }
+
+    #[test]
+    fn ink_without_strokes_is_an_error() {
+        assert!(to_svg(r#"{"paths": [[], [1]]}"#, "#000").is_err());
+        assert!(to_svg("not json", "#000").is_err());
+    }
+}
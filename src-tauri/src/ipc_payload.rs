@@ -0,0 +1,32 @@
+//! Large JSON command results sent as raw IPC responses. The JSON is
+//! serialized once, in the command's worker thread, and gzip-compressed when
+//! the caller asked for it and it is big enough to be worth it. The webview
+//! tells the two apart by the gzip magic bytes, which JSON never starts with.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::io::Write;
+use tauri::ipc::Response;
+
+/// Payloads smaller than this are sent as plain JSON even when compression
+/// was asked for; decompressing them would take longer than sending them.
+pub const COMPRESS_ABOVE: usize = 64 * 1024;
+
+/// `value` as JSON bytes, gzip-compressed when `compress` is set and the JSON
+/// is at least [`COMPRESS_ABOVE`] bytes.
+pub fn json<T: Serialize>(value: &T, compress: bool) -> Result<Response, String> {
+    let bytes = serde_json::to_vec(value).map_err(|err| format!("failed to serialize response: {err}"))?;
+    if !compress || bytes.len() < COMPRESS_ABOVE {
+        return Ok(Response::new(bytes));
+    }
+    // Fast compression: the point is a shorter transfer, not the smallest one.
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 4), Compression::fast());
+    encoder
+        .write_all(&bytes)
+        .map_err(|err| format!("failed to compress response: {err}"))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|err| format!("failed to compress response: {err}"))?;
+    Ok(Response::new(compressed))
+}
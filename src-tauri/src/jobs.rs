@@ -0,0 +1,279 @@
+//! Background jobs for exports, syncs and downloads. Jobs are stored in the
+//! state database, so a job interrupted by a crash or restart is picked up
+//! again on the next launch and skips the items it already finished.
+
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::history::{self, SyncTrigger};
+use crate::progress::Progress;
+use crate::report::{now_seconds, ExportReport};
+use crate::state_db::StateDb;
+use crate::{
+    app_data_dir, apply_api_key, ensure_parent, export,
+    open_better_bibtex_connection, open_state_db, open_zotero_connection, read_settings, record_export_report,
+    lock_sync, write_scope, AppSettings,
+};
+
+pub const JOBS_CHANGED_EVENT: &str = "jobs-changed";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a job does. Sync jobs resolve their item keys when they first run
+/// and are then resumed like exports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum JobTask {
+    Export { item_keys: Vec<String> },
+    Sync { item_keys: Option<Vec<String>> },
+    Download { url: String, path: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "queued" => Self::Queued,
+            "running" => Self::Running,
+            "completed" => Self::Completed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: i64,
+    pub task: JobTask,
+    pub status: JobStatus,
+    /// Item keys exported so far; skipped when the job resumes.
+    pub completed: Vec<String>,
+    pub failures: Vec<String>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+pub fn notify_changed(app: &AppHandle, job_id: i64) {
+    let _ = app.emit(JOBS_CHANGED_EVENT, job_id);
+}
+
+/// Exports the job's remaining items, saving progress after each one.
+/// Returns `None` when the job was cancelled part-way.
+fn run_export(
+    app: &AppHandle,
+    state: &StateDb,
+    job: &mut Job,
+    item_keys: &[String],
+) -> Result<Option<ExportReport>, String> {
+    let _guard = lock_sync(app)?;
+
+    let settings = read_settings(app)?;
+    let conn = open_zotero_connection(app)?;
+    let citations = open_better_bibtex_connection(app).ok();
+    let data_dir = app_data_dir(app)?;
+    let exporter = export::Exporter {
+        settings: &settings,
+        zotero: &conn,
+        citations: citations.as_deref(),
+        state,
+        data_dir: &data_dir,
+        dry_run: false,
+    };
+    let progress = Progress::new(app, "job", Some(format!("job-{}", job.id)));
+
+    let excluded = export::excluded_item_keys(&conn, &settings.exclude_tag)?;
+    let item_keys = item_keys
+        .iter()
+        .filter(|key| !excluded.contains(*key))
+        .collect::<Vec<_>>();
+    let mut report = ExportReport::default();
+    for key in &item_keys {
+        if job.completed.contains(key) {
+            continue;
+        }
+        if state.job_status(job.id)? == Some(JobStatus::Cancelled) {
+            return Ok(None);
+        }
+
+        match exporter.export_item(key) {
+            Ok(item) => {
+                job.completed.push((*key).clone());
+                report.items.push(item);
+            }
+            Err(err) => {
+                job.failures.push(format!("{key}: {err}"));
+                report.failed.push(format!("{key}: {err}"));
+            }
+        }
+        state.save_job_progress(job)?;
+        progress.report(
+            (job.completed.len() + job.failures.len()) as u64,
+            item_keys.len() as u64,
+            key,
+        );
+    }
+
+    if !report.items.is_empty() {
+        record_export_report(&settings, &report)?;
+    }
+    Ok(Some(report))
+}
+
+/// Whether `url` is on api.zotero.org or the configured Zotero base URL, the
+/// only places the API key is sent to.
+fn is_zotero_url(settings: &AppSettings, url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    ["https://api.zotero.org", settings.zotero_base_url.trim()]
+        .into_iter()
+        .filter_map(|base| Url::parse(base).ok())
+        .any(|base| base.origin() == url.origin())
+}
+
+fn run_download(app: &AppHandle, job: &Job, url: &str, path: &str) -> Result<(), String> {
+    let settings = read_settings(app)?;
+    let destination = Path::new(path);
+    write_scope::check(&settings, destination)?;
+    let api_key = is_zotero_url(&settings, url).then(|| settings.zotero_api_key.clone());
+    let headers = apply_api_key(HeaderMap::new(), api_key);
+    let progress = Progress::new(app, "job", Some(format!("job-{}", job.id)));
+
+    let bytes = tauri::async_runtime::block_on(async {
+        let mut response = reqwest::Client::new()
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|err| format!("download failed for {url}: {err}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Zotero HTTP {status} for {url}"));
+        }
+
+        let total = response.content_length().unwrap_or(0);
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|err| format!("failed to read download body: {err}"))?
+        {
+            bytes.extend_from_slice(&chunk);
+            progress.report(bytes.len() as u64, total, url);
+        }
+        Ok(bytes)
+    })?;
+
+    ensure_parent(destination)?;
+    std::fs::write(destination, bytes)
+        .map_err(|err| format!("failed to write download {}: {err}", destination.display()))
+}
+
+/// Runs one job to completion, cancellation or failure.
+fn run_job(app: &AppHandle, state: &StateDb, job: &mut Job) -> Result<bool, String> {
+    match job.task.clone() {
+        JobTask::Download { url, path } => run_download(app, job, &url, &path).map(|_| true),
+        JobTask::Export { item_keys } => Ok(run_export(app, state, job, &item_keys)?.is_some()),
+        JobTask::Sync { item_keys } => {
+            let started_at = now_seconds();
+            let item_keys = match item_keys {
+                Some(item_keys) => item_keys,
+                None => {
+                    let settings = read_settings(app)?;
+                    let conn = open_zotero_connection(app)?;
+                    let item_keys = export::changed_records(&conn, state, &settings.exclude_tag)?
+                        .into_iter()
+                        .map(|record| record.item_key)
+                        .collect::<Vec<_>>();
+                    job.task = JobTask::Sync {
+                        item_keys: Some(item_keys.clone()),
+                    };
+                    state.save_job_progress(job)?;
+                    item_keys
+                }
+            };
+
+            let result = run_export(app, state, job, &item_keys);
+            match &result {
+                Ok(Some(report)) => history::record(app, SyncTrigger::Job, started_at, Ok(report)),
+                Ok(None) => {}
+                Err(err) => history::record(app, SyncTrigger::Job, started_at, Err(err)),
+            }
+            Ok(result?.is_some())
+        }
+    }
+}
+
+fn process_next(app: &AppHandle) -> Result<(), String> {
+    let state = open_state_db(app)?;
+    let Some(mut job) = state.next_queued_job()? else {
+        return Ok(());
+    };
+    if !state.start_job(job.id)? {
+        return Ok(());
+    }
+    notify_changed(app, job.id);
+    tracing::info!(job = job.id, "job started");
+
+    match run_job(app, &state, &mut job) {
+        Ok(false) => tracing::info!(job = job.id, "job cancelled"),
+        Ok(true) if job.failures.is_empty() => {
+            tracing::info!(job = job.id, "job completed");
+            state.finish_job(job.id, JobStatus::Completed, None)?;
+        }
+        Ok(true) => {
+            let error = format!("{} item(s) failed", job.failures.len());
+            for failure in &job.failures {
+                tracing::warn!(job = job.id, "job item failed: {failure}");
+            }
+            state.finish_job(job.id, JobStatus::Failed, Some(&error))?;
+        }
+        Err(err) => {
+            tracing::error!(job = job.id, "job failed: {err}");
+            state.finish_job(job.id, JobStatus::Failed, Some(&err))?;
+        }
+    }
+    notify_changed(app, job.id);
+    Ok(())
+}
+
+/// Runs queued jobs one at a time. Jobs left running by a previous session
+/// are queued again first, so they resume.
+pub fn spawn(app: AppHandle) {
+    std::thread::spawn(move || {
+        if let Ok(state) = open_state_db(&app) {
+            let _ = state.requeue_interrupted_jobs();
+        }
+
+        loop {
+            let _ = process_next(&app);
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
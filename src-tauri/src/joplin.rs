@@ -0,0 +1,276 @@
+//! Export target that sends notes to Joplin through its Web Clipper API
+//! (Joplin: Tools → Options → Web Clipper) instead of writing Markdown
+//! files. Annotation images are uploaded as Joplin resources.
+//!
+//! The export-state database records a Joplin note by its
+//! `joplin://x-callback-url/openNote?id=…` URI in place of a file path, so
+//! later exports update the same note and "Open" jumps to it in Joplin.
+
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const NOTE_URI_PREFIX: &str = "joplin://x-callback-url/openNote?id=";
+
+/// Where exports are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputTarget {
+    /// Markdown files under `markdown_dir`.
+    #[default]
+    Markdown,
+    /// Notes in Joplin, through its Web Clipper API.
+    Joplin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct JoplinSettings {
+    /// Web Clipper service address.
+    pub base_url: String,
+    /// Authorization token shown in Joplin's Web Clipper options.
+    pub token: String,
+    /// Notebook the notes go to, created when missing; empty uses Joplin's
+    /// default notebook.
+    pub notebook: String,
+}
+
+impl Default for JoplinSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "http://127.0.0.1:41184".to_string(),
+            token: String::new(),
+            notebook: String::new(),
+        }
+    }
+}
+
+/// URI recorded as the note's path; opens the note in Joplin.
+pub fn note_uri(id: &str) -> String {
+    format!("{NOTE_URI_PREFIX}{id}")
+}
+
+/// The Joplin note ID of a recorded path, if it is a Joplin note.
+pub fn note_id(path: &str) -> Option<&str> {
+    path.strip_prefix(NOTE_URI_PREFIX).filter(|id| !id.is_empty())
+}
+
+/// A note as it is in Joplin.
+pub struct RemoteNote {
+    /// Last change in Joplin, unix seconds.
+    pub updated_at: i64,
+    /// IDs and titles of the resources attached to the note.
+    pub resources: Vec<(String, String)>,
+}
+
+pub struct JoplinClient<'a> {
+    http: Client,
+    settings: &'a JoplinSettings,
+}
+
+impl<'a> JoplinClient<'a> {
+    pub fn new(settings: &'a JoplinSettings) -> Result<Self, String> {
+        if settings.token.trim().is_empty() {
+            return Err("Set the Joplin Web Clipper token in Settings to export to Joplin.".to_string());
+        }
+        Ok(Self {
+            http: Client::new(),
+            settings,
+        })
+    }
+
+    fn url(&self, path: &str, query: &[(&str, &str)]) -> Result<Url, String> {
+        let base = self.settings.base_url.trim().trim_end_matches('/');
+        let mut url = Url::parse(&format!("{base}/{path}"))
+            .map_err(|err| format!("invalid Joplin URL {base}: {err}"))?;
+        url.query_pairs_mut()
+            .append_pair("token", self.settings.token.trim())
+            .extend_pairs(query);
+        Ok(url)
+    }
+
+    /// Sends `request`; `None` when Joplin answers 404.
+    fn send(&self, request: RequestBuilder, what: &str) -> Result<Option<Value>, String> {
+        let response = tauri::async_runtime::block_on(request.timeout(REQUEST_TIMEOUT).send()).map_err(|err| {
+            format!(
+                "Joplin is not reachable at {}: {err}. Is Joplin running with the Web Clipper service enabled?",
+                self.settings.base_url.trim()
+            )
+        })?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if status == StatusCode::FORBIDDEN {
+            return Err("Joplin rejected the Web Clipper token.".to_string());
+        }
+        let body = tauri::async_runtime::block_on(response.text()).unwrap_or_default();
+        if !status.is_success() {
+            return Err(format!("failed to {what}: Joplin HTTP {status}: {}", body.trim()));
+        }
+        if body.trim().is_empty() {
+            return Ok(Some(Value::Null));
+        }
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|err| format!("failed to {what}: invalid response from Joplin: {err}"))
+    }
+
+    /// Every item of a paginated list endpoint.
+    fn list(&self, path: &str, fields: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let page = page.to_string();
+            let url = self.url(path, &[("fields", fields), ("page", &page)])?;
+            let Some(body) = self.send(self.http.get(url), &format!("list {path}"))? else {
+                break;
+            };
+            items.extend(body.get("items").and_then(Value::as_array).cloned().unwrap_or_default());
+            if !body.get("has_more").and_then(Value::as_bool).unwrap_or(false) {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// ID of the configured notebook, created when missing; `None` for
+    /// Joplin's default notebook.
+    pub fn notebook_id(&self) -> Result<Option<String>, String> {
+        let title = self.settings.notebook.trim();
+        if title.is_empty() {
+            return Ok(None);
+        }
+        let existing = self.list("folders", "id,title")?.into_iter().find_map(|folder| {
+            (folder.get("title").and_then(Value::as_str) == Some(title))
+                .then(|| folder.get("id").and_then(Value::as_str).map(str::to_string))
+                .flatten()
+        });
+        if existing.is_some() {
+            return Ok(existing);
+        }
+        let created = self
+            .send(
+                self.http.post(self.url("folders", &[])?).json(&json!({ "title": title })),
+                "create the Joplin notebook",
+            )?
+            .unwrap_or_default();
+        Ok(created.get("id").and_then(Value::as_str).map(str::to_string))
+    }
+
+    /// The note with `id`, or `None` when it was deleted in Joplin.
+    pub fn note(&self, id: &str) -> Result<Option<RemoteNote>, String> {
+        let url = self.url(&format!("notes/{id}"), &[("fields", "id,updated_time")])?;
+        let Some(note) = self.send(self.http.get(url), "read the Joplin note")? else {
+            return Ok(None);
+        };
+        let resources = self
+            .list(&format!("notes/{id}/resources"), "id,title")?
+            .into_iter()
+            .filter_map(|resource| {
+                let id = resource.get("id").and_then(Value::as_str)?.to_string();
+                let title = resource.get("title").and_then(Value::as_str).unwrap_or_default().to_string();
+                Some((id, title))
+            })
+            .collect();
+        Ok(Some(RemoteNote {
+            updated_at: note.get("updated_time").and_then(Value::as_i64).unwrap_or(0) / 1000,
+            resources,
+        }))
+    }
+
+    /// Uploads an image and returns its resource ID.
+    pub fn upload_resource(&self, title: &str, mime: &str, bytes: &[u8]) -> Result<String, String> {
+        // reqwest's multipart support isn't enabled, so the form is built by hand.
+        let boundary = format!("zotnotes-{}", crate::state_db::content_hash(bytes));
+        let props = json!({ "title": title }).to_string();
+        let mut body = Vec::new();
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"props\"\r\n\r\n{props}\r\n\
+                 --{boundary}\r\nContent-Disposition: form-data; name=\"data\"; filename=\"{}\"\r\n\
+                 Content-Type: {mime}\r\n\r\n",
+                title.replace('"', "")
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(bytes);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        let request = self
+            .http
+            .post(self.url("resources", &[])?)
+            .header("Content-Type", format!("multipart/form-data; boundary={boundary}"))
+            .body(body);
+        self.send(request, &format!("upload {title} to Joplin"))?
+            .and_then(|resource| resource.get("id").and_then(Value::as_str).map(str::to_string))
+            .ok_or_else(|| format!("Joplin did not return an ID for {title}."))
+    }
+
+    pub fn delete_resource(&self, id: &str) -> Result<(), String> {
+        self.send(
+            self.http.delete(self.url(&format!("resources/{id}"), &[])?),
+            "delete an old Joplin resource",
+        )
+        .map(|_| ())
+    }
+
+    /// Creates a note and returns its ID.
+    pub fn create_note(&self, title: &str, body: &str, notebook: Option<&str>) -> Result<String, String> {
+        let mut note = json!({ "title": title, "body": body });
+        if let Some(notebook) = notebook {
+            note["parent_id"] = json!(notebook);
+        }
+        self.send(
+            self.http.post(self.url("notes", &[])?).json(&note),
+            "create the Joplin note",
+        )?
+        .and_then(|note| note.get("id").and_then(Value::as_str).map(str::to_string))
+        .ok_or_else(|| "Joplin did not return an ID for the new note.".to_string())
+    }
+
+    pub fn update_note(&self, id: &str, title: &str, body: &str) -> Result<(), String> {
+        self.send(
+            self.http
+                .put(self.url(&format!("notes/{id}"), &[])?)
+                .json(&json!({ "title": title, "body": body })),
+            "update the Joplin note",
+        )?
+        .map(|_| ())
+        .ok_or_else(|| "the Joplin note was deleted during the export.".to_string())
+    }
+}
+
+/// Rewrites the note's `[[link]]` and `[[link|alias]]` image embeds of
+/// `link` as Joplin resource embeds.
+pub fn replace_image_link(markdown: &str, link: &str, resource_id: &str) -> String {
+    let opening = format!("[[{link}");
+    let mut output = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find(&opening) {
+        let after = &rest[start + opening.len()..];
+        let alias = if after.starts_with("]]") {
+            Some("")
+        } else {
+            after
+                .strip_prefix('|')
+                .and_then(|alias| alias.find("]]").map(|end| &alias[..end]))
+        };
+        output.push_str(&rest[..start]);
+        match alias {
+            Some(alias) => {
+                output.push_str(&format!("![{alias}](:/{resource_id})"));
+                let consumed = if alias.is_empty() && after.starts_with("]]") { 2 } else { alias.len() + 3 };
+                rest = &after[consumed..];
+            }
+            None => {
+                output.push_str(&opening);
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
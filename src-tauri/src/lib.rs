@@ -1,58 +1,244 @@
+mod annotation_protocol;
+mod api_server;
+mod assets;
+mod autosync;
+mod bundle;
+mod citation;
+mod citation_keys;
+mod citation_scan;
+mod cli;
+mod clipboard;
+mod collections;
+mod conflict;
+mod connections;
+mod csv_export;
+mod dates;
+mod db_pool;
+mod debug_dump;
+mod diagnostics;
+mod docx;
+mod editor;
+mod error;
+mod export;
+mod export_pool;
+mod git;
+mod highlight;
+mod history;
+mod html;
+mod image_cache;
+mod images;
+mod ink;
+mod ipc_payload;
+mod jobs;
+mod joplin;
+mod logging;
+mod managed;
+mod mcp;
+mod note_import;
+mod note_search;
+mod notion;
+mod obsidian;
+mod ocr;
+mod orphans;
+mod pdf_render;
+mod profiles;
+mod progress;
+mod readwise_export;
+mod render;
+mod report;
+mod ris_export;
+mod search_index;
+mod search_shadow;
+mod search_stream;
+mod settings_bundle;
+mod settings_reset;
+mod state_db;
+mod sync_lock;
+mod validation;
+mod watcher;
+mod web_search;
+mod webdav;
+mod write_queue;
+mod write_scope;
+mod writeback;
+mod zotero_db;
+mod zotero_profile;
+
+use api_server::{ApiServerSettings, ApiServerStatus};
+use assets::{AssetContext, ImageLinkStyle};
+use autosync::AutoSyncSettings;
+use citation_scan::CitationScan;
+use bundle::{BundleAnnotation, BundleNote, ItemBundle};
+use collections::{CollectionOverride, CollectionRef, ExportTarget};
+use conflict::ConflictStrategy;
+use connections::ConnectionReport;
+use csv_export::CsvItem;
+use db_pool::{DbPool, Pooled};
+use error::{AppError, ErrorCode};
+use export_pool::OpenWorker;
+use git::GitSettings;
+use highlight::FieldMatch;
+use history::{SyncRun, SyncTrigger};
+use images::{ImageOutputFormat, ImageSettings};
+use jobs::{Job, JobTask};
+use joplin::{JoplinSettings, OutputTarget};
+use logging::LogLevel;
+use managed::DeletedAnnotations;
+use obsidian::ObsidianSettings;
+use ocr::OcrSettings;
+use orphans::{OrphanAction, OrphanedNote};
+use pdf_render::AnnotationPosition;
+use profiles::ProfileList;
+use progress::Progress;
+use readwise_export::ReadwiseFormat;
+use render::{NoteInput, TemplateSettings};
+use report::{AnnotationStamp, ExportReport, FileChange, NoteWrite};
+use note_import::ImportReport;
+use note_search::NoteMatch;
+use search_index::{IndexHit, IndexRefresh, SearchQuery};
+use settings_reset::SettingsReset;
+use state_db::{ItemExportRecord, StateDb};
+use web_search::{SearchSource, UnifiedHit, UnifiedSearch};
+use webdav::WebDavSettings;
+use write_queue::{WriteOutcome, WriteQueue, ZoteroWrite};
+use writeback::{CommentEdit, WritebackReport};
+use zotero_db::ZoteroDb;
 use reqwest::header::HeaderMap;
 use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::Map;
 use serde_json::Value;
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, RwLock};
+use tauri::ipc::Response;
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::Manager;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 struct AppSettings {
+    /// Where exports go: Markdown files under `markdown_dir`, or Joplin.
+    output_target: OutputTarget,
     markdown_dir: String,
     attachment_base_dir: String,
     zotero_api_key: String,
     zotero_base_url: String,
+    /// Numeric user ID for the Zotero web API; comment write-back needs it
+    /// together with an API key that has write access.
+    zotero_user_id: String,
     template_settings: TemplateSettings,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[serde(default)]
-struct TemplateSettings {
-    property_order: Vec<String>,
-    color_heading_overrides: BTreeMap<String, String>,
-}
-
-impl Default for TemplateSettings {
-    fn default() -> Self {
-        Self {
-            property_order: vec![
-                "title".to_string(),
-                "author".to_string(),
-                "year".to_string(),
-                "company".to_string(),
-            ],
-            color_heading_overrides: BTreeMap::new(),
-        }
-    }
+    image_settings: ImageSettings,
+    /// Folder under `attachment_base_dir` for item images, e.g. `assets/{{citekey}}/`.
+    asset_path_template: String,
+    image_link_style: ImageLinkStyle,
+    /// Text recognition for area annotations.
+    ocr: OcrSettings,
+    /// Joplin connection, used when `output_target` is Joplin.
+    joplin: JoplinSettings,
+    /// Changelog note under `markdown_dir` that export reports are appended
+    /// to; empty disables it.
+    changelog_note: String,
+    /// Committing the Markdown folder to Git after exports.
+    git: GitSettings,
+    /// Per-collection template, file name and folder overrides; the first
+    /// matching entry wins.
+    collection_overrides: Vec<CollectionOverride>,
+    /// Command used to open exported notes: empty for the system default,
+    /// `obsidian`, or a command line such as `code {path}`.
+    editor_command: String,
+    /// Opening notes in Obsidian after an export.
+    obsidian: ObsidianSettings,
+    /// Columns for CSV metadata exports; empty uses the defaults.
+    csv_columns: Vec<String>,
+    auto_sync: AutoSyncSettings,
+    /// Local HTTP server for scripts and editor plugins.
+    api_server: ApiServerSettings,
+    /// What to write when a note was edited locally and its Zotero item
+    /// changed too.
+    conflict_strategy: ConflictStrategy,
+    /// Whether annotations deleted in Zotero are dropped from re-exported
+    /// notes or kept in a callout.
+    deleted_annotations: DeletedAnnotations,
+    /// Folder under `markdown_dir` that orphaned notes are archived to.
+    orphan_archive_dir: String,
+    /// Frontmatter tag added to orphaned notes.
+    orphan_tag: String,
+    /// Zotero tag that keeps items out of batch exports and sync; empty
+    /// disables it.
+    exclude_tag: String,
+    /// Zotero data directory (the folder holding zotero.sqlite); empty to
+    /// look in the default locations.
+    zotero_data_dir: String,
+    /// WebDAV server attachment files are downloaded from when they are
+    /// missing locally, for libraries that sync files through WebDAV.
+    webdav: WebDavSettings,
+    /// Most items a library search returns, at most
+    /// [`validation::MAX_SEARCH_LIMIT`].
+    search_limit: usize,
+    /// Typos tolerated per word by the full-text search, at most
+    /// [`validation::MAX_SEARCH_FUZZINESS`]; 0 matches exactly.
+    search_fuzziness: usize,
+    /// Search through the app's full-text index. When off, searches match
+    /// substrings, against lowercased shadow copies of titles, creators and
+    /// years kept in the state database.
+    full_text_search: bool,
+    log_level: LogLevel,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
+            output_target: OutputTarget::default(),
             markdown_dir: String::new(),
             attachment_base_dir: String::new(),
             zotero_api_key: String::new(),
             zotero_base_url: "http://127.0.0.1:23119".to_string(),
+            zotero_user_id: String::new(),
             template_settings: TemplateSettings::default(),
+            image_settings: ImageSettings::default(),
+            asset_path_template: String::new(),
+            image_link_style: ImageLinkStyle::default(),
+            ocr: OcrSettings::default(),
+            joplin: JoplinSettings::default(),
+            changelog_note: String::new(),
+            git: GitSettings::default(),
+            collection_overrides: Vec::new(),
+            editor_command: String::new(),
+            obsidian: ObsidianSettings::default(),
+            csv_columns: Vec::new(),
+            auto_sync: AutoSyncSettings::default(),
+            api_server: ApiServerSettings::default(),
+            conflict_strategy: ConflictStrategy::default(),
+            deleted_annotations: DeletedAnnotations::default(),
+            orphan_archive_dir: "_archive".to_string(),
+            orphan_tag: "zotero/deleted".to_string(),
+            exclude_tag: String::new(),
+            zotero_data_dir: String::new(),
+            webdav: WebDavSettings::default(),
+            search_limit: 75,
+            search_fuzziness: 1,
+            full_text_search: true,
+            log_level: LogLevel::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedAsset {
+    path: String,
+    link: String,
+    /// The inverted `-dark` copy, when `imageSettings.darkMode` is `variant`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dark_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dark_link: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct SqliteItemSummary {
@@ -60,6 +246,20 @@ struct SqliteItemSummary {
     title: String,
     creators: String,
     year: String,
+    /// Annotations on the item's attachments, outside the trash.
+    annotation_count: usize,
+    /// Whether the item has an attachment outside the trash.
+    has_attachment: bool,
+    /// Where a search query matched; empty outside search results.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    matches: Vec<FieldMatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SqliteChildNote {
+    key: String,
+    note: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -72,7 +272,11 @@ struct SqliteAnnotation {
     comment: String,
     page_label: String,
     sort_index: usize,
+    /// Area (`image`) and `ink` annotations, which are exported as images.
     is_image_selection: bool,
+    attachment_title: String,
+    date_added: String,
+    date_modified: String,
 }
 
 fn extract_year(raw: &str) -> String {
@@ -91,13 +295,54 @@ fn extract_year(raw: &str) -> String {
     String::new()
 }
 
+/// The user's home folder: `$HOME`, or `%USERPROFILE%` on Windows.
 fn home_dir() -> Result<PathBuf, String> {
-    std::env::var("HOME")
+    let vars: &[&str] = if cfg!(target_os = "windows") {
+        &["USERPROFILE", "HOME"]
+    } else {
+        &["HOME"]
+    };
+    vars.iter()
+        .find_map(|name| std::env::var_os(name).filter(|value| !value.is_empty()))
         .map(PathBuf::from)
-        .map_err(|_| "HOME environment variable is not set.".to_string())
+        .ok_or_else(|| format!("{} environment variable is not set.", vars[0]))
+}
+
+/// Zotero data directory from the settings. Kept in sync whenever settings
+/// are read or saved, so the database helpers need no settings argument.
+static ZOTERO_DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Applies the settings that live in process-wide state: the Zotero data
+/// directory, the WebDAV server and the log level.
+fn apply_runtime_settings(settings: &AppSettings) {
+    let dir = settings.zotero_data_dir.trim();
+    if let Ok(mut configured) = ZOTERO_DATA_DIR.write() {
+        *configured = (!dir.is_empty()).then(|| PathBuf::from(dir));
+    }
+    webdav::configure(&settings.webdav);
+    logging::set_level(settings.log_level);
+}
+
+fn configured_zotero_data_dir() -> Option<PathBuf> {
+    ZOTERO_DATA_DIR.read().ok()?.clone()
 }
 
-fn resolve_zotero_sqlite_path() -> Result<PathBuf, String> {
+fn resolve_zotero_sqlite_path() -> Result<PathBuf, AppError> {
+    if let Some(dir) = configured_zotero_data_dir() {
+        let candidate = dir.join("zotero.sqlite");
+        if candidate.exists() {
+            return Ok(candidate);
+        }
+        return Err(AppError::new(
+            ErrorCode::ZoteroNotFound,
+            format!(
+                "{} does not contain zotero.sqlite. Check the Zotero data directory in Settings.",
+                dir.display()
+            ),
+        )
+        .with_context(dir.display().to_string()));
+    }
+
     if let Ok(path) = std::env::var("ZOTERO_SQLITE_PATH") {
         let candidate = PathBuf::from(path.trim());
         if candidate.exists() {
@@ -106,15 +351,17 @@ fn resolve_zotero_sqlite_path() -> Result<PathBuf, String> {
     }
 
     let home = home_dir()?;
-    let candidates = [
-        home.join("Zotero").join("zotero.sqlite"),
-        home.join("Zotero Beta").join("zotero.sqlite"),
-    ];
-
-    candidates
+    zotero_profile::default_data_dirs(&home)
         .into_iter()
+        .map(|dir| dir.join("zotero.sqlite"))
         .find(|path| path.exists())
-        .ok_or_else(|| "Could not locate zotero.sqlite. Set ZOTERO_SQLITE_PATH to the database file.".to_string())
+        .or_else(|| zotero_profile::discover_data_dir(&home).map(|dir| dir.join("zotero.sqlite")))
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::ZoteroNotFound,
+                "Could not locate zotero.sqlite. Set the Zotero data directory in Settings or ZOTERO_SQLITE_PATH to the database file.",
+            )
+        })
 }
 
 fn resolve_zotero_profile_dir() -> Result<PathBuf, String> {
@@ -125,7 +372,7 @@ fn resolve_zotero_profile_dir() -> Result<PathBuf, String> {
         .ok_or_else(|| format!("failed to resolve Zotero profile directory from {}", sqlite_path.display()))
 }
 
-fn sqlite_file_uri(path: &PathBuf) -> String {
+fn sqlite_file_uri(path: &Path) -> String {
     let escaped = path
         .to_string_lossy()
         .replace('%', "%25")
@@ -135,18 +382,18 @@ fn sqlite_file_uri(path: &PathBuf) -> String {
     format!("file:{escaped}?immutable=1")
 }
 
-fn open_zotero_connection() -> Result<Connection, String> {
-  let path = resolve_zotero_sqlite_path()?;
-  let uri = sqlite_file_uri(&path);
-
-    Connection::open_with_flags(
-        uri,
-        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
-    )
-  .map_err(|err| format!("failed to open Zotero database {}: {err}", path.display()))
+/// A read-only zotero.sqlite connection from the pool, reading a snapshot
+/// while Zotero holds the file mid-write.
+fn open_zotero_connection(app: &AppHandle) -> Result<Pooled<'_, ZoteroDb>, AppError> {
+    app.state::<DbPool>().inner().zotero(&resolve_zotero_sqlite_path()?)
 }
 
 fn resolve_better_bibtex_sqlite_path() -> Option<PathBuf> {
+    if let Some(dir) = configured_zotero_data_dir() {
+        let candidate = dir.join("better-bibtex.sqlite");
+        return candidate.exists().then_some(candidate);
+    }
+
     if let Ok(path) = std::env::var("ZOTERO_BBT_SQLITE_PATH") {
         let candidate = PathBuf::from(path.trim());
         if candidate.exists() {
@@ -155,27 +402,36 @@ fn resolve_better_bibtex_sqlite_path() -> Option<PathBuf> {
     }
 
     let home = home_dir().ok()?;
-    let candidates = [
-        home.join("Zotero").join("better-bibtex.sqlite"),
-        home.join("Zotero Beta").join("better-bibtex.sqlite"),
-    ];
-
-    candidates.into_iter().find(|path| path.exists())
+    zotero_profile::default_data_dirs(&home)
+        .into_iter()
+        .map(|dir| dir.join("better-bibtex.sqlite"))
+        .find(|path| path.exists())
+        .or_else(|| {
+            zotero_profile::discover_data_dir(&home)
+                .map(|dir| dir.join("better-bibtex.sqlite"))
+                .filter(|path| path.exists())
+        })
 }
 
-fn open_better_bibtex_connection() -> Result<Connection, String> {
+fn open_better_bibtex_connection(app: &AppHandle) -> Result<Pooled<'_, Connection>, AppError> {
     let path = resolve_better_bibtex_sqlite_path()
-        .ok_or_else(|| "Could not locate better-bibtex.sqlite".to_string())?;
-    let uri = sqlite_file_uri(&path);
+        .ok_or_else(|| AppError::new(ErrorCode::ZoteroNotFound, "Could not locate better-bibtex.sqlite"))?;
+    app.state::<DbPool>().inner().better_bibtex(&path, open_better_bibtex_file)
+}
+
+fn open_better_bibtex_file(path: &Path) -> Result<Connection, AppError> {
+    let uri = sqlite_file_uri(path);
 
     Connection::open_with_flags(
         uri,
         OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
     )
-    .map_err(|err| format!("failed to open Better BibTeX database {}: {err}", path.display()))
+    .map_err(|err| {
+        AppError::sqlite(&format!("failed to open Better BibTeX database {}", path.display()), &err)
+    })
 }
 
-fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+fn app_config_dir(app: &AppHandle) -> Result<PathBuf, String> {
     let config_dir = app
         .path()
         .app_config_dir()
@@ -188,10 +444,39 @@ fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
         )
     })?;
 
-    Ok(config_dir.join("settings.json"))
+    Ok(config_dir)
+}
+
+/// Settings file of the active profile.
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app_config_dir(app)?;
+    let profile = profiles::active_profile(&config_dir);
+    Ok(profiles::settings_file(&config_dir, &profile))
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("failed to resolve app data directory: {err}"))?;
+
+    std::fs::create_dir_all(&data_dir).map_err(|err| {
+        format!(
+            "failed to create app data directory {}: {err}",
+            data_dir.display()
+        )
+    })?;
+
+    Ok(data_dir)
+}
+
+fn open_state_db(app: &AppHandle) -> Result<StateDb, String> {
+    let data_dir = app_data_dir(app)?;
+    let profile = profiles::active_profile(&app_config_dir(app)?);
+    StateDb::open(&data_dir.join(profiles::state_db_file_name(&profile)))
 }
 
-fn ensure_parent(path: &PathBuf) -> Result<(), String> {
+fn ensure_parent(path: &Path) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent).map_err(|err| {
             format!(
@@ -212,164 +497,1050 @@ fn select_directory_dialog() -> Option<String> {
 }
 
 #[tauri::command]
-fn ensure_dir(path: String) -> Result<(), String> {
+fn select_save_file_dialog(default_name: String) -> Option<String> {
+    rfd::FileDialog::new()
+        .set_file_name(default_name)
+        .save_file()
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn select_open_file_dialog() -> Option<String> {
+    rfd::FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .pick_file()
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn ensure_dir(app: AppHandle, path: String) -> Result<(), AppError> {
+    write_scope::check(&read_settings(&app)?, Path::new(&path))?;
     std::fs::create_dir_all(&path)
-        .map_err(|err| format!("failed to create directory {path}: {err}"))
+        .map_err(|err| format!("failed to create directory {path}: {err}").into())
 }
 
 #[tauri::command]
-fn save_markdown_file(path: String, content: String) -> Result<(), String> {
+fn save_markdown_file(
+    app: AppHandle,
+    path: String,
+    content: String,
+    annotations: Option<Vec<AnnotationStamp>>,
+    item_key: Option<String>,
+) -> Result<NoteWrite, AppError> {
+    let settings = read_settings(&app)?;
+    let recorded = match item_key {
+        Some(key) => open_state_db(&app)?.item(&key)?,
+        None => None,
+    };
+
+    let annotations = annotations.unwrap_or_default();
     let destination = PathBuf::from(&path);
+    write_scope::check(&settings, &destination)?;
+    let keys = annotations.iter().map(|annotation| annotation.key.clone()).collect::<Vec<_>>();
+    let content = managed::merge_with_previous(&destination, content, &keys, settings.deleted_annotations);
+
     ensure_parent(&destination)?;
-    std::fs::write(&destination, content).map_err(|err| {
-        format!(
-            "failed to write markdown file {}: {err}",
-            destination.display()
+    Ok(report::write_note(
+        &destination,
+        &content,
+        &annotations,
+        recorded
+            .as_ref()
+            .filter(|record| record.markdown_path == path)
+            .map(|record| record.content_hash.as_str()),
+        settings.conflict_strategy,
+    )?)
+}
+
+/// Appends the export report to the configured changelog note and, with
+/// `git.autoCommit`, commits the changed files. Returns the changelog path,
+/// or `None` when no changelog note is configured.
+#[tauri::command]
+fn write_export_changelog(app: AppHandle, report: ExportReport) -> Result<Option<String>, AppError> {
+    Ok(record_export_report(&read_settings(&app)?, &report)?)
+}
+
+/// Writes the changelog entry for a finished export and commits the notes
+/// when `git.autoCommit` is on. A failed commit is logged; the export
+/// itself already succeeded.
+fn record_export_report(settings: &AppSettings, report: &ExportReport) -> Result<Option<String>, String> {
+    let changelog = append_report_changelog(settings, report)?;
+    let markdown_dir = settings.markdown_dir.trim();
+    if settings.git.auto_commit && !report.dry_run && !markdown_dir.is_empty() {
+        match git::commit_report(Path::new(markdown_dir), report, changelog.as_deref()) {
+            Ok(Some(commit)) => tracing::info!(commit = %commit, "committed exported notes"),
+            Ok(None) => {}
+            Err(err) => tracing::warn!("failed to commit exported notes: {err}"),
+        }
+    }
+    Ok(changelog)
+}
+
+fn append_report_changelog(settings: &AppSettings, report: &ExportReport) -> Result<Option<String>, String> {
+    let note_name = settings.changelog_note.trim();
+    if note_name.is_empty() || settings.markdown_dir.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let destination = PathBuf::from(settings.markdown_dir.trim()).join(note_name);
+    write_scope::check(settings, &destination)?;
+    ensure_parent(&destination)?;
+    report::append_changelog(&destination, report, &settings.template_settings.date_format)?;
+    Ok(Some(destination.to_string_lossy().to_string()))
+}
+
+/// Remembers which annotations went into an item's note so later syncs can
+/// skip items that have not changed.
+#[tauri::command]
+fn record_item_export(
+    app: AppHandle,
+    item_key: String,
+    cite_key: String,
+    markdown_path: String,
+    item_version: Option<i64>,
+    annotations: Vec<AnnotationStamp>,
+    content_hash: Option<String>,
+) -> Result<(), AppError> {
+    let content_hash = match content_hash {
+        Some(hash) => hash,
+        None => {
+            let content = std::fs::read(&markdown_path)
+                .map_err(|err| format!("failed to read exported note {markdown_path}: {err}"))?;
+            state_db::content_hash(&content)
+        }
+    };
+
+    Ok(open_state_db(&app)?.record_item(
+        &item_key,
+        &cite_key,
+        &markdown_path,
+        item_version.unwrap_or(0),
+        &content_hash,
+        &annotations,
+    )?)
+}
+
+#[tauri::command]
+fn get_item_export_record(app: AppHandle, item_key: String) -> Result<Option<ItemExportRecord>, AppError> {
+    Ok(open_state_db(&app)?.item(&item_key)?)
+}
+
+#[tauri::command]
+fn open_in_editor(app: AppHandle, path: String) -> Result<(), AppError> {
+    if joplin::note_id(&path).is_some() {
+        return Ok(editor::open_uri(&path)?);
+    }
+    let settings = read_settings(&app)?;
+    if settings.editor_command.trim().eq_ignore_ascii_case("obsidian") {
+        open_obsidian_note(&settings, Path::new(&path))?;
+        return Ok(());
+    }
+    Ok(editor::open_in_editor(&settings.editor_command, Path::new(&path))?)
+}
+
+/// Opens the note at `path` through its `obsidian://open` URI and returns
+/// the URI.
+fn open_obsidian_note(settings: &AppSettings, path: &Path) -> Result<String, String> {
+    if !path.is_file() {
+        return Err(format!("file does not exist: {}", path.display()));
+    }
+    let uri = obsidian::note_uri(&settings.obsidian, &settings.markdown_dir, path)?;
+    editor::open_uri(&uri)?;
+    Ok(uri)
+}
+
+/// Opens an exported note in Obsidian and returns the `obsidian://` URI
+/// used. The vault is `obsidian.vault`, or the folder found above the note.
+#[tauri::command]
+fn open_in_obsidian(app: AppHandle, path: String) -> Result<String, AppError> {
+    Ok(open_obsidian_note(&read_settings(&app)?, Path::new(&path))?)
+}
+
+/// Shows a note or asset folder in Finder, Explorer or the Linux file
+/// manager, selected where the platform supports it.
+#[tauri::command]
+fn reveal_in_file_manager(path: String) -> Result<(), AppError> {
+    Ok(editor::reveal_in_file_manager(Path::new(&path))?)
+}
+
+/// Runs `work` with an exporter that plans writes without making them, for
+/// rendering a note outside an export.
+fn with_dry_run_exporter<T>(
+    app: &AppHandle,
+    work: impl FnOnce(&export::Exporter) -> Result<T, String>,
+) -> Result<T, AppError> {
+    let settings = read_settings(app)?;
+    let state = open_state_db(app)?;
+    let conn = open_zotero_connection(app)?;
+    let citations = open_better_bibtex_connection(app).ok();
+    let data_dir = app_data_dir(app)?;
+    let exporter = export::Exporter {
+        settings: &settings,
+        zotero: &conn,
+        citations: citations.as_deref(),
+        state: &state,
+        data_dir: &data_dir,
+        dry_run: true,
+    };
+    Ok(work(&exporter)?)
+}
+
+/// Copies a plain-text reference for the item and returns it.
+#[tauri::command]
+async fn copy_citation(app: AppHandle, item_key: String) -> Result<String, AppError> {
+    run_blocking(move || {
+        let item = load_sqlite_item(&*open_zotero_connection(&app)?, &item_key)?;
+        let reference = citation::format_reference(&item);
+        clipboard::copy_text(&reference)?;
+        Ok(reference)
+    })
+    .await
+}
+
+/// Copies a Pandoc citation, `[@citekey]`, and returns it.
+#[tauri::command]
+async fn copy_pandoc_citation(app: AppHandle, item_key: String) -> Result<String, AppError> {
+    run_blocking(move || {
+        let citation = with_dry_run_exporter(&app, |exporter| {
+            let item = load_sqlite_item(exporter.zotero, &item_key)?;
+            Ok(format!("[@{}]", exporter.resolve_cite_key(&item, &item_key)?))
+        })?;
+        clipboard::copy_text(&citation)?;
+        Ok(citation)
+    })
+    .await
+}
+
+/// Copies the item's note as an export would write it now, and returns it.
+/// Nothing is written; images are linked where the export would put them.
+#[tauri::command]
+async fn copy_note_markdown(app: AppHandle, item_key: String) -> Result<String, AppError> {
+    run_blocking(move || {
+        let markdown = with_dry_run_exporter(&app, |exporter| Ok(exporter.render_item(&item_key)?.markdown))?;
+        clipboard::copy_text(&markdown)?;
+        Ok(markdown)
+    })
+    .await
+}
+
+/// Library part of a `zotero://` URI for the item: `library` for the
+/// user's library, or `groups/<id>` when the item is in a group.
+fn zotero_library_path(conn: &Connection, item_key: &str) -> Result<String, String> {
+    let group_id = conn
+        .query_row(
+            "SELECT g.groupID FROM items i
+             LEFT JOIN groups g ON g.libraryID = i.libraryID
+             WHERE i.key = ?1",
+            [item_key],
+            |row| row.get::<_, Option<i64>>(0),
         )
+        .optional()
+        .map_err(|err| format!("failed to look up Zotero item {item_key}: {err}"))?
+        .ok_or_else(|| format!("Zotero item {item_key} was not found."))?;
+    Ok(match group_id {
+        Some(id) => format!("groups/{id}"),
+        None => "library".to_string(),
     })
 }
 
+/// Zotero item keys are eight letters and digits; checking this keeps them
+/// safe to put in a URI handed to the OS.
+fn check_item_key(item_key: &str) -> Result<(), String> {
+    if item_key.len() == 8 && item_key.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+        Ok(())
+    } else {
+        Err(format!("{item_key:?} is not a Zotero item key."))
+    }
+}
+
+/// Selects the item in the Zotero desktop app.
 #[tauri::command]
-fn save_png_bytes(path: String, bytes: Vec<u8>) -> Result<(), String> {
-    let destination = PathBuf::from(&path);
-    ensure_parent(&destination)?;
-    std::fs::write(&destination, bytes)
-        .map_err(|err| format!("failed to write png bytes {}: {err}", destination.display()))
+async fn open_in_zotero(app: AppHandle, item_key: String) -> Result<(), AppError> {
+    run_blocking(move || {
+        check_item_key(&item_key)?;
+        let library = zotero_library_path(&*open_zotero_connection(&app)?, &item_key)?;
+        Ok(editor::open_uri(&format!("zotero://select/{library}/items/{item_key}"))?)
+    })
+    .await
 }
 
+/// Opens the annotation's attachment in Zotero's reader, scrolled to the
+/// annotation. The page is taken from the annotation's reader position,
+/// when it has one, so the right page shows even before Zotero finds the
+/// annotation.
 #[tauri::command]
-fn load_settings(app: AppHandle) -> Result<AppSettings, String> {
-    let path = settings_path(&app)?;
+async fn open_annotation_in_zotero(app: AppHandle, annotation_key: String) -> Result<(), AppError> {
+    run_blocking(move || {
+        check_item_key(&annotation_key)?;
+        let conn = open_zotero_connection(&app)?;
+        let (attachment_key, position) = conn
+            .query_row(
+                "SELECT att.key, COALESCE(ia.position, '')
+                 FROM items anno
+                 JOIN itemAnnotations ia ON ia.itemID = anno.itemID
+                 JOIN items att ON att.itemID = ia.parentItemID
+                 WHERE anno.key = ?1",
+                [&annotation_key],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()
+            .map_err(|err| format!("failed to load annotation {annotation_key}: {err}"))?
+            .ok_or_else(|| format!("annotation {annotation_key} not found in Zotero."))?;
+
+        let library = zotero_library_path(&conn, &attachment_key)?;
+        let mut uri = format!("zotero://open-pdf/{library}/items/{attachment_key}?");
+        if let Ok(position) = AnnotationPosition::parse(&position) {
+            uri.push_str(&format!("page={}&", u32::from(position.page_index) + 1));
+        }
+        uri.push_str(&format!("annotation={annotation_key}"));
+        Ok(editor::open_uri(&uri)?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn save_annotation_image(
+    app: AppHandle,
+    path: String,
+    bytes: Vec<u8>,
+    image_settings: Option<ImageSettings>,
+) -> Result<String, AppError> {
+    let settings = image_settings.unwrap_or_default();
+    let destination = images::with_format_extension(Path::new(&path), settings.format);
+    write_scope::check(&read_settings(&app)?, &destination)?;
+    let encoded = images::transcode(&bytes, &settings)?;
+
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, encoded).map_err(|err| {
+        format!(
+            "failed to write annotation image {}: {err}",
+            destination.display()
+        )
+    })?;
+    if let Some(dark_settings) = images::dark_variant_settings(&settings) {
+        write_dark_variant(&bytes, &dark_settings, &destination)?;
+    }
+
+    Ok(destination.to_string_lossy().to_string())
+}
+
+fn read_settings(app: &AppHandle) -> Result<AppSettings, String> {
+    read_settings_file(&settings_path(app)?)
+}
+
+/// Reads the settings at `path`, or the defaults when there is no file yet.
+fn read_settings_file(path: &Path) -> Result<AppSettings, String> {
     if !path.exists() {
-        return Ok(AppSettings::default());
+        let defaults = AppSettings::default();
+        apply_runtime_settings(&defaults);
+        return Ok(defaults);
     }
 
-    let raw = std::fs::read_to_string(&path)
+    let raw = std::fs::read_to_string(path)
         .map_err(|err| format!("failed to read settings {}: {err}", path.display()))?;
 
     let parsed = serde_json::from_str::<AppSettings>(&raw)
         .map_err(|err| format!("failed to parse settings {}: {err}", path.display()))?;
 
+    apply_runtime_settings(&parsed);
     Ok(parsed)
 }
 
 #[tauri::command]
-fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
-    let path = settings_path(&app)?;
-    let raw = serde_json::to_string_pretty(&settings)
-        .map_err(|err| format!("failed to serialize settings: {err}"))?;
+fn save_item_asset(
+    app: AppHandle,
+    item_key: String,
+    cite_key: String,
+    file_name: String,
+    bytes: Vec<u8>,
+    note_dir: Option<String>,
+    annotation_key: Option<String>,
+) -> Result<SavedAsset, AppError> {
+    let settings = read_settings(&app)?;
+    let note_dir = PathBuf::from(note_dir.as_deref().unwrap_or(&settings.markdown_dir).trim());
+    let (saved, content_hash, _) =
+        write_item_asset(&settings, &item_key, &cite_key, &file_name, &bytes, &note_dir, false)?;
+
+    if let Some(annotation_key) = annotation_key.as_deref() {
+        open_state_db(&app)?.record_image(&item_key, annotation_key, &saved.path, &content_hash)?;
+    }
 
-    std::fs::write(&path, raw)
-        .map_err(|err| format!("failed to write settings {}: {err}", path.display()))
+    Ok(saved)
 }
 
-#[tauri::command]
-fn write_temp_debug_dump(prefix: String, content: String) -> Result<String, String> {
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|err| format!("failed to compute timestamp: {err}"))?
-        .as_secs();
+/// Transcodes and writes an item image into its asset folder. Returns the
+/// saved asset, the hash of the encoded bytes and the file change, if any.
+/// With `dry_run` nothing is written.
+fn write_item_asset(
+    settings: &AppSettings,
+    item_key: &str,
+    cite_key: &str,
+    file_name: &str,
+    bytes: &[u8],
+    note_dir: &Path,
+    dry_run: bool,
+) -> Result<(SavedAsset, String, Option<FileChange>), String> {
+    if settings.attachment_base_dir.trim().is_empty() {
+        return Err("attachment base directory is not configured.".to_string());
+    }
 
-    let sanitized_prefix = prefix
-        .chars()
-        .filter(|ch| ch.is_ascii_alphanumeric() || *ch == '-' || *ch == '_')
-        .collect::<String>();
+    let context = AssetContext { cite_key, item_key };
+    let relative_dir = assets::render_asset_dir(&settings.asset_path_template, &context);
+    let asset_dir = PathBuf::from(settings.attachment_base_dir.trim()).join(&relative_dir);
 
-    let file_name = if sanitized_prefix.is_empty() {
-        format!("zotero-debug-{timestamp}.json")
-    } else {
-        format!("{sanitized_prefix}-{timestamp}.json")
-    };
+    let relative_file = images::with_format_extension(
+        &relative_dir.join(file_name),
+        settings.image_settings.format,
+    );
+    let destination = PathBuf::from(settings.attachment_base_dir.trim()).join(&relative_file);
+    write_scope::check(settings, &destination)?;
+    let encoded = images::transcode(bytes, &settings.image_settings)?;
+    let change = FileChange::for_content(&destination, &encoded);
+    if change.is_some() && !dry_run {
+        std::fs::create_dir_all(&asset_dir).map_err(|err| {
+            format!(
+                "failed to create asset directory {}: {err}",
+                asset_dir.display()
+            )
+        })?;
+        std::fs::write(&destination, &encoded).map_err(|err| {
+            format!(
+                "failed to write annotation image {}: {err}",
+                destination.display()
+            )
+        })?;
+    }
 
-    let path = std::env::temp_dir().join(file_name);
-    std::fs::write(&path, content)
-        .map_err(|err| format!("failed to write debug dump {}: {err}", path.display()))?;
+    let link = assets::image_link(
+        settings.image_link_style,
+        &destination,
+        &relative_file,
+        note_dir,
+    );
 
-    Ok(path.to_string_lossy().to_string())
+    let mut saved = SavedAsset {
+        path: destination.to_string_lossy().to_string(),
+        link,
+        dark_path: None,
+        dark_link: None,
+    };
+    if let Some(dark_settings) = images::dark_variant_settings(&settings.image_settings) {
+        let dark_destination = images::dark_variant_path(&destination);
+        if !dry_run {
+            write_dark_variant(bytes, &dark_settings, &destination)?;
+        }
+        saved.dark_link = Some(assets::image_link(
+            settings.image_link_style,
+            &dark_destination,
+            &images::dark_variant_path(&relative_file),
+            note_dir,
+        ));
+        saved.dark_path = Some(dark_destination.to_string_lossy().to_string());
+    }
+    Ok((saved, state_db::content_hash(&encoded), change))
 }
 
-fn apply_api_key(mut headers: HeaderMap, zotero_api_key: Option<String>) -> HeaderMap {
-    if let Some(key) = zotero_api_key {
-        if !key.trim().is_empty() {
-            if let Ok(value) = reqwest::header::HeaderValue::from_str(key.trim()) {
-                headers.insert("Zotero-API-Key", value);
+/// Writes the inverted copy of an image saved at `destination`, which was
+/// already checked against the write scope and shares its folder.
+fn write_dark_variant(bytes: &[u8], dark_settings: &ImageSettings, destination: &Path) -> Result<(), String> {
+    let dark_destination = images::dark_variant_path(destination);
+    let encoded = images::transcode(bytes, dark_settings)?;
+    if FileChange::for_content(&dark_destination, &encoded).is_none() {
+        return Ok(());
+    }
+    std::fs::write(&dark_destination, encoded).map_err(|err| {
+        format!(
+            "failed to write dark image {}: {err}",
+            dark_destination.display()
+        )
+    })
+}
+
+/// Resolves `keys` and every ancestor collection to key/name pairs. Without
+/// a readable Zotero database only the keys themselves are returned.
+fn zotero_collection_refs(conn: Option<&Connection>, keys: &[String]) -> Vec<CollectionRef> {
+    let key_only = || {
+        keys.iter()
+            .map(|key| CollectionRef {
+                key: key.clone(),
+                name: String::new(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let Some(conn) = conn else {
+        return key_only();
+    };
+    let Ok(mut stmt) = conn.prepare(
+        r#"
+        WITH RECURSIVE lineage(collectionID, key, collectionName, parentCollectionID) AS (
+            SELECT collectionID, key, collectionName, parentCollectionID
+            FROM collections
+            WHERE key = ?1
+            UNION
+            SELECT c.collectionID, c.key, c.collectionName, c.parentCollectionID
+            FROM collections c
+            JOIN lineage l ON c.collectionID = l.parentCollectionID
+        )
+        SELECT key, collectionName FROM lineage
+        "#,
+    ) else {
+        return key_only();
+    };
+
+    let mut refs = Vec::<CollectionRef>::new();
+    for key in keys {
+        let rows = stmt.query_map(params![key], |row| {
+            Ok(CollectionRef {
+                key: row.get(0)?,
+                name: row.get(1)?,
+            })
+        });
+        let Ok(rows) = rows else {
+            continue;
+        };
+        for collection in rows.flatten() {
+            if !refs.iter().any(|existing| existing.key == collection.key) {
+                refs.push(collection);
             }
         }
     }
 
-    headers
+    if refs.is_empty() {
+        key_only()
+    } else {
+        refs
+    }
 }
 
 #[tauri::command]
-async fn zotero_proxy_get_json(
-    url: String,
-    zotero_api_key: Option<String>,
-) -> Result<Value, String> {
-    let client = reqwest::Client::new();
-    let headers = apply_api_key(HeaderMap::new(), zotero_api_key);
-
-    let response = client
-        .get(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|err| format!("proxy request failed for {url}: {err}"))?;
-
-    let status = response.status();
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|err| format!("failed to read proxy response body: {err}"))?;
+fn resolve_export_target(
+    app: AppHandle,
+    item: Value,
+    cite_key: String,
+    template_settings: Option<TemplateSettings>,
+) -> Result<ExportTarget, AppError> {
+    let settings = read_settings(&app)?;
+    let conn = open_zotero_connection(&app).ok();
+    let collections = zotero_collection_refs(
+        conn.as_deref().map(|db| &**db),
+        &collections::item_collection_keys(&item),
+    );
 
-    if !status.is_success() {
-        let body = String::from_utf8_lossy(&bytes);
-        return Err(format!("Zotero HTTP {status}: {body}"));
+    let mut target = collections::resolve_target(
+        &settings.markdown_dir,
+        &settings.collection_overrides,
+        &collections,
+        &item,
+        &cite_key,
+        template_settings.unwrap_or(settings.template_settings),
+    );
+    // Notes mapped by `import_notes` stay where they are.
+    if let Some(item_key) = item.get("key").and_then(Value::as_str) {
+        if let Some(imported) = open_state_db(&app)?.imported_note_path(item_key)? {
+            target.markdown_path = imported;
+        }
     }
+    Ok(target)
+}
 
-    serde_json::from_slice(&bytes).or_else(|_| {
-        String::from_utf8(bytes.to_vec())
-            .map(Value::String)
-            .map_err(|err| format!("response was not valid JSON or UTF-8 text: {err}"))
-    })
+#[tauri::command]
+fn render_note(input: NoteInput, template_settings: Option<TemplateSettings>) -> String {
+    render::render_note(&input, &template_settings.unwrap_or_default())
 }
 
 #[tauri::command]
-async fn zotero_proxy_get_bytes(
-    url: String,
-    zotero_api_key: Option<String>,
-) -> Result<Vec<u8>, String> {
-    let client = reqwest::Client::new();
-    let headers = apply_api_key(HeaderMap::new(), zotero_api_key);
+fn html_to_markdown(html: String) -> String {
+    html::html_to_markdown(&html)
+}
 
-    let response = client
-        .get(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|err| format!("proxy request failed for {url}: {err}"))?;
+#[tauri::command]
+fn load_settings(app: AppHandle) -> Result<AppSettings, AppError> {
+    Ok(read_settings(&app)?)
+}
 
-    let status = response.status();
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|err| format!("failed to read proxy response body: {err}"))?
-        .to_vec();
+/// Emitted with the new settings after they are saved, patched, reset,
+/// imported or replaced by a profile switch.
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
 
-    if !status.is_success() {
-        let body = String::from_utf8_lossy(&bytes);
-        return Err(format!("Zotero HTTP {status}: {body}"));
-    }
+/// Serializes settings writes so a full save and a patch never drop each
+/// other's changes.
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
 
-    Ok(bytes)
+fn lock_settings() -> Result<std::sync::MutexGuard<'static, ()>, String> {
+    SETTINGS_LOCK
+        .lock()
+        .map_err(|_| "settings lock was poisoned by a failed save.".to_string())
+}
+
+/// Writes the settings to a temporary file and renames it over `path`, so
+/// readers never see a half-written file.
+fn write_settings(path: &Path, settings: &AppSettings) -> Result<(), String> {
+    let raw = serde_json::to_string_pretty(settings)
+        .map_err(|err| format!("failed to serialize settings: {err}"))?;
+
+    ensure_parent(path)?;
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, raw)
+        .map_err(|err| format!("failed to write settings {}: {err}", temp.display()))?;
+    std::fs::rename(&temp, path)
+        .map_err(|err| format!("failed to replace settings {}: {err}", path.display()))
+}
+
+/// Applies saved settings to the running app and tells every window and
+/// background task about them.
+fn settings_changed(app: &AppHandle, settings: &AppSettings) {
+    apply_runtime_settings(settings);
+    let _ = app.emit(SETTINGS_CHANGED_EVENT, settings);
+}
+
+/// Applies a JSON merge patch (RFC 7386): objects are merged key by key,
+/// `null` removes a key so it falls back to its default, and any other
+/// value replaces the old one.
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Validates and saves the settings; nothing is written while any field is
+/// invalid.
+#[tauri::command]
+fn save_settings(app: AppHandle, settings: AppSettings) -> Result<(), AppError> {
+    let _guard = lock_settings()?;
+    let invalid = validation::validate(&settings);
+    if !invalid.is_empty() {
+        return Err(AppError::invalid_settings(invalid));
+    }
+
+    write_settings(&settings_path(&app)?, &settings)?;
+    settings_changed(&app, &settings);
+    Ok(())
+}
+
+/// `current` with `patch` merged in (RFC 7386). Keys that are not settings
+/// are rejected, and only the top-level settings named in the patch are
+/// validated.
+fn patched_settings(current: &AppSettings, patch: Value) -> Result<AppSettings, Vec<validation::FieldError>> {
+    let form_error = |message: String| {
+        vec![validation::FieldError {
+            field: String::new(),
+            message,
+        }]
+    };
+    let Value::Object(fields) = &patch else {
+        return Err(form_error("A settings patch must be a JSON object.".to_string()));
+    };
+
+    let mut merged = serde_json::to_value(current)
+        .map_err(|err| form_error(format!("failed to serialize settings: {err}")))?;
+    let patched = fields.keys().cloned().collect::<Vec<_>>();
+    let unknown = patched
+        .iter()
+        .filter(|key| merged.get(key.as_str()).is_none())
+        .map(|key| validation::FieldError {
+            field: key.clone(),
+            message: format!("Unknown setting {key:?}."),
+        })
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+
+    merge_patch(&mut merged, patch);
+    let settings = serde_json::from_value::<AppSettings>(merged)
+        .map_err(|err| form_error(format!("failed to apply settings patch: {err}")))?;
+    let invalid = validation::validate(&settings)
+        .into_iter()
+        .filter(|error| {
+            let top = error.field.split('.').next().unwrap_or_default();
+            patched.iter().any(|key| key == top)
+        })
+        .collect::<Vec<_>>();
+    if !invalid.is_empty() {
+        return Err(invalid);
+    }
+    Ok(settings)
+}
+
+/// Merges `patch`, a partial settings object such as
+/// `{"templateSettings": {"tableOfContents": true}}`, into the saved settings and
+/// returns the result. Read, merge and write happen under one lock, so
+/// concurrent patches to different fields all apply.
+#[tauri::command]
+fn update_settings(app: AppHandle, patch: Value) -> Result<AppSettings, AppError> {
+    let _guard = lock_settings()?;
+    let settings = patched_settings(&read_settings(&app)?, patch).map_err(AppError::invalid_settings)?;
+
+    write_settings(&settings_path(&app)?, &settings)?;
+    settings_changed(&app, &settings);
+    Ok(settings)
+}
+
+/// Restores the defaults for the named sections (`templates`, `images`,
+/// `export`, `sync`, `orphans`, `logging`). Folders, the Zotero data
+/// directory, URLs and keys are kept. The previous file is backed up first.
+#[tauri::command]
+fn reset_settings(app: AppHandle, sections: Vec<String>) -> Result<SettingsReset, AppError> {
+    let _guard = lock_settings()?;
+    let mut settings = read_settings(&app)?;
+    settings_reset::reset_sections(&mut settings, &sections)?;
+
+    let path = settings_path(&app)?;
+    let backup = settings_reset::backup(&path)?;
+    write_settings(&path, &settings)?;
+    settings_changed(&app, &settings);
+    Ok(SettingsReset {
+        settings,
+        backup: backup.map(|path| path.display().to_string()),
+    })
+}
+
+/// Writes a zip for bug reports to `path`: the settings without the API
+/// key, recent logs, sync history, environment details and the Zotero
+/// schema version. Sources that cannot be read are noted instead.
+#[tauri::command]
+fn create_diagnostics_bundle(app: AppHandle, path: String) -> Result<String, AppError> {
+    let mut archive = diagnostics::ZipArchive::new();
+    let pretty = |value: &Value| serde_json::to_vec_pretty(value).unwrap_or_default();
+    let describe = |result: Result<PathBuf, String>| match result {
+        Ok(path) => path.display().to_string(),
+        Err(err) => err,
+    };
+
+    let settings = serde_json::to_value(read_settings(&app)?)
+        .map_err(|err| format!("failed to serialize settings: {err}"))?;
+    archive.add("settings.json", &pretty(&diagnostics::redacted_settings(&settings)))?;
+
+    let data_dir = app_data_dir(&app)?;
+    archive.add("zotnotes.log", logging::recent_lines(&data_dir, 5_000).join("\n").as_bytes())?;
+
+    let history = open_state_db(&app)
+        .and_then(|state| state.sync_runs(50))
+        .and_then(|runs| serde_json::to_value(runs).map_err(|err| err.to_string()));
+    let history = history.unwrap_or_else(|err| serde_json::json!({ "error": err }));
+    archive.add("sync-history.json", &pretty(&history))?;
+
+    let schema = open_zotero_connection(&app)
+        .and_then(|conn| Ok(diagnostics::zotero_schema_versions(&conn)?))
+        .unwrap_or_else(|err| serde_json::json!({ "error": err }));
+    let environment = diagnostics::environment(serde_json::json!({
+        "profile": profiles::active_profile(&app_config_dir(&app)?),
+        "zoteroSqlite": describe(resolve_zotero_sqlite_path().map_err(String::from)),
+        "betterBibtexSqlite": describe(
+            resolve_better_bibtex_sqlite_path().ok_or_else(|| "not found".to_string())
+        ),
+        "zoteroSchema": schema,
+    }));
+    archive.add("environment.json", &pretty(&environment))?;
+
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, archive.finish())
+        .map_err(|err| format!("failed to write diagnostics bundle {}: {err}", destination.display()))?;
+    tracing::info!("diagnostics bundle written to {path}");
+    Ok(path)
+}
+
+/// The last `lines` lines of the log file (200 by default), oldest first.
+#[tauri::command]
+fn get_recent_logs(app: AppHandle, lines: Option<usize>) -> Result<Vec<String>, AppError> {
+    Ok(logging::recent_lines(&app_data_dir(&app)?, lines.unwrap_or(200)))
+}
+
+/// Checks zotero.sqlite, Better BibTeX, the local Zotero API and the web API
+/// key in one call. `settings` are the possibly unsaved values from the
+/// settings dialog.
+#[tauri::command]
+async fn test_connections(settings: AppSettings) -> Result<ConnectionReport, AppError> {
+    let data_dir = settings.zotero_data_dir.trim().to_string();
+    let (zotero_database, better_bibtex) = tauri::async_runtime::spawn_blocking(move || {
+        let (zotero, citations) = if data_dir.is_empty() {
+            let citations = resolve_better_bibtex_sqlite_path()
+                .ok_or_else(|| "Could not locate better-bibtex.sqlite".to_string());
+            (
+                resolve_zotero_sqlite_path()
+                    .and_then(|path| zotero_db::open(&path))
+                    .map_err(String::from),
+                citations.and_then(|path| open_better_bibtex_file(&path).map_err(String::from)),
+            )
+        } else {
+            let path = Path::new(&data_dir).join("zotero.sqlite");
+            let zotero = if path.exists() {
+                zotero_db::open(&path).map_err(String::from)
+            } else {
+                Err(format!("{data_dir} does not contain zotero.sqlite."))
+            };
+            (zotero, connections::open_in_data_dir(&data_dir, "better-bibtex.sqlite"))
+        };
+        (
+            zotero.and_then(|db| {
+                let message = connections::check_zotero_database(&db)?;
+                Ok(match db.freshness {
+                    zotero_db::Freshness::Snapshot { reason, .. } => {
+                        format!("{message} Read from a copy because the live file failed: {reason}")
+                    }
+                    _ => message,
+                })
+            }),
+            citations.and_then(|conn| connections::check_better_bibtex(&conn)),
+        )
+    })
+    .await
+    .map_err(|err| format!("connection test failed: {err}"))?;
+
+    let client = reqwest::Client::new();
+    Ok(ConnectionReport {
+        zotero_database: zotero_database.into(),
+        better_bibtex: better_bibtex.into(),
+        local_api: connections::check_local_api(&client, &settings).await.into(),
+        web_api: connections::check_web_api(&client, &settings).await.into(),
+    })
+}
+
+/// Writes the active profile's settings, minus the API key, to `path`.
+#[tauri::command]
+fn export_settings_bundle(app: AppHandle, path: String) -> Result<(), AppError> {
+    let raw = settings_bundle::to_bundle(&read_settings(&app)?)?;
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, raw)
+        .map_err(|err| format!("failed to write settings bundle {}: {err}", destination.display()).into())
+}
+
+/// Replaces the active profile's settings with those in the bundle at
+/// `path`, keeping the current API key, and returns the new settings. They
+/// are not validated: paths from another machine are fixed up afterwards
+/// in the settings dialog.
+#[tauri::command]
+fn import_settings_bundle(app: AppHandle, path: String) -> Result<AppSettings, AppError> {
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|err| format!("failed to read settings bundle {path}: {err}"))?;
+    let _guard = lock_settings()?;
+    let settings = settings_bundle::from_bundle(&raw, &read_settings(&app)?)?;
+    write_settings(&settings_path(&app)?, &settings)?;
+    settings_changed(&app, &settings);
+    Ok(settings)
+}
+
+#[tauri::command]
+fn list_profiles(app: AppHandle) -> Result<ProfileList, AppError> {
+    Ok(profiles::list(&app_config_dir(&app)?)?)
+}
+
+/// Creates a profile from a copy of the active profile's settings, or from
+/// the defaults. The active profile does not change.
+#[tauri::command]
+fn create_profile(app: AppHandle, name: String, copy_current: Option<bool>) -> Result<ProfileList, AppError> {
+    let config_dir = app_config_dir(&app)?;
+    let name = profiles::validate_name(&name)?;
+    let path = profiles::settings_file(&config_dir, &name);
+    if path.exists() {
+        return Err(format!("Profile {name:?} already exists.").into());
+    }
+
+    let settings = if copy_current.unwrap_or(false) {
+        read_settings(&app)?
+    } else {
+        AppSettings::default()
+    };
+    write_settings(&path, &settings)?;
+    Ok(profiles::list(&config_dir)?)
+}
+
+/// Makes `name` the active profile and returns its settings. Waits for a
+/// running sync so it never mixes two profiles' settings.
+#[tauri::command]
+fn switch_profile(app: AppHandle, name: String) -> Result<AppSettings, AppError> {
+    let _guard = SYNC_LOCK
+        .lock()
+        .map_err(|_| "sync lock was poisoned by a failed sync.".to_string())?;
+
+    let name = profiles::validate_name(&name)?;
+    profiles::set_active(&app_config_dir(&app)?, &name)?;
+    let settings = read_settings(&app)?;
+    settings_changed(&app, &settings);
+    Ok(settings)
+}
+
+/// Deletes a profile's settings file. Its state database is kept, so
+/// re-creating the profile picks up its export records again.
+#[tauri::command]
+fn delete_profile(app: AppHandle, name: String) -> Result<ProfileList, AppError> {
+    let config_dir = app_config_dir(&app)?;
+    let name = profiles::validate_name(&name)?;
+    if name == profiles::DEFAULT_PROFILE {
+        return Err("The default profile cannot be deleted.".to_string().into());
+    }
+    if profiles::active_profile(&config_dir) == name {
+        return Err("Switch to another profile before deleting this one.".to_string().into());
+    }
+
+    let path = profiles::settings_file(&config_dir, &name);
+    std::fs::remove_file(&path)
+        .map_err(|err| format!("failed to delete profile {}: {err}", path.display()))?;
+    Ok(profiles::list(&config_dir)?)
+}
+
+/// Writes a debug dump with secrets redacted; see [`debug_dump`].
+#[tauri::command]
+fn write_temp_debug_dump(app: AppHandle, prefix: String, content: String) -> Result<String, AppError> {
+    let settings = serde_json::to_value(read_settings(&app)?)
+        .map_err(|err| format!("failed to serialize settings: {err}"))?;
+    let secrets = diagnostics::secret_values(&settings);
+    let secrets = secrets.iter().map(String::as_str).collect::<Vec<_>>();
+    let path = debug_dump::write(&prefix, content, &secrets)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn apply_api_key(mut headers: HeaderMap, zotero_api_key: Option<String>) -> HeaderMap {
+    if let Some(key) = zotero_api_key {
+        if !key.trim().is_empty() {
+            if let Ok(value) = reqwest::header::HeaderValue::from_str(key.trim()) {
+                headers.insert("Zotero-API-Key", value);
+            }
+        }
+    }
+
+    headers
+}
+
+#[tauri::command]
+async fn zotero_proxy_get_json(
+    url: String,
+    zotero_api_key: Option<String>,
+) -> Result<Value, AppError> {
+    let client = reqwest::Client::new();
+    let headers = apply_api_key(HeaderMap::new(), zotero_api_key);
+
+    let response = client
+        .get(&url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|err| AppError::request(&url, &err))?;
+
+    let status = response.status();
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|err| AppError::request(&url, &err))?;
+
+    if !status.is_success() {
+        return Err(AppError::http_status(&url, status, &String::from_utf8_lossy(&bytes)));
+    }
+
+    serde_json::from_slice(&bytes).or_else(|_| {
+        String::from_utf8(bytes.to_vec())
+            .map(Value::String)
+            .map_err(|err| format!("response was not valid JSON or UTF-8 text: {err}").into())
+    })
+}
+
+/// Downloads `url` as a raw IPC response, which reaches the webview as an
+/// `ArrayBuffer` rather than a JSON array of numbers.
+#[tauri::command]
+async fn zotero_proxy_get_bytes(
+    app: AppHandle,
+    url: String,
+    zotero_api_key: Option<String>,
+    operation_id: Option<String>,
+) -> Result<Response, AppError> {
+    let client = reqwest::Client::new();
+    let headers = apply_api_key(HeaderMap::new(), zotero_api_key);
+
+    let mut response = client
+        .get(&url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|err| AppError::request(&url, &err))?;
+
+    // Downloads report progress only when the caller asked for it; image
+    // fetches during an export are too small to be worth the events.
+    let progress = operation_id.map(|id| Progress::new(&app, "download", Some(id)));
+    let total = response.content_length().unwrap_or(0);
+    let status = response.status();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| AppError::request(&url, &err))?
+    {
+        bytes.extend_from_slice(&chunk);
+        if let Some(progress) = &progress {
+            progress.report(bytes.len() as u64, total, &url);
+        }
+    }
+
+    if !status.is_success() {
+        return Err(AppError::http_status(&url, status, &String::from_utf8_lossy(&bytes)));
+    }
+
+    Ok(Response::new(bytes))
+}
+
+/// Runs SQLite work on the blocking thread pool, so a large query never
+/// holds up the IPC thread and the UI stays responsive.
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|err| format!("database task failed: {err}"))?
 }
 
 #[tauri::command]
-fn zotero_sqlite_search_items(query: String) -> Result<Vec<SqliteItemSummary>, String> {
-    let conn = open_zotero_connection()?;
+async fn zotero_sqlite_search_items(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SqliteItemSummary>, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+        Ok(substring_search(&app, &settings, &*open_zotero_connection(&app)?, &query, limit)?)
+    })
+    .await
+}
+
+/// Substring search of titles, creators and years: through the shadow
+/// table when the full-text index is off, else straight from zotero.sqlite.
+fn substring_search(
+    app: &AppHandle,
+    settings: &AppSettings,
+    conn: &Connection,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SqliteItemSummary>, String> {
+    if settings.full_text_search {
+        return search_items(conn, query, limit);
+    }
+    search_shadow::search(conn, &open_state_db(app)?, query, limit)
+}
+
+fn search_items(conn: &Connection, query: &str, limit: usize) -> Result<Vec<SqliteItemSummary>, String> {
     let term = query.trim().to_string();
 
     let mut stmt = conn
-        .prepare(
+        .prepare_cached(
             r#"
             WITH title_data AS (
                 SELECT d.itemID AS itemID, CAST(v.value AS TEXT) AS value
@@ -401,17 +1572,33 @@ fn zotero_sqlite_search_items(query: String) -> Result<Vec<SqliteItemSummary>, S
                 FROM itemCreators ic
                 JOIN creators c ON c.creatorID = ic.creatorID
                 GROUP BY ic.itemID
+            ),
+            attachment_data AS (
+                SELECT
+                    iatt.parentItemID AS itemID,
+                    COUNT(DISTINCT iatt.itemID) AS attachments,
+                    COUNT(ia.itemID) AS annotations
+                FROM itemAttachments iatt
+                LEFT JOIN itemAnnotations ia
+                    ON ia.parentItemID = iatt.itemID
+                    AND ia.itemID NOT IN (SELECT itemID FROM deletedItems)
+                WHERE iatt.parentItemID IS NOT NULL
+                  AND iatt.itemID NOT IN (SELECT itemID FROM deletedItems)
+                GROUP BY iatt.parentItemID
             )
             SELECT
                 i.key,
                 COALESCE(title_data.value, '(untitled)') AS title,
                 COALESCE(creator_data.value, '') AS creators,
-                COALESCE(date_data.value, '') AS dateValue
+                COALESCE(date_data.value, '') AS dateValue,
+                COALESCE(attachment_data.annotations, 0) AS annotationCount,
+                COALESCE(attachment_data.attachments, 0) AS attachmentCount
             FROM items i
             JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
             LEFT JOIN title_data ON title_data.itemID = i.itemID
             LEFT JOIN date_data ON date_data.itemID = i.itemID
             LEFT JOIN creator_data ON creator_data.itemID = i.itemID
+            LEFT JOIN attachment_data ON attachment_data.itemID = i.itemID
             WHERE
                 it.typeName NOT IN ('attachment', 'note', 'annotation')
                 AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
@@ -425,214 +1612,2024 @@ fn zotero_sqlite_search_items(query: String) -> Result<Vec<SqliteItemSummary>, S
             LIMIT ?2
             "#,
         )
-        .map_err(|err| format!("failed to prepare Zotero search query: {err}"))?;
+        .map_err(|err| format!("failed to prepare Zotero search query: {err}"))?;
+
+    let rows = stmt
+        .query_map(params![term, limit as i64], summary_from_row)
+        .map_err(|err| format!("failed to execute Zotero search query: {err}"))?;
+
+    let mut items = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to read Zotero search rows: {err}"))?;
+    highlight_substring_matches(&mut items, &term);
+    Ok(items)
+}
+
+/// Fills in where `term` occurs in each item, as one substring, ignoring
+/// case, the way the substring searches match.
+fn highlight_substring_matches(items: &mut [SqliteItemSummary], term: &str) {
+    let needle = [term.to_lowercase()];
+    for item in items {
+        item.matches = highlight::field_matches(
+            [
+                ("title", item.title.as_str()),
+                ("creators", item.creators.as_str()),
+                ("year", item.year.as_str()),
+            ],
+            |text| highlight::substring_ranges(text, &needle),
+        );
+    }
+}
+
+/// Searches the app's full-text index of titles, creators, years, abstracts
+/// and annotations. The index is refreshed first, which only re-reads items
+/// changed since the last search.
+#[tauri::command]
+async fn search_index(app: AppHandle, query: String, limit: Option<usize>) -> Result<Vec<IndexHit>, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+        let state = open_state_db(&app)?;
+        let conn = open_zotero_connection(&app)?;
+        let Some(query) = refreshed_search_query(&conn, &state, &query, settings.search_fuzziness)? else {
+            return Ok(Vec::new());
+        };
+        let mut hits = state.search_index(&query.expression(), limit, 0)?;
+        for hit in &mut hits {
+            query.highlight_hit(hit);
+        }
+        Ok(hits)
+    })
+    .await
+}
+
+/// Streaming variant of [`search_index`] for search-as-you-type: hits are
+/// emitted as `search-results` events in pages tagged with `search_id`.
+/// Starting another search, or calling [`cancel_search`], stops this one,
+/// interrupting its queries. Returns whether the search ran to completion.
+#[tauri::command]
+async fn stream_search(
+    app: AppHandle,
+    query: String,
+    search_id: String,
+    limit: Option<usize>,
+) -> Result<bool, AppError> {
+    search_stream::begin(&search_id);
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+        let state = open_state_db(&app)?;
+        let conn = open_zotero_connection(&app)?;
+        // Declared after the connections so it is dropped before they are.
+        let _registration = search_stream::interrupt_on_cancel(
+            &search_id,
+            vec![state.interrupt_handle(), conn.get_interrupt_handle()],
+        );
+        let completed = (|| {
+            let Some(query) = refreshed_search_query(&conn, &state, &query, settings.search_fuzziness)? else {
+                search_stream::emit(&app, &search_id, &[], true);
+                return Ok(true);
+            };
+            let mut completed = false;
+            state.search_index_pages(&query.expression(), limit, search_stream::PAGE_SIZE, |mut hits, done| {
+                if !search_stream::is_current(&search_id) {
+                    return false;
+                }
+                for hit in &mut hits {
+                    query.highlight_hit(hit);
+                }
+                search_stream::emit(&app, &search_id, &hits, done);
+                completed = done;
+                true
+            })?;
+            Ok::<_, String>(completed)
+        })();
+        match completed {
+            Ok(completed) => Ok(completed),
+            // An interrupted query fails; that is how a cancel looks here.
+            Err(_) if !search_stream::is_current(&search_id) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+fn cancel_search() {
+    search_stream::cancel();
+}
+
+/// Annotations matching a library-wide search, under the item they belong to.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnnotationSearchGroup {
+    key: String,
+    title: String,
+    creators: String,
+    year: String,
+    annotations: Vec<AnnotationSearchHit>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnnotationSearchHit {
+    annotation: SqliteAnnotation,
+    /// Where the query matched in the annotation's `text` and `comment`.
+    matches: Vec<FieldMatch>,
+}
+
+/// Searches highlight text and comments across the library. Items are
+/// ranked like [`search_index`], at most `limit` of them, and each lists
+/// only the annotations containing every word of the query.
+#[tauri::command]
+async fn search_annotations(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<AnnotationSearchGroup>, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+        let state = open_state_db(&app)?;
+        let conn = open_zotero_connection(&app)?;
+        let Some(query) = refreshed_search_query(&conn, &state, &query, settings.search_fuzziness)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut groups = Vec::new();
+        for hit in state.search_index(&query.annotation_expression(), limit, 0)? {
+            // The index matches per item, so the words may be spread over
+            // several annotations; keep those that contain them all.
+            let annotations = load_annotations(&conn, &hit.key)?
+                .into_iter()
+                .filter(|annotation| query.matches(&format!("{}\n{}", annotation.text, annotation.comment)))
+                .map(|annotation| AnnotationSearchHit {
+                    matches: highlight::field_matches(
+                        [("text", annotation.text.as_str()), ("comment", annotation.comment.as_str())],
+                        |text| query.highlights(text),
+                    ),
+                    annotation,
+                })
+                .collect::<Vec<_>>();
+            if !annotations.is_empty() {
+                groups.push(AnnotationSearchGroup {
+                    key: hit.key,
+                    title: hit.title,
+                    creators: hit.creators,
+                    year: hit.year,
+                    annotations,
+                });
+            }
+        }
+        Ok(groups)
+    })
+    .await
+}
+
+/// Searches the Markdown files in `markdown_dir` line by line, including
+/// notes the app didn't write. Matches in exported notes carry their item
+/// key.
+#[tauri::command]
+async fn search_notes(app: AppHandle, query: String, limit: Option<usize>) -> Result<Vec<NoteMatch>, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let markdown_dir = settings.markdown_dir.trim();
+        if markdown_dir.is_empty() {
+            return Err("markdown directory is not configured.".to_string().into());
+        }
+        let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+        let exported = open_state_db(&app)?
+            .items()?
+            .into_iter()
+            .map(|record| (PathBuf::from(record.markdown_path), record.item_key))
+            .collect::<HashMap<_, _>>();
+        Ok(note_search::search(Path::new(markdown_dir), &query, limit, |path| {
+            exported.get(path).cloned()
+        }))
+    })
+    .await
+}
+
+/// Library name for each of `items` and the IDs of the group libraries
+/// synced to zotero.sqlite. Items outside a group are in "My Library".
+fn local_libraries(
+    conn: &Connection,
+    items: &[SqliteItemSummary],
+) -> Result<(HashMap<String, String>, Vec<i64>), String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT COALESCE(g.name, 'My Library') FROM items i
+             LEFT JOIN groups g ON g.libraryID = i.libraryID
+             WHERE i.key = ?1",
+        )
+        .map_err(|err| format!("failed to prepare library lookup: {err}"))?;
+    let mut names = HashMap::new();
+    for item in items {
+        let name = stmt
+            .query_row([&item.key], |row| row.get::<_, String>(0))
+            .optional()
+            .map_err(|err| format!("failed to look up the library of {}: {err}", item.key))?;
+        names.insert(item.key.clone(), name.unwrap_or_else(|| "My Library".to_string()));
+    }
+
+    let groups = conn
+        .prepare("SELECT groupID FROM groups")
+        .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>())
+        .map_err(|err| format!("failed to read synced groups: {err}"))?;
+    Ok((names, groups))
+}
+
+/// Searches zotero.sqlite and, when a Zotero user ID and API key are set,
+/// the group libraries on api.zotero.org that aren't synced locally. Each
+/// result is labelled with its library and where it was found; an item in
+/// both is reported once, from the local database. A library that can't be
+/// searched becomes a warning instead of failing the whole search, and
+/// without a local database the user's own web library is searched too.
+#[tauri::command]
+async fn unified_search(app: AppHandle, query: String, limit: Option<usize>) -> Result<UnifiedSearch, AppError> {
+    let settings = read_settings(&app)?;
+    let limit = limit.unwrap_or(settings.search_limit).clamp(1, validation::MAX_SEARCH_LIMIT);
+
+    let local_app = app.clone();
+    let local_query = query.clone();
+    let local_settings = settings.clone();
+    let local = run_blocking(move || {
+        let conn = open_zotero_connection(&local_app)?;
+        let items = substring_search(&local_app, &local_settings, &conn, &local_query, limit)?;
+        let (names, groups) = local_libraries(&conn, &items)?;
+        let hits = items
+            .into_iter()
+            .map(|item| UnifiedHit {
+                library: names.get(&item.key).cloned().unwrap_or_default(),
+                key: item.key,
+                title: item.title,
+                creators: item.creators,
+                year: item.year,
+                matches: item.matches,
+                source: SearchSource::Local,
+            })
+            .collect::<Vec<_>>();
+        Ok((hits, groups))
+    })
+    .await;
+
+    let mut warnings = Vec::new();
+    let (mut items, synced_groups) = match local {
+        Ok((hits, groups)) => (hits, Some(groups)),
+        Err(err) => {
+            warnings.push(format!("Local database: {err}"));
+            (Vec::new(), None)
+        }
+    };
+
+    if web_search::is_configured(&settings) {
+        let client = reqwest::Client::new();
+        match web_search::libraries(&client, &settings).await {
+            Ok(libraries) => {
+                let unsynced = libraries.iter().filter(|library| match (&synced_groups, library.group_id) {
+                    (Some(groups), Some(id)) => !groups.contains(&id),
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                });
+                for library in unsynced {
+                    match web_search::search_library(&client, &settings, library, &query, limit).await {
+                        Ok(hits) => items.extend(hits),
+                        Err(err) => warnings.push(err),
+                    }
+                }
+            }
+            Err(err) => warnings.push(format!("Zotero web API: {err}")),
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    items.retain(|item| seen.insert(item.key.clone()));
+    Ok(UnifiedSearch { items, warnings })
+}
+
+/// Recent items kept in the quick list when no `limit` is given.
+const QUICK_LIST_RECENT: usize = 10;
+
+/// An item in the picker's quick list.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuickListItem {
+    key: String,
+    title: String,
+    creators: String,
+    year: String,
+    annotation_count: usize,
+    has_attachment: bool,
+    pinned: bool,
+    /// When the item was pinned, or last opened or exported (unix seconds).
+    last_used_at: i64,
+}
+
+#[tauri::command]
+async fn record_item_opened(app: AppHandle, item_key: String) -> Result<(), AppError> {
+    run_blocking(move || Ok(open_state_db(&app)?.record_item_opened(&item_key)?)).await
+}
+
+#[tauri::command]
+async fn pin_item(app: AppHandle, item_key: String) -> Result<(), AppError> {
+    run_blocking(move || Ok(open_state_db(&app)?.set_item_pinned(&item_key, true)?)).await
+}
+
+#[tauri::command]
+async fn unpin_item(app: AppHandle, item_key: String) -> Result<(), AppError> {
+    run_blocking(move || Ok(open_state_db(&app)?.set_item_pinned(&item_key, false)?)).await
+}
+
+/// Pinned items, then up to `limit` of the most recently opened or exported
+/// ones. Items since deleted or trashed in Zotero are left out.
+#[tauri::command]
+async fn get_quick_list(app: AppHandle, limit: Option<usize>) -> Result<Vec<QuickListItem>, AppError> {
+    run_blocking(move || {
+        let state = open_state_db(&app)?;
+        let conn = open_zotero_connection(&app)?;
+        let limit = limit.unwrap_or(QUICK_LIST_RECENT).min(validation::MAX_SEARCH_LIMIT);
+        let pinned = state.pinned_items()?.into_iter().map(|entry| (entry, true));
+        let recent = state.recent_items(limit)?.into_iter().map(|entry| (entry, false));
+
+        let mut items = Vec::new();
+        for ((item_key, last_used_at), pinned) in pinned.chain(recent) {
+            if let Some(summary) = item_summary(&conn, &item_key)? {
+                items.push(QuickListItem {
+                    key: summary.key,
+                    title: summary.title,
+                    creators: summary.creators,
+                    year: summary.year,
+                    annotation_count: summary.annotation_count,
+                    has_attachment: summary.has_attachment,
+                    pinned,
+                    last_used_at,
+                });
+            }
+        }
+        Ok(items)
+    })
+    .await
+}
+
+/// Refreshes the search index and parses `query` against it.
+fn refreshed_search_query(
+    conn: &Connection,
+    state: &StateDb,
+    query: &str,
+    fuzziness: usize,
+) -> Result<Option<SearchQuery>, String> {
+    search_index::refresh(conn, state)?;
+    SearchQuery::parse(state, query, fuzziness.min(validation::MAX_SEARCH_FUZZINESS))
+}
+
+/// Drops the full-text index and indexes the whole library again.
+#[tauri::command]
+async fn rebuild_search_index(app: AppHandle) -> Result<IndexRefresh, AppError> {
+    run_blocking(move || {
+        let state = open_state_db(&app)?;
+        state.clear_search_index()?;
+        Ok(search_index::refresh(&*open_zotero_connection(&app)?, &state)?)
+    })
+    .await
+}
+
+/// Reads a summary row: key, title, creators, date, annotation count and
+/// attachment count.
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<SqliteItemSummary> {
+    let date_value: String = row.get(3)?;
+    Ok(SqliteItemSummary {
+        key: row.get(0)?,
+        title: row.get(1)?,
+        creators: row.get(2)?,
+        year: extract_year(&date_value),
+        annotation_count: row.get::<_, i64>(4)?.max(0) as usize,
+        has_attachment: row.get::<_, i64>(5)? > 0,
+        matches: Vec::new(),
+    })
+}
+
+/// Query for item summaries, formatted like [`search_items`], of the items
+/// matching `key_filter` outside the trash.
+fn item_summary_sql(key_filter: &str) -> String {
+    let field = |name: &str| {
+        format!(
+            r#"
+            SELECT CAST(v.value AS TEXT)
+            FROM itemData d
+            JOIN fields f ON f.fieldID = d.fieldID
+            JOIN itemDataValues v ON v.valueID = d.valueID
+            WHERE d.itemID = i.itemID AND f.fieldName = '{name}'
+            "#
+        )
+    };
+    format!(
+        r#"
+        SELECT
+            i.key,
+            COALESCE(({}), '(untitled)'),
+            COALESCE(
+                (
+                    SELECT GROUP_CONCAT(name, '; ')
+                    FROM (
+                        SELECT
+                            CASE
+                                WHEN c.fieldMode = 1 THEN COALESCE(c.lastName, '')
+                                ELSE TRIM(
+                                    COALESCE(c.lastName, '') ||
+                                    CASE WHEN COALESCE(c.firstName, '') <> '' THEN ', ' || c.firstName ELSE '' END
+                                )
+                            END AS name
+                        FROM itemCreators ic
+                        JOIN creators c ON c.creatorID = ic.creatorID
+                        WHERE ic.itemID = i.itemID
+                        ORDER BY ic.orderIndex
+                    )
+                ),
+                ''
+            ),
+            COALESCE(({}), ''),
+            (
+                SELECT COUNT(*)
+                FROM itemAttachments iatt
+                JOIN itemAnnotations ia ON ia.parentItemID = iatt.itemID
+                WHERE iatt.parentItemID = i.itemID
+                  AND iatt.itemID NOT IN (SELECT itemID FROM deletedItems)
+                  AND ia.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ),
+            (
+                SELECT COUNT(*)
+                FROM itemAttachments iatt
+                WHERE iatt.parentItemID = i.itemID
+                  AND iatt.itemID NOT IN (SELECT itemID FROM deletedItems)
+            )
+        FROM items i
+        WHERE {}
+          AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+        "#,
+        field("title"),
+        field("date"),
+        key_filter
+    )
+}
+
+/// Summary of one item, formatted like [`search_items`],
+/// or `None` when it is missing or in the trash.
+fn item_summary(conn: &Connection, item_key: &str) -> Result<Option<SqliteItemSummary>, String> {
+    conn.prepare_cached(&item_summary_sql("i.key = ?1"))
+        .and_then(|mut stmt| stmt.query_row(params![item_key], summary_from_row))
+        .optional()
+        .map_err(|err| format!("failed to load summary of {item_key}: {err}"))
+}
+
+/// Summaries of the items in `item_keys` by key, from one query. Missing
+/// and trashed items are left out.
+fn item_summaries(conn: &Connection, item_keys: &[String]) -> Result<HashMap<String, SqliteItemSummary>, String> {
+    let keys = serde_json::to_string(item_keys).map_err(|err| format!("failed to encode item keys: {err}"))?;
+    let read_err = |err: rusqlite::Error| format!("failed to load item summaries: {err}");
+    let mut stmt = conn
+        .prepare_cached(&item_summary_sql("i.key IN (SELECT value FROM json_each(?1))"))
+        .map_err(read_err)?;
+    let summaries = stmt
+        .query_map(params![keys], summary_from_row)
+        .map_err(read_err)?
+        .map(|summary| summary.map(|summary| (summary.key.clone(), summary)))
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(read_err)?;
+    Ok(summaries)
+}
+
+/// The item's API-shaped payload as raw JSON, gzip-compressed when
+/// `compress` is set and it is large; see [`ipc_payload::json`].
+#[tauri::command]
+async fn zotero_sqlite_get_item(app: AppHandle, item_key: String, compress: Option<bool>) -> Result<Response, AppError> {
+    run_blocking(move || {
+        let item = load_sqlite_item(&*open_zotero_connection(&app)?, &item_key)?;
+        Ok(ipc_payload::json(&item, compress.unwrap_or(false))?)
+    })
+    .await
+}
+
+/// Builds an API-shaped item payload (`key`, `data`, `meta`) from zotero.sqlite.
+fn load_sqlite_item(conn: &Connection, item_key: &str) -> Result<Value, String> {
+    let (item_id, key, item_type, item_version): (i64, String, String, i64) = conn
+        .prepare_cached(
+            r#"
+            SELECT i.itemID, i.key, it.typeName, i.version
+            FROM items i
+            JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
+            WHERE i.key = ?1
+              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+            LIMIT 1
+            "#,
+        )
+        .and_then(|mut stmt| {
+            stmt.query_row(params![item_key], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+        })
+        .map_err(|err| format!("failed to load Zotero item: {err}"))?;
+
+    let mut data = Map::new();
+    data.insert("itemType".to_string(), Value::String(item_type));
+
+    let mut field_stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT f.fieldName, CAST(v.value AS TEXT) AS fieldValue
+            FROM itemData d
+            JOIN fields f ON f.fieldID = d.fieldID
+            JOIN itemDataValues v ON v.valueID = d.valueID
+            WHERE d.itemID = ?1
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero field query: {err}"))?;
+
+    let field_rows = field_stmt
+        .query_map(params![item_id], |row| {
+            let field_name: String = row.get(0)?;
+            let value: Option<String> = row.get(1)?;
+            Ok((field_name, value.unwrap_or_default()))
+        })
+        .map_err(|err| format!("failed to execute Zotero field query: {err}"))?;
+
+    for row in field_rows {
+        let (field_name, value) =
+            row.map_err(|err| format!("failed to read Zotero field row: {err}"))?;
+        data.insert(field_name, Value::String(value));
+    }
+
+    let mut creators_stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT c.firstName, c.lastName, c.fieldMode
+            FROM itemCreators ic
+            JOIN creators c ON c.creatorID = ic.creatorID
+            WHERE ic.itemID = ?1
+            ORDER BY ic.orderIndex ASC
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero creator query: {err}"))?;
+
+    let creator_rows = creators_stmt
+        .query_map(params![item_id], |row| {
+            let first_name: Option<String> = row.get(0)?;
+            let last_name: Option<String> = row.get(1)?;
+            let field_mode: i64 = row.get(2)?;
+            Ok((first_name.unwrap_or_default(), last_name.unwrap_or_default(), field_mode))
+        })
+        .map_err(|err| format!("failed to execute Zotero creator query: {err}"))?;
+
+    let mut creators = Vec::<Value>::new();
+    for creator in creator_rows {
+        let (first_name, last_name, field_mode) =
+            creator.map_err(|err| format!("failed to read Zotero creator row: {err}"))?;
+
+        let mut creator_value = Map::new();
+        if field_mode == 1 {
+            creator_value.insert("name".to_string(), Value::String(last_name));
+        } else {
+            creator_value.insert("firstName".to_string(), Value::String(first_name));
+            creator_value.insert("lastName".to_string(), Value::String(last_name));
+        }
+        creators.push(Value::Object(creator_value));
+    }
+    data.insert("creators".to_string(), Value::Array(creators));
+
+    let mut tags_stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT t.name, it.type
+            FROM itemTags it
+            JOIN tags t ON t.tagID = it.tagID
+            WHERE it.itemID = ?1
+            ORDER BY t.name ASC
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero tag query: {err}"))?;
+
+    let tag_rows = tags_stmt
+        .query_map(params![item_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|err| format!("failed to execute Zotero tag query: {err}"))?;
+
+    let mut tags = Vec::<Value>::new();
+    for tag in tag_rows {
+        let (name, tag_type) = tag.map_err(|err| format!("failed to read Zotero tag row: {err}"))?;
+
+        let mut tag_value = Map::new();
+        tag_value.insert("tag".to_string(), Value::String(name));
+        tag_value.insert("type".to_string(), Value::from(tag_type));
+        tags.push(Value::Object(tag_value));
+    }
+    data.insert("tags".to_string(), Value::Array(tags));
+
+    let mut collections_stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT c.key
+            FROM collectionItems ci
+            JOIN collections c ON c.collectionID = ci.collectionID
+            WHERE ci.itemID = ?1
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero collection query: {err}"))?;
+
+    let collection_keys = collections_stmt
+        .query_map(params![item_id], |row| row.get::<_, String>(0))
+        .map_err(|err| format!("failed to execute Zotero collection query: {err}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to read Zotero collection row: {err}"))?;
+    data.insert(
+        "collections".to_string(),
+        Value::Array(collection_keys.into_iter().map(Value::String).collect()),
+    );
+
+    let mut payload = Map::new();
+    payload.insert("key".to_string(), Value::String(key));
+    payload.insert("version".to_string(), Value::from(item_version));
+    payload.insert("data".to_string(), Value::Object(data));
+    payload.insert("meta".to_string(), Value::Object(Map::new()));
+
+    Ok(Value::Object(payload))
+}
+
+#[tauri::command]
+async fn zotero_sqlite_get_citation_key(app: AppHandle, item_key: String) -> Result<Option<String>, AppError> {
+    run_blocking(move || {
+        let conn = match open_better_bibtex_connection(&app) {
+            Ok(conn) => conn,
+            Err(_) => return Ok(None),
+        };
+        Ok(load_citation_key(&conn, &item_key)?)
+    })
+    .await
+}
+
+/// The item's Better BibTeX citation key, from the in-memory cache while
+/// better-bibtex.sqlite is unchanged.
+fn load_citation_key(conn: &Connection, item_key: &str) -> Result<Option<String>, String> {
+    citation_keys::lookup(conn, None, item_key)
+}
+
+/// Keys of the regular items in a collection (matched by key or name) and
+/// its subcollections.
+fn collection_item_keys(conn: &Connection, collection: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            r#"
+            WITH RECURSIVE tree(collectionID) AS (
+                SELECT collectionID
+                FROM collections
+                WHERE key = ?1 OR collectionName = ?1 COLLATE NOCASE
+                UNION
+                SELECT c.collectionID
+                FROM collections c
+                JOIN tree t ON c.parentCollectionID = t.collectionID
+            )
+            SELECT DISTINCT i.key
+            FROM collectionItems ci
+            JOIN tree t ON t.collectionID = ci.collectionID
+            JOIN items i ON i.itemID = ci.itemID
+            JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
+            WHERE it.typeName NOT IN ('attachment', 'note', 'annotation')
+              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ORDER BY ci.orderIndex ASC
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero collection item query: {err}"))?;
+
+    let keys = stmt
+        .query_map(params![collection.trim()], |row| row.get::<_, String>(0))
+        .map_err(|err| format!("failed to execute Zotero collection item query: {err}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to read Zotero collection item row: {err}"))?;
+    Ok(keys)
+}
+
+/// `item_keys` followed by the items of `collection`, when it is set, that
+/// are not among them.
+fn with_collection_items(
+    conn: &Connection,
+    item_keys: Vec<String>,
+    collection: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let mut keys = item_keys;
+    if let Some(collection) = collection.filter(|value| !value.trim().is_empty()) {
+        for key in collection_item_keys(conn, collection)? {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Loads the given items plus, when `collection` is set, every item in that
+/// collection, each with its Better BibTeX cite key.
+fn load_metadata_rows(
+    app: &AppHandle,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    progress: &Progress,
+) -> Result<Vec<CsvItem>, AppError> {
+    let conn = open_zotero_connection(app)?;
+    let citation_conn = open_better_bibtex_connection(app).ok();
+    let keys = with_collection_items(&conn, item_keys, collection.as_deref())?;
+
+    let mut rows = Vec::<CsvItem>::new();
+    for (index, key) in keys.iter().enumerate() {
+        let item = load_sqlite_item(&conn, key)?;
+        let cite_key = match &citation_conn {
+            Some(citation_conn) => load_citation_key(citation_conn, key)?.unwrap_or_default(),
+            None => String::new(),
+        };
+        rows.push(CsvItem { item, cite_key });
+        progress.report(index as u64 + 1, keys.len() as u64, key);
+    }
+    Ok(rows)
+}
+
+/// Writes item metadata as CSV using the configured columns. Items are the
+/// given keys plus, when `collection` is set, every item in that collection.
+/// Returns the number of rows written.
+#[tauri::command]
+fn export_items_csv(
+    app: AppHandle,
+    path: String,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    let settings = read_settings(&app)?;
+    let progress = Progress::new(&app, "csv-export", operation_id);
+    let rows = load_metadata_rows(&app, item_keys, collection, &progress)?;
+
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, csv_export::items_to_csv(&rows, &settings.csv_columns))
+        .map_err(|err| format!("failed to write CSV file {}: {err}", destination.display()))?;
+
+    Ok(rows.len())
+}
+
+/// Writes the notes of the given items, plus every item in `collection`
+/// when set, to one .docx file with their images embedded. Several notes
+/// are merged, each on a new page. Returns the number of notes written.
+#[tauri::command]
+async fn export_notes_docx(
+    app: AppHandle,
+    path: String,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    run_blocking(move || {
+        let progress = Progress::new(&app, "docx-export", operation_id);
+        let rendered = with_dry_run_exporter(&app, |exporter| {
+            let keys = with_collection_items(exporter.zotero, item_keys, collection.as_deref())?;
+            let mut rendered = Vec::new();
+            for (index, key) in keys.iter().enumerate() {
+                rendered.push(exporter.render_item_in_memory(key)?);
+                progress.report(index as u64 + 1, keys.len() as u64, key);
+            }
+            Ok(rendered)
+        })?;
+        if rendered.is_empty() {
+            return Err("Select at least one item to export.".to_string().into());
+        }
+
+        let notes = rendered
+            .iter()
+            .map(|note| docx::DocxNote {
+                markdown: &note.markdown,
+                base_dir: note.markdown_path.parent().unwrap_or(Path::new("")),
+                images: &note.images,
+            })
+            .collect::<Vec<_>>();
+        let destination = PathBuf::from(&path);
+        ensure_parent(&destination)?;
+        std::fs::write(&destination, docx::notes_to_docx(&notes)?)
+            .map_err(|err| format!("failed to write DOCX file {}: {err}", destination.display()))?;
+        Ok(notes.len())
+    })
+    .await
+}
+
+/// Writes the notes of the given items, plus every item in `collection`
+/// when set, to `dir` in the shape Notion's Markdown import expects. Each
+/// note's images go to a folder named after the note. The state database
+/// is left alone, so these copies never count as exports. Returns the
+/// number of notes written.
+#[tauri::command]
+async fn export_notes_notion(
+    app: AppHandle,
+    dir: String,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    run_blocking(move || {
+        let progress = Progress::new(&app, "notion-export", operation_id);
+        let dir = PathBuf::from(dir.trim());
+        if dir.as_os_str().is_empty() {
+            return Err("Choose a folder for the Notion export.".to_string().into());
+        }
+        with_dry_run_exporter(&app, |exporter| {
+            let keys = with_collection_items(exporter.zotero, item_keys, collection.as_deref())?;
+            if keys.is_empty() {
+                return Err("Select at least one item to export.".to_string());
+            }
+            let mut written = HashMap::<PathBuf, String>::new();
+            for (index, key) in keys.iter().enumerate() {
+                let rendered = exporter.render_item_in_memory(key)?;
+                let file_name = rendered
+                    .markdown_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| format!("@{}.md", rendered.cite_key));
+                // Notes keep their folder under the Markdown folder, so
+                // notes with the same name in different folders both land.
+                let relative = rendered
+                    .markdown_path
+                    .strip_prefix(&exporter.settings.markdown_dir)
+                    .ok()
+                    .filter(|relative| relative.file_name().is_some())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(&file_name));
+                let destination = dir.join(&relative);
+                if let Some(first) = written.insert(destination.clone(), key.clone()) {
+                    return Err(format!(
+                        "{first} and {key} would both be written to {}; export them separately.",
+                        destination.display()
+                    ));
+                }
+                let stem = file_name.strip_suffix(".md").unwrap_or(&file_name).to_string();
+                let page = notion::to_notion(&rendered.markdown, &rendered.images, &stem);
+
+                let note_dir = destination.parent().unwrap_or(&dir);
+                for (relative, bytes) in &page.images {
+                    let destination = note_dir.join(relative);
+                    ensure_parent(&destination)?;
+                    std::fs::write(&destination, bytes)
+                        .map_err(|err| format!("failed to write image {}: {err}", destination.display()))?;
+                }
+                ensure_parent(&destination)?;
+                std::fs::write(&destination, &page.markdown)
+                    .map_err(|err| format!("failed to write note {}: {err}", destination.display()))?;
+                progress.report(index as u64 + 1, keys.len() as u64, key);
+            }
+            Ok(keys.len())
+        })
+    })
+    .await
+}
+
+/// Writes item metadata as RIS. Items are chosen as for the CSV export.
+/// Returns the number of records written.
+#[tauri::command]
+fn export_items_ris(
+    app: AppHandle,
+    path: String,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    let progress = Progress::new(&app, "ris-export", operation_id);
+    let rows = load_metadata_rows(&app, item_keys, collection, &progress)?;
+
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, ris_export::items_to_ris(&rows))
+        .map_err(|err| format!("failed to write RIS file {}: {err}", destination.display()))?;
+
+    Ok(rows.len())
+}
+
+/// Writes the text annotations of the chosen items in Readwise's CSV or
+/// JSON import format. Items are chosen as for the CSV export. Returns the
+/// number of highlights written.
+#[tauri::command]
+fn export_highlights_readwise(
+    app: AppHandle,
+    path: String,
+    item_keys: Vec<String>,
+    collection: Option<String>,
+    format: Option<ReadwiseFormat>,
+    operation_id: Option<String>,
+) -> Result<usize, AppError> {
+    let progress = Progress::new(&app, "readwise-export", operation_id);
+    let rows = load_metadata_rows(&app, item_keys, collection, &progress)?;
+    let conn = open_zotero_connection(&app)?;
+    let items = rows
+        .into_iter()
+        .map(|row| {
+            let item_key = row.item.get("key").and_then(Value::as_str).unwrap_or_default();
+            let annotations = load_annotations(&conn, item_key)?;
+            Ok((row, annotations))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let (contents, count) = readwise_export::highlights_to_readwise(&items, format.unwrap_or_default())?;
+
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, contents)
+        .map_err(|err| format!("failed to write Readwise file {}: {err}", destination.display()))?;
+
+    Ok(count)
+}
+
+/// An item's annotations in reading order, as raw JSON. With `limit`, only
+/// that many are returned, starting at `offset`, so items with thousands of
+/// annotations can be loaded a page at a time; with `compress`, a large page
+/// is gzip-compressed.
+#[tauri::command]
+async fn zotero_sqlite_get_annotations(
+    app: AppHandle,
+    item_key: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    compress: Option<bool>,
+) -> Result<Response, AppError> {
+    run_blocking(move || {
+        let conn = open_zotero_connection(&app)?;
+        let annotations = load_annotation_page(&conn, &item_key, offset.unwrap_or(0), limit)?;
+        Ok(ipc_payload::json(&annotations, compress.unwrap_or(false))?)
+    })
+    .await
+}
+
+/// Number of annotations `zotero_sqlite_get_annotations` returns for the
+/// item, without loading them.
+#[tauri::command]
+async fn zotero_sqlite_count_annotations(app: AppHandle, item_key: String) -> Result<usize, AppError> {
+    run_blocking(move || {
+        let count = open_zotero_connection(&app)?
+            .prepare_cached(
+                r#"
+                SELECT COUNT(*)
+                FROM items root
+                JOIN itemAttachments iatt ON iatt.parentItemID = root.itemID
+                JOIN itemAnnotations ia ON ia.parentItemID = iatt.itemID
+                WHERE root.key = ?1
+                  AND ia.itemID NOT IN (SELECT itemID FROM deletedItems)
+                "#,
+            )
+            .and_then(|mut stmt| stmt.query_row(params![item_key], |row| row.get::<_, i64>(0)))
+            .map_err(|err| format!("failed to count annotations of {item_key}: {err}"))?;
+        Ok(count.max(0) as usize)
+    })
+    .await
+}
+
+fn load_annotations(conn: &Connection, item_key: &str) -> Result<Vec<SqliteAnnotation>, String> {
+    load_annotation_page(conn, item_key, 0, None)
+}
+
+/// Up to `limit` annotations (all without one) after the first `offset`.
+/// `sort_index` counts from the start of the item, not of the page.
+fn load_annotation_page(
+    conn: &Connection,
+    item_key: &str,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<Vec<SqliteAnnotation>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT
+                anno.key AS annotationKey,
+                att.key AS attachmentKey,
+                COALESCE(ia.color, '') AS colorHex,
+                COALESCE(ia.text, '') AS annotationText,
+                COALESCE(ia.comment, '') AS annotationComment,
+                COALESCE(ia.pageLabel, '') AS pageLabel,
+                ia.sortIndex AS sortKey,
+                ia.type AS annotationType,
+                COALESCE(
+                    (
+                        SELECT CAST(v.value AS TEXT)
+                        FROM itemData d
+                        JOIN fields f ON f.fieldID = d.fieldID
+                        JOIN itemDataValues v ON v.valueID = d.valueID
+                        WHERE d.itemID = att.itemID AND f.fieldName = 'title'
+                    ),
+                    ''
+                ) AS attachmentTitle,
+                anno.dateAdded AS dateAdded,
+                anno.dateModified AS dateModified
+            FROM items root
+            JOIN itemAttachments iatt ON iatt.parentItemID = root.itemID
+            JOIN items att ON att.itemID = iatt.itemID
+            JOIN itemAnnotations ia ON ia.parentItemID = att.itemID
+            JOIN items anno ON anno.itemID = ia.itemID
+            WHERE root.key = ?1
+              AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ORDER BY att.itemID ASC, ia.sortIndex ASC, anno.itemID ASC
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero annotation query: {err}"))?;
+
+    // A negative limit is no limit in SQLite.
+    let limit = limit.map_or(-1, |limit| limit as i64);
+    let rows = stmt
+        .query_map(params![item_key, limit, offset as i64], |row| {
+            let annotation_type: i64 = row.get(7)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                annotation_type,
+                row.get::<_, String>(8)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, String>(10)?,
+            ))
+        })
+        .map_err(|err| format!("failed to execute Zotero annotation query: {err}"))?;
+
+    let mut annotations = Vec::<SqliteAnnotation>::new();
+    for (sort_index, row) in rows.enumerate() {
+        let sort_index = offset + sort_index;
+        let (
+            key,
+            attachment_key,
+            color_hex,
+            text,
+            comment,
+            page_label,
+            annotation_type,
+            attachment_title,
+            date_added,
+            date_modified,
+        ) = row.map_err(|err| format!("failed to read Zotero annotation row: {err}"))?;
+
+        annotations.push(SqliteAnnotation {
+            key,
+            attachment_key,
+            color_hex: color_hex.trim().to_lowercase(),
+            text: text.trim().to_string(),
+            comment: comment.trim().to_string(),
+            page_label: page_label.trim().to_string(),
+            sort_index,
+            is_image_selection: matches!(annotation_type, 3 | 4),
+            attachment_title: attachment_title.trim().to_string(),
+            date_added,
+            date_modified,
+        });
+    }
+
+    Ok(annotations)
+}
+
+/// Keys of previously exported items whose Zotero version or annotations
+/// changed since their last export.
+#[tauri::command]
+async fn zotero_sqlite_changed_exported_items(app: AppHandle) -> Result<Vec<String>, AppError> {
+    run_blocking(move || {
+        let state = open_state_db(&app)?;
+        let conn = open_zotero_connection(&app)?;
+        let settings = read_settings(&app)?;
+        Ok(export::changed_records(&conn, &state, &settings.exclude_tag)?
+            .into_iter()
+            .map(|record| record.item_key)
+            .collect())
+    })
+    .await
+}
+
+/// Serializes syncs so the scheduled task and a manual sync never write the
+/// same notes concurrently. Exports take it through [`lock_sync`], which
+/// also locks out other processes.
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+/// Waits for the sync lock, shared with command-line exports.
+fn lock_sync(app: &AppHandle) -> Result<sync_lock::SyncGuard, String> {
+    sync_lock::acquire(&app_data_dir(app)?)
+}
+
+/// Re-exports every item whose Zotero version or annotations changed since
+/// its last export. `on_progress` receives `(current, total, cite_key)`
+/// after each item. A dry run only reports what would be written; other
+/// runs are recorded in the sync history.
+fn sync_changed(
+    app: &AppHandle,
+    trigger: SyncTrigger,
+    dry_run: bool,
+    on_progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<ExportReport, AppError> {
+    let started_at = report::now_seconds();
+    tracing::info!(trigger = trigger.as_str(), dry_run, "sync started");
+    let result = run_sync(app, dry_run, on_progress);
+    match &result {
+        Ok(report) => {
+            for failure in &report.failed {
+                tracing::warn!(trigger = trigger.as_str(), "item failed to sync: {failure}");
+            }
+            tracing::info!(
+                trigger = trigger.as_str(),
+                items = report.items.len(),
+                failed = report.failed.len(),
+                "sync finished"
+            );
+        }
+        Err(err) => tracing::error!(trigger = trigger.as_str(), "sync failed: {err}"),
+    }
+    if !dry_run {
+        history::record(app, trigger, started_at, result.as_ref().map_err(|err| &err.message));
+    }
+    result
+}
+
+fn run_sync(
+    app: &AppHandle,
+    dry_run: bool,
+    on_progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<ExportReport, AppError> {
+    let _guard = lock_sync(app)?;
+
+    let settings = read_settings(app)?;
+    let state = open_state_db(app)?;
+    let conn = open_zotero_connection(app)?;
+    let citations = open_better_bibtex_connection(app).ok();
+    let data_dir = app_data_dir(app)?;
+    let exporter = export::Exporter {
+        settings: &settings,
+        zotero: &conn,
+        citations: citations.as_deref(),
+        state: &state,
+        data_dir: &data_dir,
+        dry_run,
+    };
+
+    let open_worker = |run: &mut dyn FnMut(&export::Exporter)| with_worker_exporter(app, &settings, &data_dir, dry_run, run);
+    export_changed(&exporter, &conn, &open_worker, on_progress)
+}
+
+/// Opens an export worker's own Zotero, Better BibTeX and state database
+/// connections and runs `run` with an exporter on them.
+fn with_worker_exporter(
+    app: &AppHandle,
+    settings: &AppSettings,
+    data_dir: &Path,
+    dry_run: bool,
+    run: &mut dyn FnMut(&export::Exporter),
+) -> Result<(), String> {
+    let conn = open_zotero_connection(app)?;
+    let citations = open_better_bibtex_connection(app).ok();
+    let state = open_state_db(app)?;
+    run(&export::Exporter {
+        settings,
+        zotero: &conn,
+        citations: citations.as_deref(),
+        state: &state,
+        data_dir,
+        dry_run,
+    });
+    Ok(())
+}
+
+/// Re-exports the items changed since their last export and appends the
+/// report to the changelog. Shared by syncs and the CLI.
+fn export_changed(
+    exporter: &export::Exporter,
+    zotero: &ZoteroDb,
+    open_worker: &OpenWorker,
+    on_progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<ExportReport, AppError> {
+    let changed = export::changed_records(zotero, exporter.state, &exporter.settings.exclude_tag)?;
+    let mut report = ExportReport {
+        dry_run: exporter.dry_run,
+        zotero_database: zotero.freshness.clone(),
+        ..ExportReport::default()
+    };
+    let item_keys = changed.iter().map(|record| record.item_key.clone()).collect::<Vec<_>>();
+    let results = export_pool::export_all(&item_keys, open_worker, &mut |done, index| {
+        on_progress(done, changed.len(), &changed[index].cite_key)
+    });
+    for (record, result) in changed.iter().zip(results) {
+        match result {
+            Ok(item) => report.items.push(item),
+            Err(err) => report.failed.push(format!("{}: {err}", record.item_key)),
+        }
+    }
+
+    if !exporter.dry_run && !report.items.is_empty() {
+        record_export_report(exporter.settings, &report)?;
+    }
+    Ok(report)
+}
+
+/// Exports `item_keys`, leaving out excluded items, and appends the report
+/// to the changelog. Shared by `export_items` and the CLI.
+fn export_keys(
+    exporter: &export::Exporter,
+    zotero: &ZoteroDb,
+    item_keys: Vec<String>,
+    open_worker: &OpenWorker,
+    on_progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<ExportReport, AppError> {
+    let (item_keys, excluded) = export::without_excluded(zotero, item_keys, &exporter.settings.exclude_tag)?;
+    let mut report = ExportReport {
+        dry_run: exporter.dry_run,
+        excluded,
+        zotero_database: zotero.freshness.clone(),
+        ..ExportReport::default()
+    };
+    let results = export_pool::export_all(&item_keys, open_worker, &mut |done, index| {
+        on_progress(done, item_keys.len(), &item_keys[index])
+    });
+    for (key, result) in item_keys.iter().zip(results) {
+        match result {
+            Ok(item) => report.items.push(item),
+            Err(err) => {
+                tracing::warn!(item_key = %key, "export failed: {err}");
+                report.failed.push(format!("{key}: {err}"));
+            }
+        }
+    }
+
+    if !exporter.dry_run && !report.items.is_empty() {
+        record_export_report(exporter.settings, &report)?;
+    }
+    Ok(report)
+}
+
+/// Re-exports only the items whose Zotero version or annotations changed
+/// since their last export, and returns the change report.
+#[tauri::command]
+async fn sync_changed_items(
+    app: AppHandle,
+    operation_id: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ExportReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let progress = Progress::new(&app, "sync", operation_id);
+        sync_changed(
+            &app,
+            SyncTrigger::Manual,
+            dry_run.unwrap_or(false),
+            &mut |current, total, cite_key| {
+                progress.report(current as u64, total as u64, &format!("@{cite_key}"))
+            },
+        )
+    })
+    .await
+    .map_err(|err| AppError::from(format!("sync task failed: {err}")))?
+}
+
+/// Recent sync runs, newest first.
+#[tauri::command]
+fn get_sync_history(app: AppHandle, limit: Option<usize>) -> Result<Vec<SyncRun>, AppError> {
+    Ok(open_state_db(&app)?.sync_runs(limit.unwrap_or(50))?)
+}
+
+/// Exports `item_keys` with the backend renderer and saved settings,
+/// holding the sync lock. Shared by `export_items` and the API server.
+fn export_with_settings(
+    app: &AppHandle,
+    item_keys: Vec<String>,
+    dry_run: bool,
+    on_progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<ExportReport, AppError> {
+    let _guard = lock_sync(app)?;
+
+    let settings = read_settings(app)?;
+    let state = open_state_db(app)?;
+    let conn = open_zotero_connection(app)?;
+    let citations = open_better_bibtex_connection(app).ok();
+    let data_dir = app_data_dir(app)?;
+    let exporter = export::Exporter {
+        settings: &settings,
+        zotero: &conn,
+        citations: citations.as_deref(),
+        state: &state,
+        data_dir: &data_dir,
+        dry_run,
+    };
+    let open_worker = |run: &mut dyn FnMut(&export::Exporter)| with_worker_exporter(app, &settings, &data_dir, dry_run, run);
+    export_keys(&exporter, &conn, item_keys, &open_worker, on_progress)
+}
+
+/// Exports the given items with the backend renderer and saved settings.
+/// With `dry_run` the report lists the files that would be created or
+/// updated and nothing is written or recorded.
+#[tauri::command]
+async fn export_items(
+    app: AppHandle,
+    item_keys: Vec<String>,
+    dry_run: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<ExportReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let progress = Progress::new(&app, "export", operation_id);
+        export_with_settings(&app, item_keys, dry_run.unwrap_or(false), &mut |current, total, key| {
+            progress.report(current as u64, total as u64, key)
+        })
+    })
+    .await
+    .map_err(|err| AppError::from(format!("export task failed: {err}")))?
+}
+
+/// Maps notes already under `dir` (the Markdown folder by default) to their
+/// Zotero items by cite key or DOI, so exports update them in place. With
+/// `dry_run` the report lists the matches and nothing is recorded.
+#[tauri::command]
+async fn import_notes(app: AppHandle, dir: Option<String>, dry_run: Option<bool>) -> Result<ImportReport, AppError> {
+    run_blocking(move || {
+        let _guard = lock_sync(&app)?;
+        with_dry_run_exporter(&app, |exporter| {
+            let dir = dir
+                .filter(|dir| !dir.trim().is_empty())
+                .unwrap_or_else(|| exporter.settings.markdown_dir.clone());
+            if dir.trim().is_empty() {
+                return Err("Choose a folder to import, or set the Markdown folder in Settings.".to_string());
+            }
+            note_import::import(exporter, Path::new(dir.trim()), dry_run.unwrap_or(false))
+        })
+    })
+    .await
+}
+
+/// Cited items without a literature note: `@citekey` mentions in the notes
+/// under `dir` (the Markdown folder by default). Export the returned item
+/// keys to create their notes.
+#[tauri::command]
+async fn find_unlinked_citations(app: AppHandle, dir: Option<String>) -> Result<CitationScan, AppError> {
+    run_blocking(move || {
+        with_dry_run_exporter(&app, |exporter| {
+            let dir = dir
+                .filter(|dir| !dir.trim().is_empty())
+                .unwrap_or_else(|| exporter.settings.markdown_dir.clone());
+            if dir.trim().is_empty() {
+                return Err("Choose a folder to scan, or set the Markdown folder in Settings.".to_string());
+            }
+            citation_scan::scan(exporter, Path::new(dir.trim()))
+        })
+    })
+    .await
+}
+
+/// Whether the local API server is running, and why not when it failed to
+/// start.
+#[tauri::command]
+fn get_api_server_status() -> ApiServerStatus {
+    api_server::status()
+}
+
+/// Exported notes whose Zotero item was deleted or trashed.
+#[tauri::command]
+fn find_orphaned_notes(app: AppHandle) -> Result<Vec<OrphanedNote>, AppError> {
+    let state = open_state_db(&app)?;
+    let conn = open_zotero_connection(&app)?;
+    Ok(orphans::find_orphans(&conn, &state.items()?)?)
+}
+
+/// Archives or tags the notes of the given orphaned items. Returns the paths
+/// of the notes that were changed.
+#[tauri::command]
+fn resolve_orphaned_notes(
+    app: AppHandle,
+    item_keys: Vec<String>,
+    action: OrphanAction,
+) -> Result<Vec<String>, AppError> {
+    let settings = read_settings(&app)?;
+    if action == OrphanAction::Tag && settings.orphan_tag.trim().is_empty() {
+        return Err(AppError::new(
+            ErrorCode::InvalidSettings,
+            "Set an orphan tag in Settings before tagging notes.",
+        ));
+    }
+    let state = open_state_db(&app)?;
+    let conn = open_zotero_connection(&app)?;
+    let records = state
+        .items()?
+        .into_iter()
+        .filter(|record| item_keys.contains(&record.item_key))
+        .collect::<Vec<_>>();
+
+    let mut changed = Vec::new();
+    for orphan in orphans::find_orphans(&conn, &records)? {
+        let note = Path::new(&orphan.markdown_path);
+        match action {
+            OrphanAction::Archive => {
+                if orphan.note_exists {
+                    let archive_dir =
+                        Path::new(settings.markdown_dir.trim()).join(settings.orphan_archive_dir.trim());
+                    let archived = orphans::archive_note(note, &archive_dir)?;
+                    changed.push(archived.to_string_lossy().to_string());
+                }
+                state.remove_item(&orphan.item_key)?;
+            }
+            OrphanAction::Tag if orphan.note_exists => {
+                let markdown = std::fs::read_to_string(note)
+                    .map_err(|err| format!("failed to read {}: {err}", orphan.markdown_path))?;
+                if let Some(tagged) = orphans::add_frontmatter_tag(&markdown, settings.orphan_tag.trim()) {
+                    std::fs::write(note, tagged)
+                        .map_err(|err| format!("failed to write {}: {err}", orphan.markdown_path))?;
+                    changed.push(orphan.markdown_path);
+                }
+            }
+            OrphanAction::Tag => {}
+        }
+    }
+    Ok(changed)
+}
+
+/// Collects comment edits from exported notes: the given note only, or every
+/// note in the state database.
+fn collect_comment_edits(
+    app: &AppHandle,
+    markdown_path: Option<&str>,
+    report: &mut WritebackReport,
+) -> Result<Vec<CommentEdit>, String> {
+    let state = open_state_db(app)?;
+    let conn = open_zotero_connection(app)?;
+
+    let records = state
+        .items()?
+        .into_iter()
+        .filter(|record| markdown_path.is_none_or(|path| record.markdown_path == path))
+        .collect::<Vec<_>>();
+    if records.is_empty() {
+        if let Some(path) = markdown_path {
+            return Err(format!("{path} has no export record; export it before pushing edits."));
+        }
+    }
+
+    let mut edits = Vec::new();
+    for record in &records {
+        match writeback::collect_edits(&conn, record, report) {
+            Ok(found) => edits.extend(found),
+            Err(err) => report.failed.push(format!("{}: {err}", record.item_key)),
+        }
+    }
+    Ok(edits)
+}
+
+/// Pushes annotation comments edited in exported notes back to Zotero
+/// through the write queue. Each update carries the annotation's version; if
+/// Zotero has a newer one, the edit is retried only when the comment itself
+/// is unchanged there, otherwise it is reported as a conflict.
+#[tauri::command]
+async fn push_comment_edits(
+    app: AppHandle,
+    markdown_path: Option<String>,
+) -> Result<WritebackReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = read_settings(&app)?;
+        let queue = WriteQueue::new(&settings)?;
+
+        let mut report = WritebackReport::default();
+        let edits = collect_comment_edits(&app, markdown_path.as_deref(), &mut report)?;
+        for edit in edits {
+            let write = ZoteroWrite {
+                item_key: edit.annotation_key.clone(),
+                version: edit.version,
+                changes: serde_json::json!({ "annotationComment": edit.comment }),
+            };
+            let outcome = queue.submit(&write, |current| {
+                current.get("annotationComment").and_then(Value::as_str).unwrap_or("") == edit.base_comment
+            });
+            match outcome {
+                Ok(WriteOutcome::Written) => report.pushed.push(edit),
+                Ok(WriteOutcome::Conflict) => report.conflicts.push(edit.annotation_key),
+                Err(err) => report.failed.push(format!("{}: {err}", edit.annotation_key)),
+            }
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|err| format!("write-back task failed: {err}"))?
+}
+
+/// Queues an export, sync or download to run in the background job runner.
+#[tauri::command]
+fn enqueue_job(app: AppHandle, task: JobTask) -> Result<Job, AppError> {
+    let job = open_state_db(&app)?.insert_job(&task)?;
+    jobs::notify_changed(&app, job.id);
+    Ok(job)
+}
+
+#[tauri::command]
+fn list_jobs(app: AppHandle, limit: Option<usize>) -> Result<Vec<Job>, AppError> {
+    Ok(open_state_db(&app)?.jobs(limit.unwrap_or(50))?)
+}
+
+/// Cancels a queued or running job; a running export stops after its
+/// current item.
+#[tauri::command]
+fn cancel_job(app: AppHandle, id: i64) -> Result<bool, AppError> {
+    let cancelled = open_state_db(&app)?.cancel_job(id)?;
+    jobs::notify_changed(&app, id);
+    Ok(cancelled)
+}
+
+#[tauri::command]
+fn retry_job(app: AppHandle, id: i64) -> Result<bool, AppError> {
+    let queued = open_state_db(&app)?.retry_job(id)?;
+    jobs::notify_changed(&app, id);
+    Ok(queued)
+}
+
+/// The item's child notes as raw JSON, gzip-compressed when `compress` is
+/// set and they are large.
+#[tauri::command]
+async fn zotero_sqlite_get_child_notes(
+    app: AppHandle,
+    item_key: String,
+    compress: Option<bool>,
+) -> Result<Response, AppError> {
+    run_blocking(move || {
+        let notes = load_child_notes(&*open_zotero_connection(&app)?, &item_key)?;
+        Ok(ipc_payload::json(&notes, compress.unwrap_or(false))?)
+    })
+    .await
+}
+
+fn load_child_notes(conn: &Connection, item_key: &str) -> Result<Vec<SqliteChildNote>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            r#"
+            SELECT child.key, COALESCE(n.note, '') AS noteHtml
+            FROM items root
+            JOIN itemNotes n ON n.parentItemID = root.itemID
+            JOIN items child ON child.itemID = n.itemID
+            WHERE root.key = ?1
+              AND child.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ORDER BY child.dateAdded ASC, child.itemID ASC
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare Zotero note query: {err}"))?;
 
     let rows = stmt
-        .query_map(params![term, 75_i64], |row| {
-            let date_value: String = row.get(3)?;
-            Ok(SqliteItemSummary {
+        .query_map(params![item_key], |row| {
+            Ok(SqliteChildNote {
                 key: row.get(0)?,
-                title: row.get(1)?,
-                creators: row.get(2)?,
-                year: extract_year(&date_value),
+                note: row.get(1)?,
             })
         })
-        .map_err(|err| format!("failed to execute Zotero search query: {err}"))?;
+        .map_err(|err| format!("failed to execute Zotero note query: {err}"))?;
 
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|err| format!("failed to read Zotero search rows: {err}"))
+        .map_err(|err| format!("failed to read Zotero note rows: {err}"))
 }
 
-#[tauri::command]
-fn zotero_sqlite_get_item(item_key: String) -> Result<Value, String> {
-    let conn = open_zotero_connection()?;
-
-    let (item_id, key, item_type): (i64, String, String) = conn
-        .query_row(
+fn load_bundle_annotations(conn: &Connection, item_key: &str) -> Result<Vec<BundleAnnotation>, String> {
+    let mut stmt = conn
+        .prepare_cached(
             r#"
-            SELECT i.itemID, i.key, it.typeName
-            FROM items i
-            JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
-            WHERE i.key = ?1
-              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
-            LIMIT 1
+            SELECT
+                anno.key,
+                att.key,
+                COALESCE(
+                    (
+                        SELECT CAST(v.value AS TEXT)
+                        FROM itemData d
+                        JOIN fields f ON f.fieldID = d.fieldID
+                        JOIN itemDataValues v ON v.valueID = d.valueID
+                        WHERE d.itemID = att.itemID AND f.fieldName = 'title'
+                    ),
+                    ''
+                ),
+                ia.type,
+                COALESCE(ia.color, ''),
+                COALESCE(ia.text, ''),
+                COALESCE(ia.comment, ''),
+                COALESCE(ia.pageLabel, ''),
+                COALESCE(ia.sortIndex, ''),
+                COALESCE(ia.position, ''),
+                anno.dateAdded,
+                anno.dateModified
+            FROM items root
+            JOIN itemAttachments iatt ON iatt.parentItemID = root.itemID
+            JOIN items att ON att.itemID = iatt.itemID
+            JOIN itemAnnotations ia ON ia.parentItemID = att.itemID
+            JOIN items anno ON anno.itemID = ia.itemID
+            WHERE root.key = ?1
+              AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ORDER BY att.itemID ASC, ia.sortIndex ASC, anno.itemID ASC
             "#,
-            params![item_key],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         )
-        .map_err(|err| format!("failed to load Zotero item: {err}"))?;
-
-    let mut data = Map::new();
-    data.insert("itemType".to_string(), Value::String(item_type));
+        .map_err(|err| format!("failed to prepare Zotero annotation bundle query: {err}"))?;
 
-    let mut field_stmt = conn
-        .prepare(
-            r#"
-            SELECT f.fieldName, CAST(v.value AS TEXT) AS fieldValue
-            FROM itemData d
-            JOIN fields f ON f.fieldID = d.fieldID
-            JOIN itemDataValues v ON v.valueID = d.valueID
-            WHERE d.itemID = ?1
-            "#,
-        )
-        .map_err(|err| format!("failed to prepare Zotero field query: {err}"))?;
+    let rows = stmt
+        .query_map(params![item_key], |row| {
+            let annotation_type: i64 = row.get(3)?;
+            let position: String = row.get(9)?;
+            Ok(BundleAnnotation {
+                key: row.get(0)?,
+                attachment_key: row.get(1)?,
+                attachment_title: row.get(2)?,
+                annotation_type: bundle::annotation_type_name(annotation_type).to_string(),
+                color_hex: row.get(4)?,
+                text: row.get(5)?,
+                comment: row.get(6)?,
+                page_label: row.get(7)?,
+                sort_index: row.get(8)?,
+                position: serde_json::from_str(&position).unwrap_or(Value::Null),
+                date_added: row.get(10)?,
+                date_modified: row.get(11)?,
+                image_path: None,
+            })
+        })
+        .map_err(|err| format!("failed to execute Zotero annotation bundle query: {err}"))?;
+
+    let mut annotations = Vec::new();
+    for row in rows {
+        let mut annotation =
+            row.map_err(|err| format!("failed to read Zotero annotation bundle row: {err}"))?;
+        if annotation.annotation_type == "image" {
+            annotation.image_path = cached_annotation_image_path(conn, &annotation.key)
+                .ok()
+                .flatten()
+                .map(|path| path.to_string_lossy().to_string());
+        }
+        annotations.push(annotation);
+    }
+    Ok(annotations)
+}
 
-    let field_rows = field_stmt
-        .query_map(params![item_id], |row| {
-            let field_name: String = row.get(0)?;
-            let value: Option<String> = row.get(1)?;
-            Ok((field_name, value.unwrap_or_default()))
+/// Writes a JSON bundle with the item's metadata, citation key, annotations
+/// (including reader positions and cached image paths) and child notes.
+#[tauri::command]
+fn export_item_bundle(app: AppHandle, item_key: String, path: String) -> Result<String, AppError> {
+    let conn = open_zotero_connection(&app)?;
+    let item = load_sqlite_item(&conn, &item_key)?;
+    let cite_key = match open_better_bibtex_connection(&app) {
+        Ok(citation_conn) => load_citation_key(&citation_conn, &item_key)?,
+        Err(_) => None,
+    };
+    let annotations = load_bundle_annotations(&conn, &item_key)?;
+    let child_notes = load_child_notes(&conn, &item_key)?
+        .into_iter()
+        .map(|note| BundleNote {
+            key: note.key,
+            html: note.note,
         })
-        .map_err(|err| format!("failed to execute Zotero field query: {err}"))?;
+        .collect();
 
-    for row in field_rows {
-        let (field_name, value) =
-            row.map_err(|err| format!("failed to read Zotero field row: {err}"))?;
-        data.insert(field_name, Value::String(value));
-    }
+    let bundle = ItemBundle::new(item_key, cite_key, item, annotations, child_notes);
+    let serialized = serde_json::to_string_pretty(&bundle)
+        .map_err(|err| format!("failed to serialize item bundle: {err}"))?;
 
-    let mut creators_stmt = conn
-        .prepare(
-            r#"
-            SELECT c.firstName, c.lastName, c.fieldMode
-            FROM itemCreators ic
-            JOIN creators c ON c.creatorID = ic.creatorID
-            WHERE ic.itemID = ?1
-            ORDER BY ic.orderIndex ASC
-            "#,
-        )
-        .map_err(|err| format!("failed to prepare Zotero creator query: {err}"))?;
+    let destination = PathBuf::from(&path);
+    ensure_parent(&destination)?;
+    std::fs::write(&destination, serialized)
+        .map_err(|err| format!("failed to write item bundle {}: {err}", destination.display()))?;
+    Ok(destination.to_string_lossy().to_string())
+}
 
-    let creator_rows = creators_stmt
-        .query_map(params![item_id], |row| {
-            let first_name: Option<String> = row.get(0)?;
-            let last_name: Option<String> = row.get(1)?;
-            let field_mode: i64 = row.get(2)?;
-            Ok((first_name.unwrap_or_default(), last_name.unwrap_or_default(), field_mode))
+#[tauri::command]
+async fn zotero_sqlite_get_cached_annotation_image(
+    app: AppHandle,
+    annotation_key: String,
+    format: Option<ImageOutputFormat>,
+) -> Result<Response, AppError> {
+    run_blocking(move || {
+        let image_settings = read_settings(&app)?.image_settings;
+        let conn = open_zotero_connection(&app)?;
+        let bytes = load_annotation_image(&conn, &annotation_key, image_settings.render_dpi)?;
+        Ok(Response::new(convert_annotation_image(bytes, format, &image_settings)?))
+    })
+    .await
+}
+
+/// Re-encodes an image for IPC when the caller asked for a format; `None`
+/// returns it as loaded (PNG, or whatever Zotero cached).
+fn convert_annotation_image(
+    bytes: Vec<u8>,
+    format: Option<ImageOutputFormat>,
+    image_settings: &ImageSettings,
+) -> Result<Vec<u8>, String> {
+    match format {
+        Some(format) => images::convert(&bytes, format, image_settings),
+        None => Ok(bytes),
+    }
+}
+
+/// Zotero's cached image for an area or ink annotation, or a render when it
+/// is missing or `render_dpi` asks for one.
+fn load_annotation_image(conn: &Connection, annotation_key: &str, render_dpi: Option<u32>) -> Result<Vec<u8>, String> {
+    let render = |dpi: u32| render_annotation_image(conn, annotation_key, dpi);
+    let read_cached = |candidate: PathBuf| {
+        std::fs::read(&candidate).map_err(|err| {
+            format!("failed to read cached annotation image {}: {err}", candidate.display())
         })
-        .map_err(|err| format!("failed to execute Zotero creator query: {err}"))?;
+    };
 
-    let mut creators = Vec::<Value>::new();
-    for creator in creator_rows {
-        let (first_name, last_name, field_mode) =
-            creator.map_err(|err| format!("failed to read Zotero creator row: {err}"))?;
+    match (cached_annotation_image_path(conn, annotation_key)?, render_dpi) {
+        (Some(candidate), None) => read_cached(candidate),
+        // A configured resolution prefers the PDF, but Zotero's image still
+        // beats failing when pdfium or the file is unavailable.
+        (Some(candidate), Some(dpi)) => render(dpi).or_else(|err| {
+            tracing::warn!(annotation_key, "rendering from the PDF failed, using Zotero's cached image: {err}");
+            read_cached(candidate)
+        }),
+        (None, dpi) => render(dpi.unwrap_or(pdf_render::DEFAULT_DPI)),
+    }
+}
 
-        let mut creator_value = Map::new();
-        if field_mode == 1 {
-            creator_value.insert("name".to_string(), Value::String(last_name));
-        } else {
-            creator_value.insert("firstName".to_string(), Value::String(first_name));
-            creator_value.insert("lastName".to_string(), Value::String(last_name));
+/// Like [`load_annotation_image`], but served from and stored in the app's
+/// image cache.
+fn resolve_annotation_image(
+    conn: &Connection,
+    image_settings: &ImageSettings,
+    data_dir: &Path,
+    annotation_key: &str,
+    date_modified: &str,
+) -> Result<Vec<u8>, String> {
+    let stamp = image_cache_stamp(image_settings, date_modified);
+    if let Some(bytes) = stamp
+        .as_deref()
+        .and_then(|stamp| image_cache::get(data_dir, annotation_key, stamp))
+    {
+        return Ok(bytes);
+    }
+
+    let bytes = load_annotation_image(conn, annotation_key, image_settings.render_dpi)?;
+    if let Some(stamp) = stamp.as_deref() {
+        if let Err(err) = image_cache::put(data_dir, annotation_key, stamp, &bytes) {
+            tracing::warn!(annotation_key, "{err}");
         }
-        creators.push(Value::Object(creator_value));
     }
-    data.insert("creators".to_string(), Value::Array(creators));
+    Ok(bytes)
+}
 
-    let mut payload = Map::new();
-    payload.insert("key".to_string(), Value::String(key));
-    payload.insert("data".to_string(), Value::Object(data));
-    payload.insert("meta".to_string(), Value::Object(Map::new()));
+/// Cache stamp for an annotation image: its `dateModified` plus the render
+/// resolution, which changes what the image looks like. The timestamp is
+/// normalized so the API and SQLite forms match. `None` when the
+/// modification time is unknown, since the entry could never be invalidated.
+fn image_cache_stamp(image_settings: &ImageSettings, date_modified: &str) -> Option<String> {
+    let date_modified = date_modified.trim();
+    if date_modified.is_empty() {
+        return None;
+    }
+    let modified = report::parse_zotero_timestamp(date_modified)
+        .map_or_else(|| date_modified.to_string(), |seconds| seconds.to_string());
+    let resolution = match image_settings.render_dpi {
+        Some(dpi) => format!("{dpi}dpi"),
+        None => "zotero".to_string(),
+    };
+    Some(format!("{modified}|{resolution}"))
+}
 
-    Ok(Value::Object(payload))
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrefetchFailure {
+    annotation_key: String,
+    message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PrefetchReport {
+    /// Images now in the app's image cache.
+    cached: usize,
+    /// Images not cached because their annotation has no `dateModified`
+    /// to key the cache entry by; exports render them each time.
+    skipped: usize,
+    failed: Vec<PrefetchFailure>,
+}
+
+/// Images resolved at once by `prefetch_annotation_images`; rendering is
+/// CPU-bound and each worker holds a database connection.
+const PREFETCH_WORKERS: usize = 4;
+
+/// Warms the image cache with every area and ink annotation image of an
+/// item, [`PREFETCH_WORKERS`] at a time, reporting progress per image.
+#[tauri::command]
+async fn prefetch_annotation_images(
+    app: AppHandle,
+    item_key: String,
+    operation_id: Option<String>,
+) -> Result<PrefetchReport, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?;
+        let data_dir = app_data_dir(&app)?;
+        let (annotations, uncacheable): (Vec<_>, Vec<_>) =
+            load_annotations(&*open_zotero_connection(&app)?, &item_key)?
+                .into_iter()
+                .filter(|annotation| annotation.is_image_selection)
+                .partition(|annotation| image_cache_stamp(&settings.image_settings, &annotation.date_modified).is_some());
+        let progress = Progress::new(&app, "prefetch", operation_id);
+        let total = annotations.len() as u64;
+
+        let next = AtomicUsize::new(0);
+        let done = AtomicU64::new(0);
+        let report = Mutex::new(PrefetchReport {
+            skipped: uncacheable.len(),
+            ..PrefetchReport::default()
+        });
+        std::thread::scope(|scope| {
+            for _ in 0..PREFETCH_WORKERS.min(annotations.len()) {
+                scope.spawn(|| {
+                    // Each worker checks out its own pooled connection.
+                    let conn = open_zotero_connection(&app).map_err(String::from);
+                    while let Some(annotation) = annotations.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let result = conn.as_ref().map_err(Clone::clone).and_then(|conn| {
+                            resolve_annotation_image(
+                                conn,
+                                &settings.image_settings,
+                                &data_dir,
+                                &annotation.key,
+                                &annotation.date_modified,
+                            )
+                        });
+                        let mut report = report.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        match result {
+                            Ok(_) => report.cached += 1,
+                            Err(message) => report.failed.push(PrefetchFailure {
+                                annotation_key: annotation.key.clone(),
+                                message,
+                            }),
+                        }
+                        drop(report);
+                        let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        progress.report(current, total, &annotation.key);
+                    }
+                });
+            }
+        });
+        Ok(report.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    })
+    .await
 }
 
+/// The cached image as a raw IPC response; an empty one when there is none.
 #[tauri::command]
-fn zotero_sqlite_get_citation_key(item_key: String) -> Result<Option<String>, String> {
-    let conn = match open_better_bibtex_connection() {
-        Ok(conn) => conn,
-        Err(_) => return Ok(None),
+fn read_image_cache(app: AppHandle, annotation_key: String, date_modified: String) -> Result<Response, AppError> {
+    let Some(stamp) = image_cache_stamp(&read_settings(&app)?.image_settings, &date_modified) else {
+        return Ok(Response::new(Vec::new()));
     };
+    let bytes = image_cache::get(&app_data_dir(&app)?, &annotation_key, &stamp);
+    Ok(Response::new(bytes.unwrap_or_default()))
+}
 
-    let citation_key = conn
-        .query_row(
-            r#"
-            SELECT citationKey
-            FROM citationkey
-            WHERE itemKey = ?1
-            LIMIT 1
-            "#,
-            params![item_key],
-            |row| row.get::<_, String>(0),
-        )
-        .optional()
-        .map_err(|err| format!("failed to read Better BibTeX citation key: {err}"))?;
+#[tauri::command]
+fn write_image_cache(
+    app: AppHandle,
+    annotation_key: String,
+    date_modified: String,
+    bytes: Vec<u8>,
+) -> Result<(), AppError> {
+    let Some(stamp) = image_cache_stamp(&read_settings(&app)?.image_settings, &date_modified) else {
+        return Ok(());
+    };
+    Ok(image_cache::put(&app_data_dir(&app)?, &annotation_key, &stamp, &bytes)?)
+}
 
-    Ok(citation_key.and_then(|value| {
-        let trimmed = value.trim();
-        if trimmed.is_empty() {
-            None
-        } else {
-            Some(trimmed.to_string())
+/// Text recognized in an annotation image, or `None` when OCR is off or
+/// found nothing.
+#[tauri::command]
+async fn ocr_annotation_image(app: AppHandle, bytes: Vec<u8>) -> Result<Option<String>, AppError> {
+    run_blocking(move || {
+        let settings = read_settings(&app)?.ocr;
+        if !settings.enabled {
+            return Ok(None);
         }
-    }))
+        Ok(ocr::recognize(&bytes, &settings)?)
+    })
+    .await
 }
 
 #[tauri::command]
-fn zotero_sqlite_get_annotations(item_key: String) -> Result<Vec<SqliteAnnotation>, String> {
-    let conn = open_zotero_connection()?;
+fn clear_image_cache(app: AppHandle) -> Result<usize, AppError> {
+    Ok(image_cache::clear(&app_data_dir(&app)?)?)
+}
 
-    let mut stmt = conn
-        .prepare(
+/// Re-renders an area or ink annotation from its position at `dpi` (the
+/// configured render resolution, or the default, when omitted), ignoring
+/// Zotero's cached image, for re-exporting at a higher quality.
+#[tauri::command]
+async fn zotero_sqlite_render_annotation_image(
+    app: AppHandle,
+    annotation_key: String,
+    dpi: Option<u32>,
+    format: Option<ImageOutputFormat>,
+) -> Result<Response, AppError> {
+    run_blocking(move || {
+        let image_settings = read_settings(&app)?.image_settings;
+        let dpi = dpi
+            .or(image_settings.render_dpi)
+            .unwrap_or(pdf_render::DEFAULT_DPI)
+            .clamp(pdf_render::MIN_DPI, pdf_render::MAX_DPI);
+        let conn = open_zotero_connection(&app)?;
+        let bytes = render_annotation_image(&conn, &annotation_key, dpi)?;
+        Ok(Response::new(convert_annotation_image(bytes, format, &image_settings)?))
+    })
+    .await
+}
+
+/// Renders running at once across prefetches, export workers and render
+/// commands; a high-resolution page render holds a lot of memory.
+const MAX_CONCURRENT_RENDERS: usize = PREFETCH_WORKERS;
+
+static RENDERS_RUNNING: Mutex<usize> = Mutex::new(0);
+static RENDER_FINISHED: Condvar = Condvar::new();
+
+/// One of the [`MAX_CONCURRENT_RENDERS`] render slots, freed when dropped.
+struct RenderSlot;
+
+impl RenderSlot {
+    fn acquire() -> Self {
+        let running = RENDERS_RUNNING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut running = RENDER_FINISHED
+            .wait_while(running, |running| *running >= MAX_CONCURRENT_RENDERS)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *running += 1;
+        Self
+    }
+}
+
+impl Drop for RenderSlot {
+    fn drop(&mut self) {
+        let mut running = RENDERS_RUNNING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *running = running.saturating_sub(1);
+        RENDER_FINISHED.notify_one();
+    }
+}
+
+/// Renders an annotation without Zotero's cache: ink strokes are drawn from
+/// their stored paths, anything else is cropped from the attachment PDF
+/// using its reader position. Waits for a free render slot first.
+fn render_annotation_image(conn: &Connection, annotation_key: &str, dpi: u32) -> Result<Vec<u8>, String> {
+    let (annotation_type, color, position, attachment_key, link_mode, path) = conn
+        .query_row(
             r#"
-            SELECT
-                anno.key AS annotationKey,
-                att.key AS attachmentKey,
-                COALESCE(ia.color, '') AS colorHex,
-                COALESCE(ia.text, '') AS annotationText,
-                COALESCE(ia.comment, '') AS annotationComment,
-                COALESCE(ia.pageLabel, '') AS pageLabel,
-                ia.sortIndex AS sortKey,
-                ia.type AS annotationType
-            FROM items root
-            JOIN itemAttachments iatt ON iatt.parentItemID = root.itemID
-            JOIN items att ON att.itemID = iatt.itemID
-            JOIN itemAnnotations ia ON ia.parentItemID = att.itemID
-            JOIN items anno ON anno.itemID = ia.itemID
-            WHERE root.key = ?1
-              AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
-            ORDER BY att.itemID ASC, ia.sortIndex ASC, anno.itemID ASC
+            SELECT ia.type, COALESCE(ia.color, ''), COALESCE(ia.position, ''),
+                   att.key, iatt.linkMode, COALESCE(iatt.path, '')
+            FROM items anno
+            JOIN itemAnnotations ia ON ia.itemID = anno.itemID
+            JOIN items att ON att.itemID = ia.parentItemID
+            JOIN itemAttachments iatt ON iatt.itemID = att.itemID
+            WHERE anno.key = ?1
+            LIMIT 1
             "#,
+            params![annotation_key],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            },
         )
-        .map_err(|err| format!("failed to prepare Zotero annotation query: {err}"))?;
-
-    let rows = stmt
-        .query_map(params![item_key], |row| {
-            let annotation_type: i64 = row.get(7)?;
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, String>(4)?,
-                row.get::<_, String>(5)?,
-                annotation_type,
-            ))
-        })
-        .map_err(|err| format!("failed to execute Zotero annotation query: {err}"))?;
+        .optional()
+        .map_err(|err| format!("failed to load annotation {annotation_key}: {err}"))?
+        .ok_or_else(|| format!("annotation {annotation_key} not found in Zotero."))?;
 
-    let mut annotations = Vec::<SqliteAnnotation>::new();
-    for (sort_index, row) in rows.enumerate() {
-        let (key, attachment_key, color_hex, text, comment, page_label, annotation_type) =
-            row.map_err(|err| format!("failed to read Zotero annotation row: {err}"))?;
+    if bundle::annotation_type_name(annotation_type) == "ink" {
+        let _slot = RenderSlot::acquire();
+        return ink::render_png(&position, &color, dpi);
+    }
 
-        annotations.push(SqliteAnnotation {
-            key,
-            attachment_key,
-            color_hex: color_hex.trim().to_lowercase(),
-            text: text.trim().to_string(),
-            comment: comment.trim().to_string(),
-            page_label: page_label.trim().to_string(),
-            sort_index,
-            is_image_selection: annotation_type == 3,
-        });
+    let position = AnnotationPosition::parse(&position)?;
+    let pdf_path = resolve_attachment_file(&attachment_key, link_mode, &path)?;
+    if !pdf_path.exists() {
+        return Err(format!(
+            "attachment file {} for annotation {annotation_key} does not exist; sync or download it in Zotero first.",
+            pdf_path.display()
+        ));
     }
+    let _slot = RenderSlot::acquire();
+    pdf_render::render_region(&pdf_path, &position, dpi)
+}
 
-    Ok(annotations)
+/// Where an attachment's file lives: `storage:` paths are inside the data
+/// directory's storage folder, `attachments:` paths are relative to the
+/// linked-file base directory, anything else is an absolute linked path.
+/// Stored files missing locally are downloaded from WebDAV when configured.
+fn resolve_attachment_file(attachment_key: &str, link_mode: i64, path: &str) -> Result<PathBuf, String> {
+    if let Some(name) = path.strip_prefix("storage:") {
+        let dir = resolve_zotero_profile_dir()?.join("storage").join(attachment_key);
+        let file = dir.join(name);
+        if !file.exists() {
+            webdav::download_attachment(attachment_key, &dir)?;
+        }
+        return Ok(file);
+    }
+    if let Some(relative) = path.strip_prefix("attachments:") {
+        let base = zotero_profile::base_attachment_dir(&home_dir()?).ok_or_else(|| {
+            format!("attachment {attachment_key} is relative to Zotero's linked attachment base directory, which is not set.")
+        })?;
+        return Ok(base.join(relative));
+    }
+    // Linked URLs (link mode 3) have no file to render from.
+    if path.is_empty() || link_mode == 3 {
+        return Err(format!("attachment {attachment_key} has no local file."));
+    }
+    Ok(PathBuf::from(path))
 }
 
-#[tauri::command]
-fn zotero_sqlite_get_cached_annotation_image(annotation_key: String) -> Result<Vec<u8>, String> {
-    let conn = open_zotero_connection()?;
+/// Locates the PNG Zotero renders for an image annotation in its cache.
+fn cached_annotation_image_path(conn: &Connection, annotation_key: &str) -> Result<Option<PathBuf>, String> {
     let profile_dir = resolve_zotero_profile_dir()?;
 
     let library_scope = conn
@@ -645,7 +3642,7 @@ fn zotero_sqlite_get_cached_annotation_image(annotation_key: String) -> Result<V
             WHERE i.key = ?1
             LIMIT 1
             "#,
-            params![annotation_key.clone()],
+            params![annotation_key],
             |row| {
                 let library_type: String = row.get(0)?;
                 let group_id: Option<i64> = row.get(1)?;
@@ -684,37 +3681,186 @@ fn zotero_sqlite_get_cached_annotation_image(annotation_key: String) -> Result<V
         }
     }
 
-    for candidate in candidates {
-        if candidate.exists() {
-            return std::fs::read(&candidate)
-                .map_err(|err| format!("failed to read cached annotation image {}: {err}", candidate.display()));
-        }
-    }
+    Ok(candidates.into_iter().find(|candidate| candidate.exists()))
+}
 
-    Err(format!(
-        "no cached annotation image found for {} in Zotero cache.",
-        annotation_key
-    ))
+/// Handles `zotnotes export …` and `zotnotes mcp`; see [`cli::run`].
+/// Returns `None` when the arguments are not a CLI command and the app
+/// should start.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    cli::run(args)
 }
 
 pub fn run() {
     tauri::Builder::default()
+        .manage(DbPool::default())
+        .register_asynchronous_uri_scheme_protocol(annotation_protocol::SCHEME, annotation_protocol::handle)
+        .setup(|app| {
+            let settings = read_settings(app.handle()).unwrap_or_default();
+            if let Ok(data_dir) = app_data_dir(app.handle()) {
+                let _ = logging::init(&data_dir, settings.log_level);
+            }
+            tracing::info!(version = env!("CARGO_PKG_VERSION"), "ZotNotes started");
+            watcher::spawn(app.handle().clone());
+            autosync::spawn(app.handle().clone());
+            jobs::spawn(app.handle().clone());
+            api_server::spawn(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             select_directory_dialog,
+            select_save_file_dialog,
+            select_open_file_dialog,
             save_markdown_file,
+            open_in_editor,
+            open_in_obsidian,
+            open_in_zotero,
+            open_annotation_in_zotero,
+            reveal_in_file_manager,
+            copy_citation,
+            copy_pandoc_citation,
+            copy_note_markdown,
+            record_item_export,
+            get_item_export_record,
+            write_export_changelog,
             ensure_dir,
-            save_png_bytes,
+            save_annotation_image,
+            save_item_asset,
+            render_note,
+            resolve_export_target,
+            html_to_markdown,
             load_settings,
             save_settings,
+            update_settings,
+            reset_settings,
+            test_connections,
+            get_recent_logs,
+            create_diagnostics_bundle,
+            export_settings_bundle,
+            import_settings_bundle,
+            list_profiles,
+            create_profile,
+            switch_profile,
+            delete_profile,
             write_temp_debug_dump,
             zotero_proxy_get_json,
             zotero_proxy_get_bytes,
             zotero_sqlite_search_items,
+            search_index,
+            stream_search,
+            cancel_search,
+            search_annotations,
+            search_notes,
+            unified_search,
+            record_item_opened,
+            pin_item,
+            unpin_item,
+            get_quick_list,
+            rebuild_search_index,
             zotero_sqlite_get_item,
             zotero_sqlite_get_citation_key,
             zotero_sqlite_get_annotations,
+            zotero_sqlite_count_annotations,
+            zotero_sqlite_get_child_notes,
+            export_items_csv,
+            export_items_ris,
+            export_highlights_readwise,
+            export_notes_docx,
+            export_notes_notion,
+            export_item_bundle,
+            zotero_sqlite_changed_exported_items,
+            sync_changed_items,
+            export_items,
+            import_notes,
+            find_unlinked_citations,
+            get_api_server_status,
+            get_sync_history,
+            find_orphaned_notes,
+            resolve_orphaned_notes,
+            push_comment_edits,
+            enqueue_job,
+            list_jobs,
+            cancel_job,
+            retry_job,
             zotero_sqlite_get_cached_annotation_image,
+            zotero_sqlite_render_annotation_image,
+            read_image_cache,
+            write_image_cache,
+            clear_image_cache,
+            ocr_annotation_image,
+            prefetch_annotation_images,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn fields(errors: &[validation::FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn settings_patches_merge_nested_objects() {
+        let mut current = AppSettings::default();
+        current.template_settings.section_heading_level = 2;
+        let settings = patched_settings(&current, json!({"templateSettings": {"tableOfContents": true}})).unwrap();
+        assert!(settings.template_settings.table_of_contents);
+        assert_eq!(settings.template_settings.section_heading_level, 2);
+        assert_eq!(settings.search_limit, current.search_limit);
+    }
+
+    #[test]
+    fn null_in_a_settings_patch_removes_the_value() {
+        let mut current = AppSettings {
+            markdown_dir: "/vault".to_string(),
+            ..AppSettings::default()
+        };
+        current.template_settings.color_heading_overrides =
+            BTreeMap::from([("Yellow".to_string(), "Key".to_string()), ("Blue".to_string(), "Method".to_string())]);
+        let patch = json!({
+            "markdownDir": null,
+            "templateSettings": {"colorHeadingOverrides": {"Yellow": null}}
+        });
+        let settings = patched_settings(&current, patch).unwrap();
+        assert_eq!(settings.markdown_dir, "");
+        assert_eq!(
+            settings.template_settings.color_heading_overrides,
+            BTreeMap::from([("Blue".to_string(), "Method".to_string())])
+        );
+    }
+
+    #[test]
+    fn settings_patches_must_be_objects_of_known_keys() {
+        let current = AppSettings::default();
+        let errors = patched_settings(&current, json!(["markdownDir"])).unwrap_err();
+        assert_eq!(fields(&errors), [""]);
+        assert_eq!(errors[0].message, "A settings patch must be a JSON object.");
+
+        let errors = patched_settings(&current, json!({"searchLimit": 10, "bogus": 1})).unwrap_err();
+        assert_eq!(fields(&errors), ["bogus"]);
+
+        let errors = patched_settings(&current, json!({"searchLimit": "ten"})).unwrap_err();
+        assert_eq!(fields(&errors), [""]);
+    }
+
+    #[test]
+    fn only_patched_settings_are_validated() {
+        let current = AppSettings {
+            search_limit: 0,
+            attachment_base_dir: "/missing/zotnotes/attachments".to_string(),
+            ..AppSettings::default()
+        };
+
+        let settings = patched_settings(&current, json!({"searchFuzziness": 1})).unwrap();
+        assert_eq!(settings.search_fuzziness, 1);
+
+        let patch = json!({"markdownDir": "/missing/zotnotes/vault", "templateSettings": {"sectionHeadingLevel": 7}});
+        let errors = patched_settings(&current, patch).unwrap_err();
+        assert_eq!(fields(&errors), ["markdownDir", "templateSettings.sectionHeadingLevel"]);
+    }
+}
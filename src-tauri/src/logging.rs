@@ -0,0 +1,131 @@
+//! Structured logging through `tracing`, written by `tracing-subscriber` to
+//! a daily `zotnotes.YYYY-MM-DD.log` in the app data directory, keeping the
+//! last five days. The level comes from the settings and can change while
+//! the app runs.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+const LOG_FILE_PREFIX: &str = "zotnotes";
+const LOG_FILE_SUFFIX: &str = "log";
+const KEPT_LOG_FILES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Changes the level of the installed logger; set once by [`init`].
+static LEVEL: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+pub fn set_level(level: LogLevel) {
+    if let Some(handle) = LEVEL.get() {
+        if let Err(err) = handle.reload(LevelFilter::from(level)) {
+            tracing::warn!("failed to change the log level: {err}");
+        }
+    }
+}
+
+/// Installs the file logger for the whole process.
+pub fn init(log_dir: &Path, level: LogLevel) -> Result<(), String> {
+    std::fs::create_dir_all(log_dir)
+        .map_err(|err| format!("failed to create log directory {}: {err}", log_dir.display()))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(KEPT_LOG_FILES)
+        .build(log_dir)
+        .map_err(|err| format!("failed to open the log in {}: {err}", log_dir.display()))?;
+    let (filter, handle) = reload::Layer::new(LevelFilter::from(level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(appender).with_ansi(false))
+        .try_init()
+        .map_err(|err| format!("failed to install logger: {err}"))?;
+    let _ = LEVEL.set(handle);
+    Ok(())
+}
+
+/// Log files in `log_dir`, newest first. Their names carry the day, so
+/// they sort by name.
+fn log_files(log_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(log_dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().and_then(|name| name.to_str()).is_some_and(|name| {
+                name.starts_with(&format!("{LOG_FILE_PREFIX}."))
+                    && name.ends_with(&format!(".{LOG_FILE_SUFFIX}"))
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// The last `count` lines of the log, oldest first, reaching into older
+/// files when the current one is shorter.
+pub fn recent_lines(log_dir: &Path, count: usize) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for file in log_files(log_dir) {
+        if lines.len() >= count {
+            break;
+        }
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        let older = content.lines().map(str::to_string).collect::<Vec<_>>();
+        lines.splice(0..0, older);
+    }
+    let skip = lines.len().saturating_sub(count);
+    lines.split_off(skip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_lines_read_back_through_older_days() {
+        let dir = std::env::temp_dir().join(format!("zotnotes-logging-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("zotnotes.2026-10-15.log"), "a\nb\n").unwrap();
+        std::fs::write(dir.join("zotnotes.2026-10-16.log"), "c\nd\n").unwrap();
+        std::fs::write(dir.join("zotnotes.2026-10-17.log"), "e\n").unwrap();
+        std::fs::write(dir.join("other.log"), "x\n").unwrap();
+
+        assert_eq!(recent_lines(&dir, 1), ["e"]);
+        assert_eq!(recent_lines(&dir, 3), ["c", "d", "e"]);
+        assert_eq!(recent_lines(&dir, 10), ["a", "b", "c", "d", "e"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
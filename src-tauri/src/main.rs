@@ -1,5 +1,31 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+/// Release builds on Windows start without a console, so the command-line
+/// interface attaches to the one of the shell it was started from; without
+/// it, everything it prints is lost.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails harmlessly when there is no parent console, e.g. from Explorer.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    #[cfg(windows)]
+    if !args.is_empty() {
+        attach_parent_console();
+    }
+    if let Some(code) = zotnotes_lib::run_cli(&args) {
+        std::process::exit(code);
+    }
     zotnotes_lib::run();
 }
@@ -0,0 +1,281 @@
+//! The managed region of exported notes: everything from `## Annotations` up
+//! to `## Notes` (or the end of the note). Each annotation in it is a
+//! blockquote; its Zotero key comes from the page link
+//! (`zotero://select/...` or `annotation=` in an `open-pdf` link) or from a
+//! `^zot-<key>` block ID.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const ANNOTATIONS_HEADING: &str = "## Annotations";
+const NOTES_HEADING: &str = "## Notes";
+
+const KEY_MARKERS: [&str; 3] = ["zotero://select/library/items/", "annotation=", "^zot-"];
+
+/// First line of the callout that wraps annotations deleted in Zotero.
+pub const DELETED_CALLOUT: &str = "[!warning] Deleted in Zotero";
+
+/// What a re-export does with annotations that were removed in Zotero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeletedAnnotations {
+    /// Drop them from the note, like any other re-rendered content.
+    #[default]
+    Remove,
+    /// Keep them at the end of the annotations, inside a callout.
+    Mark,
+}
+
+/// One blockquote in the managed region, without its `> ` prefixes.
+pub struct AnnotationBlock<'a> {
+    pub key: Option<String>,
+    pub lines: Vec<&'a str>,
+}
+
+impl AnnotationBlock<'_> {
+    /// Whether the block is already wrapped in the deleted-in-Zotero callout.
+    pub fn is_deleted_callout(&self) -> bool {
+        self.lines.first() == Some(&DELETED_CALLOUT)
+    }
+}
+
+fn block_key(lines: &[&str]) -> Option<String> {
+    lines.iter().find_map(|line| {
+        KEY_MARKERS.iter().find_map(|marker| {
+            let start = line.find(marker)? + marker.len();
+            let key = line[start..]
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect::<String>();
+            (!key.is_empty()).then_some(key)
+        })
+    })
+}
+
+/// Splits the managed region into annotation blockquotes.
+pub fn annotation_blocks(markdown: &str) -> Vec<AnnotationBlock<'_>> {
+    let mut in_region = false;
+    let mut blocks = Vec::<Vec<&str>>::new();
+    let mut current = Vec::<&str>::new();
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == ANNOTATIONS_HEADING {
+            in_region = true;
+        } else if trimmed == NOTES_HEADING {
+            in_region = false;
+        } else if let Some(quoted) = line.strip_prefix('>').filter(|_| in_region) {
+            current.push(quoted.strip_prefix(' ').unwrap_or(quoted));
+            continue;
+        }
+        if !current.is_empty() {
+            blocks.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks
+        .into_iter()
+        .map(|lines| AnnotationBlock {
+            key: block_key(&lines),
+            lines,
+        })
+        .collect()
+}
+
+/// Carries annotations from the previous version of a note that are no
+/// longer in Zotero into the freshly rendered note, each wrapped in a
+/// deleted-in-Zotero callout at the end of the annotations.
+pub fn mark_deleted_annotations(previous: &str, rendered: &str, current_keys: &[String]) -> String {
+    let deleted = annotation_blocks(previous)
+        .into_iter()
+        .filter(|block| {
+            block
+                .key
+                .as_ref()
+                .is_some_and(|key| !current_keys.contains(key))
+        })
+        .collect::<Vec<_>>();
+    if deleted.is_empty() {
+        return rendered.to_string();
+    }
+
+    let mut callouts = Vec::<String>::new();
+    for block in &deleted {
+        let body = if block.is_deleted_callout() {
+            &block.lines[1..]
+        } else {
+            &block.lines[..]
+        };
+        callouts.push(format!("> {DELETED_CALLOUT}"));
+        callouts.extend(body.iter().map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        }));
+        callouts.push(String::new());
+    }
+
+    let mut lines = rendered.lines().map(str::to_string).collect::<Vec<_>>();
+    let region_start = lines
+        .iter()
+        .position(|line| line.trim_end() == ANNOTATIONS_HEADING);
+    let insert_at = match region_start {
+        Some(start) => lines[start..]
+            .iter()
+            .position(|line| line.trim_end() == NOTES_HEADING)
+            .map(|offset| start + offset)
+            .unwrap_or(lines.len()),
+        None => {
+            lines.push(ANNOTATIONS_HEADING.to_string());
+            lines.push(String::new());
+            lines.len()
+        }
+    };
+    lines.splice(insert_at..insert_at, callouts);
+
+    let mut merged = lines.join("\n");
+    if rendered.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged
+}
+
+/// Applies `mode` to a note about to be written to `path`, using the note
+/// currently on disk as the previous export.
+pub fn merge_with_previous(
+    path: &Path,
+    rendered: String,
+    current_keys: &[String],
+    mode: DeletedAnnotations,
+) -> String {
+    if mode == DeletedAnnotations::Remove {
+        return rendered;
+    }
+    match std::fs::read_to_string(path) {
+        Ok(previous) => mark_deleted_annotations(&previous, &rendered, current_keys),
+        Err(_) => rendered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREVIOUS: &str = "\
+# Paper
+
+User intro.
+
+## Annotations
+
+> Kept [p. 1](zotero://select/library/items/KEEP1)
+
+> Gone [p. 2](zotero://open-pdf/library/items/PDF?annotation=GONE2)
+> second line
+
+> No key here
+
+## Notes
+
+User notes.
+";
+
+    fn keys(markdown: &str) -> Vec<Option<String>> {
+        annotation_blocks(markdown)
+            .into_iter()
+            .map(|block| block.key)
+            .collect()
+    }
+
+    #[test]
+    fn blocks_are_read_between_the_annotations_and_notes_headings() {
+        let blocks = annotation_blocks(PREVIOUS);
+        assert_eq!(
+            keys(PREVIOUS),
+            [Some("KEEP1".to_string()), Some("GONE2".to_string()), None]
+        );
+        assert_eq!(
+            blocks[1].lines,
+            [
+                "Gone [p. 2](zotero://open-pdf/library/items/PDF?annotation=GONE2)",
+                "second line"
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_outside_the_region_are_ignored() {
+        let markdown = "> Before ^zot-BEFORE\n\n## Annotations\n\n> In ^zot-INSIDE\n\n## Notes\n\n> After ^zot-AFTER\n";
+        assert_eq!(keys(markdown), [Some("INSIDE".to_string())]);
+    }
+
+    #[test]
+    fn region_without_notes_heading_runs_to_the_end() {
+        let markdown = "## Annotations\n\n> One ^zot-ONE\n\n> Two ^zot-TWO";
+        assert_eq!(
+            keys(markdown),
+            [Some("ONE".to_string()), Some("TWO".to_string())]
+        );
+        assert!(annotation_blocks("> Loose ^zot-LOOSE\n").is_empty());
+    }
+
+    #[test]
+    fn deleted_annotations_are_marked_and_user_text_kept() {
+        let rendered = "# Paper\n\nUser intro.\n\n## Annotations\n\n> Kept [p. 1](zotero://select/library/items/KEEP1)\n\n## Notes\n\nUser notes.\n";
+        let merged = mark_deleted_annotations(PREVIOUS, rendered, &["KEEP1".to_string()]);
+        assert_eq!(
+            merged,
+            "# Paper\n\nUser intro.\n\n## Annotations\n\n\
+             > Kept [p. 1](zotero://select/library/items/KEEP1)\n\n\
+             > [!warning] Deleted in Zotero\n\
+             > Gone [p. 2](zotero://open-pdf/library/items/PDF?annotation=GONE2)\n\
+             > second line\n\n\
+             ## Notes\n\nUser notes.\n"
+        );
+    }
+
+    #[test]
+    fn marking_again_does_not_nest_callouts() {
+        let rendered = "## Annotations\n\n## Notes\n";
+        let once = mark_deleted_annotations(PREVIOUS, rendered, &[]);
+        let twice = mark_deleted_annotations(&once, rendered, &[]);
+        assert_eq!(once, twice);
+        assert_eq!(once.matches(DELETED_CALLOUT).count(), 2);
+    }
+
+    #[test]
+    fn missing_region_is_appended_to_the_rendered_note() {
+        let merged = mark_deleted_annotations(PREVIOUS, "# Paper", &["KEEP1".to_string()]);
+        assert!(merged.starts_with("# Paper\n## Annotations\n\n> [!warning]"));
+    }
+
+    #[test]
+    fn nothing_deleted_leaves_the_rendered_note_alone() {
+        let rendered = "## Annotations\n\n> ^zot-KEEP1\n";
+        let current = ["KEEP1".to_string(), "GONE2".to_string()];
+        assert_eq!(mark_deleted_annotations(PREVIOUS, rendered, &current), rendered);
+    }
+
+    #[test]
+    fn remove_mode_and_missing_file_return_the_rendered_note() {
+        let path = std::env::temp_dir().join(format!("zotnotes-managed-{}.md", std::process::id()));
+        std::fs::write(&path, PREVIOUS).unwrap();
+        let rendered = "## Annotations\n".to_string();
+        assert_eq!(
+            merge_with_previous(&path, rendered.clone(), &[], DeletedAnnotations::Remove),
+            rendered
+        );
+        let marked = merge_with_previous(&path, rendered.clone(), &[], DeletedAnnotations::Mark);
+        assert_eq!(marked.matches(DELETED_CALLOUT).count(), 2);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            merge_with_previous(&path, rendered.clone(), &[], DeletedAnnotations::Mark),
+            rendered
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! `zotnotes mcp` serves the Zotero library to AI assistants over the Model
+//! Context Protocol: JSON-RPC 2.0 messages, one per line, on stdin and
+//! stdout. Assistants get tools to search the library, read an item's
+//! metadata and annotations, and render its note. No notes are written;
+//! rendering may add annotation images to the app's image cache.
+//!
+//! The settings and databases are opened by the first tool call and kept
+//! for the session. zotero.sqlite is read live, so the answers follow
+//! Zotero while the server keeps running.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+use crate::cli::Headless;
+use crate::zotero_db::Freshness;
+use crate::{check_item_key, load_annotations, load_sqlite_item, profiles, search_items, validation};
+
+/// Protocol revisions this server speaks, newest first. The tools it uses
+/// are the same in each.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const USAGE: &str = "\
+Usage: zotnotes mcp [--profile <name>]
+
+Serves the Zotero library to AI assistants over the Model Context Protocol
+on stdin and stdout. Start it from the assistant's MCP server settings.
+
+Options:
+  --profile <name>  Use this settings profile instead of the active one
+  -h, --help        Show this help";
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+fn tools() -> Value {
+    let item_key = json!({
+        "type": "object",
+        "properties": {
+            "itemKey": { "type": "string", "description": "Zotero item key, eight letters and digits." }
+        },
+        "required": ["itemKey"]
+    });
+    json!([
+        {
+            "name": "search_library",
+            "description": "Search the Zotero library by title, creator or year. Returns item keys, titles, creators and years.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Text to look for; empty lists items by title." },
+                    "limit": { "type": "integer", "minimum": 1, "description": "Most items to return." }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_item",
+            "description": "Metadata of a Zotero item in the Zotero web API's JSON shape, plus its cite key, or null when it has none.",
+            "inputSchema": item_key
+        },
+        {
+            "name": "get_annotations",
+            "description": "The item's PDF and EPUB annotations: highlighted text, comments, colors and page labels.",
+            "inputSchema": item_key
+        },
+        {
+            "name": "render_note",
+            "description": "The item's Markdown note as ZotNotes would export it now, merged with edits to the exported note.",
+            "inputSchema": item_key
+        }
+    ])
+}
+
+fn item_key_argument(arguments: &Value) -> Result<String, String> {
+    let item_key = arguments
+        .get("itemKey")
+        .and_then(Value::as_str)
+        .ok_or("itemKey is required.")?;
+    check_item_key(item_key)?;
+    Ok(item_key.to_string())
+}
+
+fn pretty(value: &impl serde::Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|err| format!("failed to serialize the result: {err}"))
+}
+
+/// The profile the server was started for, and its settings and databases
+/// once a tool needed them.
+struct Session {
+    profile: Option<String>,
+    headless: Option<Headless>,
+}
+
+impl Session {
+    /// Opens the profile on first use. A snapshot of zotero.sqlite, taken
+    /// while Zotero held the file, is dropped so the next call reads the
+    /// live file again.
+    fn headless(&mut self) -> Result<&Headless, String> {
+        let headless = match self.headless.take() {
+            Some(headless) if !matches!(headless.zotero.freshness, Freshness::Snapshot { .. }) => headless,
+            _ => Headless::open(self.profile.as_deref())?,
+        };
+        Ok(self.headless.insert(headless))
+    }
+}
+
+/// Runs a tool and returns its text result.
+fn call_tool(headless: &Headless, name: &str, arguments: &Value) -> Result<String, String> {
+    match name {
+        "search_library" => {
+            let query = arguments.get("query").and_then(Value::as_str).unwrap_or_default();
+            let limit = arguments
+                .get("limit")
+                .and_then(Value::as_u64)
+                .map_or(headless.settings.search_limit, |limit| limit as usize)
+                .clamp(1, validation::MAX_SEARCH_LIMIT);
+            pretty(&search_items(&headless.zotero, query, limit)?)
+        }
+        "get_item" => {
+            let item_key = item_key_argument(arguments)?;
+            let mut item = load_sqlite_item(&headless.zotero, &item_key)?;
+            // Items without a cite key still have metadata worth returning.
+            let cite_key = headless.exporter(true).resolve_cite_key(&item, &item_key).ok();
+            if let Some(item) = item.as_object_mut() {
+                item.insert("citeKey".to_string(), json!(cite_key));
+            }
+            pretty(&item)
+        }
+        "get_annotations" => pretty(&load_annotations(&headless.zotero, &item_key_argument(arguments)?)?),
+        "render_note" => Ok(headless.exporter(true).render_item(&item_key_argument(arguments)?)?.markdown),
+        other => Err(format!("unknown tool {other}.")),
+    }
+}
+
+fn dispatch(session: &mut Session, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => {
+            let requested = params.get("protocolVersion").and_then(Value::as_str);
+            let version = requested
+                .filter(|version| PROTOCOL_VERSIONS.contains(version))
+                .unwrap_or(PROTOCOL_VERSIONS[0]);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "zotnotes", "version": env!("CARGO_PKG_VERSION") },
+                "instructions": "Search the user's Zotero library and read item metadata, annotations and Markdown notes."
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let name = params
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "tools/call needs a tool name."))?;
+            let known = tools()
+                .as_array()
+                .is_some_and(|tools| tools.iter().any(|tool| tool["name"] == name));
+            if !known {
+                return Err(RpcError::new(INVALID_PARAMS, format!("unknown tool {name}.")));
+            }
+            let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            let result = session
+                .headless()
+                .and_then(|headless| call_tool(headless, name, &arguments));
+            let (text, is_error) = match result {
+                Ok(text) => (text, false),
+                Err(err) => {
+                    tracing::warn!(tool = name, "MCP tool failed: {err}");
+                    (err, true)
+                }
+            };
+            Ok(json!({
+                "content": [{ "type": "text", "text": text }],
+                "isError": is_error
+            }))
+        }
+        other => Err(RpcError::new(METHOD_NOT_FOUND, format!("method {other} is not supported."))),
+    }
+}
+
+/// Answers one message, or returns `None` for a notification.
+fn handle_message(session: &mut Session, line: &str) -> Option<Value> {
+    let error = |id: Value, err: RpcError| {
+        json!({ "jsonrpc": "2.0", "id": id, "error": { "code": err.code, "message": err.message } })
+    };
+    let message = match serde_json::from_str::<Value>(line) {
+        Ok(message) => message,
+        Err(err) => return Some(error(Value::Null, RpcError::new(PARSE_ERROR, format!("invalid JSON: {err}")))),
+    };
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        // Responses to requests we never send, and batches, which MCP dropped.
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        return (!id.is_null() || message.is_array())
+            .then(|| error(id, RpcError::new(INVALID_REQUEST, "expected a JSON-RPC request.")));
+    };
+    let id = message.get("id").cloned()?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    Some(match dispatch(session, method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => error(id, err),
+    })
+}
+
+/// Serves requests from stdin until it closes. Returns the exit code: 0
+/// after a clean shutdown, 2 on a usage error.
+pub fn run(args: &[String]) -> i32 {
+    let profile = match args {
+        [] => None,
+        [flag, name] if flag == "--profile" => match profiles::validate_name(name) {
+            Ok(name) => Some(name),
+            Err(err) => {
+                eprintln!("zotnotes: {err}\n\n{USAGE}");
+                return 2;
+            }
+        },
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{USAGE}");
+            return 0;
+        }
+        _ => {
+            eprintln!("zotnotes: unexpected arguments.\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    let mut session = Session {
+        profile,
+        headless: None,
+    };
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(&mut session, &line) {
+            if writeln!(stdout, "{response}").and_then(|()| stdout.flush()).is_err() {
+                break;
+            }
+        }
+    }
+    0
+}
@@ -0,0 +1,348 @@
+//! Maps notes written before ZotNotes to their Zotero items, so exports
+//! update them instead of writing duplicates next to them. A note is
+//! matched by a cite key in its frontmatter (`citekey`, `citationKey`, …)
+//! or file name (`@key.md`), then by a DOI in its frontmatter.
+//!
+//! A mapped note keeps its path: exports of the item go to it whatever the
+//! file name pattern says. Its current content is recorded as the last
+//! export, so edits made after the import follow `conflictStrategy`.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::export::{cite_key_from_item, Exporter};
+use crate::note_search::markdown_files;
+use crate::report::AnnotationStamp;
+use crate::state_db::content_hash;
+use crate::{load_annotations, load_sqlite_item};
+
+const CITE_KEY_FIELDS: [&str; 5] = ["citekey", "citationkey", "cite_key", "citation-key", "citation_key"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchedBy {
+    CiteKey,
+    Doi,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedNote {
+    pub path: String,
+    pub item_key: String,
+    pub cite_key: String,
+    pub matched_by: MatchedBy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedNote {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: Vec<ImportedNote>,
+    /// Notes that matched an item but were left alone.
+    pub skipped: Vec<SkippedNote>,
+    /// Notes with no cite key or DOI that matches an item.
+    pub unmatched: Vec<String>,
+}
+
+/// `key: value` pairs of the note's YAML frontmatter, keys lowercased.
+/// Only plain scalar values are read; lists and nested maps are skipped.
+fn frontmatter(content: &str) -> Vec<(String, String)> {
+    let mut lines = content.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Vec::new();
+    }
+    lines
+        .take_while(|line| !matches!(line.trim_end(), "---" | "..."))
+        .filter(|line| !line.starts_with([' ', '\t', '-']))
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim().trim_matches(['"', '\'']).trim();
+            (!value.is_empty()).then(|| (key.trim().to_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+/// `10.1000/xyz` from a DOI, a `doi:` prefix or a doi.org URL, lowercased.
+fn normalize_doi(raw: &str) -> Option<String> {
+    let lower = raw.trim().to_lowercase();
+    let doi = ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/", "doi:"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix))
+        .unwrap_or(&lower)
+        .trim();
+    doi.starts_with("10.").then(|| doi.to_string())
+}
+
+/// Cite keys named in the note's frontmatter.
+fn frontmatter_cite_keys(fields: &[(String, String)]) -> Vec<String> {
+    let mut keys = fields
+        .iter()
+        .filter(|(key, _)| CITE_KEY_FIELDS.contains(&key.as_str()))
+        .map(|(_, value)| value.trim_start_matches('@').to_string())
+        .filter(|key| !key.is_empty())
+        .collect::<Vec<_>>();
+    keys.dedup();
+    keys
+}
+
+/// The cite key an `@key` file name, or a file name without spaces, may be.
+fn file_name_cite_key(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    let key = match stem.strip_prefix('@') {
+        Some(key) => key.to_string(),
+        None if !stem.contains(char::is_whitespace) => stem,
+        None => return None,
+    };
+    (!key.is_empty()).then_some(key)
+}
+
+/// The item Better BibTeX gives `cite_key`.
+fn better_bibtex_item(citations: Option<&Connection>, cite_key: &str) -> Result<Option<String>, String> {
+    let Some(citations) = citations else {
+        return Ok(None);
+    };
+    citations
+        .prepare_cached("SELECT itemKey FROM citationkey WHERE citationKey = ?1 LIMIT 1")
+        .and_then(|mut stmt| stmt.query_row(params![cite_key], |row| row.get::<_, String>(0)))
+        .optional()
+        .map_err(|err| format!("failed to look up Better BibTeX cite key {cite_key}: {err}"))
+}
+
+/// The regular item with `cite_key`: from Better BibTeX, else from Zotero's
+/// own `citationKey` field or a `Citation Key:` line in Extra.
+pub fn item_for_cite_key(
+    zotero: &Connection,
+    citations: Option<&Connection>,
+    cite_key: &str,
+) -> Result<Option<String>, String> {
+    if let Some(item_key) = better_bibtex_item(citations, cite_key)? {
+        return Ok(Some(item_key));
+    }
+
+    // `citationKey` values are found through the index on
+    // `itemDataValues.value`; Extra is matched by whole line, so only
+    // Extra values are read and other keys containing this one don't match.
+    let mut stmt = zotero
+        .prepare_cached(
+            r#"
+            SELECT i.key
+            FROM items i
+            JOIN itemData d ON d.itemID = i.itemID
+            JOIN fields f ON f.fieldID = d.fieldID
+            JOIN itemDataValues v ON v.valueID = d.valueID
+            WHERE (
+                (f.fieldName = 'citationKey' AND v.value = ?1)
+                OR (
+                    f.fieldName = 'extra'
+                    AND instr(
+                        char(10) || REPLACE(CAST(v.value AS TEXT), char(13), '') || char(10),
+                        char(10) || 'Citation Key: ' || ?1 || char(10)
+                    ) > 0
+                )
+            )
+              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare cite key lookup: {err}"))?;
+    let candidates = stmt
+        .query_map(params![cite_key], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| format!("failed to look up cite key {cite_key}: {err}"))?;
+    // An item with both takes the key in its `citationKey` field.
+    for item_key in candidates {
+        let item = load_sqlite_item(zotero, &item_key)?;
+        if cite_key_from_item(&item).as_deref() == Some(cite_key) {
+            return Ok(Some(item_key));
+        }
+    }
+    Ok(None)
+}
+
+fn item_for_doi(zotero: &Connection, doi: &str) -> Result<Option<String>, String> {
+    zotero
+        .query_row(
+            r#"
+            SELECT i.key
+            FROM items i
+            JOIN itemData d ON d.itemID = i.itemID
+            JOIN fields f ON f.fieldID = d.fieldID
+            JOIN itemDataValues v ON v.valueID = d.valueID
+            WHERE f.fieldName = 'DOI'
+              AND LOWER(TRIM(CAST(v.value AS TEXT))) IN (?1, 'https://doi.org/' || ?1)
+              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+            LIMIT 1
+            "#,
+            params![doi],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|err| format!("failed to look up DOI {doi}: {err}"))
+}
+
+/// The item the note at `path` is about, and what identified it.
+fn match_note(
+    zotero: &Connection,
+    citations: Option<&Connection>,
+    path: &Path,
+    content: &str,
+) -> Result<Option<(String, MatchedBy)>, String> {
+    let fields = frontmatter(content);
+    for cite_key in frontmatter_cite_keys(&fields) {
+        if let Some(item_key) = item_for_cite_key(zotero, citations, &cite_key)? {
+            return Ok(Some((item_key, MatchedBy::CiteKey)));
+        }
+    }
+    // A file name is only a guess, so only Better BibTeX is asked.
+    if let Some(cite_key) = file_name_cite_key(path) {
+        if let Some(item_key) = better_bibtex_item(citations, &cite_key)? {
+            return Ok(Some((item_key, MatchedBy::CiteKey)));
+        }
+    }
+    let doi = fields
+        .iter()
+        .find(|(key, _)| key == "doi")
+        .and_then(|(_, value)| normalize_doi(value));
+    if let Some(doi) = doi {
+        if let Some(item_key) = item_for_doi(zotero, &doi)? {
+            return Ok(Some((item_key, MatchedBy::Doi)));
+        }
+    }
+    Ok(None)
+}
+
+/// Records `note`, holding `content`, as the item's note, at the item's
+/// current version so sync only revisits it once Zotero changes.
+fn record(exporter: &Exporter, note: &ImportedNote, content: &str) -> Result<(), String> {
+    let item = load_sqlite_item(exporter.zotero, &note.item_key)?;
+    let version = item.get("version").and_then(serde_json::Value::as_i64).unwrap_or(0);
+    let stamps = load_annotations(exporter.zotero, &note.item_key)?
+        .into_iter()
+        .map(|annotation| AnnotationStamp {
+            key: annotation.key,
+            date_added: annotation.date_added,
+            date_modified: annotation.date_modified,
+        })
+        .collect::<Vec<_>>();
+    exporter.state.record_imported_note(&note.item_key, &note.path)?;
+    exporter.state.record_item(
+        &note.item_key,
+        &note.cite_key,
+        &note.path,
+        version,
+        &content_hash(content.as_bytes()),
+        &stamps,
+    )
+}
+
+/// Matches the Markdown notes under `dir` to Zotero items and, unless
+/// `dry_run`, records the mappings. Notes ZotNotes already tracks are
+/// passed over, and an item already exported to another existing note is
+/// skipped rather than moved.
+pub fn import(exporter: &Exporter, dir: &Path, dry_run: bool) -> Result<ImportReport, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not an existing folder.", dir.display()));
+    }
+    let recorded = exporter
+        .state
+        .items()?
+        .into_iter()
+        .map(|record| (record.item_key, PathBuf::from(record.markdown_path)))
+        .collect::<HashMap<_, _>>();
+    let mut report = ImportReport {
+        dry_run,
+        ..ImportReport::default()
+    };
+    let mut claimed = HashMap::<String, String>::new();
+
+    for path in markdown_files(dir) {
+        let is_conflict_copy = path
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().ends_with(".conflict"));
+        if is_conflict_copy || recorded.values().any(|recorded| *recorded == path) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let path_text = path.to_string_lossy().to_string();
+        let Some((item_key, matched_by)) = match_note(exporter.zotero, exporter.citations, &path, &content)? else {
+            report.unmatched.push(path_text);
+            continue;
+        };
+
+        let item = load_sqlite_item(exporter.zotero, &item_key)?;
+        let cite_key = exporter.resolve_cite_key(&item, &item_key).unwrap_or_default();
+        let skip = |reason: String| SkippedNote {
+            path: path_text.clone(),
+            reason,
+        };
+        if let Some(existing) = recorded.get(&item_key).filter(|existing| existing.exists()) {
+            report.skipped.push(skip(format!("@{cite_key} is already exported to {}.", existing.display())));
+            continue;
+        }
+        if let Some(first) = claimed.get(&item_key) {
+            report.skipped.push(skip(format!("@{cite_key} was already matched to {first}.")));
+            continue;
+        }
+
+        let note = ImportedNote {
+            path: path_text.clone(),
+            item_key: item_key.clone(),
+            cite_key,
+            matched_by,
+        };
+        if !dry_run {
+            record(exporter, &note, &content)?;
+        }
+        claimed.insert(item_key, path_text);
+        report.imported.push(note);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_reads_scalar_fields() {
+        let content = "---\nCiteKey: \"@smith2020\"\ntags:\n  - one\ndoi: 'https://doi.org/10.1000/XYZ'\nempty:\n---\nBody";
+        let fields = frontmatter(content);
+        assert_eq!(
+            fields,
+            [
+                ("citekey".to_string(), "@smith2020".to_string()),
+                ("doi".to_string(), "https://doi.org/10.1000/XYZ".to_string()),
+            ]
+        );
+        assert_eq!(frontmatter_cite_keys(&fields), ["smith2020"]);
+        assert!(frontmatter("No frontmatter\ncitekey: x").is_empty());
+    }
+
+    #[test]
+    fn dois_are_normalized() {
+        assert_eq!(normalize_doi(" https://doi.org/10.1000/XYZ ").as_deref(), Some("10.1000/xyz"));
+        assert_eq!(normalize_doi("doi:10.5555/abc").as_deref(), Some("10.5555/abc"));
+        assert_eq!(normalize_doi("10.1/a").as_deref(), Some("10.1/a"));
+        assert_eq!(normalize_doi("https://example.org/10.1/a"), None);
+        assert_eq!(normalize_doi(""), None);
+    }
+
+    #[test]
+    fn file_names_may_be_cite_keys() {
+        assert_eq!(file_name_cite_key(Path::new("notes/@smith2020.md")).as_deref(), Some("smith2020"));
+        assert_eq!(file_name_cite_key(Path::new("doe2021.md")).as_deref(), Some("doe2021"));
+        assert_eq!(file_name_cite_key(Path::new("@.md")), None);
+        assert_eq!(file_name_cite_key(Path::new("Reading notes.md")), None);
+    }
+}
@@ -0,0 +1,153 @@
+//! Full-text search over the Markdown files in `markdown_dir`, including
+//! prose written by hand, so the app can answer "which notes mention X".
+//! Hidden files and folders (`.obsidian`, `.trash`, …) are skipped, and so
+//! are the folders listed under "Excluded files" in the vault's Obsidian
+//! settings.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::highlight::{self, FieldMatch};
+
+/// Characters of context kept around the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteMatch {
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    pub snippet: String,
+    /// Zotero item the note was exported from, when it is an exported note.
+    pub item_key: Option<String>,
+    /// Where the query words occur in `snippet`.
+    pub matches: Vec<FieldMatch>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+struct ObsidianAppConfig {
+    user_ignore_filters: Vec<String>,
+}
+
+fn is_regex_filter(filter: &str) -> bool {
+    filter.len() > 1 && filter.starts_with('/') && filter.ends_with('/')
+}
+
+/// Obsidian's excluded paths for the vault containing `dir`, resolved
+/// against the vault root. Regular-expression filters (`/…/`) are not
+/// supported and are skipped.
+fn excluded_paths(dir: &Path) -> Vec<PathBuf> {
+    let Some(vault) = dir.ancestors().find(|ancestor| ancestor.join(".obsidian").is_dir()) else {
+        return Vec::new();
+    };
+    let config = std::fs::read_to_string(vault.join(".obsidian").join("app.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<ObsidianAppConfig>(&raw).ok())
+        .unwrap_or_default();
+    config
+        .user_ignore_filters
+        .iter()
+        .map(|filter| filter.trim())
+        .filter(|filter| !filter.is_empty() && !is_regex_filter(filter))
+        .map(|filter| vault.join(filter.trim_end_matches('/')))
+        .collect()
+}
+
+/// Markdown files under `dir`, in path order. Symlinks are not followed.
+pub fn markdown_files(dir: &Path) -> Vec<PathBuf> {
+    let excluded = excluded_paths(dir);
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if hidden || excluded.iter().any(|excluded| path.starts_with(excluded)) {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(path),
+                Ok(kind) if kind.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("md")) => {
+                    files.push(path)
+                }
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// The line around its first match, trimmed to [`SNIPPET_CONTEXT`]
+/// characters either side.
+fn snippet(line: &str, match_at: usize) -> String {
+    let chars = line.trim().chars().collect::<Vec<_>>();
+    let offset = line.len() - line.trim_start().len();
+    let match_char = line[..match_at].chars().count().saturating_sub(line[..offset].chars().count());
+    let start = match_char.saturating_sub(SNIPPET_CONTEXT);
+    let end = (match_char + SNIPPET_CONTEXT).min(chars.len());
+    format!(
+        "{}{}{}",
+        if start > 0 { "…" } else { "" },
+        chars[start..end].iter().collect::<String>(),
+        if end < chars.len() { "…" } else { "" }
+    )
+}
+
+/// Lines of the notes under `dir` containing every word of `query`
+/// (case-insensitive), at most `limit`. `item_key_for` maps a note path to
+/// the item it was exported from.
+pub fn search(
+    dir: &Path,
+    query: &str,
+    limit: usize,
+    item_key_for: impl Fn(&Path) -> Option<String>,
+) -> Vec<NoteMatch> {
+    let words = query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for file in markdown_files(dir) {
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        let item_key = item_key_for(&file);
+        for (index, line) in content.lines().enumerate() {
+            let lower = line.to_lowercase();
+            if !words.iter().all(|word| lower.contains(word.as_str())) {
+                continue;
+            }
+            // Lowercasing can change byte lengths; fall back to the line
+            // start when the offset no longer lands on a character.
+            let match_at = lower
+                .find(words[0].as_str())
+                .filter(|at| line.is_char_boundary(*at))
+                .unwrap_or(0);
+            let snippet = snippet(line, match_at);
+            matches.push(NoteMatch {
+                path: file.to_string_lossy().to_string(),
+                line: index + 1,
+                matches: highlight::field_matches([("snippet", snippet.as_str())], |text| {
+                    highlight::substring_ranges(text, &words)
+                }),
+                snippet,
+                item_key: item_key.clone(),
+            });
+            if matches.len() >= limit {
+                return matches;
+            }
+        }
+    }
+    matches
+}
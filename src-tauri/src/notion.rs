@@ -0,0 +1,266 @@
+//! Notes rewritten for Notion's Markdown import. Notion ignores YAML
+//! frontmatter and Obsidian syntax, so the frontmatter and Dataview fields
+//! become a property table at the top of the page, image embeds become
+//! standard Markdown images next to the note, callout markers turn into
+//! bold quote titles, and block IDs, anchors and other wikilinks are
+//! reduced to plain text.
+
+use std::path::Path;
+
+use crate::export::NoteImage;
+use crate::render::encode_uri_component;
+
+/// A page for Notion: its Markdown and the images it links to, by path
+/// relative to the note.
+pub struct NotionPage {
+    pub markdown: String,
+    pub images: Vec<(String, Vec<u8>)>,
+}
+
+/// Unquotes a frontmatter value written as `'value'` or `"value"`.
+fn yaml_value(raw: &str) -> String {
+    let raw = raw.trim();
+    if let Some(quoted) = raw.strip_prefix('\'').and_then(|rest| rest.strip_suffix('\'')) {
+        return quoted.replace("''", "'");
+    }
+    if let Some(quoted) = raw.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return quoted.replace("\\\"", "\"");
+    }
+    raw.to_string()
+}
+
+/// `(key, values)` of the frontmatter, and the body after it.
+fn split_frontmatter(markdown: &str) -> (Vec<(String, Vec<String>)>, &str) {
+    let Some(rest) = markdown.strip_prefix("---\n") else {
+        return (Vec::new(), markdown);
+    };
+    let end = if rest.starts_with("---\n") {
+        Some(0)
+    } else {
+        rest.find("\n---\n").map(|end| end + 1)
+    };
+    let Some(end) = end else {
+        return (Vec::new(), markdown);
+    };
+    let mut properties = Vec::<(String, Vec<String>)>::new();
+    for line in rest[..end].lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some((_, values)) = properties.last_mut() {
+                values.push(yaml_value(item));
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            let value = yaml_value(value);
+            let values = if value.is_empty() || value == "[]" { Vec::new() } else { vec![value] };
+            properties.push((key.trim().to_string(), values));
+        }
+    }
+    (properties, &rest[end + "---\n".len()..])
+}
+
+/// `key` and value of a Dataview inline field line (`key:: value`). The key
+/// starts the line and is one word of letters, digits, `_` and `-`, so
+/// prose that happens to contain `:: ` stays in the body.
+fn inline_field(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(":: ")?;
+    let valid_key = key.starts_with(char::is_alphanumeric)
+        && key.chars().all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-'));
+    valid_key.then_some((key, value.trim()))
+}
+
+/// Replaces `[[target]]` and `[[target|alias]]` links: image embeds become
+/// Markdown images from `image_links`, other links their display text.
+fn replace_wikilinks(line: &str, image_links: &[(String, String)]) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("[[") {
+        let Some(end) = rest[start..].find("]]").map(|end| start + end) else {
+            break;
+        };
+        let embed = rest[..start].ends_with('!');
+        output.push_str(if embed { &rest[..start - 1] } else { &rest[..start] });
+        let inner = &rest[start + 2..end];
+        let (target, alias) = inner.split_once('|').unwrap_or((inner, ""));
+        match image_links.iter().find(|(link, _)| link == target) {
+            Some((_, path)) => output.push_str(&format!("![{alias}]({path})")),
+            None if alias.is_empty() => output.push_str(target),
+            None => output.push_str(alias),
+        }
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Removes a trailing Obsidian block ID (`^zot-…`).
+fn strip_block_id(line: &str) -> &str {
+    let is_block_id = |id: &str| {
+        id.strip_prefix('^')
+            .is_some_and(|id| !id.is_empty() && id.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-'))
+    };
+    match line.rsplit_once(' ') {
+        Some((text, id)) if is_block_id(id) => text,
+        _ => line,
+    }
+}
+
+/// `> [!type] Title` as `> **Title**`; a callout without a title keeps
+/// its type as the title.
+fn callout_title(line: &str) -> Option<String> {
+    let content = line.strip_prefix('>')?.trim_start();
+    let callout = content.strip_prefix("[!")?;
+    let (kind, title) = callout.split_once(']')?;
+    let title = title.trim_start_matches(['+', '-']).trim();
+    let title = if title.is_empty() {
+        let mut kind = kind.to_lowercase();
+        if let Some(first) = kind.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        kind
+    } else {
+        title.to_string()
+    };
+    Some(format!("> **{title}**"))
+}
+
+fn table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Rewrites a rendered note for Notion. Its images are linked from a folder
+/// named `image_dir` next to the note.
+pub fn to_notion(markdown: &str, images: &[NoteImage], image_dir: &str) -> NotionPage {
+    let file_names = images
+        .iter()
+        .map(|image| {
+            Path::new(&image.link)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| image.link.clone())
+        })
+        .collect::<Vec<_>>();
+    // Notion resolves image paths as URLs, so spaces and `@` are escaped.
+    let image_links = images
+        .iter()
+        .zip(&file_names)
+        .map(|(image, file_name)| {
+            let link = format!("{}/{}", encode_uri_component(image_dir), encode_uri_component(file_name));
+            (image.link.clone(), link)
+        })
+        .collect::<Vec<_>>();
+
+    let (mut properties, body) = split_frontmatter(markdown);
+    let mut lines = Vec::<String>::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            lines.push(line.to_string());
+            continue;
+        }
+        if line.trim_start().starts_with("<a id=") {
+            continue;
+        }
+        if let Some((key, value)) = inline_field(line) {
+            if !properties.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(key)) {
+                properties.push((key.to_string(), vec![replace_wikilinks(value, &[])]));
+            }
+            continue;
+        }
+        if let Some(title) = callout_title(line) {
+            lines.push(title);
+            continue;
+        }
+        let line = replace_wikilinks(strip_block_id(line), &image_links);
+        // Notion drops images inside quotes, so they are moved out of them.
+        match line.strip_prefix("> ") {
+            Some(image) if image.starts_with("![") && image.ends_with(')') => {
+                // A blank line ends the quote; otherwise the image continues it.
+                if lines.last().is_some_and(|last| !last.is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(image.to_string());
+            }
+            _ => lines.push(line),
+        }
+    }
+
+    let mut page = Vec::<String>::new();
+    let rows = properties
+        .iter()
+        .filter(|(_, values)| values.iter().any(|value| !value.is_empty()))
+        .collect::<Vec<_>>();
+    if !rows.is_empty() {
+        page.push("| Property | Value |".to_string());
+        page.push("| --- | --- |".to_string());
+        for (key, values) in rows {
+            page.push(format!("| {} | {} |", table_cell(key), table_cell(&values.join(", "))));
+        }
+        page.push(String::new());
+    }
+    // Drop the blank lines that separated the frontmatter and fields.
+    let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(lines.len());
+    page.extend(lines.drain(start..));
+
+    let mut markdown = page.join("\n").trim_end().to_string();
+    markdown.push('\n');
+    NotionPage {
+        markdown,
+        images: file_names
+            .into_iter()
+            .zip(images)
+            .map(|(file_name, image)| (format!("{image_dir}/{file_name}"), image.bytes.clone()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_is_split_into_properties_and_body() {
+        let markdown = "---\ntitle: 'It''s here'\ntags:\n  - one\n  - \"two\"\nempty: []\n---\nBody\n";
+        let (properties, body) = split_frontmatter(markdown);
+        assert_eq!(
+            properties,
+            [
+                ("title".to_string(), vec!["It's here".to_string()]),
+                ("tags".to_string(), vec!["one".to_string(), "two".to_string()]),
+                ("empty".to_string(), Vec::new()),
+            ]
+        );
+        assert_eq!(body, "Body\n");
+    }
+
+    #[test]
+    fn notes_without_closed_frontmatter_are_all_body() {
+        assert_eq!(split_frontmatter("Body only").1, "Body only");
+        assert_eq!(split_frontmatter("---\ntitle: x\n").1, "---\ntitle: x\n");
+        let (properties, body) = split_frontmatter("---\n---\nBody");
+        assert!(properties.is_empty());
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn inline_fields_need_a_single_word_key() {
+        assert_eq!(inline_field("rating:: 5 "), Some(("rating", "5")));
+        assert_eq!(inline_field("read-on_2:: today"), Some(("read-on_2", "today")));
+        assert_eq!(inline_field("Note that a:: b"), None);
+        assert_eq!(inline_field("- item:: value"), None);
+        assert_eq!(inline_field(":: value"), None);
+        assert_eq!(inline_field("plain text"), None);
+    }
+
+    #[test]
+    fn block_ids_and_wikilinks_are_rewritten() {
+        assert_eq!(strip_block_id("Quote ^zot-ab12"), "Quote");
+        assert_eq!(strip_block_id("Cost ^"), "Cost ^");
+        let images = [("img.png".to_string(), "images/img.png".to_string())];
+        assert_eq!(
+            replace_wikilinks("See [[Note|alias]], [[Other]] and ![[img.png]]", &images),
+            "See alias, Other and ![](images/img.png)"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Opens exported notes in Obsidian through `obsidian://open?vault=…&file=…`
+//! URIs. The file is given relative to the vault root: the nearest folder
+//! above the note that holds Obsidian's `.obsidian` settings folder, or the
+//! Markdown folder when there is none.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use crate::render::encode_uri_component;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ObsidianSettings {
+    /// Open the note in Obsidian after exporting a single item.
+    pub open_after_export: bool,
+    /// Vault name as Obsidian shows it; empty uses the vault folder's name.
+    pub vault: String,
+}
+
+/// The vault folder holding `path`: the nearest ancestor with a `.obsidian`
+/// folder, else `markdown_dir` when the note is inside it.
+fn vault_root(path: &Path, markdown_dir: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(".obsidian").is_dir())
+        .map(Path::to_path_buf)
+        .or_else(|| {
+            let markdown_dir = markdown_dir.to_path_buf();
+            (!markdown_dir.as_os_str().is_empty() && path.starts_with(&markdown_dir)).then_some(markdown_dir)
+        })
+}
+
+/// `path` relative to `root` with `/` separators, as Obsidian expects on
+/// every platform.
+fn vault_relative(path: &Path, root: &Path) -> Option<String> {
+    let parts = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// The `obsidian://open` URI for the note at `path`.
+pub fn note_uri(settings: &ObsidianSettings, markdown_dir: &str, path: &Path) -> Result<String, String> {
+    let root = vault_root(path, Path::new(markdown_dir.trim())).ok_or_else(|| {
+        format!(
+            "{} is not in an Obsidian vault: no folder above it has a .obsidian folder, and it is outside the Markdown folder.",
+            path.display()
+        )
+    })?;
+    let file = vault_relative(path, &root)
+        .ok_or_else(|| format!("failed to find {} inside vault {}", path.display(), root.display()))?;
+    let vault = match settings.vault.trim() {
+        "" => root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Set the Obsidian vault name in Settings; {} has no folder name.", root.display()))?,
+        vault => vault.to_string(),
+    };
+    Ok(format!(
+        "obsidian://open?vault={}&file={}",
+        encode_uri_component(&vault),
+        encode_uri_component(&file)
+    ))
+}
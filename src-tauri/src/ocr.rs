@@ -0,0 +1,91 @@
+//! Optional OCR of area annotations with the Tesseract command-line tool, so
+//! text in captured figures and tables ends up in the note and becomes
+//! searchable. Tesseract runs as a separate process; it isn't linked into
+//! the app, so OCR only needs it to be installed when it is turned on.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct OcrSettings {
+    pub enabled: bool,
+    /// Tesseract language codes joined by `+`, e.g. `eng+deu`.
+    pub languages: String,
+    /// Path to the `tesseract` executable; empty looks it up on `PATH`.
+    pub tesseract_path: String,
+}
+
+impl Default for OcrSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            languages: "eng".to_string(),
+            tesseract_path: String::new(),
+        }
+    }
+}
+
+/// Language codes are letters, digits and `_` (e.g. `chi_sim`), joined by `+`.
+pub fn valid_languages(languages: &str) -> bool {
+    !languages.trim().is_empty()
+        && languages
+            .trim()
+            .split('+')
+            .all(|code| !code.is_empty() && code.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_'))
+}
+
+/// Drops Tesseract's trailing form feed, trailing spaces and runs of blank
+/// lines.
+fn clean_output(raw: &str) -> String {
+    let mut lines = Vec::<&str>::new();
+    for line in raw.split(['\n', '\x0c']).map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Recognizes the text in an image. Returns `None` when nothing was found.
+pub fn recognize(bytes: &[u8], settings: &OcrSettings) -> Result<Option<String>, String> {
+    let program = match settings.tesseract_path.trim() {
+        "" => "tesseract",
+        path => path,
+    };
+    let languages = settings.languages.trim();
+    if !valid_languages(languages) {
+        return Err(format!("invalid OCR languages {languages:?}; use codes like eng or eng+deu."));
+    }
+
+    let mut child = Command::new(program)
+        .args(["stdin", "stdout", "-l", languages])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to start {program}; install Tesseract or set its path in Settings: {err}"))?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| "failed to open Tesseract input.".to_string())?
+        .write_all(bytes)
+        .map_err(|err| format!("failed to send image to Tesseract: {err}"))?;
+
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("failed to run Tesseract: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Tesseract failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let text = clean_output(&String::from_utf8_lossy(&output.stdout));
+    Ok((!text.is_empty()).then_some(text))
+}
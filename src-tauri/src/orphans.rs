@@ -0,0 +1,135 @@
+//! Exported notes whose Zotero item was deleted or moved to the trash, and
+//! the archive/tag actions for cleaning them up.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::state_db::ItemExportRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrphanReason {
+    /// The item is gone from zotero.sqlite.
+    Deleted,
+    /// The item is in the trash and may still be restored.
+    Trashed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedNote {
+    pub item_key: String,
+    pub cite_key: String,
+    pub markdown_path: String,
+    pub reason: OrphanReason,
+    /// Whether the note file still exists in the vault.
+    pub note_exists: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrphanAction {
+    /// Move the note into the archive folder and forget its export record.
+    Archive,
+    /// Add the orphan tag to the note's frontmatter.
+    Tag,
+}
+
+fn orphan_reason(zotero: &Connection, item_key: &str) -> Result<Option<OrphanReason>, String> {
+    let trashed = zotero
+        .query_row(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM deletedItems WHERE itemID = items.itemID)
+            FROM items
+            WHERE key = ?1
+            "#,
+            params![item_key],
+            |row| row.get::<_, bool>(0),
+        )
+        .optional()
+        .map_err(|err| format!("failed to look up Zotero item {item_key}: {err}"))?;
+
+    Ok(match trashed {
+        None => Some(OrphanReason::Deleted),
+        Some(true) => Some(OrphanReason::Trashed),
+        Some(false) => None,
+    })
+}
+
+/// Export records whose item no longer exists in Zotero or is trashed.
+pub fn find_orphans(zotero: &Connection, records: &[ItemExportRecord]) -> Result<Vec<OrphanedNote>, String> {
+    let mut orphans = Vec::new();
+    for record in records {
+        if let Some(reason) = orphan_reason(zotero, &record.item_key)? {
+            orphans.push(OrphanedNote {
+                item_key: record.item_key.clone(),
+                cite_key: record.cite_key.clone(),
+                markdown_path: record.markdown_path.clone(),
+                reason,
+                note_exists: Path::new(&record.markdown_path).exists(),
+            });
+        }
+    }
+    Ok(orphans)
+}
+
+/// Moves a note into `archive_dir`, adding a numeric suffix rather than
+/// replacing a note archived earlier under the same name.
+pub fn archive_note(note: &Path, archive_dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(archive_dir)
+        .map_err(|err| format!("failed to create archive folder {}: {err}", archive_dir.display()))?;
+
+    let stem = note
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = note
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut destination = archive_dir.join(format!("{stem}{extension}"));
+    let mut suffix = 1;
+    while destination.exists() {
+        suffix += 1;
+        destination = archive_dir.join(format!("{stem} {suffix}{extension}"));
+    }
+
+    std::fs::rename(note, &destination).map_err(|err| {
+        format!(
+            "failed to move {} to {}: {err}",
+            note.display(),
+            destination.display()
+        )
+    })?;
+    Ok(destination)
+}
+
+/// Adds `tag` to the `tags:` list of the note's frontmatter, creating the
+/// frontmatter when the note has none. Returns `None` if already tagged.
+pub fn add_frontmatter_tag(markdown: &str, tag: &str) -> Option<String> {
+    let tag_line = format!("  - {tag}");
+    let mut lines = markdown.lines().map(str::to_string).collect::<Vec<_>>();
+
+    let closing = (lines.first().map(String::as_str) == Some("---"))
+        .then(|| lines.iter().skip(1).position(|line| line == "---").map(|index| index + 1))
+        .flatten();
+    let Some(closing) = closing else {
+        return Some(format!("---\ntags:\n{tag_line}\n---\n\n{markdown}"));
+    };
+
+    let frontmatter = &lines[1..closing];
+    if frontmatter.iter().any(|line| line.trim_end() == tag_line) {
+        return None;
+    }
+    match frontmatter.iter().position(|line| line.trim_end() == "tags:") {
+        Some(index) => lines.insert(index + 2, tag_line),
+        None => lines.insert(closing, format!("tags:\n{tag_line}")),
+    }
+
+    let mut tagged = lines.join("\n");
+    if markdown.ends_with('\n') {
+        tagged.push('\n');
+    }
+    Some(tagged)
+}
@@ -0,0 +1,114 @@
+//! Renders annotation regions straight from the attachment PDF, for image
+//! annotations Zotero hasn't cached yet. Pdfium is loaded at runtime: first
+//! from the folder containing the executable, then from the system library
+//! path, so the app still starts (and only this fallback fails) without it.
+//!
+//! Positions are the ones Zotero's reader stores: a page index and rects in
+//! PDF points, measured from the bottom-left of the page.
+
+use image::ImageFormat;
+use pdfium_render::prelude::*;
+use serde::Deserialize;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Resolution used when no other is requested.
+pub const DEFAULT_DPI: u32 = 150;
+/// Bounds for a requested resolution; 600 DPI already makes a full page
+/// about 5000 pixels wide.
+pub const MIN_DPI: u32 = 36;
+pub const MAX_DPI: u32 = 600;
+const POINTS_PER_INCH: f32 = 72.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationPosition {
+    pub page_index: u16,
+    #[serde(default)]
+    pub rects: Vec<[f32; 4]>,
+}
+
+impl AnnotationPosition {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        serde_json::from_str(raw).map_err(|err| format!("failed to parse annotation position: {err}"))
+    }
+
+    /// Smallest rect `[x1, y1, x2, y2]` enclosing all rects.
+    fn bounds(&self) -> Option<[f32; 4]> {
+        self.rects.iter().copied().reduce(|acc, rect| {
+            [
+                acc[0].min(rect[0]),
+                acc[1].min(rect[1]),
+                acc[2].max(rect[2]),
+                acc[3].max(rect[3]),
+            ]
+        })
+    }
+}
+
+fn load_pdfium() -> Result<Pdfium, String> {
+    let beside_executable = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Pdfium::pdfium_platform_library_name_at_path))
+        .filter(|path| path.exists());
+    let bindings = match beside_executable {
+        Some(path) => Pdfium::bind_to_library(&path),
+        None => Pdfium::bind_to_system_library(),
+    }
+    .map_err(|err| {
+        format!(
+            "failed to load the pdfium library ({}): install it next to the app or on the library path to render annotations Zotero hasn't cached: {err}",
+            Pdfium::pdfium_platform_library_name().to_string_lossy()
+        )
+    })?;
+    Ok(Pdfium::new(bindings))
+}
+
+/// Renders the region covered by `position` in `pdf_path` at `dpi` (clamped
+/// to [`MIN_DPI`]..=[`MAX_DPI`]) and returns it as PNG.
+pub fn render_region(pdf_path: &Path, position: &AnnotationPosition, dpi: u32) -> Result<Vec<u8>, String> {
+    let [x1, y1, x2, y2] = position
+        .bounds()
+        .ok_or_else(|| "annotation position has no rects to render.".to_string())?;
+
+    let pdfium = load_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(pdf_path, None)
+        .map_err(|err| format!("failed to open PDF {}: {err}", pdf_path.display()))?;
+    let page = document.pages().get(position.page_index).map_err(|err| {
+        format!(
+            "failed to load page {} of {}: {err}",
+            position.page_index + 1,
+            pdf_path.display()
+        )
+    })?;
+
+    // Pdfium renders the crop box, so offsets are taken from its corner.
+    let boundaries = page.boundaries();
+    let visible = boundaries
+        .crop()
+        .or_else(|_| boundaries.media())
+        .map_err(|err| format!("failed to read page bounds of {}: {err}", pdf_path.display()))?
+        .bounds;
+
+    let scale = dpi.clamp(MIN_DPI, MAX_DPI) as f32 / POINTS_PER_INCH;
+    let rendered = page
+        .render_with_config(&PdfRenderConfig::new().scale_page_by_factor(scale))
+        .map_err(|err| format!("failed to render page {}: {err}", position.page_index + 1))?
+        .as_image();
+
+    let left = ((x1 - visible.left().value) * scale).floor().max(0.0) as u32;
+    let top = ((visible.top().value - y2) * scale).floor().max(0.0) as u32;
+    let right = (((x2 - visible.left().value) * scale).ceil().max(0.0) as u32).min(rendered.width());
+    let bottom = (((visible.top().value - y1) * scale).ceil().max(0.0) as u32).min(rendered.height());
+    if right <= left || bottom <= top {
+        return Err("annotation region lies outside the rendered page.".to_string());
+    }
+
+    let region = rendered.crop_imm(left, top, right - left, bottom - top);
+    let mut bytes = Vec::new();
+    region
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|err| format!("failed to encode rendered annotation: {err}"))?;
+    Ok(bytes)
+}
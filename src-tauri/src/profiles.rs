@@ -0,0 +1,97 @@
+//! Named settings profiles, e.g. separate vaults for work and personal
+//! research. The default profile keeps `settings.json`; every other profile
+//! has its own `profiles/<name>.json` and its own state database, so export
+//! records of one vault never mark notes in another as up to date.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const ACTIVE_PROFILE_FILE: &str = "active-profile";
+const PROFILES_DIR: &str = "profiles";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileList {
+    pub active: String,
+    /// Profile names, the default profile first.
+    pub profiles: Vec<String>,
+}
+
+/// Trims `name` and checks it can be used as a file name.
+pub fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty.".to_string());
+    }
+    if !name
+        .chars()
+        .all(|ch| ch.is_alphanumeric() || matches!(ch, ' ' | '-' | '_'))
+    {
+        return Err(format!(
+            "Profile name {name:?} may only contain letters, digits, spaces, '-' and '_'."
+        ));
+    }
+    Ok(name.to_string())
+}
+
+pub fn settings_file(config_dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        config_dir.join("settings.json")
+    } else {
+        config_dir.join(PROFILES_DIR).join(format!("{name}.json"))
+    }
+}
+
+pub fn state_db_file_name(name: &str) -> String {
+    if name == DEFAULT_PROFILE {
+        "zotnotes-state.sqlite".to_string()
+    } else {
+        format!("zotnotes-state-{name}.sqlite")
+    }
+}
+
+/// The selected profile, or the default one when the selection is missing
+/// or points at a profile that was removed.
+pub fn active_profile(config_dir: &Path) -> String {
+    std::fs::read_to_string(config_dir.join(ACTIVE_PROFILE_FILE))
+        .ok()
+        .and_then(|name| validate_name(&name).ok())
+        .filter(|name| settings_file(config_dir, name).exists())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub fn set_active(config_dir: &Path, name: &str) -> Result<(), String> {
+    if name != DEFAULT_PROFILE && !settings_file(config_dir, name).exists() {
+        return Err(format!("Profile {name:?} does not exist."));
+    }
+    let path = config_dir.join(ACTIVE_PROFILE_FILE);
+    std::fs::write(&path, name)
+        .map_err(|err| format!("failed to write active profile {}: {err}", path.display()))
+}
+
+pub fn list(config_dir: &Path) -> Result<ProfileList, String> {
+    let mut profiles = Vec::<String>::new();
+    let dir = config_dir.join(PROFILES_DIR);
+    if dir.exists() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|err| format!("failed to read profiles folder {}: {err}", dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    profiles.push(stem.to_string());
+                }
+            }
+        }
+    }
+    profiles.sort_by_key(|name| name.to_lowercase());
+    profiles.retain(|name| name != DEFAULT_PROFILE);
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+
+    Ok(ProfileList {
+        active: active_profile(config_dir),
+        profiles,
+    })
+}
@@ -0,0 +1,48 @@
+//! Generic progress channel for long-running commands. The frontend passes
+//! an operation ID with the command and matches it against the events.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::report::now_seconds;
+
+pub const OPERATION_PROGRESS_EVENT: &str = "operation-progress";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OperationProgress<'a> {
+    operation_id: &'a str,
+    current: u64,
+    /// Zero when the total is not known up front (e.g. a download without
+    /// `Content-Length`).
+    total: u64,
+    message: &'a str,
+}
+
+pub struct Progress {
+    app: AppHandle,
+    operation_id: String,
+}
+
+impl Progress {
+    /// Uses the caller's operation ID, or one derived from `kind` and the
+    /// current time when the caller does not track progress itself.
+    pub fn new(app: &AppHandle, kind: &str, operation_id: Option<String>) -> Self {
+        Self {
+            app: app.clone(),
+            operation_id: operation_id.unwrap_or_else(|| format!("{kind}-{}", now_seconds())),
+        }
+    }
+
+    pub fn report(&self, current: u64, total: u64, message: &str) {
+        let _ = self.app.emit(
+            OPERATION_PROGRESS_EVENT,
+            OperationProgress {
+                operation_id: &self.operation_id,
+                current,
+                total,
+                message,
+            },
+        );
+    }
+}
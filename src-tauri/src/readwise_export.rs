@@ -0,0 +1,259 @@
+//! Highlights in Readwise's import formats: the CSV accepted by Readwise's
+//! CSV upload, or the JSON body of its highlight API
+//! (`POST /api/v2/highlights/`). Every text annotation becomes a highlight
+//! of its item, with the comment as the highlight's note. Area and ink
+//! annotations have no text for Readwise and are left out.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::csv_export::{escape_field, CsvItem};
+use crate::render::comment_markdown;
+use crate::SqliteAnnotation;
+
+/// Readwise rejects longer highlights.
+const MAX_TEXT_CHARS: usize = 8191;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadwiseFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Highlight {
+    text: String,
+    title: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    author: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    source_url: String,
+    source_type: &'static str,
+    category: &'static str,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    note: String,
+    location: usize,
+    location_type: &'static str,
+    highlighted_at: String,
+    highlight_url: String,
+}
+
+#[derive(Serialize)]
+struct HighlightBatch {
+    highlights: Vec<Highlight>,
+}
+
+fn data_field<'a>(item: &'a Value, key: &str) -> &'a str {
+    item.pointer(&format!("/data/{key}"))
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+/// Authors as `First Last`, joined with commas; other creators only when
+/// the item has no authors.
+fn authors(item: &Value) -> String {
+    let creators = item
+        .pointer("/data/creators")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let names = |only_authors: bool| {
+        creators
+            .iter()
+            .filter(|creator| !only_authors || creator.get("creatorType").and_then(Value::as_str) == Some("author"))
+            .filter_map(|creator| {
+                let text = |key: &str| creator.get(key).and_then(Value::as_str).unwrap_or_default().trim();
+                let name = match (text("firstName"), text("lastName")) {
+                    ("", "") => text("name").to_string(),
+                    (first, "") => first.to_string(),
+                    ("", last) => last.to_string(),
+                    (first, last) => format!("{first} {last}"),
+                };
+                (!name.is_empty()).then_some(name)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut authors = names(true);
+    if authors.is_empty() {
+        authors = names(false);
+    }
+    authors.join(", ")
+}
+
+/// Link to the item's source: its URL, else its DOI.
+fn source_url(item: &Value) -> String {
+    match (data_field(item, "url"), data_field(item, "DOI")) {
+        ("", "") => String::new(),
+        ("", doi) => format!("https://doi.org/{doi}"),
+        (url, _) => url.to_string(),
+    }
+}
+
+fn category(item: &Value) -> &'static str {
+    match data_field(item, "itemType") {
+        "book" | "bookSection" => "books",
+        "podcast" | "audioRecording" | "radioBroadcast" => "podcasts",
+        _ => "articles",
+    }
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` from Zotero's UTC `YYYY-MM-DD HH:MM:SS`.
+fn iso_timestamp(date: &str) -> String {
+    match date.trim().split_once(' ') {
+        Some((day, time)) => format!("{day}T{time}Z"),
+        None => date.trim().to_string(),
+    }
+}
+
+fn item_highlights(row: &CsvItem, annotations: &[SqliteAnnotation]) -> Vec<Highlight> {
+    let item = &row.item;
+    let title = match data_field(item, "title") {
+        "" => row.cite_key.clone(),
+        title => title.to_string(),
+    };
+    let author = authors(item);
+    let source_url = source_url(item);
+    let category = category(item);
+
+    annotations
+        .iter()
+        .filter(|annotation| !annotation.is_image_selection && !annotation.text.is_empty())
+        .map(|annotation| {
+            // Numeric page labels are pages; anything else keeps reading order.
+            let page = annotation.page_label.parse::<usize>().ok();
+            let mut highlight_url = format!("zotero://open-pdf/library/items/{}?", annotation.attachment_key);
+            if let Some(page) = page {
+                highlight_url.push_str(&format!("page={page}&"));
+            }
+            highlight_url.push_str(&format!("annotation={}", annotation.key));
+            Highlight {
+                text: annotation.text.chars().take(MAX_TEXT_CHARS).collect(),
+                title: title.clone(),
+                author: author.clone(),
+                source_url: source_url.clone(),
+                source_type: "zotnotes",
+                category,
+                note: comment_markdown(&annotation.comment).trim().to_string(),
+                location: page.unwrap_or(annotation.sort_index + 1),
+                location_type: if page.is_some() { "page" } else { "order" },
+                highlighted_at: iso_timestamp(&annotation.date_added),
+                highlight_url,
+            }
+        })
+        .collect()
+}
+
+fn to_csv(highlights: &[Highlight]) -> String {
+    let mut csv = String::from("Highlight,Title,Author,URL,Note,Location,Date\n");
+    for highlight in highlights {
+        let date = highlight.highlighted_at.trim_end_matches('Z').replace('T', " ");
+        let row = [
+            highlight.text.as_str(),
+            &highlight.title,
+            &highlight.author,
+            &highlight.source_url,
+            &highlight.note,
+            &highlight.location.to_string(),
+            &date,
+        ]
+        .map(escape_field)
+        .join(",");
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Renders the highlights of every item in `format`. Returns the file
+/// contents and the number of highlights.
+pub fn highlights_to_readwise(
+    items: &[(CsvItem, Vec<SqliteAnnotation>)],
+    format: ReadwiseFormat,
+) -> Result<(String, usize), String> {
+    let highlights = items
+        .iter()
+        .flat_map(|(row, annotations)| item_highlights(row, annotations))
+        .collect::<Vec<_>>();
+    let count = highlights.len();
+    let contents = match format {
+        ReadwiseFormat::Csv => to_csv(&highlights),
+        ReadwiseFormat::Json => serde_json::to_string_pretty(&HighlightBatch { highlights })
+            .map_err(|err| format!("failed to serialize Readwise highlights: {err}"))?,
+    };
+    Ok((contents, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn annotation(text: &str, page_label: &str, is_image_selection: bool) -> SqliteAnnotation {
+        SqliteAnnotation {
+            key: "ANNOT001".to_string(),
+            attachment_key: "ATTACH01".to_string(),
+            color_hex: "#ffd400".to_string(),
+            text: text.to_string(),
+            comment: String::new(),
+            page_label: page_label.to_string(),
+            sort_index: 2,
+            is_image_selection,
+            attachment_title: "PDF".to_string(),
+            date_added: "2024-05-01 09:30:00".to_string(),
+            date_modified: "2024-05-01 09:30:00".to_string(),
+        }
+    }
+
+    fn row() -> CsvItem {
+        CsvItem {
+            item: json!({"data": {
+                "itemType": "book",
+                "title": "Information Theory",
+                "DOI": "10.1000/xyz",
+                "creators": [
+                    {"creatorType": "editor", "firstName": "Ed", "lastName": "Itor"},
+                    {"creatorType": "author", "firstName": "Claude", "lastName": "Shannon"}
+                ]
+            }}),
+            cite_key: "shannon1948".to_string(),
+        }
+    }
+
+    #[test]
+    fn csv_rows_use_page_locations_and_escape_fields() {
+        let items = [(
+            row(),
+            vec![
+                annotation("=entropy, defined", "12", false),
+                annotation("", "13", false),
+                annotation("area", "14", true),
+            ],
+        )];
+        let (csv, count) = highlights_to_readwise(&items, ReadwiseFormat::Csv).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            csv,
+            "Highlight,Title,Author,URL,Note,Location,Date\n\
+             \"'=entropy, defined\",Information Theory,Claude Shannon,https://doi.org/10.1000/xyz,,12,2024-05-01 09:30:00\n"
+        );
+    }
+
+    #[test]
+    fn json_highlights_fall_back_to_reading_order() {
+        let items = [(row(), vec![annotation("entropy", "iv", false)])];
+        let (json, _) = highlights_to_readwise(&items, ReadwiseFormat::Json).unwrap();
+        let value = serde_json::from_str::<Value>(&json).unwrap();
+        let highlight = &value["highlights"][0];
+        assert_eq!(highlight["location"], 3);
+        assert_eq!(highlight["location_type"], "order");
+        assert_eq!(highlight["category"], "books");
+        assert_eq!(highlight["highlighted_at"], "2024-05-01T09:30:00Z");
+        assert_eq!(
+            highlight["highlight_url"],
+            "zotero://open-pdf/library/items/ATTACH01?annotation=ANNOT001"
+        );
+    }
+}
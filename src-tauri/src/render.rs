@@ -0,0 +1,1444 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::dates::{self, DateFormat};
+use crate::extract_year;
+use crate::html::html_to_markdown;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct TemplateSettings {
+    pub property_order: Vec<String>,
+    pub color_heading_overrides: BTreeMap<String, String>,
+    /// Append `^zot-<annotationKey>` block anchors to exported annotations.
+    pub block_ids: bool,
+    /// Adjustments applied on top of `property_order` when writing frontmatter.
+    pub frontmatter_fields: Vec<FrontmatterField>,
+    /// Where item metadata is written: YAML frontmatter, Dataview inline fields, or both.
+    pub metadata_output: MetadataOutput,
+    pub tag_mapping: TagMapping,
+    /// Backslash-escape markdown syntax in highlighted text so PDF
+    /// characters like `*` or `_` are not interpreted as formatting.
+    pub escape_markdown: bool,
+    /// Keep `$...$`, `$$...$$`, `\(...\)` and `\[...\]` spans verbatim
+    /// (normalized to dollar delimiters) instead of escaping them.
+    pub preserve_math: bool,
+    /// Markdown heading level (1-6) used for annotation sections.
+    pub section_heading_level: u8,
+    /// Color names in the order their sections appear; empty keeps the default order.
+    pub color_order: Vec<String>,
+    /// When set alongside `color_order`, colors not listed there are merged
+    /// into one trailing section with this heading.
+    pub other_colors_heading: String,
+    pub grouping: AnnotationGrouping,
+    /// Emit a linked table of contents below the frontmatter.
+    pub table_of_contents: bool,
+    /// Link each highlight to its position in the PDF (`zotero://open-pdf`)
+    /// instead of selecting the annotation in the library.
+    pub pdf_links: bool,
+    /// How `dateAdded`, `dateModified` and `accessDate` values and
+    /// chronological section headings are written.
+    pub date_format: DateFormat,
+    /// Where text recognized in area annotations is written.
+    pub image_text: ImageTextStyle,
+}
+
+/// How annotations are split into sections in the note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnotationGrouping {
+    #[default]
+    Color,
+    Page,
+    Chronological,
+    Attachment,
+}
+
+/// Placement of OCR text for image annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageTextStyle {
+    /// As the image link's display text, shortened to one line.
+    #[default]
+    AltText,
+    /// As a quoted block below the image link.
+    Quote,
+}
+
+/// Controls how Zotero item tags are copied into the note's `tags` list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct TagMapping {
+    pub enabled: bool,
+    /// Prepended to every exported tag, e.g. `zotero/`.
+    pub prefix: String,
+    /// Zotero tags (case-insensitive) that are never exported, e.g. `_todo`.
+    pub deny_list: Vec<String>,
+    /// Lowercase tags and collapse whitespace/punctuation into dashes.
+    pub slugify: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum MetadataOutput {
+    #[default]
+    Frontmatter,
+    Inline,
+    Both,
+}
+
+impl MetadataOutput {
+    fn frontmatter(self) -> bool {
+        matches!(self, Self::Frontmatter | Self::Both)
+    }
+
+    fn inline(self) -> bool {
+        matches!(self, Self::Inline | Self::Both)
+    }
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        Self {
+            property_order: vec![
+                "title".to_string(),
+                "author".to_string(),
+                "year".to_string(),
+                "company".to_string(),
+            ],
+            color_heading_overrides: BTreeMap::new(),
+            block_ids: false,
+            frontmatter_fields: Vec::new(),
+            metadata_output: MetadataOutput::default(),
+            tag_mapping: TagMapping::default(),
+            escape_markdown: false,
+            preserve_math: false,
+            section_heading_level: 3,
+            color_order: Vec::new(),
+            other_colors_heading: String::new(),
+            grouping: AnnotationGrouping::default(),
+            table_of_contents: false,
+            pdf_links: false,
+            date_format: DateFormat::default(),
+            image_text: ImageTextStyle::default(),
+        }
+    }
+}
+
+/// One frontmatter rule. A rule whose `field` matches a property from
+/// `property_order` modifies it in place; any other rule appends a new key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct FrontmatterField {
+    /// Zotero field name (`title`, `publicationTitle`, ...) or one of the
+    /// derived values `author`, `year`, `company`, `citekey`.
+    pub field: String,
+    /// Frontmatter key to write; defaults to the built-in label or field name.
+    pub key: String,
+    /// Drop the property from the frontmatter entirely.
+    pub omit: bool,
+    /// Fixed value written instead of the item's field.
+    pub constant: Option<String>,
+    /// Write the value as a YAML list (one entry per creator for `author`).
+    pub list: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderAnnotation {
+    pub key: String,
+    pub text: String,
+    pub comment: String,
+    pub page_label: String,
+    #[serde(default)]
+    pub image_markdown_path: Option<String>,
+    #[serde(default)]
+    pub missing_image_message: Option<String>,
+    /// Text recognized in the image by OCR.
+    #[serde(default)]
+    pub image_text: Option<String>,
+    #[serde(default)]
+    pub attachment_key: String,
+    #[serde(default)]
+    pub attachment_title: String,
+    #[serde(default)]
+    pub sort_index: usize,
+    /// Zotero `dateAdded` in UTC: `YYYY-MM-DD hh:mm:ss` from zotero.sqlite, ISO 8601 from the API.
+    #[serde(default)]
+    pub date_added: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorGroup {
+    pub color_name: String,
+    pub annotations: Vec<RenderAnnotation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteInput {
+    /// Zotero item payload as returned by `zotero_sqlite_get_item` or the API.
+    pub item: Value,
+    pub cite_key: String,
+    pub grouped_annotations: Vec<ColorGroup>,
+    /// Child note bodies as Zotero stores them (HTML); converted on render.
+    #[serde(default)]
+    pub child_notes: Vec<String>,
+}
+
+const COLOR_ORDER: [&str; 8] = [
+    "Yellow", "Green", "Blue", "Pink", "Orange", "Purple", "Gray", "Unknown",
+];
+
+fn color_rank(color_name: &str) -> usize {
+    let unknown_index = COLOR_ORDER.len() - 1;
+    if let Some(index) = COLOR_ORDER.iter().position(|name| *name == color_name) {
+        return index;
+    }
+    if color_name.starts_with("Unknown") {
+        return unknown_index;
+    }
+    unknown_index + 1
+}
+
+fn default_label(field: &str) -> String {
+    match field {
+        "title" => "Title".to_string(),
+        "author" => "Author".to_string(),
+        "year" => "Year".to_string(),
+        "company" => "Company".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn item_field(item: &Value, key: &str) -> String {
+    item.get("data")
+        .and_then(|data| data.get(key))
+        .and_then(Value::as_str)
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn creator_names(item: &Value) -> Vec<String> {
+    let creators = item
+        .get("data")
+        .and_then(|data| data.get("creators"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    creators
+        .iter()
+        .filter_map(|creator| {
+            let text = |key: &str| {
+                creator
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            let (last, first) = (text("lastName"), text("firstName"));
+            let name = if !last.is_empty() || !first.is_empty() {
+                [last, first]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            } else {
+                text("name")
+            };
+            (!name.is_empty()).then_some(name)
+        })
+        .collect()
+}
+
+fn resolve_company(item: &Value) -> String {
+    // Zotero item types vary; publisher/institution/company cover the common publication records.
+    ["publisher", "institution", "company", "university"]
+        .iter()
+        .map(|key| item_field(item, key))
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+/// Resolves a frontmatter source field to its values. Derived fields are
+/// computed; anything else is read from the item's `data` map.
+pub fn field_values(input: &NoteInput, field: &str) -> Vec<String> {
+    match field {
+        "author" => creator_names(&input.item),
+        "year" => vec![extract_year(&item_field(&input.item, "date"))],
+        "company" => vec![resolve_company(&input.item)],
+        "citekey" => vec![input.cite_key.clone()],
+        other => vec![item_field(&input.item, other)],
+    }
+}
+
+/// Zotero timestamp fields that `date_format` applies to.
+const TIMESTAMP_FIELDS: [&str; 3] = ["dateAdded", "dateModified", "accessDate"];
+/// Longest OCR text used as an image link's display text.
+const MAX_ALIAS_CHARS: usize = 200;
+
+/// [`field_values`] with timestamps formatted for the note.
+fn note_field_values(input: &NoteInput, field: &str, settings: &TemplateSettings) -> Vec<String> {
+    let values = field_values(input, field);
+    if !TIMESTAMP_FIELDS.contains(&field) {
+        return values;
+    }
+    values
+        .iter()
+        .map(|value| dates::format_zotero_date(value, &settings.date_format))
+        .collect()
+}
+
+fn slugify_tag(tag: &str) -> String {
+    let mut slug = String::new();
+    for ch in tag.trim().to_lowercase().chars() {
+        if ch.is_alphanumeric() || ch == '/' || ch == '_' {
+            slug.push(ch);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Translates the item's Zotero tags into note tags according to `mapping`.
+fn mapped_tags(item: &Value, mapping: &TagMapping) -> Vec<String> {
+    if !mapping.enabled {
+        return Vec::new();
+    }
+
+    let raw_tags = item
+        .get("data")
+        .and_then(|data| data.get("tags"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut tags = Vec::<String>::new();
+    for raw in raw_tags {
+        let Some(name) = raw.get("tag").and_then(Value::as_str).map(str::trim) else {
+            continue;
+        };
+        if name.is_empty()
+            || mapping
+                .deny_list
+                .iter()
+                .any(|denied| denied.trim().eq_ignore_ascii_case(name))
+        {
+            continue;
+        }
+
+        let body = if mapping.slugify {
+            slugify_tag(name)
+        } else {
+            // Tags cannot contain spaces in most vault tools.
+            name.split_whitespace().collect::<Vec<_>>().join("-")
+        };
+        if body.is_empty() {
+            continue;
+        }
+
+        let tag = format!("{}{body}", mapping.prefix.trim());
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn yaml_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+struct FrontmatterEntry {
+    source: String,
+    key: String,
+    values: Vec<String>,
+    list: bool,
+}
+
+fn normalized_property_order(order: &[String]) -> Vec<String> {
+    let defaults = TemplateSettings::default().property_order;
+    let mut normalized = Vec::<String>::new();
+    for key in order.iter().chain(defaults.iter()) {
+        if defaults.contains(key) && !normalized.contains(key) {
+            normalized.push(key.clone());
+        }
+    }
+    normalized
+}
+
+fn frontmatter_entries(input: &NoteInput, settings: &TemplateSettings) -> Vec<FrontmatterEntry> {
+    let mut entries = normalized_property_order(&settings.property_order)
+        .into_iter()
+        .map(|field| FrontmatterEntry {
+            key: default_label(&field),
+            values: note_field_values(input, &field, settings),
+            list: false,
+            source: field,
+        })
+        .collect::<Vec<_>>();
+
+    for rule in &settings.frontmatter_fields {
+        let field = rule.field.trim();
+        let existing = entries
+            .iter()
+            .position(|entry| !field.is_empty() && entry.source == field);
+
+        if rule.omit {
+            if let Some(index) = existing {
+                entries.remove(index);
+            }
+            continue;
+        }
+
+        let values = match &rule.constant {
+            Some(constant) => vec![constant.clone()],
+            None if field.is_empty() => continue,
+            None => note_field_values(input, field, settings),
+        };
+        let key = match rule.key.trim() {
+            "" if field.is_empty() => continue,
+            "" => default_label(field),
+            key => key.to_string(),
+        };
+        let entry = FrontmatterEntry {
+            source: field.to_string(),
+            key,
+            values,
+            list: rule.list,
+        };
+
+        match existing {
+            Some(index) => entries[index] = entry,
+            None => entries.push(entry),
+        }
+    }
+
+    entries
+}
+
+/// Renders an entry as a Dataview inline field. Creators become wikilinks so
+/// author pages can be queried; empty values are skipped.
+fn inline_field_line(entry: &FrontmatterEntry) -> Option<String> {
+    let values = entry
+        .values
+        .iter()
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            if entry.source == "author" {
+                format!("[[{value}]]")
+            } else {
+                value.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return None;
+    }
+
+    let separator = if entry.list || entry.source == "author" { ", " } else { "; " };
+    Some(format!("{}:: {}", entry.key, values.join(separator)))
+}
+
+fn frontmatter_lines(entry: &FrontmatterEntry) -> Vec<String> {
+    if !entry.list {
+        return vec![format!("{}: {}", entry.key, yaml_quote(&entry.values.join("; ")))];
+    }
+
+    let values = entry
+        .values
+        .iter()
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return vec![format!("{}: []", entry.key)];
+    }
+
+    let mut lines = vec![format!("{}:", entry.key)];
+    lines.extend(values.iter().map(|value| format!("  - {}", yaml_quote(value))));
+    lines
+}
+
+fn block_id(annotation_key: &str) -> String {
+    // Obsidian block IDs only allow letters, digits and dashes.
+    let sanitized = annotation_key
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric() || *ch == '-')
+        .collect::<String>();
+    format!("^zot-{sanitized}")
+}
+
+pub fn encode_uri_component(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*'
+            | b'\'' | b'(' | b')' => encoded.push(byte as char),
+            other => encoded.push_str(&format!("%{other:02X}")),
+        }
+    }
+    encoded
+}
+
+enum TextSpan<'a> {
+    Plain(&'a str),
+    Math { body: &'a str, display: bool },
+}
+
+/// Finds the end of a math span body starting at `from`, returning the
+/// body end and the index just past the closing delimiter.
+fn find_closing(text: &str, from: usize, closing: &str) -> Option<(usize, usize)> {
+    let relative = text[from..].find(closing)?;
+    let body_end = from + relative;
+    (body_end > from).then_some((body_end, body_end + closing.len()))
+}
+
+fn split_math_spans(text: &str) -> Vec<TextSpan<'_>> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut idx = 0;
+
+    while idx < text.len() {
+        let rest = &text[idx..];
+        let candidate = if rest.starts_with("$$") {
+            find_closing(text, idx + 2, "$$").map(|end| (end, true))
+        } else if rest.starts_with("\\[") {
+            find_closing(text, idx + 2, "\\]").map(|end| (end, true))
+        } else if rest.starts_with("\\(") {
+            find_closing(text, idx + 2, "\\)").map(|end| (end, false))
+        } else if rest.starts_with('$') && !rest[1..].starts_with(char::is_whitespace) {
+            // Inline dollars need a non-space body edge so prices like "$5 and $6" stay text.
+            find_closing(text, idx + 1, "$")
+                .filter(|(body_end, _)| !text[..*body_end].ends_with(char::is_whitespace))
+                .map(|end| (end, false))
+        } else {
+            None
+        };
+
+        match candidate {
+            Some(((body_end, span_end), display)) => {
+                if plain_start < idx {
+                    spans.push(TextSpan::Plain(&text[plain_start..idx]));
+                }
+                let opening_len = if rest.starts_with('$') && !display { 1 } else { 2 };
+                spans.push(TextSpan::Math {
+                    body: &text[idx + opening_len..body_end],
+                    display,
+                });
+                idx = span_end;
+                plain_start = span_end;
+            }
+            None => {
+                idx += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+            }
+        }
+    }
+
+    if plain_start < text.len() {
+        spans.push(TextSpan::Plain(&text[plain_start..]));
+    }
+    spans
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '[' | ']' | '`' | '<' | '>' | '|' | '~' | '#' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Applies the escaping / math options to highlighted text.
+fn format_highlight_text(text: &str, settings: &TemplateSettings) -> String {
+    if !settings.preserve_math {
+        return if settings.escape_markdown {
+            escape_markdown(text)
+        } else {
+            text.to_string()
+        };
+    }
+
+    split_math_spans(text)
+        .into_iter()
+        .map(|span| match span {
+            TextSpan::Plain(plain) if settings.escape_markdown => escape_markdown(plain),
+            TextSpan::Plain(plain) => plain.to_string(),
+            TextSpan::Math { body, display: true } => format!("$${}$$", body.trim()),
+            TextSpan::Math { body, display: false } => format!("${}$", body.trim()),
+        })
+        .collect()
+}
+
+/// Zotero's annotation editor stores rich-text comments as HTML; plain
+/// comments are passed through so literal `<` / `>` in prose survive.
+pub fn comment_markdown(comment: &str) -> String {
+    let looks_like_html = (comment.contains('<') && comment.contains("</"))
+        || comment.contains("<br")
+        || comment.contains("&amp;");
+    if looks_like_html {
+        html_to_markdown(comment)
+    } else {
+        comment.to_string()
+    }
+}
+
+/// `zotero://open-pdf` link that opens the attachment at the annotation. The
+/// page number is only added when the label is numeric, since Zotero
+/// resolves it as a page index.
+fn open_pdf_link(annotation: &RenderAnnotation) -> String {
+    let mut link = format!(
+        "zotero://open-pdf/library/items/{}?",
+        encode_uri_component(&annotation.attachment_key)
+    );
+    if !annotation.page_label.is_empty() && annotation.page_label.chars().all(|ch| ch.is_ascii_digit()) {
+        link.push_str(&format!("page={}&", annotation.page_label));
+    }
+    link.push_str(&format!("annotation={}", encode_uri_component(&annotation.key)));
+    link
+}
+
+/// OCR text as wikilink display text: one line, without the characters
+/// that end the link, and at most [`MAX_ALIAS_CHARS`] characters.
+fn link_alias(text: &str) -> String {
+    let words = text
+        .split(|ch: char| ch.is_whitespace() || matches!(ch, '|' | '[' | ']'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if words.chars().count() <= MAX_ALIAS_CHARS {
+        return words;
+    }
+    let shortened = words.chars().take(MAX_ALIAS_CHARS).collect::<String>();
+    format!("{}…", shortened.trim_end())
+}
+
+fn annotation_quote_lines(annotation: &RenderAnnotation, settings: &TemplateSettings) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    let comment = comment_markdown(&annotation.comment);
+    let text = format_highlight_text(&annotation.text, settings);
+
+    let page_suffix = if settings.pdf_links && !annotation.attachment_key.is_empty() {
+        let label = if annotation.page_label.is_empty() {
+            "open".to_string()
+        } else {
+            format!("p. {}", annotation.page_label)
+        };
+        format!(" ([{label}]({}))", open_pdf_link(annotation))
+    } else if annotation.page_label.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " ([p. {}](zotero://select/library/items/{}))",
+            annotation.page_label,
+            encode_uri_component(&annotation.key)
+        )
+    };
+
+    if !text.is_empty() {
+        lines.push(format!("{text}{page_suffix}"));
+    } else if !comment.is_empty() {
+        lines.push(format!("{comment}{page_suffix}"));
+    } else {
+        lines.push(format!("(No text extracted){page_suffix}"));
+    }
+
+    if !text.is_empty() && !comment.is_empty() {
+        lines.push(format!("Comment: {comment}"));
+    }
+
+    if let Some(path) = annotation.image_markdown_path.as_deref().filter(|path| !path.is_empty()) {
+        let image_text = annotation.image_text.as_deref().map(str::trim).filter(|text| !text.is_empty());
+        match (image_text, settings.image_text) {
+            (Some(text), ImageTextStyle::AltText) => lines.push(format!("[[{path}|{}]]", link_alias(text))),
+            (Some(text), ImageTextStyle::Quote) => {
+                lines.push(format!("[[{path}]]"));
+                lines.extend(text.lines().map(|line| format!("> {line}").trim_end().to_string()));
+            }
+            (None, _) => lines.push(format!("[[{path}]]")),
+        }
+    }
+
+    if let Some(message) = annotation.missing_image_message.as_deref().filter(|message| !message.is_empty()) {
+        lines.push(format!("TODO: {message}"));
+    }
+
+    if settings.block_ids {
+        if let Some(last) = lines.last_mut() {
+            last.push(' ');
+            last.push_str(&block_id(&annotation.key));
+        }
+    }
+
+    lines
+}
+
+fn abstract_callout_lines(abstract_text: &str) -> Vec<String> {
+    let paragraphs = abstract_text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    if paragraphs.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec![
+        "> [!INFO]".to_string(),
+        "> ".to_string(),
+        "> Abstract".to_string(),
+        "> ".to_string(),
+    ];
+    lines.extend(paragraphs.iter().map(|line| format!("> {line}")));
+    lines.push("> ".to_string());
+    lines.push(String::new());
+    lines
+}
+
+/// Generates GitHub-style heading anchors, suffixing duplicates with `-1`,
+/// `-2`, ... the way most markdown renderers do.
+#[derive(Default)]
+struct AnchorSet {
+    seen: BTreeMap<String, usize>,
+}
+
+impl AnchorSet {
+    fn slug(&mut self, heading: &str) -> String {
+        let base = heading
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|ch| match ch {
+                ' ' => Some('-'),
+                ch if ch.is_alphanumeric() || ch == '-' || ch == '_' => Some(ch),
+                _ => None,
+            })
+            .collect::<String>();
+
+        let count = self.seen.entry(base.clone()).or_insert(0);
+        let slug = if *count == 0 {
+            base
+        } else {
+            format!("{base}-{count}")
+        };
+        *count += 1;
+        slug
+    }
+}
+
+struct Section<'a> {
+    heading: String,
+    annotations: Vec<&'a RenderAnnotation>,
+}
+
+fn color_heading(color_name: &str, settings: &TemplateSettings) -> String {
+    settings
+        .color_heading_overrides
+        .get(color_name)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .unwrap_or(color_name)
+        .to_string()
+}
+
+/// Splits annotations into sections according to `settings.grouping`.
+fn annotation_sections<'a>(input: &'a NoteInput, settings: &TemplateSettings) -> Vec<Section<'a>> {
+    if settings.grouping == AnnotationGrouping::Color {
+        return color_sections(input, settings);
+    }
+
+    let mut annotations = input
+        .grouped_annotations
+        .iter()
+        .flat_map(|group| group.annotations.iter())
+        .collect::<Vec<_>>();
+
+    let heading_for = |annotation: &RenderAnnotation| -> String {
+        match settings.grouping {
+            AnnotationGrouping::Page if annotation.page_label.is_empty() => "No page".to_string(),
+            AnnotationGrouping::Page => format!("Page {}", annotation.page_label),
+            AnnotationGrouping::Chronological => {
+                let day = annotation.date_added.get(..10).unwrap_or(&annotation.date_added);
+                if day.is_empty() {
+                    "Undated".to_string()
+                } else {
+                    dates::format_zotero_date(day, &settings.date_format)
+                }
+            }
+            _ if !annotation.attachment_title.is_empty() => annotation.attachment_title.clone(),
+            _ if !annotation.attachment_key.is_empty() => {
+                format!("Attachment {}", annotation.attachment_key)
+            }
+            _ => "Attachment".to_string(),
+        }
+    };
+
+    if settings.grouping == AnnotationGrouping::Chronological {
+        annotations.sort_by(|a, b| {
+            a.date_added
+                .cmp(&b.date_added)
+                .then_with(|| a.sort_index.cmp(&b.sort_index))
+        });
+    } else {
+        annotations.sort_by_key(|annotation| annotation.sort_index);
+    }
+
+    // Sections appear in order of their first annotation.
+    let mut sections = Vec::<Section>::new();
+    for annotation in annotations {
+        let heading = heading_for(annotation);
+        match sections.iter_mut().find(|section| section.heading == heading) {
+            Some(section) => section.annotations.push(annotation),
+            None => sections.push(Section {
+                heading,
+                annotations: vec![annotation],
+            }),
+        }
+    }
+    sections
+}
+
+/// Orders color groups into sections, honoring `color_order` and the
+/// optional "other colors" bucket.
+fn color_sections<'a>(input: &'a NoteInput, settings: &TemplateSettings) -> Vec<Section<'a>> {
+    let custom_order = settings
+        .color_order
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    let rank = |color_name: &str| match custom_order.iter().position(|name| *name == color_name) {
+        Some(index) => index,
+        None if custom_order.is_empty() => color_rank(color_name),
+        None => custom_order.len() + color_rank(color_name),
+    };
+
+    let mut groups = input.grouped_annotations.iter().collect::<Vec<_>>();
+    groups.sort_by(|a, b| {
+        rank(&a.color_name)
+            .cmp(&rank(&b.color_name))
+            .then_with(|| a.color_name.cmp(&b.color_name))
+    });
+
+    let other_heading = settings.other_colors_heading.trim();
+    let bucket_others = !custom_order.is_empty() && !other_heading.is_empty();
+
+    let mut sections = Vec::<Section>::new();
+    let mut others = Vec::<&RenderAnnotation>::new();
+    for group in groups {
+        if bucket_others && !custom_order.contains(&group.color_name.as_str()) {
+            others.extend(group.annotations.iter());
+            continue;
+        }
+        sections.push(Section {
+            heading: color_heading(&group.color_name, settings),
+            annotations: group.annotations.iter().collect(),
+        });
+    }
+
+    if !others.is_empty() {
+        sections.push(Section {
+            heading: other_heading.to_string(),
+            annotations: others,
+        });
+    }
+    sections
+}
+
+/// Renders the full markdown note for an item. Mirrors the layout of the
+/// frontend's `generateMarkdown`, which remains as the browser-only fallback.
+pub fn render_note(input: &NoteInput, settings: &TemplateSettings) -> String {
+    let mut lines = vec![
+        "---".to_string(),
+        "tags:".to_string(),
+        "  - type/source/paper".to_string(),
+    ];
+    for tag in mapped_tags(&input.item, &settings.tag_mapping) {
+        lines.push(format!("  - {}", yaml_quote(&tag)));
+    }
+    let entries = frontmatter_entries(input, settings);
+    if settings.metadata_output.frontmatter() {
+        for entry in &entries {
+            lines.extend(frontmatter_lines(entry));
+        }
+    }
+    lines.push("---".to_string());
+    lines.push(String::new());
+
+    let inline_fields = if settings.metadata_output.inline() {
+        entries.iter().filter_map(inline_field_line).collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+    let abstract_lines = abstract_callout_lines(&item_field(&input.item, "abstractNote"));
+    let sections = annotation_sections(input, settings);
+    let notes = input
+        .child_notes
+        .iter()
+        .map(|note| html_to_markdown(note))
+        .filter(|note| !note.is_empty())
+        .collect::<Vec<_>>();
+
+    let toc_enabled = settings.table_of_contents;
+    if toc_enabled {
+        // Explicit `<a id>` anchors mark the metadata and abstract blocks,
+        // which have no heading of their own.
+        let mut anchors = AnchorSet::default();
+        anchors.slug("Contents");
+        let mut toc = vec!["## Contents".to_string(), String::new()];
+        if !inline_fields.is_empty() {
+            toc.push("- [Metadata](#metadata)".to_string());
+        }
+        if !abstract_lines.is_empty() {
+            toc.push("- [Abstract](#abstract)".to_string());
+        }
+        toc.push(format!("- [Annotations](#{})", anchors.slug("Annotations")));
+        for section in &sections {
+            toc.push(format!("  - [{}](#{})", section.heading, anchors.slug(&section.heading)));
+        }
+        if !notes.is_empty() {
+            toc.push(format!("- [Notes](#{})", anchors.slug("Notes")));
+        }
+        lines.extend(toc);
+        lines.push(String::new());
+    }
+
+    if !inline_fields.is_empty() {
+        if toc_enabled {
+            lines.push("<a id=\"metadata\"></a>".to_string());
+        }
+        lines.extend(inline_fields);
+        lines.push(String::new());
+    }
+
+    lines.push("Project:".to_string());
+    lines.push(String::new());
+
+    if !abstract_lines.is_empty() && toc_enabled {
+        lines.push("<a id=\"abstract\"></a>".to_string());
+    }
+    lines.extend(abstract_lines);
+
+    lines.push("## Annotations".to_string());
+    lines.push(String::new());
+
+    let heading_marks = "#".repeat(usize::from(settings.section_heading_level.clamp(1, 6)));
+    for section in &sections {
+        lines.push(format!("{heading_marks} {}", section.heading));
+
+        for (index, annotation) in section.annotations.iter().enumerate() {
+            // Converted comments can span several lines; each needs the quote prefix.
+            for line in annotation_quote_lines(annotation, settings)
+                .iter()
+                .flat_map(|line| line.lines())
+            {
+                lines.push(if line.is_empty() { ">".to_string() } else { format!("> {line}") });
+            }
+            if index + 1 < section.annotations.len() {
+                lines.push(String::new());
+            }
+        }
+
+        lines.push(String::new());
+    }
+
+    if !notes.is_empty() {
+        lines.push("## Notes".to_string());
+        lines.push(String::new());
+        for note in notes {
+            lines.push(note);
+            lines.push(String::new());
+        }
+    }
+
+    let mut markdown = lines.join("\n").trim_end().to_string();
+    markdown.push('\n');
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn annotation(key: &str, text: &str, page_label: &str) -> RenderAnnotation {
+        RenderAnnotation {
+            key: key.to_string(),
+            text: text.to_string(),
+            comment: String::new(),
+            page_label: page_label.to_string(),
+            image_markdown_path: None,
+            missing_image_message: None,
+            image_text: None,
+            attachment_key: String::new(),
+            attachment_title: String::new(),
+            sort_index: 0,
+            date_added: String::new(),
+        }
+    }
+
+    fn group(color_name: &str, annotations: Vec<RenderAnnotation>) -> ColorGroup {
+        ColorGroup {
+            color_name: color_name.to_string(),
+            annotations,
+        }
+    }
+
+    fn input(grouped_annotations: Vec<ColorGroup>) -> NoteInput {
+        NoteInput {
+            item: json!({"data": {
+                "title": "A Mathematical Theory of Communication",
+                "date": "1948-07-01",
+                "publisher": "Bell Labs",
+                "dateAdded": "2024-05-01 09:30:00",
+                "creators": [
+                    {"creatorType": "author", "lastName": "Shannon", "firstName": "Claude"},
+                    {"creatorType": "author", "name": "Weaver"}
+                ],
+                "tags": [{"tag": "Information Theory"}, {"tag": "_todo"}, {"tag": "C++ / Rust"}]
+            }}),
+            cite_key: "shannon1948".to_string(),
+            grouped_annotations,
+            child_notes: Vec::new(),
+        }
+    }
+
+    fn render(grouped_annotations: Vec<ColorGroup>, settings: &TemplateSettings) -> String {
+        render_note(&input(grouped_annotations), settings)
+    }
+
+    #[test]
+    fn block_ids_keep_only_letters_digits_and_dashes() {
+        assert_eq!(block_id("ABCD1234"), "^zot-ABCD1234");
+        assert_eq!(block_id("a_b.c-d"), "^zot-abc-d");
+    }
+
+    #[test]
+    fn block_ids_end_each_annotation() {
+        let mut commented = annotation("KEY2", "Second", "");
+        commented.comment = "Why".to_string();
+        let settings = TemplateSettings {
+            block_ids: true,
+            ..TemplateSettings::default()
+        };
+        let markdown = render(
+            vec![group("Yellow", vec![annotation("KEY1", "First", "3"), commented])],
+            &settings,
+        );
+        assert!(markdown.contains(
+            "### Yellow\n> First ([p. 3](zotero://select/library/items/KEY1)) ^zot-KEY1\n\n> Second\n> Comment: Why ^zot-KEY2\n"
+        ));
+        assert!(!render(vec![group("Yellow", vec![annotation("KEY1", "First", "3")])], &TemplateSettings::default())
+            .contains("^zot-"));
+    }
+
+    fn frontmatter(settings: &TemplateSettings) -> Vec<(String, Vec<String>, bool)> {
+        frontmatter_entries(&input(Vec::new()), settings)
+            .into_iter()
+            .map(|entry| (entry.key, entry.values, entry.list))
+            .collect()
+    }
+
+    fn rule(field: &str) -> FrontmatterField {
+        FrontmatterField {
+            field: field.to_string(),
+            ..FrontmatterField::default()
+        }
+    }
+
+    #[test]
+    fn default_frontmatter_follows_the_property_order() {
+        let settings = TemplateSettings {
+            property_order: vec!["year".to_string(), "unknown".to_string(), "year".to_string()],
+            ..TemplateSettings::default()
+        };
+        let keys = frontmatter(&settings).into_iter().map(|(key, _, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys, ["Year", "Title", "Author", "Company"]);
+    }
+
+    #[test]
+    fn frontmatter_rules_omit_replace_and_add_keys() {
+        let settings = TemplateSettings {
+            frontmatter_fields: vec![
+                FrontmatterField { omit: true, ..rule("company") },
+                FrontmatterField { key: "authors".to_string(), list: true, ..rule("author") },
+                FrontmatterField { constant: Some("paper".to_string()), key: "kind".to_string(), ..rule("") },
+                FrontmatterField { key: "cite".to_string(), ..rule("citekey") },
+                rule("publisher"),
+                rule(""),
+            ],
+            ..TemplateSettings::default()
+        };
+        assert_eq!(
+            frontmatter(&settings),
+            [
+                ("Title".to_string(), vec!["A Mathematical Theory of Communication".to_string()], false),
+                ("authors".to_string(), vec!["Shannon, Claude".to_string(), "Weaver".to_string()], true),
+                ("Year".to_string(), vec!["1948".to_string()], false),
+                ("kind".to_string(), vec!["paper".to_string()], false),
+                ("cite".to_string(), vec!["shannon1948".to_string()], false),
+                ("publisher".to_string(), vec!["Bell Labs".to_string()], false),
+            ]
+        );
+    }
+
+    #[test]
+    fn frontmatter_values_are_quoted_and_lists_expanded() {
+        let settings = TemplateSettings {
+            frontmatter_fields: vec![
+                FrontmatterField { list: true, ..rule("author") },
+                FrontmatterField { list: true, key: "none".to_string(), ..rule("volume") },
+                FrontmatterField { constant: Some("it's".to_string()), key: "quote".to_string(), ..rule("") },
+            ],
+            ..TemplateSettings::default()
+        };
+        let markdown = render(Vec::new(), &settings);
+        assert!(markdown.starts_with(
+            "---\ntags:\n  - type/source/paper\nTitle: 'A Mathematical Theory of Communication'\n\
+             Author:\n  - 'Shannon, Claude'\n  - 'Weaver'\nYear: '1948'\nCompany: 'Bell Labs'\n\
+             none: []\nquote: 'it''s'\n---\n"
+        ));
+    }
+
+    #[test]
+    fn inline_fields_link_authors_and_skip_empty_values() {
+        let entry = |source: &str, values: &[&str], list: bool| FrontmatterEntry {
+            source: source.to_string(),
+            key: "Key".to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+            list,
+        };
+        assert_eq!(
+            inline_field_line(&entry("author", &["Shannon, Claude", "", "Weaver"], false)).as_deref(),
+            Some("Key:: [[Shannon, Claude]], [[Weaver]]")
+        );
+        assert_eq!(inline_field_line(&entry("tags", &["a", "b"], true)).as_deref(), Some("Key:: a, b"));
+        assert_eq!(inline_field_line(&entry("title", &["a", "b"], false)).as_deref(), Some("Key:: a; b"));
+        assert_eq!(inline_field_line(&entry("title", &[""], false)), None);
+    }
+
+    #[test]
+    fn metadata_output_picks_frontmatter_inline_or_both() {
+        let with = |metadata_output| TemplateSettings {
+            metadata_output,
+            ..TemplateSettings::default()
+        };
+        let inline = render(Vec::new(), &with(MetadataOutput::Inline));
+        assert!(inline.starts_with("---\ntags:\n  - type/source/paper\n---\n\nTitle:: A Mathematical"));
+        assert!(inline.contains("Author:: [[Shannon, Claude]], [[Weaver]]\nYear:: 1948\nCompany:: Bell Labs\n\nProject:"));
+
+        let both = render(Vec::new(), &with(MetadataOutput::Both));
+        assert!(both.contains("Year: '1948'\n"));
+        assert!(both.contains("Year:: 1948\n"));
+
+        let frontmatter = render(Vec::new(), &with(MetadataOutput::Frontmatter));
+        assert!(!frontmatter.contains("::"));
+    }
+
+    #[test]
+    fn tags_are_mapped_with_prefix_deny_list_and_slugs() {
+        let item = input(Vec::new()).item;
+        let mut mapping = TagMapping {
+            enabled: true,
+            prefix: " zotero/ ".to_string(),
+            deny_list: vec!["_TODO".to_string()],
+            slugify: false,
+        };
+        assert_eq!(mapped_tags(&item, &mapping), ["zotero/Information-Theory", "zotero/C++-/-Rust"]);
+        mapping.slugify = true;
+        assert_eq!(mapped_tags(&item, &mapping), ["zotero/information-theory", "zotero/c-/-rust"]);
+        mapping.enabled = false;
+        assert!(mapped_tags(&item, &mapping).is_empty());
+    }
+
+    #[test]
+    fn mapped_tags_follow_the_default_tag() {
+        let settings = TemplateSettings {
+            tag_mapping: TagMapping {
+                enabled: true,
+                slugify: true,
+                ..TagMapping::default()
+            },
+            ..TemplateSettings::default()
+        };
+        assert!(render(Vec::new(), &settings).starts_with(
+            "---\ntags:\n  - type/source/paper\n  - 'information-theory'\n  - '_todo'\n  - 'c-/-rust'\nTitle:"
+        ));
+    }
+
+    #[test]
+    fn html_comments_are_converted_and_plain_ones_kept() {
+        assert_eq!(comment_markdown("<p>Key <b>idea</b></p><p>Second</p>"), "Key **idea**\n\nSecond");
+        assert_eq!(comment_markdown("one<br>two"), "one\ntwo");
+        assert_eq!(comment_markdown("a < b > c"), "a < b > c");
+    }
+
+    #[test]
+    fn multi_line_comments_stay_inside_the_quote() {
+        let mut commented = annotation("KEY1", "Text", "");
+        commented.comment = "<p>One</p><p>Two</p>".to_string();
+        let mut comment_only = annotation("KEY2", "", "");
+        comment_only.comment = "Just a note".to_string();
+        let markdown = render(vec![group("Yellow", vec![commented, comment_only, annotation("KEY3", "", "")])], &TemplateSettings::default());
+        assert!(markdown.contains("> Text\n> Comment: One\n>\n> Two\n\n> Just a note\n\n> (No text extracted)\n"));
+    }
+
+    fn spans(text: &str) -> Vec<String> {
+        split_math_spans(text)
+            .into_iter()
+            .map(|span| match span {
+                TextSpan::Plain(plain) => format!("text:{plain}"),
+                TextSpan::Math { body, display } => format!("{}:{body}", if display { "display" } else { "inline" }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn math_spans_are_found_by_their_delimiters() {
+        assert_eq!(spans("Let $x^2$ be"), ["text:Let ", "inline:x^2", "text: be"]);
+        assert_eq!(spans("$$E = mc^2$$"), ["display:E = mc^2"]);
+        assert_eq!(spans(r"with \(a_i\) and \[b\]"), ["text:with ", "inline:a_i", "text: and ", "display:b"]);
+        assert_eq!(spans("costs $5 and $6 today"), ["text:costs $5 and $6 today"]);
+        assert_eq!(spans("empty $$$$ and $ x$"), ["text:empty $$$$ and $ x$"]);
+    }
+
+    #[test]
+    fn escaping_protects_markdown_but_not_math() {
+        assert_eq!(escape_markdown(r"a*b_c [d] `e` <f> |g ~h #i $j \k"), r"a\*b\_c \[d\] \`e\` \<f\> \|g \~h \#i \$j \\k");
+        let settings = |escape_markdown, preserve_math| TemplateSettings {
+            escape_markdown,
+            preserve_math,
+            ..TemplateSettings::default()
+        };
+        let text = r"*bold* and \( x_1 \) for $5 and $6";
+        assert_eq!(format_highlight_text(text, &settings(false, false)), text);
+        assert_eq!(format_highlight_text(text, &settings(true, false)), r"\*bold\* and \\( x\_1 \\) for \$5 and \$6");
+        assert_eq!(format_highlight_text(text, &settings(true, true)), r"\*bold\* and $x_1$ for \$5 and \$6");
+        assert_eq!(format_highlight_text(r"\[ a \]", &settings(false, true)), "$$a$$");
+    }
+
+    fn headings(markdown: &str) -> Vec<&str> {
+        markdown.lines().filter(|line| line.starts_with('#')).collect()
+    }
+
+    #[test]
+    fn color_sections_use_the_default_order_and_overrides() {
+        let groups = vec![
+            group("Unknown (#123456)", vec![annotation("K1", "a", "")]),
+            group("Blue", vec![annotation("K2", "b", "")]),
+            group("Magenta", vec![annotation("K3", "c", "")]),
+            group("Yellow", vec![annotation("K4", "d", "")]),
+        ];
+        let settings = TemplateSettings {
+            color_heading_overrides: BTreeMap::from([("Yellow".to_string(), "Key points".to_string())]),
+            section_heading_level: 9,
+            ..TemplateSettings::default()
+        };
+        assert_eq!(
+            headings(&render(groups, &settings)),
+            ["## Annotations", "###### Key points", "###### Blue", "###### Unknown (#123456)", "###### Magenta"]
+        );
+    }
+
+    #[test]
+    fn color_order_puts_listed_colors_first_and_buckets_the_rest() {
+        let groups = || {
+            vec![
+                group("Yellow", vec![annotation("K1", "a", "")]),
+                group("Blue", vec![annotation("K2", "b", "")]),
+                group("Green", vec![annotation("K3", "c", "")]),
+                group("Red", vec![annotation("K4", "d", "")]),
+            ]
+        };
+        let mut settings = TemplateSettings {
+            color_order: vec!["Green".to_string(), " ".to_string(), "Blue".to_string()],
+            section_heading_level: 2,
+            ..TemplateSettings::default()
+        };
+        assert_eq!(
+            headings(&render(groups(), &settings)),
+            ["## Annotations", "## Green", "## Blue", "## Yellow", "## Red"]
+        );
+
+        settings.other_colors_heading = "Other".to_string();
+        let markdown = render(groups(), &settings);
+        assert_eq!(headings(&markdown), ["## Annotations", "## Green", "## Blue", "## Other"]);
+        assert!(markdown.contains("## Other\n> a\n\n> d\n"));
+    }
+
+    fn dated(key: &str, page_label: &str, sort_index: usize, date_added: &str, attachment_title: &str) -> RenderAnnotation {
+        RenderAnnotation {
+            sort_index,
+            date_added: date_added.to_string(),
+            attachment_title: attachment_title.to_string(),
+            attachment_key: "ATTACH01".to_string(),
+            ..annotation(key, key, page_label)
+        }
+    }
+
+    fn grouped_by(grouping: AnnotationGrouping) -> Vec<(String, Vec<String>)> {
+        let input = input(vec![
+            group(
+                "Yellow",
+                vec![
+                    dated("K1", "2", 1, "2024-05-02 08:00:00", "Main"),
+                    dated("K2", "", 3, "", ""),
+                ],
+            ),
+            group(
+                "Blue",
+                vec![
+                    dated("K3", "1", 0, "2024-05-01 23:59:59", "Main"),
+                    dated("K4", "2", 2, "2024-05-01T10:00:00Z", "Appendix"),
+                ],
+            ),
+        ]);
+        let settings = TemplateSettings {
+            grouping,
+            ..TemplateSettings::default()
+        };
+        annotation_sections(&input, &settings)
+            .into_iter()
+            .map(|section| {
+                let keys = section.annotations.iter().map(|annotation| annotation.key.clone()).collect();
+                (section.heading, keys)
+            })
+            .collect()
+    }
+
+    fn section(heading: &str, keys: &[&str]) -> (String, Vec<String>) {
+        (heading.to_string(), keys.iter().map(|key| key.to_string()).collect())
+    }
+
+    #[test]
+    fn page_sections_follow_reading_order() {
+        assert_eq!(
+            grouped_by(AnnotationGrouping::Page),
+            [section("Page 1", &["K3"]), section("Page 2", &["K1", "K4"]), section("No page", &["K2"])]
+        );
+    }
+
+    #[test]
+    fn chronological_sections_are_days_in_date_order() {
+        assert_eq!(
+            grouped_by(AnnotationGrouping::Chronological),
+            [
+                section("Undated", &["K2"]),
+                section("2024-05-01", &["K3", "K4"]),
+                section("2024-05-02", &["K1"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn attachment_sections_use_the_title_or_key() {
+        assert_eq!(
+            grouped_by(AnnotationGrouping::Attachment),
+            [
+                section("Main", &["K3", "K1"]),
+                section("Appendix", &["K4"]),
+                section("Attachment ATTACH01", &["K2"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn anchors_are_slugged_and_deduplicated() {
+        let mut anchors = AnchorSet::default();
+        assert_eq!(anchors.slug(" Key Points! "), "key-points");
+        assert_eq!(anchors.slug("Key points"), "key-points-1");
+        assert_eq!(anchors.slug("key-points"), "key-points-2");
+        assert_eq!(anchors.slug("Über_Größe"), "über_größe");
+    }
+
+    #[test]
+    fn table_of_contents_links_every_section() {
+        let mut note = input(vec![
+            group("Yellow", vec![annotation("K1", "a", "")]),
+            group("Green", vec![annotation("K2", "b", "")]),
+        ]);
+        note.item["data"]["abstractNote"] = json!("Summary.");
+        note.child_notes = vec!["<p>Child</p>".to_string()];
+        let settings = TemplateSettings {
+            table_of_contents: true,
+            metadata_output: MetadataOutput::Both,
+            color_heading_overrides: BTreeMap::from([
+                ("Yellow".to_string(), "Notes".to_string()),
+                ("Green".to_string(), "Notes".to_string()),
+            ]),
+            ..TemplateSettings::default()
+        };
+        let markdown = render_note(&note, &settings);
+        assert!(markdown.contains(
+            "---\n\n## Contents\n\n- [Metadata](#metadata)\n- [Abstract](#abstract)\n- [Annotations](#annotations)\n  \
+             - [Notes](#notes)\n  - [Notes](#notes-1)\n- [Notes](#notes-2)\n\n<a id=\"metadata\"></a>\nTitle::"
+        ));
+        assert!(markdown.contains("Project:\n\n<a id=\"abstract\"></a>\n> [!INFO]"));
+        assert!(!render(Vec::new(), &TemplateSettings::default()).contains("## Contents"));
+    }
+
+    #[test]
+    fn pdf_links_open_the_attachment_at_the_annotation() {
+        let mut highlight = annotation("ANN 1", "Text", "12");
+        highlight.attachment_key = "ATTACH01".to_string();
+        assert_eq!(
+            open_pdf_link(&highlight),
+            "zotero://open-pdf/library/items/ATTACH01?page=12&annotation=ANN%201"
+        );
+        highlight.page_label = "xii".to_string();
+        assert_eq!(open_pdf_link(&highlight), "zotero://open-pdf/library/items/ATTACH01?annotation=ANN%201");
+    }
+
+    #[test]
+    fn pdf_links_replace_select_links_when_enabled() {
+        let mut numbered = annotation("K1", "One", "4");
+        numbered.attachment_key = "ATTACH01".to_string();
+        let mut unnumbered = annotation("K2", "Two", "");
+        unnumbered.attachment_key = "ATTACH01".to_string();
+        let no_attachment = annotation("K3", "Three", "5");
+        let settings = TemplateSettings {
+            pdf_links: true,
+            ..TemplateSettings::default()
+        };
+        let markdown = render(vec![group("Yellow", vec![numbered, unnumbered, no_attachment])], &settings);
+        assert!(markdown.contains(
+            "> One ([p. 4](zotero://open-pdf/library/items/ATTACH01?page=4&annotation=K1))\n\n\
+             > Two ([open](zotero://open-pdf/library/items/ATTACH01?annotation=K2))\n\n\
+             > Three ([p. 5](zotero://select/library/items/K3))\n"
+        ));
+    }
+
+    #[test]
+    fn link_aliases_are_one_line_and_truncated() {
+        assert_eq!(link_alias("Figure 1:\n  [a|b]  results"), "Figure 1: a b results");
+        let long = "word ".repeat(100);
+        let alias = link_alias(&long);
+        assert_eq!(alias.chars().count(), MAX_ALIAS_CHARS);
+        assert!(alias.ends_with("word…"));
+    }
+
+    #[test]
+    fn image_text_is_the_alias_or_a_quote() {
+        let mut image = annotation("K1", "", "");
+        image.image_markdown_path = Some("images/K1.png".to_string());
+        image.image_text = Some("Line one\nLine two".to_string());
+        let alias = render(vec![group("Yellow", vec![image.clone()])], &TemplateSettings::default());
+        assert!(alias.contains("> (No text extracted)\n> [[images/K1.png|Line one Line two]]\n"));
+
+        let settings = TemplateSettings {
+            image_text: ImageTextStyle::Quote,
+            ..TemplateSettings::default()
+        };
+        let quote = render(vec![group("Yellow", vec![image])], &settings);
+        assert!(quote.contains("> [[images/K1.png]]\n> > Line one\n> > Line two\n"));
+    }
+}
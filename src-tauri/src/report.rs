@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::dates::{format_seconds, DateFormat};
+use crate::conflict::{conflict_copy_path, divergence, ConflictStrategy, Divergence};
+use crate::state_db::content_hash;
+use crate::zotero_db::Freshness;
+
+/// What happened to a note file during an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteStatus {
+    Created,
+    Updated,
+    Unchanged,
+    /// Both the note and Zotero changed; the note was left as it is.
+    Conflict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileAction {
+    Create,
+    Update,
+}
+
+/// A file an export writes, or would write in a dry run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: String,
+    pub action: FileAction,
+}
+
+impl FileChange {
+    /// `None` when `path` already holds exactly `content`.
+    pub fn for_content(path: &Path, content: &[u8]) -> Option<Self> {
+        let action = match std::fs::read(path) {
+            Ok(existing) if existing == content => return None,
+            Ok(_) => FileAction::Update,
+            Err(_) => FileAction::Create,
+        };
+        Some(Self {
+            path: path.to_string_lossy().to_string(),
+            action,
+        })
+    }
+}
+
+/// Identifies an annotation included in a note, for new-annotation detection.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationStamp {
+    pub key: String,
+    #[serde(default)]
+    pub date_added: String,
+    #[serde(default)]
+    pub date_modified: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteWrite {
+    pub path: String,
+    pub status: WriteStatus,
+    /// Annotations added since the note was last written. Every annotation
+    /// counts as new when the note is created.
+    pub new_annotation_keys: Vec<String>,
+    /// Hash of the rendered content, to record as the exported state even
+    /// when a conflict kept the local file.
+    pub content_hash: String,
+    /// Where the rendered note went when it conflicted with local edits.
+    pub conflict_path: Option<String>,
+    pub changes: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemReport {
+    pub item_key: String,
+    pub cite_key: String,
+    pub path: String,
+    pub status: WriteStatus,
+    pub annotation_count: usize,
+    pub new_annotations: usize,
+    pub conflict_path: Option<String>,
+    /// Note, conflict copy and image files written for the item.
+    #[serde(default)]
+    pub changes: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ExportReport {
+    pub items: Vec<ItemReport>,
+    pub failed: Vec<String>,
+    /// Set when nothing was written and the report only lists what an
+    /// export would change.
+    pub dry_run: bool,
+    /// Keys of the requested items skipped because they carry the exclusion
+    /// tag.
+    pub excluded: Vec<String>,
+    /// Whether the Zotero data was read live, possibly behind Zotero's
+    /// write-ahead log, or from a snapshot.
+    pub zotero_database: Freshness,
+}
+
+impl ExportReport {
+    fn with_status(&self, status: WriteStatus) -> impl Iterator<Item = &ItemReport> {
+        self.items.iter().filter(move |item| item.status == status)
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Parses Zotero timestamps (`2024-03-01 09:30:00` from SQLite or
+/// `2024-03-01T09:30:00Z` from the API, both UTC) into unix seconds.
+pub fn parse_zotero_timestamp(raw: &str) -> Option<i64> {
+    let digits = raw
+        .split(|ch: char| !ch.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let [year, month, day, rest @ ..] = digits.as_slice() else {
+        return None;
+    };
+    let hour = rest.first().copied().unwrap_or(0);
+    let minute = rest.get(1).copied().unwrap_or(0);
+    let second = rest.get(2).copied().unwrap_or(0);
+
+    Some(days_from_civil(*year, *month, *day) * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// Formats unix seconds as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_timestamp(seconds: i64) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let remainder = seconds.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        remainder / 3_600,
+        (remainder % 3_600) / 60
+    )
+}
+
+pub fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn modified_seconds(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_secs() as i64)
+}
+
+/// Works out what writing `content` to `path` would do, without writing.
+/// The note is left alone when it already holds exactly that content.
+/// Annotations added after the previous write are reported as new.
+///
+/// `recorded_hash` is the note hash from the last export. Local edits made
+/// since then are never overwritten silently: they are kept when only the
+/// file changed, and `strategy` decides when Zotero changed too.
+pub fn plan_note(
+    path: &Path,
+    content: &str,
+    annotations: &[AnnotationStamp],
+    recorded_hash: Option<&str>,
+    strategy: ConflictStrategy,
+) -> NoteWrite {
+    let previous = std::fs::read_to_string(path).ok();
+    let last_written = previous.as_ref().and_then(|_| modified_seconds(path));
+
+    let new_annotation_keys = annotations
+        .iter()
+        .filter(|annotation| match last_written {
+            None => true,
+            Some(written) => parse_zotero_timestamp(&annotation.date_added)
+                .map(|added| added > written)
+                .unwrap_or(false),
+        })
+        .map(|annotation| annotation.key.clone())
+        .collect();
+
+    let rendered_hash = content_hash(content.as_bytes());
+    let (status, conflict_path) = match previous.as_deref() {
+        None => (WriteStatus::Created, None),
+        Some(existing) if existing == content => (WriteStatus::Unchanged, None),
+        Some(existing) => match divergence(&content_hash(existing.as_bytes()), &rendered_hash, recorded_hash) {
+            Divergence::None => (WriteStatus::Updated, None),
+            Divergence::LocalOnly => (WriteStatus::Unchanged, None),
+            Divergence::Both => match strategy {
+                ConflictStrategy::KeepZotero => (WriteStatus::Updated, None),
+                ConflictStrategy::KeepLocal => (WriteStatus::Conflict, None),
+                ConflictStrategy::ConflictCopy => (WriteStatus::Conflict, Some(conflict_copy_path(path))),
+            },
+        },
+    };
+
+    let mut changes = Vec::new();
+    if matches!(status, WriteStatus::Created | WriteStatus::Updated) {
+        changes.extend(FileChange::for_content(path, content.as_bytes()));
+    }
+    if let Some(copy) = &conflict_path {
+        changes.extend(FileChange::for_content(copy, content.as_bytes()));
+    }
+
+    NoteWrite {
+        path: path.to_string_lossy().to_string(),
+        status,
+        new_annotation_keys,
+        content_hash: rendered_hash,
+        conflict_path: conflict_path.map(|copy| copy.to_string_lossy().to_string()),
+        changes,
+    }
+}
+
+/// Writes the note as planned by [`plan_note`].
+pub fn write_note(
+    path: &Path,
+    content: &str,
+    annotations: &[AnnotationStamp],
+    recorded_hash: Option<&str>,
+    strategy: ConflictStrategy,
+) -> Result<NoteWrite, String> {
+    let planned = plan_note(path, content, annotations, recorded_hash, strategy);
+    for change in &planned.changes {
+        std::fs::write(&change.path, content)
+            .map_err(|err| format!("failed to write markdown file {}: {err}", change.path))?;
+    }
+    Ok(planned)
+}
+
+/// Renders the report as a dated changelog section.
+pub fn changelog_markdown(report: &ExportReport, exported_at: i64, date_format: &DateFormat) -> String {
+    let exported = format_seconds(exported_at, date_format).unwrap_or_else(|| format_timestamp(exported_at));
+    let mut lines = vec![format!("## Export {exported}"), String::new()];
+
+    let sections = [
+        ("Created", WriteStatus::Created),
+        ("Updated", WriteStatus::Updated),
+        ("Unchanged", WriteStatus::Unchanged),
+        ("Conflicts", WriteStatus::Conflict),
+    ];
+    for (heading, status) in sections {
+        let items = report.with_status(status).collect::<Vec<_>>();
+        if items.is_empty() {
+            continue;
+        }
+
+        lines.push(format!("### {heading} ({})", items.len()));
+        lines.push(String::new());
+        for item in items {
+            let note_name = Path::new(&item.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| format!("@{}", item.cite_key));
+            let mut line = format!("- [[{note_name}]]");
+            if item.new_annotations > 0 {
+                line.push_str(&format!(
+                    " — {} new annotation{}",
+                    item.new_annotations,
+                    if item.new_annotations == 1 { "" } else { "s" }
+                ));
+            }
+            if let Some(copy) = &item.conflict_path {
+                let copy_name = Path::new(copy)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                line.push_str(&format!(" — Zotero version in [[{copy_name}]]"));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+    }
+
+    if !report.failed.is_empty() {
+        lines.push(format!("### Failed ({})", report.failed.len()));
+        lines.push(String::new());
+        lines.extend(report.failed.iter().map(|failure| format!("- {failure}")));
+        lines.push(String::new());
+    }
+
+    lines.join("\n")
+}
+
+/// Appends the report to the changelog note at `path`, creating it with a
+/// title when missing.
+pub fn append_changelog(path: &Path, report: &ExportReport, date_format: &DateFormat) -> Result<(), String> {
+    let existing = std::fs::read_to_string(path)
+        .unwrap_or_else(|_| "# ZotNotes export log\n".to_string());
+    let section = changelog_markdown(report, now_seconds(), date_format);
+    let content = format!("{}\n\n{}", existing.trim_end(), section);
+
+    std::fs::write(path, content)
+        .map_err(|err| format!("failed to write changelog {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-07 09:05:03 UTC.
+    const SECONDS: i64 = 1_709_802_303;
+
+    fn item(cite_key: &str, status: WriteStatus, new_annotations: usize) -> ItemReport {
+        ItemReport {
+            item_key: cite_key.to_uppercase(),
+            cite_key: cite_key.to_string(),
+            path: format!("/vault/@{cite_key}.md"),
+            status,
+            annotation_count: 3,
+            new_annotations,
+            conflict_path: None,
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn zotero_timestamps_are_parsed_as_utc() {
+        assert_eq!(parse_zotero_timestamp("2024-03-07 09:05:03"), Some(SECONDS));
+        assert_eq!(parse_zotero_timestamp("2024-03-07T09:05:03Z"), Some(SECONDS));
+        assert_eq!(parse_zotero_timestamp("2024-03-07"), Some(SECONDS - 32_703));
+        assert_eq!(parse_zotero_timestamp("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(parse_zotero_timestamp("1969-12-31 23:59:59"), Some(-1));
+    }
+
+    #[test]
+    fn garbage_timestamps_are_rejected() {
+        for raw in ["", "n.d.", "May 2024", "2024-03", "2024-03-07 99999999999999999999"] {
+            assert_eq!(parse_zotero_timestamp(raw), None, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in (-800_000..800_000).step_by(997) {
+            let (year, month, day) = civil_from_days(days);
+            assert!((1..=12).contains(&month) && (1..=31).contains(&day));
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn timestamps_are_formatted_to_the_minute() {
+        assert_eq!(format_timestamp(SECONDS), "2024-03-07 09:05");
+        assert_eq!(format_timestamp(-60), "1969-12-31 23:59");
+    }
+
+    #[test]
+    fn changelog_lists_items_by_status() {
+        let mut conflicted = item("doe2021", WriteStatus::Conflict, 0);
+        conflicted.conflict_path = Some("/vault/@doe2021 (Zotero).md".to_string());
+        let report = ExportReport {
+            items: vec![
+                item("smith2020", WriteStatus::Updated, 2),
+                item("lee2019", WriteStatus::Created, 1),
+                item("kim2018", WriteStatus::Unchanged, 0),
+                conflicted,
+            ],
+            failed: vec!["ABCD1234: no attachment".to_string()],
+            ..ExportReport::default()
+        };
+        assert_eq!(
+            changelog_markdown(&report, SECONDS, &DateFormat::default()),
+            "## Export 2024-03-07 09:05\n\n\
+             ### Created (1)\n\n- [[@lee2019]] — 1 new annotation\n\n\
+             ### Updated (1)\n\n- [[@smith2020]] — 2 new annotations\n\n\
+             ### Unchanged (1)\n\n- [[@kim2018]]\n\n\
+             ### Conflicts (1)\n\n- [[@doe2021]] — Zotero version in [[@doe2021 (Zotero)]]\n\n\
+             ### Failed (1)\n\n- ABCD1234: no attachment\n"
+        );
+        let long = DateFormat {
+            pattern: "long".to_string(),
+            locale: String::new(),
+        };
+        assert!(changelog_markdown(&ExportReport::default(), SECONDS, &long).starts_with("## Export March 7, 2024\n"));
+    }
+
+    #[test]
+    fn changelog_sections_are_appended() {
+        let path = std::env::temp_dir().join(format!("zotnotes-changelog-{}.md", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let report = ExportReport {
+            items: vec![item("smith2020", WriteStatus::Created, 0)],
+            ..ExportReport::default()
+        };
+        append_changelog(&path, &report, &DateFormat::default()).unwrap();
+        append_changelog(&path, &report, &DateFormat::default()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# ZotNotes export log\n\n## Export "));
+        assert_eq!(content.matches("- [[@smith2020]]").count(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
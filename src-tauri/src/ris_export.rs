@@ -0,0 +1,214 @@
+//! RIS output of item metadata, for reference managers and submission
+//! systems that don't read BibTeX or CSL-JSON. Items come from the same
+//! rows as the CSV export; the cite key is written as the record's `ID`.
+
+use serde_json::Value;
+
+use crate::csv_export::CsvItem;
+use crate::extract_year;
+
+/// RIS reference type for a Zotero item type; unknown types are `GEN`.
+fn reference_type(item_type: &str) -> &'static str {
+    match item_type {
+        "journalArticle" => "JOUR",
+        "book" => "BOOK",
+        "bookSection" => "CHAP",
+        "conferencePaper" => "CPAPER",
+        "thesis" => "THES",
+        "report" => "RPRT",
+        "webpage" | "forumPost" => "ELEC",
+        "blogPost" => "BLOG",
+        "magazineArticle" => "MGZN",
+        "newspaperArticle" => "NEWS",
+        "manuscript" | "preprint" => "UNPB",
+        "patent" => "PAT",
+        "presentation" => "SLIDE",
+        "videoRecording" => "VIDEO",
+        "audioRecording" | "podcast" | "radioBroadcast" => "SOUND",
+        "film" | "tvBroadcast" => "MPCT",
+        "case" => "CASE",
+        "statute" => "STAT",
+        "bill" => "BILL",
+        "hearing" => "HEAR",
+        "map" => "MAP",
+        "artwork" => "ART",
+        "computerProgram" => "COMP",
+        "dataset" => "DATA",
+        "encyclopediaArticle" => "ENCYC",
+        "dictionaryEntry" => "DICT",
+        "standard" => "STAND",
+        "email" => "ICOMM",
+        "letter" | "interview" => "PCOMM",
+        _ => "GEN",
+    }
+}
+
+/// RIS tag for a creator role; primary creators of every type are `AU`.
+fn creator_tag(creator_type: &str) -> &'static str {
+    match creator_type {
+        "editor" => "ED",
+        "bookAuthor" => "A2",
+        "seriesEditor" => "A3",
+        "translator" | "contributor" | "reviewedAuthor" | "commenter" => "A4",
+        _ => "AU",
+    }
+}
+
+fn data_field<'a>(item: &'a Value, key: &str) -> &'a str {
+    item.pointer(&format!("/data/{key}"))
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+/// `YYYY/MM/DD/` from the ISO date Zotero keeps in front of the typed date,
+/// with unknown month or day left empty.
+fn ris_date(raw: &str) -> Option<String> {
+    let iso = raw.get(..10)?;
+    let parts = iso.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts.as_slice() else {
+        return None;
+    };
+    if year.len() != 4 || !iso.chars().all(|ch| ch.is_ascii_digit() || ch == '-') || *year == "0000" {
+        return None;
+    }
+    let known = |part: &str| if part == "00" { String::new() } else { part.to_string() };
+    Some(format!("{year}/{}/{}/", known(month), known(day)))
+}
+
+fn push_tag(record: &mut Vec<String>, tag: &str, value: &str) {
+    // RIS values are single lines.
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !value.is_empty() {
+        record.push(format!("{tag}  - {value}"));
+    }
+}
+
+fn item_to_ris(row: &CsvItem) -> Vec<String> {
+    let item = &row.item;
+    let field = |key: &str| data_field(item, key);
+    let mut record = Vec::new();
+    push_tag(&mut record, "TY", reference_type(field("itemType")));
+    push_tag(&mut record, "ID", &row.cite_key);
+    push_tag(&mut record, "TI", field("title"));
+
+    let creators = item
+        .pointer("/data/creators")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for creator in &creators {
+        let text = |key: &str| creator.get(key).and_then(Value::as_str).unwrap_or_default().trim();
+        let name = match (text("lastName"), text("firstName")) {
+            ("", _) => text("name").to_string(),
+            (last, "") => last.to_string(),
+            (last, first) => format!("{last}, {first}"),
+        };
+        push_tag(&mut record, creator_tag(text("creatorType")), &name);
+    }
+
+    let container = [
+        "publicationTitle",
+        "bookTitle",
+        "proceedingsTitle",
+        "websiteTitle",
+        "blogTitle",
+        "forumTitle",
+        "encyclopediaTitle",
+        "dictionaryTitle",
+        "programTitle",
+    ]
+    .into_iter()
+    .map(field)
+    .find(|value| !value.is_empty())
+    .unwrap_or_default();
+    push_tag(&mut record, "T2", container);
+    push_tag(&mut record, "J2", field("journalAbbreviation"));
+    push_tag(&mut record, "T3", if field("series").is_empty() { field("seriesTitle") } else { field("series") });
+
+    let date = field("date");
+    push_tag(&mut record, "PY", &extract_year(date));
+    if let Some(date) = ris_date(date) {
+        push_tag(&mut record, "DA", &date);
+    }
+
+    push_tag(&mut record, "VL", field("volume"));
+    push_tag(&mut record, "IS", field("issue"));
+    let pages = field("pages");
+    match pages.split_once(['-', '–']) {
+        Some((start, end)) => {
+            push_tag(&mut record, "SP", start.trim());
+            push_tag(&mut record, "EP", end.trim());
+        }
+        None => push_tag(&mut record, "SP", pages),
+    }
+    push_tag(&mut record, "ET", field("edition"));
+    push_tag(&mut record, "PB", if field("publisher").is_empty() { field("institution") } else { field("publisher") });
+    push_tag(&mut record, "CY", field("place"));
+    push_tag(&mut record, "SN", if field("ISBN").is_empty() { field("ISSN") } else { field("ISBN") });
+    push_tag(&mut record, "DO", field("DOI"));
+    push_tag(&mut record, "UR", field("url"));
+    push_tag(&mut record, "LA", field("language"));
+    push_tag(&mut record, "AB", field("abstractNote"));
+
+    let tags = item
+        .pointer("/data/tags")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for tag in &tags {
+        push_tag(&mut record, "KW", tag.get("tag").and_then(Value::as_str).unwrap_or_default());
+    }
+
+    record.push("ER  - ".to_string());
+    record
+}
+
+/// Renders one RIS record per item, separated by blank lines.
+pub fn items_to_ris(rows: &[CsvItem]) -> String {
+    let mut ris = rows
+        .iter()
+        .map(|row| item_to_ris(row).join("\r\n"))
+        .collect::<Vec<_>>()
+        .join("\r\n\r\n");
+    ris.push_str("\r\n");
+    ris
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ris_dates_keep_unknown_parts_empty() {
+        assert_eq!(ris_date("2020-03-15 March 15, 2020").as_deref(), Some("2020/03/15/"));
+        assert_eq!(ris_date("2020-00-00 2020").as_deref(), Some("2020///"));
+        assert_eq!(ris_date("0000-00-00 n.d."), None);
+        assert_eq!(ris_date("March 2020"), None);
+        assert_eq!(ris_date("2020"), None);
+    }
+
+    #[test]
+    fn records_are_single_line_tags_ending_in_er() {
+        let rows = [CsvItem {
+            item: json!({"data": {
+                "itemType": "journalArticle",
+                "title": "A\ntitle",
+                "creators": [
+                    {"creatorType": "author", "lastName": "Shannon", "firstName": "Claude"},
+                    {"creatorType": "author", "name": "Bell Labs"}
+                ]
+            }}),
+            cite_key: "shannon1948".to_string(),
+        }];
+        let ris = items_to_ris(&rows);
+        let lines = ris.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines[0], "TY  - JOUR");
+        assert!(lines.contains(&"ID  - shannon1948"));
+        assert!(lines.contains(&"TI  - A title"));
+        assert!(lines.contains(&"AU  - Shannon, Claude"));
+        assert!(lines.contains(&"AU  - Bell Labs"));
+        assert!(ris.ends_with("ER  - \r\n"));
+    }
+}
@@ -0,0 +1,558 @@
+//! Full-text index of the library in the app's state database, so item
+//! search doesn't LIKE-scan `itemDataValues`. Every top-level item is one
+//! FTS5 row holding its title, creators, year, abstract, annotation text and
+//! comments. A refresh first compares the library's version with the one
+//! last indexed and returns at once when nothing changed. When items were
+//! only added or edited, it re-reads just the items touched since then;
+//! after deletions it compares every item, to find the ones that went.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::extract_year;
+use crate::highlight::{self, FieldMatch};
+use crate::state_db::StateDb;
+
+/// One item as stored in the index. `stamp` summarizes the Zotero state the
+/// row was built from.
+pub struct IndexDocument {
+    pub item_key: String,
+    pub stamp: String,
+    pub title: String,
+    pub creators: String,
+    pub year: String,
+    pub abstract_note: String,
+    pub annotations: String,
+    pub comments: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexHit {
+    pub key: String,
+    pub title: String,
+    pub creators: String,
+    pub year: String,
+    /// Best-matching fragment of the indexed text.
+    pub snippet: String,
+    pub matches: Vec<FieldMatch>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexRefresh {
+    /// Items added or re-indexed.
+    pub indexed: usize,
+    /// Items dropped because they were deleted or trashed in Zotero.
+    pub removed: usize,
+    /// Items in the index afterwards.
+    pub total: usize,
+}
+
+/// How far the Zotero library has changed: the latest `clientDateModified`
+/// of any item, attachment or annotation, and how many items exist and are
+/// in the trash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryVersion {
+    pub modified: String,
+    pub items: i64,
+    pub trashed: i64,
+    /// Sum of the trashed item IDs. Restoring one item while trashing
+    /// another keeps the count but changes this.
+    pub trashed_sum: i64,
+}
+
+pub fn library_version(zotero: &Connection) -> Result<LibraryVersion, String> {
+    zotero
+        .prepare_cached(
+            r#"
+            SELECT COALESCE(MAX(clientDateModified), ''), COUNT(*),
+                   (SELECT COUNT(*) FROM deletedItems),
+                   (SELECT COALESCE(SUM(itemID), 0) FROM deletedItems)
+            FROM items
+            "#,
+        )
+        .and_then(|mut stmt| {
+            stmt.query_row([], |row| {
+                Ok(LibraryVersion {
+                    modified: row.get(0)?,
+                    items: row.get(1)?,
+                    trashed: row.get(2)?,
+                    trashed_sum: row.get(3)?,
+                })
+            })
+        })
+        .map_err(|err| format!("failed to read the Zotero library version: {err}"))
+}
+
+/// Current stamp of every top-level item: its `clientDateModified` plus the
+/// count and latest edit of its annotations, which don't touch the parent.
+/// With `modified_since`, only items that were, or whose attachments or
+/// annotations were, modified at or after that time.
+fn item_stamps(zotero: &Connection, modified_since: Option<&str>) -> Result<HashMap<String, String>, String> {
+    let mut stmt = zotero
+        .prepare_cached(
+            r#"
+            SELECT i.key,
+                   i.clientDateModified || '|' || COUNT(anno.itemID) || '|' ||
+                   COALESCE(MAX(anno.clientDateModified), '')
+            FROM items i
+            JOIN itemTypes it ON it.itemTypeID = i.itemTypeID
+            LEFT JOIN itemAttachments iatt ON iatt.parentItemID = i.itemID
+            LEFT JOIN itemAnnotations ia ON ia.parentItemID = iatt.itemID
+            LEFT JOIN items anno ON anno.itemID = ia.itemID
+                AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
+            WHERE it.typeName NOT IN ('attachment', 'note', 'annotation')
+              AND i.itemID NOT IN (SELECT itemID FROM deletedItems)
+              AND (
+                  ?1 IS NULL
+                  OR i.itemID IN (
+                      SELECT itemID FROM items WHERE clientDateModified >= ?1
+                      UNION
+                      SELECT att.parentItemID
+                      FROM itemAttachments att
+                      JOIN items changed ON changed.itemID = att.itemID
+                      WHERE changed.clientDateModified >= ?1
+                      UNION
+                      SELECT att.parentItemID
+                      FROM itemAnnotations ann
+                      JOIN itemAttachments att ON att.itemID = ann.parentItemID
+                      JOIN items changed ON changed.itemID = ann.itemID
+                      WHERE changed.clientDateModified >= ?1
+                  )
+              )
+            GROUP BY i.itemID
+            "#,
+        )
+        .map_err(|err| format!("failed to prepare search index stamp query: {err}"))?;
+    let stamps = stmt
+        .query_map(params![modified_since], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|err| format!("failed to execute search index stamp query: {err}"))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|err| format!("failed to read search index stamp row: {err}"))?;
+    Ok(stamps)
+}
+
+fn load_document(zotero: &Connection, item_key: &str, stamp: &str) -> Result<IndexDocument, String> {
+    let read_err = |err: rusqlite::Error| format!("failed to read {item_key} for the search index: {err}");
+
+    let mut fields = zotero
+        .prepare_cached(
+            r#"
+            SELECT f.fieldName, CAST(v.value AS TEXT)
+            FROM items i
+            JOIN itemData d ON d.itemID = i.itemID
+            JOIN fields f ON f.fieldID = d.fieldID
+            JOIN itemDataValues v ON v.valueID = d.valueID
+            WHERE i.key = ?1 AND f.fieldName IN ('title', 'date', 'abstractNote')
+            "#,
+        )
+        .map_err(read_err)?;
+    let fields = fields
+        .query_map(params![item_key], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(read_err)?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(read_err)?;
+
+    let mut creators = zotero
+        .prepare_cached(
+            r#"
+            SELECT TRIM(COALESCE(c.firstName, '') || ' ' || COALESCE(c.lastName, ''))
+            FROM items i
+            JOIN itemCreators ic ON ic.itemID = i.itemID
+            JOIN creators c ON c.creatorID = ic.creatorID
+            WHERE i.key = ?1
+            ORDER BY ic.orderIndex
+            "#,
+        )
+        .map_err(read_err)?;
+    let creators = creators
+        .query_map(params![item_key], |row| row.get::<_, String>(0))
+        .map_err(read_err)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_err)?;
+
+    let mut annotations = zotero
+        .prepare_cached(
+            r#"
+            SELECT COALESCE(ia.text, ''), COALESCE(ia.comment, '')
+            FROM items i
+            JOIN itemAttachments iatt ON iatt.parentItemID = i.itemID
+            JOIN itemAnnotations ia ON ia.parentItemID = iatt.itemID
+            WHERE i.key = ?1
+              AND ia.itemID NOT IN (SELECT itemID FROM deletedItems)
+            ORDER BY ia.sortIndex
+            "#,
+        )
+        .map_err(read_err)?;
+    let (texts, comments): (Vec<String>, Vec<String>) = annotations
+        .query_map(params![item_key], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(read_err)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_err)?
+        .into_iter()
+        .unzip();
+    let join = |values: Vec<String>| {
+        values
+            .into_iter()
+            .filter(|value| !value.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let field = |name: &str| fields.get(name).cloned().unwrap_or_default();
+    Ok(IndexDocument {
+        item_key: item_key.to_string(),
+        stamp: stamp.to_string(),
+        title: field("title"),
+        creators: creators.join("; "),
+        year: extract_year(&field("date")),
+        abstract_note: field("abstractNote"),
+        annotations: join(texts),
+        comments: join(comments),
+    })
+}
+
+/// What changed in Zotero since a copy of the library was last refreshed.
+pub struct LibraryChanges {
+    pub library: LibraryVersion,
+    /// `(item key, stamp)` of items added or changed since.
+    pub changed: Vec<(String, String)>,
+    /// Items gone from the library or moved to the trash since.
+    pub removed: Vec<String>,
+}
+
+/// Compares Zotero with a copy last refreshed at `previous`, holding items
+/// with the stamps in `indexed`. Returns `None` when the library version is
+/// unchanged.
+pub fn library_changes(
+    zotero: &Connection,
+    previous: Option<LibraryVersion>,
+    indexed: impl FnOnce() -> Result<HashMap<String, String>, String>,
+) -> Result<Option<LibraryChanges>, String> {
+    let library = library_version(zotero)?;
+    if previous.as_ref() == Some(&library) {
+        return Ok(None);
+    }
+    let indexed = indexed()?;
+
+    // With nothing deleted or trashed, only items touched since the last
+    // refresh can differ. Timestamps have whole seconds, so that second is
+    // read again.
+    let modified_since = previous
+        .filter(|previous| {
+            library.items >= previous.items
+                && library.trashed == previous.trashed
+                && library.trashed_sum == previous.trashed_sum
+        })
+        .map(|previous| previous.modified);
+    let current = item_stamps(zotero, modified_since.as_deref())?;
+    let removed = if modified_since.is_some() {
+        Vec::new()
+    } else {
+        indexed
+            .keys()
+            .filter(|key| !current.contains_key(*key))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let changed = current
+        .into_iter()
+        .filter(|(key, stamp)| indexed.get(key) != Some(stamp))
+        .collect::<Vec<_>>();
+    Ok(Some(LibraryChanges {
+        library,
+        changed,
+        removed,
+    }))
+}
+
+/// Brings the index in line with Zotero. The first refresh indexes the whole
+/// library; later ones only touch changed and deleted items.
+pub fn refresh(zotero: &Connection, state: &StateDb) -> Result<IndexRefresh, String> {
+    let Some(changes) = library_changes(zotero, state.search_index_library()?, || state.search_index_stamps())? else {
+        return Ok(IndexRefresh {
+            total: state.search_index_count()?,
+            ..IndexRefresh::default()
+        });
+    };
+    let documents = changes
+        .changed
+        .iter()
+        .map(|(key, stamp)| load_document(zotero, key, stamp))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    state.write_search_index(&documents, &changes.removed, &changes.library)?;
+    Ok(IndexRefresh {
+        indexed: documents.len(),
+        removed: changes.removed.len(),
+        total: state.search_index_count()?,
+    })
+}
+
+/// Most spellings a fuzzy word expands to; the closest ones are kept.
+const MAX_FUZZY_TERMS: usize = 16;
+
+/// Edit distance (insertions, deletions, substitutions and swaps of
+/// neighbouring letters) between `a` and `b`, or `None` when it exceeds
+/// `max`.
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before = Vec::<usize>::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                current[j + 1] = current[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+/// Typos tolerated in `word`: one per four letters, up to `fuzziness`, so
+/// short words stay exact.
+fn allowed_typos(word: &str, fuzziness: usize) -> usize {
+    fuzziness.min(word.chars().count() / 4)
+}
+
+/// Lowercased words of `text`, split at anything but letters and digits.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// A query word plus the indexed spellings within its typo allowance.
+struct QueryWord {
+    word: String,
+    alternatives: Vec<String>,
+}
+
+impl QueryWord {
+    fn expression(&self) -> String {
+        let exact = format!("\"{}\"*", self.word);
+        if self.alternatives.is_empty() {
+            return exact;
+        }
+        let alternatives = self
+            .alternatives
+            .iter()
+            .map(|term| format!(" OR \"{}\"", term.replace('"', "\"\"")))
+            .collect::<String>();
+        format!("({exact}{alternatives})")
+    }
+
+    fn matches_word(&self, text_word: &str) -> bool {
+        text_word.starts_with(&self.word) || self.alternatives.iter().any(|term| term == text_word)
+    }
+}
+
+/// Free text turned into FTS5 queries: every word must match, as a prefix.
+/// Operators and quotes in the input are treated as text, so no query can be
+/// a syntax error. With `fuzziness` above 0, a word also matches indexed
+/// words within that many typos, so "shanon" finds "Shannon".
+pub struct SearchQuery {
+    words: Vec<QueryWord>,
+}
+
+impl SearchQuery {
+    /// Returns `None` when the query has no words.
+    pub fn parse(state: &StateDb, query: &str, fuzziness: usize) -> Result<Option<Self>, String> {
+        let mut seen = HashSet::new();
+        let words = words(query)
+            .filter(|word| seen.insert(word.clone()))
+            .collect::<Vec<_>>();
+        if words.is_empty() {
+            return Ok(None);
+        }
+
+        let fuzzy = words
+            .iter()
+            .filter(|word| allowed_typos(word, fuzziness) > 0)
+            .map(|word| word.chars().count())
+            .collect::<Vec<_>>();
+        let vocabulary = match (fuzzy.iter().min(), fuzzy.iter().max()) {
+            (Some(shortest), Some(longest)) => {
+                state.search_index_terms(shortest.saturating_sub(fuzziness), longest + fuzziness)?
+            }
+            _ => Vec::new(),
+        };
+
+        let words = words
+            .into_iter()
+            .map(|word| {
+                let typos = allowed_typos(&word, fuzziness);
+                let chars = word.chars().collect::<Vec<_>>();
+                let mut near = vocabulary
+                    .iter()
+                    .filter(|_| typos > 0)
+                    .filter(|term| !term.starts_with(word.as_str()))
+                    .filter_map(|term| {
+                        let distance = edit_distance(&chars, &term.chars().collect::<Vec<_>>(), typos)?;
+                        Some((distance, term))
+                    })
+                    .collect::<Vec<_>>();
+                near.sort();
+                QueryWord {
+                    alternatives: near
+                        .into_iter()
+                        .take(MAX_FUZZY_TERMS)
+                        .map(|(_, term)| term.clone())
+                        .collect(),
+                    word,
+                }
+            })
+            .collect();
+        Ok(Some(Self { words }))
+    }
+
+    /// Matches the words in any column.
+    pub fn expression(&self) -> String {
+        // FTS5 only joins plain phrases implicitly; groups need an explicit AND.
+        self.words
+            .iter()
+            .map(QueryWord::expression)
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Matches the words in annotation text and comments only.
+    pub fn annotation_expression(&self) -> String {
+        format!("{{annotations comments}} : ({})", self.expression())
+    }
+
+    /// Whether `text` contains every word, checked outside the index.
+    pub fn matches(&self, text: &str) -> bool {
+        let text_words = words(text).collect::<Vec<_>>();
+        self.words
+            .iter()
+            .all(|word| text_words.iter().any(|text_word| word.matches_word(text_word)))
+    }
+
+    /// Ranges of the words in `text` that match any query word.
+    pub fn highlights(&self, text: &str) -> Vec<[usize; 2]> {
+        highlight::word_ranges(text, |text_word| {
+            self.words.iter().any(|word| word.matches_word(text_word))
+        })
+    }
+
+    /// Fills in where `hit` matched among the fields it returns.
+    pub fn highlight_hit(&self, hit: &mut IndexHit) {
+        hit.matches = highlight::field_matches(
+            [
+                ("title", hit.title.as_str()),
+                ("creators", hit.creators.as_str()),
+                ("year", hit.year.as_str()),
+                ("snippet", hit.snippet.as_str()),
+            ],
+            |text| self.highlights(text),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn distance(a: &str, b: &str, max: usize) -> Option<usize> {
+        edit_distance(&a.chars().collect::<Vec<_>>(), &b.chars().collect::<Vec<_>>(), max)
+    }
+
+    fn state_with(titles: &[&str]) -> StateDb {
+        let state = StateDb::open(Path::new(":memory:")).unwrap();
+        let documents = titles
+            .iter()
+            .enumerate()
+            .map(|(index, title)| IndexDocument {
+                item_key: format!("ITEM{index}"),
+                stamp: "1".to_string(),
+                title: title.to_string(),
+                creators: String::new(),
+                year: String::new(),
+                abstract_note: String::new(),
+                annotations: String::new(),
+                comments: String::new(),
+            })
+            .collect::<Vec<_>>();
+        let library = LibraryVersion {
+            modified: String::new(),
+            items: titles.len() as i64,
+            trashed: 0,
+            trashed_sum: 0,
+        };
+        state.write_search_index(&documents, &[], &library).unwrap();
+        state
+    }
+
+    #[test]
+    fn edit_distance_counts_each_kind_of_typo() {
+        assert_eq!(distance("shannon", "shannon", 2), Some(0));
+        assert_eq!(distance("shanon", "shannon", 2), Some(1));
+        assert_eq!(distance("shannonn", "shannon", 2), Some(1));
+        assert_eq!(distance("shennon", "shannon", 2), Some(1));
+        assert_eq!(distance("hsannon", "shannon", 2), Some(1));
+    }
+
+    #[test]
+    fn edit_distance_gives_up_past_max() {
+        assert_eq!(distance("shannon", "shaw", 2), None);
+        assert_eq!(distance("abcd", "dcba", 1), None);
+    }
+
+    #[test]
+    fn short_words_allow_no_typos() {
+        assert_eq!(allowed_typos("the", 2), 0);
+        assert_eq!(allowed_typos("word", 2), 1);
+        assert_eq!(allowed_typos("shannon", 2), 1);
+        assert_eq!(allowed_typos("information", 2), 2);
+        assert_eq!(allowed_typos("information", 0), 0);
+    }
+
+    #[test]
+    fn words_are_lowercased_and_split_at_punctuation() {
+        assert_eq!(words("Shannon's C++ (1948)").collect::<Vec<_>>(), ["shannon", "s", "c", "1948"]);
+    }
+
+    #[test]
+    fn parse_drops_operators_and_repeated_words() {
+        let state = state_with(&[]);
+        assert!(SearchQuery::parse(&state, " \"*\" ", 0).unwrap().is_none());
+        let query = SearchQuery::parse(&state, "NEAR(theory) theory OR", 0).unwrap().unwrap();
+        assert_eq!(query.expression(), "\"near\"* AND \"theory\"* AND \"or\"*");
+    }
+
+    #[test]
+    fn exact_queries_match_word_prefixes() {
+        let state = state_with(&[]);
+        let query = SearchQuery::parse(&state, "inform theo", 0).unwrap().unwrap();
+        assert!(query.matches("A mathematical theory of information"));
+        assert!(!query.matches("A mathematical theory of communication"));
+        assert_eq!(query.highlights("Information theory"), vec![[0, 11], [12, 18]]);
+    }
+
+    #[test]
+    fn fuzzy_queries_add_indexed_spellings() {
+        let state = state_with(&["A mathematical theory of communication", "Shannon entropy"]);
+        let query = SearchQuery::parse(&state, "shanon", 1).unwrap().unwrap();
+        assert_eq!(query.expression(), "(\"shanon\"* OR \"shannon\")");
+        assert!(query.matches("Shannon entropy"));
+        assert!(!query.matches("Entropy"));
+    }
+
+    #[test]
+    fn annotation_expression_limits_columns() {
+        let state = state_with(&[]);
+        let query = SearchQuery::parse(&state, "entropy", 0).unwrap().unwrap();
+        assert_eq!(query.annotation_expression(), "{annotations comments} : (\"entropy\"*)");
+    }
+}
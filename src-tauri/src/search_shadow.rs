@@ -0,0 +1,59 @@
+//! Lowercased copies of every item's title, creators and year in the app's
+//! state database, for the substring search used when the full-text index
+//! is turned off. Scanning one small table, read in title order through its
+//! index, replaces joining `itemDataValues` with a leading-wildcard `LIKE`
+//! on every keystroke. The table is refreshed like the full-text index,
+//! from the library version, so unchanged libraries cost one query.
+
+use rusqlite::Connection;
+
+use crate::search_index::library_changes;
+use crate::state_db::StateDb;
+use crate::{highlight_substring_matches, item_summaries, SqliteItemSummary};
+
+/// An item's searchable fields, lowercased. `stamp` is the Zotero state the
+/// row was built from, as in the full-text index.
+pub struct ShadowRow {
+    pub item_key: String,
+    pub stamp: String,
+    pub title_lower: String,
+    pub creators_lower: String,
+    pub year: String,
+}
+
+/// Brings the shadow table in line with Zotero.
+pub fn refresh(zotero: &Connection, state: &StateDb) -> Result<(), String> {
+    let Some(changes) = library_changes(zotero, state.item_shadow_library()?, || state.item_shadow_stamps())? else {
+        return Ok(());
+    };
+    let keys = changes.changed.iter().map(|(item_key, _)| item_key.clone()).collect::<Vec<_>>();
+    let mut summaries = item_summaries(zotero, &keys)?;
+    let rows = changes
+        .changed
+        .into_iter()
+        .filter_map(|(item_key, stamp)| {
+            let summary = summaries.remove(&item_key)?;
+            Some(ShadowRow {
+                title_lower: summary.title.to_lowercase(),
+                creators_lower: summary.creators.to_lowercase(),
+                year: summary.year,
+                item_key,
+                stamp,
+            })
+        })
+        .collect::<Vec<_>>();
+    state.write_item_shadow(&rows, &changes.removed, &changes.library)
+}
+
+/// Up to `limit` items whose title, creators or year contain `query`,
+/// ignoring case, by title; like [`crate::search_items`], from the shadow
+/// table.
+pub fn search(zotero: &Connection, state: &StateDb, query: &str, limit: usize) -> Result<Vec<SqliteItemSummary>, String> {
+    refresh(zotero, state)?;
+    let term = query.trim();
+    let keys = state.search_item_shadow(&term.to_lowercase(), limit)?;
+    let mut summaries = item_summaries(zotero, &keys)?;
+    let mut items = keys.iter().filter_map(|item_key| summaries.remove(item_key)).collect::<Vec<_>>();
+    highlight_substring_matches(&mut items, term);
+    Ok(items)
+}
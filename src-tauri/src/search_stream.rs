@@ -0,0 +1,102 @@
+//! Search-as-you-type over the full-text index. Results arrive in pages as
+//! `search-results` events tagged with the caller's search ID, and starting
+//! a search cancels the one still running, so a fast typist never waits on
+//! results for a query they have already changed. Cancelling interrupts the
+//! search's database connections, so an index refresh or a ranking query
+//! already under way stops too.
+
+use rusqlite::InterruptHandle;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use crate::search_index::IndexHit;
+
+pub const SEARCH_RESULTS_EVENT: &str = "search-results";
+
+/// Hits per event; the first page is what the picker shows while typing.
+pub const PAGE_SIZE: usize = 25;
+
+struct Running {
+    search_id: String,
+    /// Connections the search is using, interrupted when it is cancelled.
+    interrupts: Vec<InterruptHandle>,
+}
+
+/// The search allowed to keep running, if any.
+static LATEST: Mutex<Option<Running>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResults<'a> {
+    search_id: &'a str,
+    /// This page of hits, to be appended to the earlier pages.
+    items: &'a [IndexHit],
+    /// Whether this is the last page.
+    done: bool,
+}
+
+/// Replaces the running search with `next`, interrupting its queries.
+fn replace(next: Option<Running>) {
+    if let Ok(mut latest) = LATEST.lock() {
+        if let Some(previous) = std::mem::replace(&mut *latest, next) {
+            previous.interrupts.iter().for_each(InterruptHandle::interrupt);
+        }
+    }
+}
+
+/// Makes `search_id` the latest search, which cancels any other.
+pub fn begin(search_id: &str) {
+    replace(Some(Running {
+        search_id: search_id.to_string(),
+        interrupts: Vec::new(),
+    }));
+}
+
+/// Cancels the running search without starting another.
+pub fn cancel() {
+    replace(None);
+}
+
+/// Whether `search_id` is still the latest search and should continue.
+pub fn is_current(search_id: &str) -> bool {
+    LATEST
+        .lock()
+        .map(|latest| latest.as_ref().is_some_and(|running| running.search_id == search_id))
+        .unwrap_or(false)
+}
+
+/// Lets cancelling `search_id` interrupt the connections behind
+/// `interrupts` until the returned guard is dropped. Drop the guard before
+/// the connections go back to the pool, so a later cancel cannot interrupt
+/// another caller's query. A search that is no longer current is
+/// interrupted at once.
+pub fn interrupt_on_cancel(search_id: &str, interrupts: Vec<InterruptHandle>) -> Registration {
+    let mut latest = LATEST.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match latest.as_mut().filter(|running| running.search_id == search_id) {
+        Some(running) => running.interrupts = interrupts,
+        None => interrupts.iter().for_each(InterruptHandle::interrupt),
+    }
+    Registration {
+        search_id: search_id.to_string(),
+    }
+}
+
+/// Unregisters a search's connections when dropped.
+pub struct Registration {
+    search_id: String,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut latest) = LATEST.lock() {
+            if let Some(running) = latest.as_mut().filter(|running| running.search_id == self.search_id) {
+                running.interrupts.clear();
+            }
+        }
+    }
+}
+
+pub fn emit(app: &AppHandle, search_id: &str, items: &[IndexHit], done: bool) {
+    let _ = app.emit(SEARCH_RESULTS_EVENT, SearchResults { search_id, items, done });
+}
@@ -0,0 +1,66 @@
+//! Settings bundles: the whole configuration (templates, image settings,
+//! collection overrides, CSV columns, sync options, ...) in one JSON file
+//! for setting up ZotNotes the same way on another machine. Secrets are
+//! left out on export and kept from the current settings on import.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::report::now_seconds;
+use crate::AppSettings;
+
+const BUNDLE_FORMAT: &str = "zotnotes-settings";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingsBundle {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    settings: Value,
+}
+
+/// Serializes `settings` as a bundle, without the Zotero API key, the
+/// Joplin token, the API server token and the WebDAV password.
+pub fn to_bundle(settings: &AppSettings) -> Result<String, String> {
+    let mut settings = settings.clone();
+    settings.zotero_api_key = String::new();
+    settings.joplin.token = String::new();
+    settings.api_server.token = String::new();
+    settings.webdav.password = String::new();
+
+    let bundle = SettingsBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: now_seconds(),
+        settings: serde_json::to_value(&settings)
+            .map_err(|err| format!("failed to serialize settings: {err}"))?,
+    };
+    serde_json::to_string_pretty(&bundle).map_err(|err| format!("failed to serialize settings bundle: {err}"))
+}
+
+/// Reads the settings from a bundle. Fields missing from older bundles get
+/// their defaults; the API key, tokens and WebDAV password are taken from
+/// `current`.
+pub fn from_bundle(raw: &str, current: &AppSettings) -> Result<AppSettings, String> {
+    let bundle = serde_json::from_str::<SettingsBundle>(raw)
+        .map_err(|err| format!("failed to parse settings bundle: {err}"))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err("This file is not a ZotNotes settings bundle.".to_string());
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Settings bundle version {} is newer than this version of ZotNotes supports.",
+            bundle.version
+        ));
+    }
+
+    let mut settings = serde_json::from_value::<AppSettings>(bundle.settings)
+        .map_err(|err| format!("failed to read settings from bundle: {err}"))?;
+    settings.zotero_api_key = current.zotero_api_key.clone();
+    settings.joplin.token = current.joplin.token.clone();
+    settings.api_server.token = current.api_server.token.clone();
+    settings.webdav.password = current.webdav.password.clone();
+    Ok(settings)
+}
@@ -0,0 +1,195 @@
+//! Restores the defaults for groups of settings, e.g. only the templates,
+//! without touching folders, the Zotero data directory or the Zotero,
+//! Joplin and API server connections. The WebDAV login has a section of
+//! its own, so it is only reset when asked for.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::report::now_seconds;
+use crate::AppSettings;
+
+/// Section names accepted by `reset_settings`, in the order the settings
+/// dialog lists them.
+pub const SECTIONS: [&str; 8] = ["templates", "images", "export", "sync", "search", "orphans", "webdav", "logging"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsReset {
+    pub settings: AppSettings,
+    /// Copy of the settings file from before the reset, if there was one.
+    pub backup: Option<String>,
+}
+
+/// Resets every setting in `sections` to its default.
+pub fn reset_sections(settings: &mut AppSettings, sections: &[String]) -> Result<(), String> {
+    if sections.is_empty() {
+        return Err("Choose at least one section to reset.".to_string());
+    }
+    // Check every name first, so a bad one leaves the settings untouched.
+    if let Some(unknown) = sections.iter().find(|section| !SECTIONS.contains(&section.as_str())) {
+        return Err(format!(
+            "Unknown settings section {unknown:?}; use one of {}.",
+            SECTIONS.join(", ")
+        ));
+    }
+
+    let defaults = AppSettings::default();
+    for section in sections {
+        match section.as_str() {
+            "templates" => {
+                settings.template_settings = defaults.template_settings.clone();
+                settings.asset_path_template = defaults.asset_path_template.clone();
+                settings.collection_overrides = defaults.collection_overrides.clone();
+            }
+            "images" => {
+                settings.image_settings = defaults.image_settings.clone();
+                settings.image_link_style = defaults.image_link_style;
+                settings.ocr = defaults.ocr.clone();
+            }
+            "export" => {
+                settings.changelog_note = defaults.changelog_note.clone();
+                settings.csv_columns = defaults.csv_columns.clone();
+                settings.editor_command = defaults.editor_command.clone();
+                settings.exclude_tag = defaults.exclude_tag.clone();
+                settings.git = defaults.git.clone();
+                settings.obsidian = defaults.obsidian.clone();
+                settings.output_target = defaults.output_target;
+            }
+            "sync" => {
+                settings.auto_sync = defaults.auto_sync.clone();
+                settings.conflict_strategy = defaults.conflict_strategy;
+                settings.deleted_annotations = defaults.deleted_annotations;
+            }
+            "search" => {
+                settings.full_text_search = defaults.full_text_search;
+                settings.search_fuzziness = defaults.search_fuzziness;
+                settings.search_limit = defaults.search_limit;
+            }
+            "orphans" => {
+                settings.orphan_archive_dir = defaults.orphan_archive_dir.clone();
+                settings.orphan_tag = defaults.orphan_tag.clone();
+            }
+            "webdav" => settings.webdav = defaults.webdav.clone(),
+            "logging" => settings.log_level = defaults.log_level,
+            other => unreachable!("unchecked settings section {other:?}"),
+        }
+    }
+    Ok(())
+}
+
+/// Copies the settings file to `<file>.<unix time>.bak` next to it.
+pub fn backup(path: &Path) -> Result<Option<PathBuf>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{}.bak", now_seconds()));
+    let backup = PathBuf::from(backup);
+    std::fs::copy(path, &backup)
+        .map_err(|err| format!("failed to back up settings to {}: {err}", backup.display()))?;
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// A setting from each section, as `(section, JSON key)`.
+    const SAMPLES: [(&str, &str); 9] = [
+        ("templates", "templateSettings"),
+        ("templates", "assetPathTemplate"),
+        ("images", "imageSettings"),
+        ("export", "changelogNote"),
+        ("sync", "autoSync"),
+        ("search", "searchLimit"),
+        ("orphans", "orphanTag"),
+        ("webdav", "webdav"),
+        ("logging", "logLevel"),
+    ];
+
+    fn customized() -> AppSettings {
+        serde_json::from_value(json!({
+            "markdownDir": "/vault",
+            "attachmentBaseDir": "/vault/assets",
+            "zoteroDataDir": "/zotero",
+            "zoteroApiKey": "secret",
+            "templateSettings": {"tableOfContents": true},
+            "assetPathTemplate": "img/{{citekey}}/",
+            "imageSettings": {"renderDpi": 300},
+            "changelogNote": "Log.md",
+            "autoSync": {"enabled": true},
+            "searchLimit": 10,
+            "orphanTag": "gone",
+            "webdav": {"url": "https://dav.example.org", "password": "pw"},
+            "logLevel": "debug"
+        }))
+        .unwrap()
+    }
+
+    fn value(settings: &AppSettings, key: &str) -> Value {
+        serde_json::to_value(settings).unwrap()[key].clone()
+    }
+
+    fn assert_paths_and_keys_kept(settings: &AppSettings) {
+        assert_eq!(settings.markdown_dir, "/vault");
+        assert_eq!(settings.attachment_base_dir, "/vault/assets");
+        assert_eq!(settings.zotero_data_dir, "/zotero");
+        assert_eq!(settings.zotero_api_key, "secret");
+    }
+
+    #[test]
+    fn each_section_resets_only_its_own_settings() {
+        let defaults = AppSettings::default();
+        let custom = customized();
+        for section in SECTIONS {
+            let mut settings = customized();
+            reset_sections(&mut settings, &[section.to_string()]).unwrap();
+            assert_paths_and_keys_kept(&settings);
+            for (owner, key) in SAMPLES {
+                let expected = if owner == section { &defaults } else { &custom };
+                assert_eq!(value(&settings, key), value(expected, key), "{key} after resetting {section}");
+            }
+        }
+    }
+
+    #[test]
+    fn samples_differ_from_the_defaults() {
+        let (defaults, custom) = (AppSettings::default(), customized());
+        for (_, key) in SAMPLES {
+            assert_ne!(value(&custom, key), value(&defaults, key), "{key}");
+        }
+    }
+
+    #[test]
+    fn unknown_sections_change_nothing() {
+        let mut settings = customized();
+        let sections = ["templates".to_string(), "bogus".to_string()];
+        let err = reset_sections(&mut settings, &sections).unwrap_err();
+        assert!(err.contains("\"bogus\""));
+        assert_eq!(serde_json::to_value(&settings).unwrap(), serde_json::to_value(customized()).unwrap());
+        assert!(reset_sections(&mut settings, &[]).is_err());
+    }
+
+    #[test]
+    fn backups_are_timestamped_copies() {
+        let path = std::env::temp_dir().join(format!("zotnotes-settings-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(backup(&path).unwrap(), None);
+
+        std::fs::write(&path, "{}").unwrap();
+        let copy = backup(&path).unwrap().unwrap();
+        let name = copy.file_name().unwrap().to_string_lossy().to_string();
+        let stamp = name
+            .strip_prefix(&format!("{}.", path.file_name().unwrap().to_string_lossy()))
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .unwrap();
+        assert!(!stamp.is_empty() && stamp.chars().all(|ch| ch.is_ascii_digit()));
+        assert_eq!(copy.parent(), path.parent());
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "{}");
+        std::fs::remove_file(copy).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1210 @@
+//! Application-local SQLite database recording what was exported for each
+//! item: the Zotero version it was rendered from, hashes of the written note
+//! and images, and timestamps. Incremental sync, conflict detection and
+//! orphan cleanup are built on these records.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::history::{SyncRun, SyncTrigger};
+use crate::jobs::{Job, JobStatus, JobTask};
+use crate::report::{now_seconds, parse_zotero_timestamp, AnnotationStamp};
+use crate::search_index::{IndexDocument, IndexHit, LibraryVersion};
+use crate::search_shadow::ShadowRow;
+
+/// Schema migrations, applied in order; `PRAGMA user_version` records how
+/// many have run.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE exported_items (
+        item_key TEXT PRIMARY KEY,
+        cite_key TEXT NOT NULL,
+        markdown_path TEXT NOT NULL,
+        item_version INTEGER NOT NULL DEFAULT 0,
+        content_hash TEXT NOT NULL,
+        annotations_modified INTEGER NOT NULL DEFAULT 0,
+        annotation_count INTEGER NOT NULL DEFAULT 0,
+        first_exported_at INTEGER NOT NULL,
+        last_exported_at INTEGER NOT NULL
+    );
+    CREATE TABLE exported_images (
+        item_key TEXT NOT NULL,
+        annotation_key TEXT NOT NULL,
+        path TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        exported_at INTEGER NOT NULL,
+        PRIMARY KEY (item_key, annotation_key)
+    );
+    "#,
+    r#"
+    CREATE TABLE jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        task TEXT NOT NULL,
+        status TEXT NOT NULL,
+        completed TEXT NOT NULL DEFAULT '[]',
+        failures TEXT NOT NULL DEFAULT '[]',
+        error TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE sync_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        trigger TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER NOT NULL,
+        created INTEGER NOT NULL DEFAULT 0,
+        updated INTEGER NOT NULL DEFAULT 0,
+        unchanged INTEGER NOT NULL DEFAULT 0,
+        conflicts INTEGER NOT NULL DEFAULT 0,
+        failures TEXT NOT NULL DEFAULT '[]',
+        error TEXT
+    );
+    "#,
+    r#"
+    CREATE VIRTUAL TABLE search_index USING fts5(
+        item_key UNINDEXED,
+        title,
+        creators,
+        year,
+        abstract_note,
+        annotations,
+        comments,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TABLE search_index_items (
+        item_key TEXT PRIMARY KEY,
+        stamp TEXT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE VIRTUAL TABLE search_index_vocab USING fts5vocab(search_index, 'row');
+    "#,
+    r#"
+    CREATE TABLE item_activity (
+        item_key TEXT PRIMARY KEY,
+        opened_at INTEGER,
+        pinned_at INTEGER
+    );
+    "#,
+    r#"
+    CREATE TABLE imported_notes (
+        item_key TEXT PRIMARY KEY,
+        markdown_path TEXT NOT NULL,
+        imported_at INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE search_index_library (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        modified TEXT NOT NULL,
+        items INTEGER NOT NULL,
+        trashed INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE item_shadow (
+        item_key TEXT PRIMARY KEY,
+        stamp TEXT NOT NULL,
+        title_lower TEXT NOT NULL,
+        creators_lower TEXT NOT NULL,
+        year TEXT NOT NULL
+    );
+    CREATE INDEX item_shadow_title ON item_shadow (title_lower);
+    CREATE TABLE item_shadow_library (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        modified TEXT NOT NULL,
+        items INTEGER NOT NULL,
+        trashed INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE citation_keys (
+        item_key TEXT PRIMARY KEY,
+        citation_key TEXT NOT NULL
+    );
+    CREATE TABLE citation_keys_source (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        fingerprint TEXT NOT NULL
+    );
+    "#,
+    // Index rows are now found by rowid; the index is rebuilt on the next
+    // search rather than matching the old rows up by key.
+    r#"
+    DELETE FROM search_index;
+    DELETE FROM search_index_items;
+    DELETE FROM search_index_library;
+    ALTER TABLE search_index_items ADD COLUMN doc_rowid INTEGER NOT NULL DEFAULT 0;
+    "#,
+    // Versions recorded without the trash checksum no longer match, so the
+    // next refresh checks every item once.
+    r#"
+    ALTER TABLE search_index_library ADD COLUMN trashed_sum INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE item_shadow_library ADD COLUMN trashed_sum INTEGER NOT NULL DEFAULT 0;
+    "#,
+];
+
+/// What was exported for an item the last time it was written.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemExportRecord {
+    pub item_key: String,
+    pub cite_key: String,
+    pub markdown_path: String,
+    pub item_version: i64,
+    pub content_hash: String,
+    /// Latest annotation `dateModified` (unix seconds) included in the note.
+    pub annotations_modified: i64,
+    pub annotation_count: usize,
+    pub first_exported_at: i64,
+    pub last_exported_at: i64,
+}
+
+/// Annotation summary for an exported item as currently stored in Zotero.
+pub struct AnnotationActivity {
+    pub latest_modified: i64,
+    pub count: usize,
+}
+
+impl ItemExportRecord {
+    /// Whether annotations were added, edited or removed since the export.
+    pub fn annotations_changed(&self, activity: &AnnotationActivity) -> bool {
+        activity.latest_modified > self.annotations_modified || activity.count != self.annotation_count
+    }
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub fn latest_modified(annotations: &[AnnotationStamp]) -> i64 {
+    annotations
+        .iter()
+        .filter_map(|annotation| parse_zotero_timestamp(&annotation.date_modified))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Records `library` as the version a copy of the library was refreshed
+/// with, in the single-row `table`.
+fn write_library_version(conn: &Connection, table: &str, library: &LibraryVersion) -> rusqlite::Result<usize> {
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO {table} (id, modified, items, trashed, trashed_sum) VALUES (1, ?1, ?2, ?3, ?4)"),
+        params![library.modified, library.items, library.trashed, library.trashed_sum],
+    )
+}
+
+pub struct StateDb {
+    conn: Connection,
+}
+
+impl StateDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|err| format!("failed to open state database {}: {err}", path.display()))?;
+        let db = Self { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        let applied: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|err| format!("failed to read state database version: {err}"))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+            self.conn
+                .execute_batch(migration)
+                .map_err(|err| format!("failed to apply state database migration {}: {err}", index + 1))?;
+            self.conn
+                .pragma_update(None, "user_version", index + 1)
+                .map_err(|err| format!("failed to update state database version: {err}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn record_item(
+        &self,
+        item_key: &str,
+        cite_key: &str,
+        markdown_path: &str,
+        item_version: i64,
+        content_hash: &str,
+        annotations: &[AnnotationStamp],
+    ) -> Result<(), String> {
+        let now = now_seconds();
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO exported_items (
+                    item_key, cite_key, markdown_path, item_version, content_hash,
+                    annotations_modified, annotation_count, first_exported_at, last_exported_at
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+                ON CONFLICT(item_key) DO UPDATE SET
+                    cite_key = excluded.cite_key,
+                    markdown_path = excluded.markdown_path,
+                    item_version = excluded.item_version,
+                    content_hash = excluded.content_hash,
+                    annotations_modified = excluded.annotations_modified,
+                    annotation_count = excluded.annotation_count,
+                    last_exported_at = excluded.last_exported_at
+                "#,
+                params![
+                    item_key,
+                    cite_key,
+                    markdown_path,
+                    item_version,
+                    content_hash,
+                    latest_modified(annotations),
+                    annotations.len() as i64,
+                    now,
+                ],
+            )
+            .map_err(|err| format!("failed to record export of {item_key}: {err}"))?;
+        Ok(())
+    }
+
+    pub fn record_image(
+        &self,
+        item_key: &str,
+        annotation_key: &str,
+        path: &str,
+        content_hash: &str,
+    ) -> Result<(), String> {
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO exported_images (item_key, annotation_key, path, content_hash, exported_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT(item_key, annotation_key) DO UPDATE SET
+                    path = excluded.path,
+                    content_hash = excluded.content_hash,
+                    exported_at = excluded.exported_at
+                "#,
+                params![item_key, annotation_key, path, content_hash, now_seconds()],
+            )
+            .map_err(|err| format!("failed to record image for {annotation_key}: {err}"))?;
+        Ok(())
+    }
+
+    /// Forgets an item's export, e.g. after its note was archived.
+    pub fn remove_item(&self, item_key: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM exported_items WHERE item_key = ?1", params![item_key])
+            .and_then(|_| {
+                self.conn
+                    .execute("DELETE FROM exported_images WHERE item_key = ?1", params![item_key])
+            })
+            .map_err(|err| format!("failed to remove export record for {item_key}: {err}"))?;
+        Ok(())
+    }
+
+    fn read_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<ItemExportRecord> {
+        Ok(ItemExportRecord {
+            item_key: row.get(0)?,
+            cite_key: row.get(1)?,
+            markdown_path: row.get(2)?,
+            item_version: row.get(3)?,
+            content_hash: row.get(4)?,
+            annotations_modified: row.get(5)?,
+            annotation_count: usize::try_from(row.get::<_, i64>(6)?).unwrap_or(0),
+            first_exported_at: row.get(7)?,
+            last_exported_at: row.get(8)?,
+        })
+    }
+
+    pub fn item(&self, item_key: &str) -> Result<Option<ItemExportRecord>, String> {
+        self.conn
+            .query_row(
+                r#"
+                SELECT item_key, cite_key, markdown_path, item_version, content_hash,
+                       annotations_modified, annotation_count, first_exported_at, last_exported_at
+                FROM exported_items
+                WHERE item_key = ?1
+                "#,
+                params![item_key],
+                Self::read_record,
+            )
+            .optional()
+            .map_err(|err| format!("failed to read export record for {item_key}: {err}"))
+    }
+
+    /// Keeps exports of `item_key` going to a note that existed before
+    /// ZotNotes, wherever the file name pattern would put it.
+    pub fn record_imported_note(&self, item_key: &str, markdown_path: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO imported_notes (item_key, markdown_path, imported_at) VALUES (?1, ?2, ?3)
+                ON CONFLICT(item_key) DO UPDATE SET
+                    markdown_path = excluded.markdown_path,
+                    imported_at = excluded.imported_at
+                "#,
+                params![item_key, markdown_path, now_seconds()],
+            )
+            .map_err(|err| format!("failed to record imported note for {item_key}: {err}"))?;
+        Ok(())
+    }
+
+    /// The imported note that exports of `item_key` update, if any.
+    pub fn imported_note_path(&self, item_key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row(
+                "SELECT markdown_path FROM imported_notes WHERE item_key = ?1",
+                params![item_key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| format!("failed to read imported note for {item_key}: {err}"))
+    }
+
+    pub fn items(&self) -> Result<Vec<ItemExportRecord>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT item_key, cite_key, markdown_path, item_version, content_hash,
+                       annotations_modified, annotation_count, first_exported_at, last_exported_at
+                FROM exported_items
+                ORDER BY item_key ASC
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare export record query: {err}"))?;
+
+        let records = stmt
+            .query_map([], Self::read_record)
+            .map_err(|err| format!("failed to execute export record query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read export record row: {err}"))?;
+        Ok(records)
+    }
+
+    fn read_job(row: &rusqlite::Row<'_>) -> rusqlite::Result<Job> {
+        fn json<T: serde::de::DeserializeOwned>(row: &rusqlite::Row<'_>, index: usize) -> rusqlite::Result<T> {
+            serde_json::from_str(&row.get::<_, String>(index)?).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(err))
+            })
+        }
+
+        Ok(Job {
+            id: row.get(0)?,
+            task: json(row, 1)?,
+            status: JobStatus::parse(&row.get::<_, String>(2)?),
+            completed: json(row, 3)?,
+            failures: json(row, 4)?,
+            error: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
+
+    pub fn insert_job(&self, task: &JobTask) -> Result<Job, String> {
+        let task_json =
+            serde_json::to_string(task).map_err(|err| format!("failed to serialize job: {err}"))?;
+        let now = now_seconds();
+        self.conn
+            .execute(
+                "INSERT INTO jobs (task, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![task_json, JobStatus::Queued.as_str(), now],
+            )
+            .map_err(|err| format!("failed to queue job: {err}"))?;
+
+        let id = self.conn.last_insert_rowid();
+        self.job(id)?
+            .ok_or_else(|| format!("job {id} disappeared after it was queued."))
+    }
+
+    pub fn job(&self, id: i64) -> Result<Option<Job>, String> {
+        self.conn
+            .query_row(
+                r#"
+                SELECT id, task, status, completed, failures, error, created_at, updated_at
+                FROM jobs
+                WHERE id = ?1
+                "#,
+                params![id],
+                Self::read_job,
+            )
+            .optional()
+            .map_err(|err| format!("failed to read job {id}: {err}"))
+    }
+
+    /// Most recent jobs first.
+    pub fn jobs(&self, limit: usize) -> Result<Vec<Job>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT id, task, status, completed, failures, error, created_at, updated_at
+                FROM jobs
+                ORDER BY id DESC
+                LIMIT ?1
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare job query: {err}"))?;
+
+        let jobs = stmt
+            .query_map(params![limit as i64], Self::read_job)
+            .map_err(|err| format!("failed to execute job query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read job row: {err}"))?;
+        Ok(jobs)
+    }
+
+    pub fn next_queued_job(&self) -> Result<Option<Job>, String> {
+        self.conn
+            .query_row(
+                r#"
+                SELECT id, task, status, completed, failures, error, created_at, updated_at
+                FROM jobs
+                WHERE status = 'queued'
+                ORDER BY id ASC
+                LIMIT 1
+                "#,
+                [],
+                Self::read_job,
+            )
+            .optional()
+            .map_err(|err| format!("failed to read queued job: {err}"))
+    }
+
+    pub fn job_status(&self, id: i64) -> Result<Option<JobStatus>, String> {
+        self.conn
+            .query_row("SELECT status FROM jobs WHERE id = ?1", params![id], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+            .map(|status| status.as_deref().map(JobStatus::parse))
+            .map_err(|err| format!("failed to read status of job {id}: {err}"))
+    }
+
+    /// Saves the task and per-item results without touching the status, so
+    /// a cancellation made meanwhile is not overwritten.
+    pub fn save_job_progress(&self, job: &Job) -> Result<(), String> {
+        let task = serde_json::to_string(&job.task).map_err(|err| format!("failed to serialize job: {err}"))?;
+        let completed =
+            serde_json::to_string(&job.completed).map_err(|err| format!("failed to serialize job: {err}"))?;
+        let failures =
+            serde_json::to_string(&job.failures).map_err(|err| format!("failed to serialize job: {err}"))?;
+        self.conn
+            .execute(
+                "UPDATE jobs SET task = ?2, completed = ?3, failures = ?4, updated_at = ?5 WHERE id = ?1",
+                params![job.id, task, completed, failures, now_seconds()],
+            )
+            .map_err(|err| format!("failed to save progress of job {}: {err}", job.id))?;
+        Ok(())
+    }
+
+    fn update_job_status(
+        &self,
+        id: i64,
+        status: JobStatus,
+        error: Option<&str>,
+        from: &[JobStatus],
+        reset_failures: bool,
+    ) -> Result<bool, String> {
+        let from = from
+            .iter()
+            .map(|status| format!("'{}'", status.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let failures = if reset_failures { ", failures = '[]'" } else { "" };
+        let updated = self
+            .conn
+            .execute(
+                &format!(
+                    "UPDATE jobs SET status = ?2, error = ?3, updated_at = ?4{failures} \
+                     WHERE id = ?1 AND status IN ({from})"
+                ),
+                params![id, status.as_str(), error, now_seconds()],
+            )
+            .map_err(|err| format!("failed to update job {id}: {err}"))?;
+        Ok(updated > 0)
+    }
+
+    pub fn start_job(&self, id: i64) -> Result<bool, String> {
+        self.update_job_status(id, JobStatus::Running, None, &[JobStatus::Queued], false)
+    }
+
+    /// Records how a running job ended; a job cancelled meanwhile stays
+    /// cancelled.
+    pub fn finish_job(&self, id: i64, status: JobStatus, error: Option<&str>) -> Result<bool, String> {
+        self.update_job_status(id, status, error, &[JobStatus::Running], false)
+    }
+
+    pub fn cancel_job(&self, id: i64) -> Result<bool, String> {
+        self.update_job_status(
+            id,
+            JobStatus::Cancelled,
+            None,
+            &[JobStatus::Queued, JobStatus::Running],
+            false,
+        )
+    }
+
+    /// Queues a failed or cancelled job again. Items it already exported are
+    /// kept and skipped; failed items are tried again.
+    pub fn retry_job(&self, id: i64) -> Result<bool, String> {
+        self.update_job_status(
+            id,
+            JobStatus::Queued,
+            None,
+            &[JobStatus::Failed, JobStatus::Cancelled],
+            true,
+        )
+    }
+
+    /// Jobs still marked running were interrupted by the app exiting.
+    pub fn requeue_interrupted_jobs(&self) -> Result<(), String> {
+        self.conn
+            .execute(
+                "UPDATE jobs SET status = 'queued', updated_at = ?1 WHERE status = 'running'",
+                params![now_seconds()],
+            )
+            .map_err(|err| format!("failed to requeue interrupted jobs: {err}"))?;
+        Ok(())
+    }
+
+    pub fn record_sync_run(&self, run: &SyncRun) -> Result<(), String> {
+        let failures = serde_json::to_string(&run.failures)
+            .map_err(|err| format!("failed to serialize sync failures: {err}"))?;
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO sync_runs (
+                    trigger, started_at, finished_at, created, updated, unchanged, conflicts, failures, error
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                "#,
+                params![
+                    run.trigger.as_str(),
+                    run.started_at,
+                    run.finished_at,
+                    run.created as i64,
+                    run.updated as i64,
+                    run.unchanged as i64,
+                    run.conflicts as i64,
+                    failures,
+                    run.error,
+                ],
+            )
+            .map_err(|err| format!("failed to record sync run: {err}"))?;
+        Ok(())
+    }
+
+    /// Most recent runs first.
+    pub fn sync_runs(&self, limit: usize) -> Result<Vec<SyncRun>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT id, trigger, started_at, finished_at, created, updated, unchanged, conflicts,
+                       failures, error
+                FROM sync_runs
+                ORDER BY id DESC
+                LIMIT ?1
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare sync history query: {err}"))?;
+
+        let count = |row: &rusqlite::Row<'_>, index: usize| -> rusqlite::Result<usize> {
+            Ok(usize::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+        };
+        let runs = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(SyncRun {
+                    id: row.get(0)?,
+                    trigger: SyncTrigger::parse(&row.get::<_, String>(1)?),
+                    started_at: row.get(2)?,
+                    finished_at: row.get(3)?,
+                    created: count(row, 4)?,
+                    updated: count(row, 5)?,
+                    unchanged: count(row, 6)?,
+                    conflicts: count(row, 7)?,
+                    failures: serde_json::from_str(&row.get::<_, String>(8)?).unwrap_or_default(),
+                    error: row.get(9)?,
+                })
+            })
+            .map_err(|err| format!("failed to execute sync history query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read sync history row: {err}"))?;
+        Ok(runs)
+    }
+
+    /// Stamps of the indexed items, keyed by item key.
+    pub fn search_index_stamps(&self) -> Result<HashMap<String, String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT item_key, stamp FROM search_index_items")
+            .map_err(|err| format!("failed to prepare search index query: {err}"))?;
+        let stamps = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|err| format!("failed to execute search index query: {err}"))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|err| format!("failed to read search index row: {err}"))?;
+        Ok(stamps)
+    }
+
+    /// Items in the search index.
+    pub fn search_index_count(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM search_index_items", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(|err| format!("failed to count search index rows: {err}"))
+    }
+
+    /// The library version the index was last brought up to date with;
+    /// `None` before the first refresh.
+    pub fn search_index_library(&self) -> Result<Option<LibraryVersion>, String> {
+        self.library_version("search_index_library")
+    }
+
+    fn library_version(&self, table: &str) -> Result<Option<LibraryVersion>, String> {
+        self.conn
+            .query_row(
+                &format!("SELECT modified, items, trashed, trashed_sum FROM {table} WHERE id = 1"),
+                [],
+                |row| {
+                    Ok(LibraryVersion {
+                        modified: row.get(0)?,
+                        items: row.get(1)?,
+                        trashed: row.get(2)?,
+                        trashed_sum: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|err| format!("failed to read the library version in {table}: {err}"))
+    }
+
+    /// Replaces the rows of `documents`, drops `removed` and records
+    /// `library` as indexed, in one transaction so a failed refresh leaves
+    /// the previous index intact.
+    pub fn write_search_index(
+        &self,
+        documents: &[IndexDocument],
+        removed: &[String],
+        library: &LibraryVersion,
+    ) -> Result<(), String> {
+        let write_err = |err: rusqlite::Error| format!("failed to update search index: {err}");
+        let tx = self.conn.unchecked_transaction().map_err(write_err)?;
+        for item_key in removed.iter().chain(documents.iter().map(|document| &document.item_key)) {
+            // `item_key` is unindexed in the FTS table, so rows are deleted by
+            // the rowid recorded next to the stamp.
+            let doc_rowid = tx
+                .query_row(
+                    "SELECT doc_rowid FROM search_index_items WHERE item_key = ?1",
+                    params![item_key],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()
+                .map_err(write_err)?;
+            if let Some(doc_rowid) = doc_rowid {
+                tx.execute("DELETE FROM search_index WHERE rowid = ?1", params![doc_rowid])
+                    .map_err(write_err)?;
+                tx.execute("DELETE FROM search_index_items WHERE item_key = ?1", params![item_key])
+                    .map_err(write_err)?;
+            }
+        }
+        for document in documents {
+            tx.execute(
+                r#"
+                INSERT INTO search_index (item_key, title, creators, year, abstract_note, annotations, comments)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                params![
+                    document.item_key,
+                    document.title,
+                    document.creators,
+                    document.year,
+                    document.abstract_note,
+                    document.annotations,
+                    document.comments,
+                ],
+            )
+            .map_err(write_err)?;
+            tx.execute(
+                "INSERT INTO search_index_items (item_key, stamp, doc_rowid) VALUES (?1, ?2, ?3)",
+                params![document.item_key, document.stamp, tx.last_insert_rowid()],
+            )
+            .map_err(write_err)?;
+        }
+        write_library_version(&tx, "search_index_library", library).map_err(write_err)?;
+        tx.commit().map_err(write_err)
+    }
+
+    /// Distinct indexed words between `min_chars` and `max_chars` long, for
+    /// fuzzy matching.
+    pub fn search_index_terms(&self, min_chars: usize, max_chars: usize) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT term FROM search_index_vocab WHERE length(term) BETWEEN ?1 AND ?2")
+            .map_err(|err| format!("failed to prepare search vocabulary query: {err}"))?;
+        let terms = stmt
+            .query_map(params![min_chars as i64, max_chars as i64], |row| row.get::<_, String>(0))
+            .map_err(|err| format!("failed to execute search vocabulary query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read search vocabulary row: {err}"))?;
+        Ok(terms)
+    }
+
+    /// The library version the shadow table was last refreshed with.
+    pub fn item_shadow_library(&self) -> Result<Option<LibraryVersion>, String> {
+        self.library_version("item_shadow_library")
+    }
+
+    /// Stamps of the items in the shadow table, keyed by item key.
+    pub fn item_shadow_stamps(&self) -> Result<HashMap<String, String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT item_key, stamp FROM item_shadow")
+            .map_err(|err| format!("failed to prepare search shadow query: {err}"))?;
+        let stamps = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|err| format!("failed to execute search shadow query: {err}"))?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|err| format!("failed to read search shadow row: {err}"))?;
+        Ok(stamps)
+    }
+
+    /// Replaces the shadow rows in `rows`, drops `removed` and records
+    /// `library`, in one transaction.
+    pub fn write_item_shadow(&self, rows: &[ShadowRow], removed: &[String], library: &LibraryVersion) -> Result<(), String> {
+        let write_err = |err: rusqlite::Error| format!("failed to update search shadow table: {err}");
+        let tx = self.conn.unchecked_transaction().map_err(write_err)?;
+        for item_key in removed {
+            tx.execute("DELETE FROM item_shadow WHERE item_key = ?1", params![item_key])
+                .map_err(write_err)?;
+        }
+        for row in rows {
+            tx.execute(
+                r#"
+                INSERT OR REPLACE INTO item_shadow (item_key, stamp, title_lower, creators_lower, year)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                "#,
+                params![row.item_key, row.stamp, row.title_lower, row.creators_lower, row.year],
+            )
+            .map_err(write_err)?;
+        }
+        write_library_version(&tx, "item_shadow_library", library).map_err(write_err)?;
+        tx.commit().map_err(write_err)
+    }
+
+    /// Keys of up to `limit` items whose title, creators or year contain
+    /// `needle` (already lowercased), by title. An empty needle matches all.
+    pub fn search_item_shadow(&self, needle: &str, limit: usize) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                r#"
+                SELECT item_key
+                FROM item_shadow
+                WHERE ?1 = ''
+                   OR instr(title_lower, ?1) > 0
+                   OR instr(creators_lower, ?1) > 0
+                   OR instr(year, ?1) > 0
+                ORDER BY title_lower ASC
+                LIMIT ?2
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare search shadow query: {err}"))?;
+        let keys = stmt
+            .query_map(params![needle, limit as i64], |row| row.get::<_, String>(0))
+            .map_err(|err| format!("failed to execute search shadow query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read search shadow row: {err}"))?;
+        Ok(keys)
+    }
+
+    /// The Better BibTeX citation key of `item_key` saved from the database
+    /// version `fingerprint`, or `None` when it was not saved from that one.
+    pub fn citation_key(&self, fingerprint: &str, item_key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row(
+                r#"
+                SELECT k.citation_key
+                FROM citation_keys k
+                JOIN citation_keys_source s ON s.id = 1
+                WHERE s.fingerprint = ?1 AND k.item_key = ?2
+                "#,
+                params![fingerprint, item_key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|err| format!("failed to read saved citation key: {err}"))
+    }
+
+    /// Saves the citation key of `item_key`, read from the Better BibTeX
+    /// database version `fingerprint`. Keys saved from another version are
+    /// dropped first.
+    pub fn save_citation_key(&self, fingerprint: &str, item_key: &str, citation_key: &str) -> Result<(), String> {
+        let write_err = |err: rusqlite::Error| format!("failed to save citation key: {err}");
+        let tx = self.conn.unchecked_transaction().map_err(write_err)?;
+        let saved = tx
+            .query_row("SELECT fingerprint FROM citation_keys_source WHERE id = 1", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+            .map_err(write_err)?;
+        if saved.as_deref() != Some(fingerprint) {
+            tx.execute("DELETE FROM citation_keys", []).map_err(write_err)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO citation_keys_source (id, fingerprint) VALUES (1, ?1)",
+                params![fingerprint],
+            )
+            .map_err(write_err)?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO citation_keys (item_key, citation_key) VALUES (?1, ?2)",
+            params![item_key, citation_key],
+        )
+        .map_err(write_err)?;
+        tx.commit().map_err(write_err)
+    }
+
+    /// Empties the index and the search shadow table; the next refresh
+    /// rebuilds them from scratch.
+    pub fn clear_search_index(&self) -> Result<(), String> {
+        self.conn
+            .execute_batch(
+                r#"
+                DELETE FROM search_index;
+                DELETE FROM search_index_items;
+                DELETE FROM search_index_library;
+                DELETE FROM item_shadow;
+                DELETE FROM item_shadow_library;
+                "#,
+            )
+            .map_err(|err| format!("failed to clear search index: {err}"))
+    }
+
+    /// Best matches first, skipping the first `offset`. Titles weigh most,
+    /// then creators and the year, then the abstract, annotations and
+    /// comments.
+    pub fn search_index(&self, expression: &str, limit: usize, offset: usize) -> Result<Vec<IndexHit>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT item_key, title, creators, year,
+                       snippet(search_index, -1, '', '', '…', 12)
+                FROM search_index
+                WHERE search_index MATCH ?1
+                ORDER BY bm25(search_index, 0.0, 10.0, 5.0, 3.0, 2.0, 1.0, 1.0)
+                LIMIT ?2 OFFSET ?3
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare search index query: {err}"))?;
+        let hits = stmt
+            .query_map(params![expression, limit as i64, offset as i64], |row| {
+                Ok(IndexHit {
+                    key: row.get(0)?,
+                    title: row.get(1)?,
+                    creators: row.get(2)?,
+                    year: row.get(3)?,
+                    snippet: row.get(4)?,
+                    matches: Vec::new(),
+                })
+            })
+            .map_err(|err| format!("failed to search index: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read search index row: {err}"))?;
+        Ok(hits)
+    }
+
+    /// Like [`StateDb::search_index`] from the start, but hands the hits to
+    /// `on_page` `page_size` at a time as they are read, with whether the
+    /// page is the last. Matches are ranked once; `on_page` returns `false`
+    /// to stop early.
+    pub fn search_index_pages(
+        &self,
+        expression: &str,
+        limit: usize,
+        page_size: usize,
+        mut on_page: impl FnMut(Vec<IndexHit>, bool) -> bool,
+    ) -> Result<(), String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT item_key, title, creators, year,
+                       snippet(search_index, -1, '', '', '…', 12)
+                FROM search_index
+                WHERE search_index MATCH ?1
+                ORDER BY bm25(search_index, 0.0, 10.0, 5.0, 3.0, 2.0, 1.0, 1.0)
+                LIMIT ?2
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare search index query: {err}"))?;
+        let mut rows = stmt
+            .query_map(params![expression, limit as i64], |row| {
+                Ok(IndexHit {
+                    key: row.get(0)?,
+                    title: row.get(1)?,
+                    creators: row.get(2)?,
+                    year: row.get(3)?,
+                    snippet: row.get(4)?,
+                    matches: Vec::new(),
+                })
+            })
+            .map_err(|err| format!("failed to search index: {err}"))?
+            .peekable();
+        loop {
+            let page = rows
+                .by_ref()
+                .take(page_size.max(1))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("failed to read search index row: {err}"))?;
+            let last = rows.peek().is_none();
+            if !on_page(page, last) || last {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handle that interrupts a query running on this database from
+    /// another thread.
+    pub fn interrupt_handle(&self) -> rusqlite::InterruptHandle {
+        self.conn.get_interrupt_handle()
+    }
+
+    pub fn record_item_opened(&self, item_key: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO item_activity (item_key, opened_at) VALUES (?1, ?2)
+                ON CONFLICT(item_key) DO UPDATE SET opened_at = excluded.opened_at
+                "#,
+                params![item_key, now_seconds()],
+            )
+            .map_err(|err| format!("failed to record {item_key} as opened: {err}"))?;
+        Ok(())
+    }
+
+    pub fn set_item_pinned(&self, item_key: &str, pinned: bool) -> Result<(), String> {
+        let pinned_at = pinned.then(now_seconds);
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO item_activity (item_key, pinned_at) VALUES (?1, ?2)
+                ON CONFLICT(item_key) DO UPDATE SET pinned_at = excluded.pinned_at
+                "#,
+                params![item_key, pinned_at],
+            )
+            .map_err(|err| format!("failed to update pin of {item_key}: {err}"))?;
+        Ok(())
+    }
+
+    /// Pinned items, most recently pinned first.
+    pub fn pinned_items(&self) -> Result<Vec<(String, i64)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT item_key, pinned_at
+                FROM item_activity
+                WHERE pinned_at IS NOT NULL
+                ORDER BY pinned_at DESC, item_key ASC
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare pinned items query: {err}"))?;
+        let items = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|err| format!("failed to execute pinned items query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read pinned item row: {err}"))?;
+        Ok(items)
+    }
+
+    /// Unpinned items by when they were last opened or exported, most
+    /// recent first.
+    pub fn recent_items(&self, limit: usize) -> Result<Vec<(String, i64)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                r#"
+                SELECT item_key, MAX(used_at) AS last_used
+                FROM (
+                    SELECT item_key, opened_at AS used_at FROM item_activity WHERE opened_at IS NOT NULL
+                    UNION ALL
+                    SELECT item_key, last_exported_at FROM exported_items
+                )
+                WHERE item_key NOT IN (SELECT item_key FROM item_activity WHERE pinned_at IS NOT NULL)
+                GROUP BY item_key
+                ORDER BY last_used DESC, item_key ASC
+                LIMIT ?1
+                "#,
+            )
+            .map_err(|err| format!("failed to prepare recent items query: {err}"))?;
+        let items = stmt
+            .query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|err| format!("failed to execute recent items query: {err}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("failed to read recent item row: {err}"))?;
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> StateDb {
+        StateDb::open(Path::new(":memory:")).unwrap()
+    }
+
+    fn stamp(key: &str, date_modified: &str) -> AnnotationStamp {
+        AnnotationStamp {
+            key: key.to_string(),
+            date_added: String::new(),
+            date_modified: date_modified.to_string(),
+        }
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn empty_database_is_migrated_to_the_latest_version() {
+        let db = memory();
+        assert_eq!(user_version(&db.conn), MIGRATIONS.len());
+        assert!(db.items().unwrap().is_empty());
+        db.migrate().unwrap();
+        assert_eq!(user_version(&db.conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn partly_migrated_database_runs_only_the_remaining_migrations() {
+        let path = std::env::temp_dir().join(format!("zotnotes-state-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.pragma_update(None, "user_version", 1).unwrap();
+            conn.execute(
+                "INSERT INTO exported_items VALUES ('OLD1', 'old', 'old.md', 3, 'hash', 0, 0, 10, 10)",
+                [],
+            )
+            .unwrap();
+        }
+        let db = StateDb::open(&path).unwrap();
+        assert_eq!(user_version(&db.conn), MIGRATIONS.len());
+        assert_eq!(db.item("OLD1").unwrap().unwrap().item_version, 3);
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn record_item_inserts_then_updates() {
+        let db = memory();
+        db.record_item("ABCD1234", "smith2020", "smith.md", 4, "first", &[])
+            .unwrap();
+        let inserted = db.item("ABCD1234").unwrap().unwrap();
+        assert_eq!(inserted.cite_key, "smith2020");
+        assert_eq!(inserted.item_version, 4);
+        assert_eq!(inserted.annotation_count, 0);
+        assert_eq!(inserted.annotations_modified, 0);
+        assert_eq!(inserted.first_exported_at, inserted.last_exported_at);
+
+        let annotations = [
+            stamp("ANNO0001", "2024-03-07 09:05:03"),
+            stamp("ANNO0002", "2024-03-01 00:00:00"),
+        ];
+        db.record_item("ABCD1234", "smith2021", "renamed.md", 5, "second", &annotations)
+            .unwrap();
+        let updated = db.item("ABCD1234").unwrap().unwrap();
+        assert_eq!(updated.cite_key, "smith2021");
+        assert_eq!(updated.markdown_path, "renamed.md");
+        assert_eq!(updated.item_version, 5);
+        assert_eq!(updated.content_hash, "second");
+        assert_eq!(updated.annotation_count, 2);
+        assert_eq!(updated.annotations_modified, 1_709_802_303);
+        assert_eq!(updated.first_exported_at, inserted.first_exported_at);
+        assert_eq!(db.items().unwrap().len(), 1);
+        assert!(db.item("MISSING1").unwrap().is_none());
+    }
+
+    #[test]
+    fn remove_item_forgets_the_record() {
+        let db = memory();
+        db.record_item("ABCD1234", "smith", "smith.md", 1, "hash", &[]).unwrap();
+        db.record_image("ABCD1234", "ANNO0001", "img.png", "hash").unwrap();
+        db.remove_item("ABCD1234").unwrap();
+        assert!(db.item("ABCD1234").unwrap().is_none());
+        let images: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM exported_images", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(images, 0);
+    }
+
+    #[test]
+    fn annotations_changed_compares_count_and_latest_edit() {
+        let db = memory();
+        db.record_item("ABCD1234", "smith", "smith.md", 1, "hash", &[stamp("ANNO0001", "2024-03-07 09:05:03")])
+            .unwrap();
+        let record = db.item("ABCD1234").unwrap().unwrap();
+        let activity = |latest_modified, count| AnnotationActivity { latest_modified, count };
+        assert!(!record.annotations_changed(&activity(1_709_802_303, 1)));
+        assert!(!record.annotations_changed(&activity(1_709_802_000, 1)));
+        assert!(record.annotations_changed(&activity(1_709_802_304, 1)));
+        assert!(record.annotations_changed(&activity(1_709_802_303, 0)));
+        assert!(record.annotations_changed(&activity(1_709_802_303, 2)));
+    }
+
+    fn zotero() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE items (itemID INTEGER PRIMARY KEY, key TEXT, version INTEGER, dateModified TEXT);
+            CREATE TABLE itemAttachments (itemID INTEGER PRIMARY KEY, parentItemID INTEGER);
+            CREATE TABLE itemAnnotations (itemID INTEGER PRIMARY KEY, parentItemID INTEGER);
+            CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY);
+            CREATE TABLE tags (tagID INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE itemTags (itemID INTEGER, tagID INTEGER);
+            INSERT INTO items VALUES
+                (1, 'SAME0001', 2, ''),
+                (2, 'VERS0002', 3, ''),
+                (3, 'ANNO0003', 1, ''),
+                (4, 'GONE0004', 1, ''),
+                (5, 'SKIP0005', 9, ''),
+                (10, 'PDF00010', 1, ''),
+                (11, 'NOTE0011', 1, '2024-03-08 00:00:00');
+            INSERT INTO itemAttachments VALUES (10, 3);
+            INSERT INTO itemAnnotations VALUES (11, 10);
+            INSERT INTO deletedItems VALUES (4);
+            INSERT INTO tags VALUES (1, 'Skip');
+            INSERT INTO itemTags VALUES (5, 1);
+            "#,
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn changed_records_selects_items_with_new_versions_or_annotations() {
+        let db = memory();
+        for (key, version) in [("SAME0001", 2), ("VERS0002", 2), ("ANNO0003", 1), ("GONE0004", 0), ("SKIP0005", 1)] {
+            db.record_item(key, key, "note.md", version, "hash", &[]).unwrap();
+        }
+        let changed = |exclude_tag| {
+            crate::export::changed_records(&zotero(), &db, exclude_tag)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.item_key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changed("skip"), ["ANNO0003", "VERS0002"]);
+        assert_eq!(changed(""), ["ANNO0003", "SKIP0005", "VERS0002"]);
+    }
+}
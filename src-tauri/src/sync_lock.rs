@@ -0,0 +1,45 @@
+//! Lock held while exports and syncs write notes and export state. The
+//! in-process mutex orders the app's own threads; a lock file in the data
+//! folder orders processes, so a command-line export waits for the running
+//! app (and the other way round) instead of writing the same files.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::Path;
+use std::sync::MutexGuard;
+
+use crate::SYNC_LOCK;
+
+const LOCK_FILE: &str = "sync.lock";
+
+/// Held until dropped; the file lock is released with the file.
+pub struct SyncGuard {
+    _file: File,
+    _guard: MutexGuard<'static, ()>,
+}
+
+/// Waits for the sync lock of the data folder `data_dir`.
+pub fn acquire(data_dir: &Path) -> Result<SyncGuard, String> {
+    let guard = SYNC_LOCK
+        .lock()
+        .map_err(|_| "sync lock was poisoned by a failed sync.".to_string())?;
+    let path = data_dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|err| format!("failed to open sync lock {}: {err}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            tracing::info!("waiting for another ZotNotes process to finish exporting");
+            file.lock()
+                .map_err(|err| format!("failed to lock {}: {err}", path.display()))?;
+        }
+        Err(TryLockError::Error(err)) => return Err(format!("failed to lock {}: {err}", path.display())),
+    }
+    Ok(SyncGuard {
+        _file: file,
+        _guard: guard,
+    })
+}
@@ -0,0 +1,367 @@
+//! Checks settings before they are saved and reports problems per field, so
+//! the settings dialog can point at the input that needs fixing. Field
+//! names are the camelCase JSON paths, e.g. `markdownDir` or
+//! `collectionOverrides.2.filenamePattern`.
+
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use crate::joplin::OutputTarget;
+use crate::pdf_render::{MAX_DPI, MIN_DPI};
+use crate::render::TemplateSettings;
+use crate::AppSettings;
+
+const ASSET_PLACEHOLDERS: [&str; 2] = ["citekey", "itemKey"];
+const FILE_NAME_PLACEHOLDERS: [&str; 4] = ["citekey", "itemKey", "title", "year"];
+const WRITE_PROBE_FILE: &str = ".zotnotes-write-test";
+/// Ports below this need elevated privileges on most systems.
+const MIN_API_PORT: u16 = 1024;
+/// Shortest accepted API server token; generated tokens are longer.
+const MIN_API_TOKEN_LENGTH: usize = 16;
+/// Upper bound for `searchLimit`; the search command clamps to it as well.
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+/// Upper bound for `searchFuzziness`; more typos per word match mostly noise.
+pub const MAX_SEARCH_FUZZINESS: usize = 2;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn check(&mut self, field: impl Into<String>, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.0.push(FieldError {
+                field: field.into(),
+                message,
+            });
+        }
+    }
+}
+
+/// A folder that exists and accepts new files. Checked by creating and
+/// removing a probe file, since permission bits don't tell the whole story
+/// on network drives and Windows ACLs.
+fn writable_dir(value: &str) -> Result<(), String> {
+    let path = Path::new(value.trim());
+    if !path.is_dir() {
+        return Err(format!("{} is not an existing folder.", path.display()));
+    }
+
+    let probe = path.join(WRITE_PROBE_FILE);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|err| format!("{} is not writable: {err}", path.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn zotero_data_dir(value: &str) -> Result<(), String> {
+    let path = Path::new(value.trim());
+    if !path.join("zotero.sqlite").is_file() {
+        return Err(format!("{} does not contain zotero.sqlite.", path.display()));
+    }
+    Ok(())
+}
+
+fn base_url(value: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(value.trim())
+        .map_err(|err| format!("{:?} is not a valid URL: {err}", value.trim()))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err("Use an http:// or https:// URL, e.g. http://127.0.0.1:23119.".to_string());
+    }
+    Ok(())
+}
+
+fn user_id(value: &str) -> Result<(), String> {
+    if !value.trim().chars().all(|ch| ch.is_ascii_digit()) {
+        return Err("The Zotero user ID is a number; find it at zotero.org/settings/keys.".to_string());
+    }
+    Ok(())
+}
+
+/// Folder names relative to the markdown directory.
+fn relative_folder(value: &str) -> Result<(), String> {
+    let path = Path::new(value.trim());
+    if path.has_root() || path.components().any(|component| component.as_os_str() == "..") {
+        return Err(format!("{} must be a folder inside the markdown directory.", path.display()));
+    }
+    Ok(())
+}
+
+/// File names relative to the markdown directory.
+fn relative_file(value: &str) -> Result<(), String> {
+    let path = Path::new(value.trim());
+    if path.has_root() || path.components().any(|component| component.as_os_str() == "..") {
+        return Err(format!("{} must be a file inside the markdown directory.", path.display()));
+    }
+    Ok(())
+}
+
+/// Every `{{name}}` in `template` must be one of `known`.
+fn placeholders(template: &str, known: &[&str]) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            return Err(format!("{template:?} has an unclosed {{{{."));
+        };
+        let name = &after[..end];
+        if !known.contains(&name) {
+            let expected = known
+                .iter()
+                .map(|name| format!("{{{{{name}}}}}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!("Unknown placeholder {{{{{name}}}}}; use {expected}."));
+        }
+        rest = &after[end + 2..];
+    }
+    Ok(())
+}
+
+fn template_settings(errors: &mut Errors, prefix: &str, settings: &TemplateSettings) {
+    errors.check(
+        format!("{prefix}.sectionHeadingLevel"),
+        if (1..=6).contains(&settings.section_heading_level) {
+            Ok(())
+        } else {
+            Err("Heading level must be between 1 and 6.".to_string())
+        },
+    );
+}
+
+pub fn validate(settings: &AppSettings) -> Vec<FieldError> {
+    let mut errors = Errors(Vec::new());
+    let set = |value: &str| !value.trim().is_empty();
+
+    if set(&settings.markdown_dir) {
+        errors.check("markdownDir", writable_dir(&settings.markdown_dir));
+    }
+    if set(&settings.attachment_base_dir) {
+        errors.check("attachmentBaseDir", writable_dir(&settings.attachment_base_dir));
+    }
+    if set(&settings.zotero_data_dir) {
+        errors.check("zoteroDataDir", zotero_data_dir(&settings.zotero_data_dir));
+    }
+    errors.check("zoteroBaseUrl", base_url(&settings.zotero_base_url));
+    errors.check("zoteroUserId", user_id(&settings.zotero_user_id));
+    errors.check("orphanArchiveDir", relative_folder(&settings.orphan_archive_dir));
+    errors.check("changelogNote", relative_file(&settings.changelog_note));
+    errors.check(
+        "assetPathTemplate",
+        placeholders(&settings.asset_path_template, &ASSET_PLACEHOLDERS),
+    );
+    errors.check(
+        "searchLimit",
+        if (1..=MAX_SEARCH_LIMIT).contains(&settings.search_limit) {
+            Ok(())
+        } else {
+            Err(format!("Search limit must be between 1 and {MAX_SEARCH_LIMIT}."))
+        },
+    );
+    errors.check(
+        "searchFuzziness",
+        if settings.search_fuzziness <= MAX_SEARCH_FUZZINESS {
+            Ok(())
+        } else {
+            Err(format!("Search fuzziness must be between 0 and {MAX_SEARCH_FUZZINESS}."))
+        },
+    );
+    if let Some(dpi) = settings.image_settings.render_dpi {
+        errors.check(
+            "imageSettings.renderDpi",
+            if (MIN_DPI..=MAX_DPI).contains(&dpi) {
+                Ok(())
+            } else {
+                Err(format!("Render resolution must be between {MIN_DPI} and {MAX_DPI} DPI."))
+            },
+        );
+    }
+    if settings.ocr.enabled && !crate::ocr::valid_languages(&settings.ocr.languages) {
+        errors.check(
+            "ocr.languages",
+            Err("Use Tesseract language codes joined by +, e.g. eng or eng+deu.".to_string()),
+        );
+    }
+    if settings.output_target == OutputTarget::Joplin {
+        errors.check("joplin.baseUrl", base_url(&settings.joplin.base_url));
+        if !set(&settings.joplin.token) {
+            errors.check(
+                "joplin.token",
+                Err("Copy the token from Joplin's Web Clipper options.".to_string()),
+            );
+        }
+    }
+    if set(&settings.webdav.url) {
+        errors.check("webdav.url", base_url(&settings.webdav.url));
+    }
+    if settings.api_server.enabled {
+        if settings.api_server.port < MIN_API_PORT {
+            errors.check(
+                "apiServer.port",
+                Err(format!("Use a port between {MIN_API_PORT} and 65535.")),
+            );
+        }
+        if settings.api_server.token.trim().len() < MIN_API_TOKEN_LENGTH {
+            errors.check(
+                "apiServer.token",
+                Err(format!("Use an API token of at least {MIN_API_TOKEN_LENGTH} characters.")),
+            );
+        }
+    }
+    template_settings(&mut errors, "templateSettings", &settings.template_settings);
+
+    for (index, entry) in settings.collection_overrides.iter().enumerate() {
+        let prefix = format!("collectionOverrides.{index}");
+        if !set(&entry.collection) {
+            errors.check(
+                format!("{prefix}.collection"),
+                Err("Choose the collection this override applies to.".to_string()),
+            );
+        }
+        errors.check(
+            format!("{prefix}.filenamePattern"),
+            placeholders(&entry.filename_pattern, &FILE_NAME_PLACEHOLDERS),
+        );
+        errors.check(
+            format!("{prefix}.outputSubfolder"),
+            placeholders(&entry.output_subfolder, &ASSET_PLACEHOLDERS),
+        );
+        if let Some(template) = &entry.template_settings {
+            template_settings(&mut errors, &format!("{prefix}.templateSettings"), template);
+        }
+    }
+
+    errors.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::CollectionOverride;
+
+    fn fields(settings: &AppSettings) -> Vec<String> {
+        validate(settings).into_iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn default_settings_are_valid() {
+        assert!(validate(&AppSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn folders_must_exist_and_accept_files() {
+        let file = std::env::temp_dir().join(format!("zotnotes-validation-{}.txt", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let settings = AppSettings {
+            markdown_dir: "/missing/zotnotes/vault".to_string(),
+            attachment_base_dir: file.display().to_string(),
+            zotero_data_dir: std::env::temp_dir().display().to_string(),
+            ..AppSettings::default()
+        };
+        assert_eq!(fields(&settings), ["markdownDir", "attachmentBaseDir", "zoteroDataDir"]);
+        std::fs::remove_file(file).unwrap();
+
+        let writable = AppSettings {
+            markdown_dir: format!("  {}  ", std::env::temp_dir().display()),
+            ..AppSettings::default()
+        };
+        assert!(validate(&writable).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn read_only_folders_are_rejected() {
+        // procfs refuses new files even for root.
+        let settings = AppSettings {
+            markdown_dir: "/proc".to_string(),
+            ..AppSettings::default()
+        };
+        let errors = validate(&settings);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "markdownDir");
+        assert!(errors[0].message.contains("is not writable"));
+    }
+
+    #[test]
+    fn base_urls_must_be_http_with_a_host() {
+        for url in ["not a url", "ftp://example.org", "http://", "file:///tmp"] {
+            let settings = AppSettings {
+                zotero_base_url: url.to_string(),
+                ..AppSettings::default()
+            };
+            assert_eq!(fields(&settings), ["zoteroBaseUrl"], "{url:?}");
+        }
+
+        let mut settings = AppSettings::default();
+        settings.webdav.url = "dav.example.org/zotero".to_string();
+        settings.output_target = OutputTarget::Joplin;
+        settings.joplin.base_url = "localhost:41184".to_string();
+        settings.joplin.token = "token".to_string();
+        assert_eq!(fields(&settings), ["joplin.baseUrl", "webdav.url"]);
+    }
+
+    #[test]
+    fn template_references_must_be_known_placeholders() {
+        let settings = AppSettings {
+            asset_path_template: "assets/{{citekey}}/{{collection}}".to_string(),
+            collection_overrides: vec![
+                CollectionOverride {
+                    collection: "Reading".to_string(),
+                    filename_pattern: "{{year}} {{title}}".to_string(),
+                    ..CollectionOverride::default()
+                },
+                CollectionOverride {
+                    collection: "Thesis".to_string(),
+                    filename_pattern: "{{author}}".to_string(),
+                    output_subfolder: "{{citekey".to_string(),
+                    ..CollectionOverride::default()
+                },
+            ],
+            ..AppSettings::default()
+        };
+        let errors = validate(&settings);
+        assert_eq!(
+            errors.iter().map(|error| error.field.as_str()).collect::<Vec<_>>(),
+            [
+                "assetPathTemplate",
+                "collectionOverrides.1.filenamePattern",
+                "collectionOverrides.1.outputSubfolder"
+            ]
+        );
+        assert!(errors[0].message.starts_with("Unknown placeholder {{collection}}"));
+        assert!(errors[2].message.contains("unclosed"));
+    }
+
+    #[test]
+    fn nested_settings_use_their_json_paths() {
+        let mut settings = AppSettings {
+            orphan_archive_dir: "../outside".to_string(),
+            changelog_note: "/Log.md".to_string(),
+            zotero_user_id: "me".to_string(),
+            search_limit: 0,
+            collection_overrides: vec![CollectionOverride::default()],
+            ..AppSettings::default()
+        };
+        settings.template_settings.section_heading_level = 0;
+        assert_eq!(
+            fields(&settings),
+            [
+                "zoteroUserId",
+                "orphanArchiveDir",
+                "changelogNote",
+                "searchLimit",
+                "templateSettings.sectionHeadingLevel",
+                "collectionOverrides.0.collection"
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter};
+
+use crate::report::now_seconds;
+use crate::{open_zotero_connection, resolve_zotero_sqlite_path};
+
+pub const LIBRARY_CHANGED_EVENT: &str = "zotero-library-changed";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryChanged {
+    changed_at: i64,
+}
+
+/// Latest modification time of the database or its write-ahead log; Zotero
+/// writes to the WAL first, so the main file alone lags behind.
+fn database_mtime(path: &Path) -> Option<SystemTime> {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+
+    [path.to_path_buf(), wal.into()]
+        .iter()
+        .filter_map(|candidate| std::fs::metadata(candidate).ok()?.modified().ok())
+        .max()
+}
+
+/// Cheap summary of library contents. A file touch alone (e.g. Zotero
+/// checkpointing the WAL) leaves it unchanged and emits nothing.
+fn library_fingerprint(app: &AppHandle) -> Option<(String, i64)> {
+    let conn = open_zotero_connection(app).ok()?;
+    conn.query_row(
+        "SELECT COALESCE(MAX(clientDateModified), ''), COUNT(*) FROM items",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .ok()
+}
+
+/// Polls zotero.sqlite in the background and emits `zotero-library-changed`
+/// whenever items are added, edited or removed.
+pub fn spawn(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_mtime = None;
+        let mut last_fingerprint = None;
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            let Ok(path) = resolve_zotero_sqlite_path() else {
+                continue;
+            };
+            let mtime = database_mtime(&path);
+            if mtime.is_none() || mtime == last_mtime {
+                continue;
+            }
+            last_mtime = mtime;
+
+            let fingerprint = library_fingerprint(&app);
+            if fingerprint.is_none() {
+                continue;
+            }
+            let first_poll = last_fingerprint.is_none();
+            if fingerprint == last_fingerprint {
+                continue;
+            }
+            last_fingerprint = fingerprint;
+
+            if !first_poll {
+                let _ = app.emit(
+                    LIBRARY_CHANGED_EVENT,
+                    LibraryChanged {
+                        changed_at: now_seconds(),
+                    },
+                );
+            }
+        }
+    });
+}
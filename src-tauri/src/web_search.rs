@@ -0,0 +1,189 @@
+//! Item search on api.zotero.org, for group libraries that aren't synced to
+//! this computer. Results are shaped like the local SQLite search so the two
+//! can be merged; see `unified_search` in `lib.rs`.
+
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::highlight::{self, FieldMatch};
+use crate::write_queue::ZOTERO_WEB_API;
+use crate::{apply_api_key, extract_year, AppSettings};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// The web API returns at most 100 items per request.
+const MAX_WEB_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchSource {
+    /// Found in zotero.sqlite.
+    Local,
+    /// Found on api.zotero.org.
+    Web,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedHit {
+    pub key: String,
+    pub title: String,
+    pub creators: String,
+    pub year: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<FieldMatch>,
+    pub source: SearchSource,
+    /// Name of the library the item is in, e.g. "My Library" or a group.
+    pub library: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedSearch {
+    pub items: Vec<UnifiedHit>,
+    /// Libraries that could not be searched, with the reason.
+    pub warnings: Vec<String>,
+}
+
+/// A library on the web API: `users/<id>` or `groups/<id>`.
+#[derive(Debug, Clone)]
+pub struct WebLibrary {
+    pub path: String,
+    pub name: String,
+    /// Group ID, or `None` for the user's own library.
+    pub group_id: Option<i64>,
+}
+
+fn headers(settings: &AppSettings) -> HeaderMap {
+    let mut headers = apply_api_key(HeaderMap::new(), Some(settings.zotero_api_key.clone()));
+    headers.insert("Zotero-API-Version", reqwest::header::HeaderValue::from_static("3"));
+    headers
+}
+
+async fn get_json(client: &Client, settings: &AppSettings, url: &str) -> Result<Value, String> {
+    let response = client
+        .get(url)
+        .headers(headers(settings))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("api.zotero.org is not reachable: {err}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Zotero HTTP {status} for {url}"));
+    }
+    response
+        .json::<Value>()
+        .await
+        .map_err(|err| format!("failed to parse response from {url}: {err}"))
+}
+
+/// Whether Settings have what the web search needs: a user ID and API key.
+pub fn is_configured(settings: &AppSettings) -> bool {
+    !settings.zotero_user_id.trim().is_empty() && !settings.zotero_api_key.trim().is_empty()
+}
+
+/// The user's library and the groups they belong to.
+pub async fn libraries(client: &Client, settings: &AppSettings) -> Result<Vec<WebLibrary>, String> {
+    let user_id = settings.zotero_user_id.trim();
+
+    let mut libraries = vec![WebLibrary {
+        path: format!("users/{user_id}"),
+        name: "My Library".to_string(),
+        group_id: None,
+    }];
+    let groups = get_json(client, settings, &format!("{ZOTERO_WEB_API}/users/{user_id}/groups")).await?;
+    for group in groups.as_array().into_iter().flatten() {
+        let Some(id) = group.get("id").and_then(Value::as_i64) else {
+            continue;
+        };
+        let name = group
+            .pointer("/data/name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        libraries.push(WebLibrary {
+            path: format!("groups/{id}"),
+            name,
+            group_id: Some(id),
+        });
+    }
+    Ok(libraries)
+}
+
+/// Creators as `Last, First; …`, like the local search.
+fn creators_summary(data: &Value) -> String {
+    data.get("creators")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|creator| {
+            let field = |name: &str| creator.get(name).and_then(Value::as_str).unwrap_or_default().trim();
+            match (field("name"), field("lastName"), field("firstName")) {
+                (name, "", _) if !name.is_empty() => name.to_string(),
+                (_, last, "") => last.to_string(),
+                (_, last, first) => format!("{last}, {first}"),
+            }
+        })
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Top-level items of `library` whose title, creators or year contain
+/// `query`, at most `limit` (capped at 100).
+pub async fn search_library(
+    client: &Client,
+    settings: &AppSettings,
+    library: &WebLibrary,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<UnifiedHit>, String> {
+    let mut url = reqwest::Url::parse(&format!("{ZOTERO_WEB_API}/{}/items/top", library.path))
+        .map_err(|err| format!("invalid web API URL: {err}"))?;
+    url.query_pairs_mut()
+        .append_pair("q", query.trim())
+        .append_pair("qmode", "titleCreatorYear")
+        .append_pair("sort", "title")
+        .append_pair("limit", &limit.clamp(1, MAX_WEB_LIMIT).to_string());
+
+    let needle = [query.trim().to_lowercase()];
+    let items = get_json(client, settings, url.as_str())
+        .await
+        .map_err(|err| format!("{}: {err}", library.name))?;
+    Ok(items
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let key = item.get("key").and_then(Value::as_str)?.to_string();
+            let data = item.get("data")?;
+            let item_type = data.get("itemType").and_then(Value::as_str).unwrap_or_default();
+            if matches!(item_type, "attachment" | "note" | "annotation") {
+                return None;
+            }
+            let title = data.get("title").and_then(Value::as_str).unwrap_or("(untitled)").to_string();
+            let creators = creators_summary(data);
+            let year = extract_year(data.get("date").and_then(Value::as_str).unwrap_or_default());
+            let matches = highlight::field_matches(
+                [
+                    ("title", title.as_str()),
+                    ("creators", creators.as_str()),
+                    ("year", year.as_str()),
+                ],
+                |text| highlight::substring_ranges(text, &needle),
+            );
+            Some(UnifiedHit {
+                key,
+                title,
+                creators,
+                year,
+                matches,
+                source: SearchSource::Web,
+                library: library.name.clone(),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,271 @@
+//! Attachment files for libraries that sync files through WebDAV instead of
+//! Zotero storage. Zotero keeps each attachment on the server as
+//! `zotero/<key>.zip` next to a `<key>.prop` with the file's modification
+//! time. When an attachment is not in the local storage folder, it is
+//! downloaded and unpacked there the way Zotero does it, with the
+//! modification time Zotero expects, so Zotero treats it as already synced.
+
+use flate2::read::DeflateDecoder;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Most bytes one attachment may unpack to, so a crafted archive can't
+/// fill memory.
+const MAX_UNPACKED: usize = 1 << 30;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct WebDavSettings {
+    /// The WebDAV URL from Zotero's Sync settings, without the `zotero/`
+    /// folder Zotero adds; empty disables downloads.
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+/// Settings from the last read or save, so attachment lookups need no
+/// settings argument.
+static SETTINGS: RwLock<Option<WebDavSettings>> = RwLock::new(None);
+
+/// Attachments the server does not have, so a batch export asks only once.
+static MISSING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+pub fn configure(settings: &WebDavSettings) {
+    if let Ok(mut configured) = SETTINGS.write() {
+        *configured = (!settings.url.trim().is_empty()).then(|| settings.clone());
+    }
+    if let Ok(mut missing) = MISSING.lock() {
+        *missing = None;
+    }
+}
+
+fn configured() -> Option<WebDavSettings> {
+    SETTINGS.read().ok()?.clone()
+}
+
+fn file_url(settings: &WebDavSettings, name: &str) -> String {
+    let base = settings.url.trim().trim_end_matches('/');
+    if base.ends_with("/zotero") {
+        format!("{base}/{name}")
+    } else {
+        format!("{base}/zotero/{name}")
+    }
+}
+
+/// Downloads `name` from the server; `None` when it does not exist.
+fn fetch(settings: &WebDavSettings, name: &str) -> Result<Option<Vec<u8>>, String> {
+    let url = file_url(settings, name);
+    let mut request = Client::new().get(&url).timeout(REQUEST_TIMEOUT);
+    if !settings.username.trim().is_empty() {
+        request = request.basic_auth(settings.username.trim(), Some(&settings.password));
+    }
+    let response = tauri::async_runtime::block_on(request.send())
+        .map_err(|err| format!("WebDAV server is not reachable at {url}: {err}"))?;
+    match response.status() {
+        StatusCode::NOT_FOUND => return Ok(None),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            return Err("The WebDAV server rejected the username or password.".to_string())
+        }
+        status if !status.is_success() => return Err(format!("failed to download {url}: WebDAV HTTP {status}")),
+        _ => {}
+    }
+    tauri::async_runtime::block_on(response.bytes())
+        .map(|bytes| Some(bytes.to_vec()))
+        .map_err(|err| format!("failed to download {url}: {err}"))
+}
+
+/// The modification time in a `.prop` file, in milliseconds.
+fn prop_mtime(prop: &str) -> Option<u64> {
+    let (_, rest) = prop.split_once("<mtime>")?;
+    let (value, _) = rest.split_once("</mtime>")?;
+    value.trim().parse().ok()
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<usize> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as usize)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<usize> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?) as usize)
+}
+
+/// `(name, contents)` of every file in a zip archive. Only stored and
+/// deflated entries are read, which is all Zotero writes. Archives that
+/// unpack to more than [`MAX_UNPACKED`] bytes are rejected.
+fn unzip(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let invalid = || "the WebDAV attachment is not a valid zip file".to_string();
+    // The end-of-central-directory record is last, before an optional comment.
+    let end = (0..archive.len().saturating_sub(21))
+        .rev()
+        .find(|&at| archive[at..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or_else(invalid)?;
+    let count = read_u16(archive, end + 10).ok_or_else(invalid)?;
+    let mut at = read_u32(archive, end + 16).ok_or_else(invalid)?;
+
+    let too_large = || format!("the WebDAV attachment unpacks to more than {} MB", MAX_UNPACKED >> 20);
+    let mut unpacked = 0usize;
+    let mut files = Vec::with_capacity(count);
+    for _ in 0..count {
+        if !archive.get(at..).is_some_and(|rest| rest.starts_with(&[0x50, 0x4b, 0x01, 0x02])) {
+            return Err(invalid());
+        }
+        let field = |offset: usize, read: fn(&[u8], usize) -> Option<usize>| {
+            read(archive, at + offset).ok_or_else(invalid)
+        };
+        let method = field(10, read_u16)?;
+        let crc = field(16, read_u32)?;
+        let compressed_size = field(20, read_u32)?;
+        let size = field(24, read_u32)?;
+        let name_len = field(28, read_u16)?;
+        let extra_len = field(30, read_u16)?;
+        let comment_len = field(32, read_u16)?;
+        let local = field(42, read_u32)?;
+        let name = archive.get(at + 46..at + 46 + name_len).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name).to_string();
+        at += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        let local_name_len = read_u16(archive, local + 26).ok_or_else(invalid)?;
+        let local_extra_len = read_u16(archive, local + 28).ok_or_else(invalid)?;
+        let data_start = local + 30 + local_name_len + local_extra_len;
+        let data = archive.get(data_start..data_start + compressed_size).ok_or_else(invalid)?;
+        let contents = match method {
+            0 => data.to_vec(),
+            8 => {
+                // The declared size is only a hint; the read is bounded by
+                // what is left of the budget.
+                let mut contents = Vec::with_capacity(size.min(64 << 20));
+                DeflateDecoder::new(data)
+                    .take((MAX_UNPACKED - unpacked) as u64 + 1)
+                    .read_to_end(&mut contents)
+                    .map_err(|err| format!("failed to unpack {name} from the WebDAV attachment: {err}"))?;
+                contents
+            }
+            other => return Err(format!("{name} in the WebDAV attachment uses unsupported compression {other}")),
+        };
+        unpacked += contents.len();
+        if unpacked > MAX_UNPACKED {
+            return Err(too_large());
+        }
+        if crc32fast::hash(&contents) as usize != crc {
+            return Err(format!("{name} in the WebDAV attachment is corrupt"));
+        }
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+/// `name` under `dir`, or `None` when it would leave it.
+fn entry_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name);
+    relative
+        .components()
+        .all(|part| matches!(part, Component::Normal(_)))
+        .then(|| dir.join(relative))
+}
+
+fn unpack(archive: &[u8], dir: &Path, mtime: Option<SystemTime>) -> Result<(), String> {
+    for (name, contents) in unzip(archive)? {
+        let path = entry_path(dir, &name)
+            .ok_or_else(|| format!("the WebDAV attachment contains an unsafe path {name:?}"))?;
+        crate::ensure_parent(&path)?;
+        // Write next to the target first, so a concurrent render never reads
+        // a half-written file.
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        std::fs::write(&partial, &contents)
+            .map_err(|err| format!("failed to write {}: {err}", partial.display()))?;
+        if let Some(mtime) = mtime {
+            let file = std::fs::File::options()
+                .write(true)
+                .open(&partial)
+                .map_err(|err| format!("failed to open {}: {err}", partial.display()))?;
+            if let Err(err) = file.set_modified(mtime) {
+                tracing::warn!("failed to set the modification time of {}: {err}", partial.display());
+            }
+        }
+        std::fs::rename(&partial, &path)
+            .map_err(|err| format!("failed to move {} into place: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Downloads the attachment `attachment_key` into its storage folder `dir`
+/// when WebDAV is configured. Returns whether anything was unpacked.
+pub fn download_attachment(attachment_key: &str, dir: &Path) -> Result<bool, String> {
+    let Some(settings) = configured() else {
+        return Ok(false);
+    };
+    let known_missing = MISSING
+        .lock()
+        .is_ok_and(|missing| missing.as_ref().is_some_and(|keys| keys.contains(attachment_key)));
+    if known_missing {
+        return Ok(false);
+    }
+
+    let Some(archive) = fetch(&settings, &format!("{attachment_key}.zip"))? else {
+        if let Ok(mut missing) = MISSING.lock() {
+            missing.get_or_insert_with(HashSet::new).insert(attachment_key.to_string());
+        }
+        tracing::warn!(attachment_key, "attachment is not on the WebDAV server");
+        return Ok(false);
+    };
+    let mtime = fetch(&settings, &format!("{attachment_key}.prop"))?
+        .and_then(|prop| prop_mtime(&String::from_utf8_lossy(&prop)))
+        .map(|millis| SystemTime::UNIX_EPOCH + Duration::from_millis(millis));
+    unpack(&archive, dir, mtime)?;
+    tracing::info!(attachment_key, "downloaded attachment from WebDAV");
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ZipArchive;
+
+    #[test]
+    fn unzip_reads_what_the_zip_writer_wrote() {
+        let mut archive = ZipArchive::new();
+        archive.add("paper.pdf", b"%PDF-1.7 body").unwrap();
+        archive.add("images/figure.png", &[0u8; 4096]).unwrap();
+        let files = unzip(&archive.finish()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0], ("paper.pdf".to_string(), b"%PDF-1.7 body".to_vec()));
+        assert_eq!(files[1].0, "images/figure.png");
+        assert_eq!(files[1].1, vec![0u8; 4096]);
+    }
+
+    #[test]
+    fn unzip_rejects_corrupt_archives() {
+        let mut archive = ZipArchive::new();
+        archive.add("paper.pdf", b"contents").unwrap();
+        let mut bytes = archive.finish();
+        // Flip the CRC recorded in the central directory header.
+        let crc_at = bytes.windows(4).rposition(|window| window == [0x50, 0x4b, 0x01, 0x02]).unwrap() + 16;
+        bytes[crc_at] ^= 0xff;
+        assert!(unzip(&bytes).is_err());
+        assert!(unzip(b"not a zip file at all, just some text").is_err());
+        assert!(unzip(&[]).is_err());
+    }
+
+    #[test]
+    fn entry_path_stays_inside_the_folder() {
+        let dir = Path::new("/storage/ABCD1234");
+        assert_eq!(entry_path(dir, "paper.pdf"), Some(dir.join("paper.pdf")));
+        assert_eq!(entry_path(dir, "a/b.png"), Some(dir.join("a/b.png")));
+        assert_eq!(entry_path(dir, "../escape.pdf"), None);
+        assert_eq!(entry_path(dir, "a/../../escape.pdf"), None);
+        assert_eq!(entry_path(dir, "/etc/passwd"), None);
+        assert_eq!(entry_path(dir, "./paper.pdf"), None);
+    }
+}
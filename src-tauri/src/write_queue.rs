@@ -0,0 +1,189 @@
+//! Serialized, rate-limited writes to the Zotero web API. Every mutation
+//! (comments, tags, notes) goes through [`WriteQueue::submit`], which runs
+//! one write at a time, waits for a running sync to finish, honours Zotero's
+//! `Backoff`/`Retry-After` headers and retries version conflicts that the
+//! caller can safely rebase.
+//!
+//! The queue blocks; call it from `spawn_blocking` or a background thread.
+
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::{Client, Response, StatusCode};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, ErrorCode};
+use crate::{apply_api_key, AppSettings, SYNC_LOCK};
+
+pub const ZOTERO_WEB_API: &str = "https://api.zotero.org";
+
+/// Minimum gap between two writes.
+const WRITE_INTERVAL: Duration = Duration::from_millis(500);
+/// Attempts per write when Zotero answers 429 or 503.
+const MAX_RATE_LIMIT_ATTEMPTS: usize = 5;
+/// Rebased retries per write after a version conflict.
+const MAX_CONFLICT_RETRIES: usize = 3;
+/// Wait used when a 429/503 response has no `Retry-After` header.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Earliest time the next write may be sent, shared by all queues.
+static NEXT_WRITE_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// A PATCH of one item's fields, sent with `If-Unmodified-Since-Version`.
+#[derive(Debug, Clone)]
+pub struct ZoteroWrite {
+    pub item_key: String,
+    /// Version of the item the changes were based on.
+    pub version: i64,
+    pub changes: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    /// The item changed in Zotero and the change could not be rebased.
+    Conflict,
+}
+
+pub struct WriteQueue {
+    client: Client,
+    headers: HeaderMap,
+    user_id: String,
+}
+
+fn header_seconds(response: &Response, name: &str) -> Option<Duration> {
+    response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+fn next_write_at() -> Result<std::sync::MutexGuard<'static, Option<Instant>>, String> {
+    NEXT_WRITE_AT
+        .lock()
+        .map_err(|_| "write queue lock was poisoned.".to_string())
+}
+
+/// Waits until the next write is allowed, then reserves the slot after it.
+fn wait_for_slot() -> Result<(), String> {
+    let mut next = next_write_at()?;
+    if let Some(at) = *next {
+        std::thread::sleep(at.saturating_duration_since(Instant::now()));
+    }
+    *next = Some(Instant::now() + WRITE_INTERVAL);
+    Ok(())
+}
+
+/// Holds off the next write for at least `delay`.
+fn delay_next(delay: Duration) -> Result<(), String> {
+    let mut next = next_write_at()?;
+    let at = Instant::now() + delay;
+    *next = Some(next.map_or(at, |current| current.max(at)));
+    Ok(())
+}
+
+impl WriteQueue {
+    pub fn new(settings: &AppSettings) -> Result<Self, AppError> {
+        let user_id = settings.zotero_user_id.trim().to_string();
+        if user_id.is_empty() || settings.zotero_api_key.trim().is_empty() {
+            return Err(AppError::new(
+                ErrorCode::InvalidSettings,
+                "Set a Zotero user ID and API key in Settings to write to Zotero.",
+            ));
+        }
+        let mut headers = apply_api_key(HeaderMap::new(), Some(settings.zotero_api_key.clone()));
+        headers.insert("Zotero-API-Version", HeaderValue::from_static("3"));
+
+        Ok(Self {
+            client: Client::new(),
+            headers,
+            user_id,
+        })
+    }
+
+    fn item_url(&self, item_key: &str) -> String {
+        format!("{ZOTERO_WEB_API}/users/{}/items/{item_key}", self.user_id)
+    }
+
+    /// Sends a request, retrying while Zotero rate-limits it. A `Backoff`
+    /// header delays the next write without failing this one.
+    fn send(&self, build: impl Fn(&Client) -> reqwest::RequestBuilder) -> Result<Response, String> {
+        for _ in 0..MAX_RATE_LIMIT_ATTEMPTS {
+            wait_for_slot()?;
+            let request = build(&self.client).headers(self.headers.clone());
+            let response = tauri::async_runtime::block_on(request.send())
+                .map_err(|err| format!("request failed: {err}"))?;
+
+            if let Some(backoff) = header_seconds(&response, "Backoff") {
+                delay_next(backoff)?;
+            }
+            let status = response.status();
+            if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+                return Ok(response);
+            }
+            let retry_after = header_seconds(&response, RETRY_AFTER.as_str()).unwrap_or(DEFAULT_RETRY_AFTER);
+            tracing::warn!(%status, retry_after = retry_after.as_secs(), "Zotero rate limited a write");
+            delay_next(retry_after)?;
+        }
+        Err("Zotero is rate limiting writes; try again later.".to_string())
+    }
+
+    /// Current `(version, data)` of an item.
+    fn fetch_item(&self, item_key: &str) -> Result<(i64, Value), String> {
+        let url = self.item_url(item_key);
+        let response = self.send(|client| client.get(&url))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Zotero HTTP {status} for {url}"));
+        }
+        let item = tauri::async_runtime::block_on(response.json::<Value>())
+            .map_err(|err| format!("failed to parse Zotero item {item_key}: {err}"))?;
+        let version = item.get("version").and_then(Value::as_i64).unwrap_or(0);
+        let data = item.get("data").cloned().unwrap_or(Value::Null);
+        Ok((version, data))
+    }
+
+    /// Sends one write. When the item changed in Zotero since `write.version`,
+    /// `rebase` gets the item's current data and returns whether the same
+    /// changes still apply; if so the write is retried against the new
+    /// version.
+    pub fn submit(&self, write: &ZoteroWrite, rebase: impl Fn(&Value) -> bool) -> Result<WriteOutcome, String> {
+        let _guard = SYNC_LOCK
+            .lock()
+            .map_err(|_| "sync lock was poisoned by a failed sync.".to_string())?;
+
+        let url = self.item_url(&write.item_key);
+        let mut version = write.version;
+        for _ in 0..=MAX_CONFLICT_RETRIES {
+            let response = self.send(|client| {
+                client
+                    .patch(&url)
+                    .header("If-Unmodified-Since-Version", version.to_string())
+                    .json(&write.changes)
+            })?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(WriteOutcome::Written);
+            }
+            if status != StatusCode::PRECONDITION_FAILED {
+                let body = tauri::async_runtime::block_on(response.text()).unwrap_or_default();
+                return Err(format!("Zotero HTTP {status}: {body}"));
+            }
+
+            let (current_version, data) = self.fetch_item(&write.item_key)?;
+            if !rebase(&data) {
+                tracing::info!(item_key = %write.item_key, "write conflicts with a change in Zotero");
+                return Ok(WriteOutcome::Conflict);
+            }
+            tracing::debug!(item_key = %write.item_key, version = current_version, "retrying write on newer version");
+            version = current_version;
+        }
+        Ok(WriteOutcome::Conflict)
+    }
+}
@@ -0,0 +1,135 @@
+//! Keeps commands that write files inside the configured markdown and
+//! attachment folders. Paths are canonicalized before the check, so `..`
+//! segments and symlinks cannot step outside; parts that don't exist yet
+//! may not contain `..` at all.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{AppError, ErrorCode};
+use crate::AppSettings;
+
+/// Resolves `path` to an absolute path, following symlinks in the part
+/// that already exists.
+fn canonical(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return missing
+                .iter()
+                .rev()
+                .try_fold(resolved, |resolved, part: &Component| match part {
+                    Component::Normal(name) => Some(resolved.join(name)),
+                    Component::CurDir => Some(resolved),
+                    _ => None,
+                });
+        }
+        missing.push(existing.components().next_back()?);
+        existing = existing.parent()?;
+    }
+}
+
+fn allowed_roots(settings: &AppSettings) -> Vec<PathBuf> {
+    [&settings.markdown_dir, &settings.attachment_base_dir]
+        .into_iter()
+        .map(|dir| dir.trim())
+        .filter(|dir| !dir.is_empty())
+        .filter_map(|dir| canonical(Path::new(dir)))
+        .collect()
+}
+
+/// Accepts `path` if it lies inside the markdown directory or the
+/// attachment base directory (or is one of them).
+pub fn check(settings: &AppSettings, path: &Path) -> Result<(), AppError> {
+    let rejected = |reason: &str| {
+        AppError::new(ErrorCode::PathNotAllowed, format!("{} {reason}", path.display()))
+            .with_context(path.display().to_string())
+    };
+
+    let roots = allowed_roots(settings);
+    if roots.is_empty() {
+        return Err(rejected(
+            "cannot be written: set the markdown directory or attachment base directory in Settings.",
+        ));
+    }
+    let resolved = canonical(path).ok_or_else(|| rejected("is not a valid destination."))?;
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        Ok(())
+    } else {
+        Err(rejected(
+            "is outside the markdown directory and attachment base directory.",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "zotnotes-write-scope-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings(markdown_dir: &Path) -> AppSettings {
+        AppSettings {
+            markdown_dir: markdown_dir.display().to_string(),
+            attachment_base_dir: String::new(),
+            ..AppSettings::default()
+        }
+    }
+
+    #[test]
+    fn paths_inside_the_markdown_folder_are_accepted() {
+        let dir = temp_dir();
+        let settings = settings(&dir);
+        assert!(check(&settings, &dir).is_ok());
+        assert!(check(&settings, &dir.join("note.md")).is_ok());
+        assert!(check(&settings, &dir.join("new/sub/folder/note.md")).is_ok());
+        assert!(check(&settings, &dir.join("./note.md")).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn paths_leaving_the_markdown_folder_are_rejected() {
+        let dir = temp_dir();
+        let settings = settings(&dir.join("vault"));
+        std::fs::create_dir_all(dir.join("vault")).unwrap();
+        assert!(check(&settings, &dir.join("vault/../outside.md")).is_err());
+        assert!(check(&settings, &dir.join("vault/missing/../../outside.md")).is_err());
+        assert!(check(&settings, &dir.join("outside.md")).is_err());
+        assert!(check(&settings, &dir.join("vault-sibling/note.md")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_markdown_folder_are_rejected() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("vault")).unwrap();
+        std::fs::create_dir_all(dir.join("elsewhere")).unwrap();
+        std::os::unix::fs::symlink(dir.join("elsewhere"), dir.join("vault/link")).unwrap();
+        assert!(check(&settings(&dir.join("vault")), &dir.join("vault/link/note.md")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_writable_without_folders() {
+        let dir = temp_dir();
+        let settings = AppSettings {
+            markdown_dir: "  ".to_string(),
+            attachment_base_dir: String::new(),
+            ..AppSettings::default()
+        };
+        assert!(check(&settings, &dir.join("note.md")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,304 @@
+//! Reads annotation comments back out of exported notes so edits made in the
+//! vault can be pushed to Zotero.
+//!
+//! Only the managed region is parsed (see [`crate::managed`]). The comment
+//! is the text after `Comment: ` up to the image or TODO line that may
+//! follow. A highlight without a `Comment: ` line has no comment, so adding
+//! the line adds one and deleting it clears the comment in Zotero.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::managed::annotation_blocks;
+use crate::render::comment_markdown;
+use crate::report::parse_zotero_timestamp;
+use crate::state_db::ItemExportRecord;
+
+/// A comment as it currently reads in the note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteComment {
+    pub annotation_key: String,
+    /// `None` when the block has no `Comment: ` line.
+    pub comment: Option<String>,
+}
+
+/// A comment edit that should be written to Zotero.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentEdit {
+    pub item_key: String,
+    pub annotation_key: String,
+    pub comment: String,
+    /// Zotero library version of the annotation, sent as
+    /// `If-Unmodified-Since-Version` so concurrent edits are rejected.
+    #[serde(skip)]
+    pub version: i64,
+    /// Comment in Zotero when the edit was collected. A write rejected for
+    /// a newer version is retried as long as Zotero still has this comment.
+    #[serde(skip)]
+    pub base_comment: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritebackReport {
+    pub pushed: Vec<CommentEdit>,
+    /// Annotations edited in both the note and Zotero since the last export.
+    pub conflicts: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+fn strip_block_id(line: &str) -> &str {
+    match line.rfind(" ^zot-") {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn block_comment(block: &[&str]) -> Option<String> {
+    let start = block.iter().position(|line| line.starts_with("Comment: "))?;
+    let mut comment_lines = vec![block[start].trim_start_matches("Comment: ")];
+    comment_lines.extend(
+        block[start + 1..]
+            .iter()
+            .take_while(|line| !line.starts_with("[[") && !line.starts_with("TODO: "))
+            .copied(),
+    );
+
+    let comment = comment_lines
+        .iter()
+        .map(|line| strip_block_id(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(comment.trim().to_string())
+}
+
+/// Extracts the comment of every annotation in the note's managed region.
+/// Annotations already marked as deleted in Zotero are ignored.
+pub fn parse_note_comments(markdown: &str) -> Vec<NoteComment> {
+    annotation_blocks(markdown)
+        .into_iter()
+        .filter(|block| !block.is_deleted_callout())
+        .filter_map(|block| {
+            Some(NoteComment {
+                comment: block_comment(&block.lines),
+                annotation_key: block.key?,
+            })
+        })
+        .collect()
+}
+
+/// Current Zotero state of one annotation.
+struct ZoteroAnnotation {
+    text: String,
+    comment: String,
+    version: i64,
+    date_modified: String,
+    user_library: bool,
+}
+
+fn load_zotero_annotation(zotero: &Connection, key: &str) -> Result<Option<ZoteroAnnotation>, String> {
+    zotero
+        .query_row(
+            r#"
+            SELECT COALESCE(ia.text, ''), COALESCE(ia.comment, ''), anno.version, anno.dateModified, lib.type
+            FROM items anno
+            JOIN itemAnnotations ia ON ia.itemID = anno.itemID
+            JOIN libraries lib ON lib.libraryID = anno.libraryID
+            WHERE anno.key = ?1
+              AND anno.itemID NOT IN (SELECT itemID FROM deletedItems)
+            "#,
+            params![key],
+            |row| {
+                Ok(ZoteroAnnotation {
+                    text: row.get(0)?,
+                    comment: row.get(1)?,
+                    version: row.get(2)?,
+                    date_modified: row.get(3)?,
+                    user_library: row.get::<_, String>(4)? == "user",
+                })
+            },
+        )
+        .optional()
+        .map_err(|err| format!("failed to read Zotero annotation {key}: {err}"))
+}
+
+/// Compares the comments in an exported note with Zotero and collects the
+/// ones edited in the note. Edits are only collected for annotations Zotero
+/// has not modified since the export; those are reported as conflicts.
+pub fn collect_edits(
+    zotero: &Connection,
+    record: &ItemExportRecord,
+    report: &mut WritebackReport,
+) -> Result<Vec<CommentEdit>, String> {
+    let markdown = std::fs::read_to_string(&record.markdown_path)
+        .map_err(|err| format!("failed to read {}: {err}", record.markdown_path))?;
+
+    let mut edits = Vec::new();
+    for note_comment in parse_note_comments(&markdown) {
+        let key = &note_comment.annotation_key;
+        let Some(current) = load_zotero_annotation(zotero, key)? else {
+            report.skipped.push(format!("{key}: annotation no longer exists in Zotero"));
+            continue;
+        };
+        // Without highlighted text the comment is rendered in place of the
+        // text, not on a `Comment: ` line, so a missing line says nothing.
+        let comment = match note_comment.comment {
+            Some(comment) => comment,
+            None if current.text.trim().is_empty() => continue,
+            None => String::new(),
+        };
+        if comment_markdown(current.comment.trim()).trim() == comment {
+            continue;
+        }
+        if !current.user_library {
+            report.skipped.push(format!("{key}: group library annotations are read-only"));
+            continue;
+        }
+        if comment_markdown(&current.comment) != current.comment {
+            report.skipped.push(format!("{key}: rich-text comments are not written back"));
+            continue;
+        }
+        let modified = parse_zotero_timestamp(&current.date_modified).unwrap_or(0);
+        if modified > record.last_exported_at {
+            report.conflicts.push(key.clone());
+            continue;
+        }
+
+        edits.push(CommentEdit {
+            item_key: record.item_key.clone(),
+            annotation_key: note_comment.annotation_key,
+            comment,
+            version: current.version,
+            base_comment: current.comment,
+        });
+    }
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(blocks: &[&str]) -> String {
+        format!("# Paper\n\n## Annotations\n\n{}\n\n## Notes\n", blocks.join("\n\n"))
+    }
+
+    fn zotero(annotations: &[(&str, &str, &str)]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE items (itemID INTEGER PRIMARY KEY, key TEXT, version INTEGER, dateModified TEXT, libraryID INTEGER);
+            CREATE TABLE itemAnnotations (itemID INTEGER PRIMARY KEY, text TEXT, comment TEXT);
+            CREATE TABLE libraries (libraryID INTEGER PRIMARY KEY, type TEXT);
+            CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY);
+            INSERT INTO libraries VALUES (1, 'user');
+            "#,
+        )
+        .unwrap();
+        for (id, (key, text, comment)) in annotations.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO items VALUES (?1, ?2, 7, '2024-01-01 00:00:00', 1)",
+                params![id as i64, key],
+            )
+            .unwrap();
+            conn.execute("INSERT INTO itemAnnotations VALUES (?1, ?2, ?3)", params![id as i64, text, comment])
+                .unwrap();
+        }
+        conn
+    }
+
+    fn edits(markdown: &str, annotations: &[(&str, &str, &str)]) -> Vec<(String, String)> {
+        // Tests run in parallel, so each call gets its own file.
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let id = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("zotnotes-writeback-{}-{id}.md", std::process::id()));
+        std::fs::write(&path, markdown).unwrap();
+        let record = ItemExportRecord {
+            item_key: "ITEMKEY1".to_string(),
+            cite_key: "doe2024".to_string(),
+            markdown_path: path.to_string_lossy().to_string(),
+            item_version: 1,
+            content_hash: String::new(),
+            annotations_modified: 0,
+            annotation_count: annotations.len(),
+            first_exported_at: 0,
+            last_exported_at: i64::MAX,
+        };
+        let mut report = WritebackReport::default();
+        let edits = collect_edits(&zotero(annotations), &record, &mut report).unwrap();
+        let _ = std::fs::remove_file(&path);
+        edits
+            .into_iter()
+            .map(|edit| (edit.annotation_key, edit.comment))
+            .collect()
+    }
+
+    #[test]
+    fn parses_comments_without_block_ids() {
+        let markdown = note(&[
+            "> Highlighted text ^zot-AAAAAAAA\n> Comment: first line\n> second line ^zot-AAAAAAAA",
+            "> Other text ^zot-BBBBBBBB",
+        ]);
+        assert_eq!(
+            parse_note_comments(&markdown),
+            vec![
+                NoteComment {
+                    annotation_key: "AAAAAAAA".to_string(),
+                    comment: Some("first line\nsecond line".to_string()),
+                },
+                NoteComment {
+                    annotation_key: "BBBBBBBB".to_string(),
+                    comment: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_stops_at_image_and_todo_lines() {
+        let block = ["Text", "Comment: why", "[[image.png]]", "TODO: missing"];
+        assert_eq!(block_comment(&block).as_deref(), Some("why"));
+        assert_eq!(block_comment(&["Text"]), None);
+    }
+
+    #[test]
+    fn collects_edited_comments() {
+        let markdown = note(&["> Text ^zot-AAAAAAAA\n> Comment: new"]);
+        assert_eq!(
+            edits(&markdown, &[("AAAAAAAA", "Text", "old")]),
+            vec![("AAAAAAAA".to_string(), "new".to_string())]
+        );
+    }
+
+    #[test]
+    fn collects_comments_added_to_annotations_without_one() {
+        let markdown = note(&["> Text ^zot-AAAAAAAA\n> Comment: added"]);
+        assert_eq!(
+            edits(&markdown, &[("AAAAAAAA", "Text", "")]),
+            vec![("AAAAAAAA".to_string(), "added".to_string())]
+        );
+    }
+
+    #[test]
+    fn clears_comments_deleted_from_the_note() {
+        let markdown = note(&["> Text ^zot-AAAAAAAA"]);
+        assert_eq!(
+            edits(&markdown, &[("AAAAAAAA", "Text", "gone")]),
+            vec![("AAAAAAAA".to_string(), String::new())]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_shown_in_place_of_missing_text() {
+        let markdown = note(&["> Only a comment ^zot-AAAAAAAA"]);
+        assert!(edits(&markdown, &[("AAAAAAAA", "", "Only a comment")]).is_empty());
+    }
+
+    #[test]
+    fn unchanged_comments_are_not_edits() {
+        let markdown = note(&["> Text ^zot-AAAAAAAA\n> Comment: same", "> More ^zot-BBBBBBBB"]);
+        assert!(edits(&markdown, &[("AAAAAAAA", "Text", "same"), ("BBBBBBBB", "More", "")]).is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+//! Read access to zotero.sqlite while Zotero is running. The database is
+//! normally opened with `immutable=1`, which never blocks on Zotero's
+//! exclusive lock but ignores its write-ahead log and can fail while Zotero
+//! rewrites pages. When that read fails, ZotNotes copies the database (with
+//! its `-wal` or `-journal` file) to a temporary folder and reads the copy.
+//! Either way the connection reports how current its data is.
+
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::AppError;
+use crate::report::now_seconds;
+use crate::sqlite_file_uri;
+
+const SNAPSHOT_DIR: &str = "zotnotes-snapshots";
+const COPY_ATTEMPTS: usize = 3;
+const COPY_RETRY_DELAY: Duration = Duration::from_millis(250);
+/// Prepared statements kept per connection. Pooled connections live across
+/// commands, so the item, annotation and search queries are parsed once.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// How current the data read through a [`ZoteroDb`] is.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Freshness {
+    /// Read from zotero.sqlite with nothing newer in its write-ahead log.
+    #[default]
+    Live,
+    /// Read from zotero.sqlite, but Zotero wrote to its write-ahead log
+    /// after the last checkpoint, so the latest edits may be missing.
+    #[serde(rename_all = "camelCase")]
+    Lagging { wal_modified_at: i64 },
+    /// Read from a copy because the live file could not be read.
+    #[serde(rename_all = "camelCase")]
+    Snapshot { taken_at: i64, reason: String },
+}
+
+/// A read-only Zotero connection plus how current its data is. Derefs to
+/// the connection, so it can be used wherever a `&Connection` is expected.
+pub struct ZoteroDb {
+    conn: Connection,
+    pub freshness: Freshness,
+}
+
+impl Deref for ZoteroDb {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+/// Size and modification time of the database and its side files, used to
+/// tell whether a cached snapshot still matches the source.
+pub type Stamp = Vec<Option<(u64, SystemTime)>>;
+
+struct CachedSnapshot {
+    stamp: Stamp,
+    path: PathBuf,
+    taken_at: i64,
+}
+
+static SNAPSHOT: Mutex<Option<CachedSnapshot>> = Mutex::new(None);
+static SNAPSHOT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn side_file(path: &Path, suffix: &str) -> PathBuf {
+    let mut side = path.as_os_str().to_owned();
+    side.push(suffix);
+    PathBuf::from(side)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+pub fn stamp(path: &Path) -> Stamp {
+    ["", "-wal", "-journal"]
+        .iter()
+        .map(|suffix| {
+            let metadata = std::fs::metadata(side_file(path, suffix)).ok()?;
+            Some((metadata.len(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Cheap read that touches the schema, so a torn or locked file fails here
+/// instead of halfway through an export.
+fn probe(conn: &Connection) -> rusqlite::Result<()> {
+    conn.query_row("SELECT COUNT(*) FROM items", [], |row| row.get::<_, i64>(0))
+        .map(|_| ())
+}
+
+fn open_live(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        sqlite_file_uri(path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    probe(&conn)?;
+    Ok(conn)
+}
+
+/// Opens a snapshot read-write, so SQLite can apply the copied WAL or roll
+/// back a copied hot journal, and then forbids writes.
+fn open_copy(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|err| err.to_string())?;
+    conn.execute_batch("PRAGMA query_only = ON")
+        .map_err(|err| err.to_string())?;
+    probe(&conn).map_err(|err| err.to_string())?;
+    Ok(conn)
+}
+
+/// Copies the database and its side files into a new folder and removes
+/// older snapshots. Copies taken mid-write are retried.
+fn take_snapshot(source: &Path) -> Result<PathBuf, String> {
+    let root = std::env::temp_dir().join(SNAPSHOT_DIR);
+    let mut last_error = String::new();
+
+    for _ in 0..COPY_ATTEMPTS {
+        let dir = root.join(format!(
+            "{}-{}-{}",
+            now_seconds(),
+            std::process::id(),
+            SNAPSHOT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)
+            .map_err(|err| format!("failed to create snapshot folder {}: {err}", dir.display()))?;
+        let copy = dir.join("zotero.sqlite");
+
+        let copied = ["", "-wal", "-journal"].iter().try_for_each(|suffix| {
+            let from = side_file(source, suffix);
+            if !suffix.is_empty() && !from.exists() {
+                return Ok(());
+            }
+            std::fs::copy(&from, side_file(&copy, suffix))
+                .map(|_| ())
+                .map_err(|err| format!("failed to copy {}: {err}", from.display()))
+        });
+        match copied.and_then(|()| open_copy(&copy).map(|_| ())) {
+            Ok(()) => {
+                remove_old_snapshots(&root, &dir);
+                return Ok(copy);
+            }
+            Err(err) => {
+                last_error = err;
+                let _ = std::fs::remove_dir_all(&dir);
+                std::thread::sleep(COPY_RETRY_DELAY);
+            }
+        }
+    }
+
+    Err(format!("failed to snapshot Zotero database: {last_error}"))
+}
+
+/// Best effort: a snapshot still open elsewhere (e.g. on Windows) stays
+/// until the next one.
+fn remove_old_snapshots(root: &Path, keep: &Path) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.path() != keep {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Reads the latest snapshot, or takes a new one when the source changed
+/// since.
+fn open_snapshot(source: &Path, reason: String) -> Result<ZoteroDb, String> {
+    let mut cached = SNAPSHOT
+        .lock()
+        .map_err(|_| "Zotero snapshot lock was poisoned.".to_string())?;
+    let current = stamp(source);
+
+    if let Some(snapshot) = cached.as_ref().filter(|snapshot| snapshot.stamp == current) {
+        if let Ok(conn) = open_copy(&snapshot.path) {
+            return Ok(ZoteroDb {
+                conn,
+                freshness: Freshness::Snapshot {
+                    taken_at: snapshot.taken_at,
+                    reason,
+                },
+            });
+        }
+    }
+
+    let path = take_snapshot(source)?;
+    let taken_at = now_seconds();
+    let conn = open_copy(&path)?;
+    tracing::warn!(source = %source.display(), "reading Zotero database from a snapshot: {reason}");
+    *cached = Some(CachedSnapshot {
+        stamp: current,
+        path,
+        taken_at,
+    });
+    Ok(ZoteroDb {
+        conn,
+        freshness: Freshness::Snapshot { taken_at, reason },
+    })
+}
+
+/// Opens zotero.sqlite at `path`, falling back to a snapshot when the live
+/// file cannot be read. When the snapshot fails too, the error is
+/// classified by the live failure, e.g. as a locked database.
+pub fn open(path: &Path) -> Result<ZoteroDb, AppError> {
+    let db = open_uncached(path)?;
+    db.conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    Ok(db)
+}
+
+fn open_uncached(path: &Path) -> Result<ZoteroDb, AppError> {
+    match open_live(path) {
+        Ok(conn) => {
+            let wal = modified(&side_file(path, "-wal"));
+            let freshness = match (wal, modified(path)) {
+                (Some(wal), Some(main)) if wal > main => Freshness::Lagging {
+                    wal_modified_at: unix_seconds(wal),
+                },
+                _ => Freshness::Live,
+            };
+            Ok(ZoteroDb { conn, freshness })
+        }
+        Err(live) => open_snapshot(path, live.to_string()).map_err(|err| {
+            let what = format!("failed to open Zotero database {}", path.display());
+            AppError::sqlite(&what, &live)
+                .with_context(format!("snapshot fallback: {err}"))
+        }),
+    }
+}
@@ -0,0 +1,229 @@
+//! Locates Zotero's data directory: the platform's default locations first,
+//! then a custom directory from Zotero's own profile (the default profile
+//! listed in `profiles.ini`, then the `extensions.zotero.dataDir` preference
+//! in that profile's `prefs.js`).
+
+use std::path::{Path, PathBuf};
+
+const SNAP_DIR: &str = "zotero-snap";
+const FLATPAK_ID: &str = "org.zotero.Zotero";
+
+/// Default data directories for this platform, most likely first.
+pub fn default_data_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![home.join("Zotero"), home.join("Zotero Beta")];
+    if cfg!(target_os = "linux") {
+        let snap = home.join("snap").join(SNAP_DIR);
+        dirs.push(snap.join("common").join("Zotero"));
+        dirs.push(snap.join("current").join("Zotero"));
+        dirs.push(home.join(".var").join("app").join(FLATPAK_ID).join("data").join("Zotero"));
+    }
+    dirs
+}
+
+/// Folders that may hold Zotero's `profiles.ini`.
+fn profile_roots(home: &Path) -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let app_data = std::env::var_os("APPDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join("AppData").join("Roaming"));
+        return vec![app_data.join("Zotero").join("Zotero")];
+    }
+    if cfg!(target_os = "macos") {
+        return vec![home.join("Library").join("Application Support").join("Zotero")];
+    }
+
+    vec![
+        home.join(".zotero").join("zotero"),
+        home.join("snap").join(SNAP_DIR).join("current").join(".zotero").join("zotero"),
+        home.join(".var").join("app").join(FLATPAK_ID).join(".zotero").join("zotero"),
+    ]
+}
+
+/// Profile directories listed in `profiles.ini`, the default profile first.
+/// Relative paths are resolved against `root`.
+fn parse_profiles_ini(content: &str, root: &Path) -> Vec<PathBuf> {
+    let mut profiles = Vec::<(bool, PathBuf)>::new();
+    let mut in_profile = false;
+    let mut path = None::<String>;
+    let mut is_relative = true;
+    let mut is_default = false;
+
+    let mut flush = |path: &mut Option<String>, is_relative: bool, is_default: bool| {
+        if let Some(path) = path.take() {
+            let dir = if is_relative { root.join(&path) } else { PathBuf::from(&path) };
+            profiles.push((is_default, dir));
+        }
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            flush(&mut path, is_relative, is_default);
+            in_profile = line.starts_with("[Profile");
+            is_relative = true;
+            is_default = false;
+            continue;
+        }
+        if !in_profile {
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Path", value)) => path = Some(value.trim().to_string()),
+            Some(("IsRelative", value)) => is_relative = value.trim() != "0",
+            Some(("Default", value)) => is_default = value.trim() == "1",
+            _ => {}
+        }
+    }
+    flush(&mut path, is_relative, is_default);
+
+    profiles.sort_by_key(|(is_default, _)| !is_default);
+    profiles.into_iter().map(|(_, dir)| dir).collect()
+}
+
+/// Value of a string `user_pref(...)` in `prefs.js`.
+fn string_pref(content: &str, name: &str) -> Option<String> {
+    let prefix = format!("user_pref(\"{name}\",");
+    content.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(&prefix)?.trim();
+        let value = value.strip_suffix(");")?.trim();
+        let value = value.strip_prefix('"')?.strip_suffix('"')?;
+
+        let mut unescaped = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => unescaped.extend(chars.next()),
+                _ => unescaped.push(ch),
+            }
+        }
+        Some(unescaped)
+    })
+}
+
+/// The custom data directory set in a profile's `prefs.js`, unless Zotero
+/// was switched back to the default location (`useDataDir` false).
+fn data_dir_from_prefs(content: &str) -> Option<PathBuf> {
+    if content.contains("user_pref(\"extensions.zotero.useDataDir\", false);") {
+        return None;
+    }
+    string_pref(content, "extensions.zotero.dataDir")
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Data directory configured in Zotero's profile, if it holds zotero.sqlite.
+pub fn discover_data_dir(home: &Path) -> Option<PathBuf> {
+    profile_roots(home).into_iter().find_map(|root| {
+        let ini = std::fs::read_to_string(root.join("profiles.ini")).ok()?;
+        parse_profiles_ini(&ini, &root).into_iter().find_map(|profile| {
+            let prefs = std::fs::read_to_string(profile.join("prefs.js")).ok()?;
+            data_dir_from_prefs(&prefs).filter(|dir| dir.join("zotero.sqlite").exists())
+        })
+    })
+}
+
+/// The linked-file base directory (`extensions.zotero.baseAttachmentPath`)
+/// that `attachments:` paths are relative to.
+pub fn base_attachment_dir(home: &Path) -> Option<PathBuf> {
+    profile_roots(home).into_iter().find_map(|root| {
+        let ini = std::fs::read_to_string(root.join("profiles.ini")).ok()?;
+        parse_profiles_ini(&ini, &root).into_iter().find_map(|profile| {
+            let prefs = std::fs::read_to_string(profile.join("prefs.js")).ok()?;
+            string_pref(&prefs, "extensions.zotero.baseAttachmentPath")
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFS: &str = r#"// Mozilla User Preferences
+user_pref("extensions.zotero.baseAttachmentPath", "/home/user/Papers");
+user_pref("extensions.zotero.dataDir", "/data/Zotero");
+user_pref("extensions.zotero.useDataDir", true);
+"#;
+
+    #[test]
+    fn data_dir_is_read_when_use_data_dir_is_true() {
+        assert_eq!(data_dir_from_prefs(PREFS), Some(PathBuf::from("/data/Zotero")));
+    }
+
+    #[test]
+    fn data_dir_is_ignored_when_use_data_dir_is_false() {
+        let prefs = PREFS.replace("useDataDir\", true", "useDataDir\", false");
+        assert_eq!(data_dir_from_prefs(&prefs), None);
+    }
+
+    #[test]
+    fn quoted_windows_paths_are_unescaped() {
+        let prefs = r#"user_pref("extensions.zotero.dataDir", "C:\\Users\\Ann \"A\"\\Zotero");"#;
+        assert_eq!(
+            data_dir_from_prefs(prefs),
+            Some(PathBuf::from(r#"C:\Users\Ann "A"\Zotero"#))
+        );
+    }
+
+    #[test]
+    fn missing_or_empty_data_dir_gives_none() {
+        assert_eq!(data_dir_from_prefs("user_pref(\"extensions.zotero.useDataDir\", true);\n"), None);
+        assert_eq!(data_dir_from_prefs("user_pref(\"extensions.zotero.dataDir\", \" \");\n"), None);
+        assert_eq!(string_pref(PREFS, "extensions.zotero.missing"), None);
+        assert_eq!(
+            string_pref(PREFS, "extensions.zotero.baseAttachmentPath").as_deref(),
+            Some("/home/user/Papers")
+        );
+    }
+
+    #[test]
+    fn default_profile_comes_first() {
+        let ini = "\
+[General]
+StartWithLastProfile=1
+
+[Profile0]
+Name=old
+IsRelative=0
+Path=/elsewhere/old
+
+[Profile1]
+Name=default
+IsRelative=1
+Path=Profiles/abcd.default
+Default=1
+";
+        let root = Path::new("/root/.zotero/zotero");
+        assert_eq!(
+            parse_profiles_ini(ini, root),
+            [root.join("Profiles/abcd.default"), PathBuf::from("/elsewhere/old")]
+        );
+        assert!(parse_profiles_ini("[General]\nPath=ignored\n", root).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn discover_data_dir_needs_zotero_sqlite() {
+        let home = std::env::temp_dir().join(format!("zotnotes-profile-{}", std::process::id()));
+        let profile = home.join(".zotero/zotero/Profiles/abcd.default");
+        let data = home.join("data");
+        std::fs::create_dir_all(&profile).unwrap();
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::write(
+            home.join(".zotero/zotero/profiles.ini"),
+            "[Profile0]\nPath=Profiles/abcd.default\nDefault=1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            profile.join("prefs.js"),
+            format!("user_pref(\"extensions.zotero.dataDir\", \"{}\");\n", data.display()),
+        )
+        .unwrap();
+
+        assert_eq!(discover_data_dir(&home), None);
+        std::fs::write(data.join("zotero.sqlite"), "").unwrap();
+        assert_eq!(discover_data_dir(&home), Some(data));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -2,19 +2,30 @@ import { useEffect, useMemo, useState } from 'react';
 import type { DragEvent } from 'react';
 import {
   ArrowDownUp,
+  AtSign,
   BookOpenText,
   CalendarDays,
   ChevronDown,
   ChevronUp,
   FileDown,
+  FileInput,
+  FileJson,
+  FileSpreadsheet,
+  FileText,
+  FileType,
   FlaskConical,
   Folder,
   GripVertical,
   Hash,
+  Highlighter,
+  History,
   KeyRound,
   LibraryBig,
   Link2,
+  ListTodo,
   LoaderCircle,
+  Unlink,
+  MessageSquareShare,
   Menu,
   Paintbrush2,
   Palette,
@@ -34,12 +45,81 @@ import { ItemPicker } from '@/components/ItemPicker';
 import { Toasts, type ToastMessage } from '@/components/Toasts';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card';
-import type { AppSettings, ItemSummary, TemplatePropertyKey, TemplateSettings, ZoteroItemData } from '@/lib/types';
-import { ensureDir, loadSettings, saveMarkdownFile, savePngBytes, saveSettings } from '@/lib/tauri';
+import type {
+  AppSettings,
+  AutoSyncProgress,
+  CitationScan,
+  ExportReport,
+  ImageTextStyle,
+  ImportReport,
+  ItemReport,
+  ItemSummary,
+  Job,
+  OrphanAction,
+  OrphanedNote,
+  SyncRun,
+  OperationProgress,
+  QuickListItem,
+  TagMapping,
+  TemplatePropertyKey,
+  TemplateSettings,
+  WriteStatus,
+  ZoteroItemData,
+  ZoteroFreshness,
+} from '@/lib/types';
+import {
+  ensureDir,
+  exportItemBundle,
+  exportItems,
+  exportItemsCsv,
+  exportHighlightsReadwise,
+  exportItemsRis,
+  exportNotesDocx,
+  exportNotesNotion,
+  loadSettings,
+  cancelJob,
+  enqueueJob,
+  findOrphanedNotes,
+  findUnlinkedCitations,
+  getQuickList,
+  importNotes,
+  isTauriRuntime,
+  getSyncHistory,
+  listJobs,
+  newOperationId,
+  ocrAnnotationImage,
+  onAutoSyncEvents,
+  onJobsChanged,
+  onOperationProgress,
+  onSettingsChanged,
+  onZoteroLibraryChanged,
+  openInEditor,
+  openInObsidian,
+  openInZotero,
+  revealInFileManager,
+  pinItem,
+  prefetchAnnotationImages,
+  pushCommentEdits,
+  resolveOrphanedNotes,
+  retryJob,
+  recordItemExport,
+  recordItemOpened,
+  renderNote,
+  resolveExportTarget,
+  saveItemAsset,
+  saveMarkdownFile,
+  saveSettings,
+  selectDirectoryDialog,
+  selectSaveFileDialog,
+  syncChangedItems,
+  unpinItem,
+  updateSettings,
+  writeExportChangelog,
+} from '@/lib/tauri';
 import { ZoteroClient } from '@/lib/zotero';
 import { resolveCiteKey } from '@/lib/citekey';
-import { addMissingImageTodo, prepareExport, renderPreparedExport } from '@/lib/exporter';
-import { cn, extractYear } from '@/lib/utils';
+import { addMissingImageTodo, prepareExport, renderPreparedExport, setImageLink, setImageText } from '@/lib/exporter';
+import { cn, errorMessage, extractYear } from '@/lib/utils';
 import { COLOR_SWATCH_HEX, ORDERED_COLOR_NAMES } from '@/lib/colors';
 
 const TEMPLATE_PROPERTY_LABELS: Record<TemplatePropertyKey, string> = {
@@ -51,17 +131,97 @@ const TEMPLATE_PROPERTY_LABELS: Record<TemplatePropertyKey, string> = {
 
 const TEMPLATE_PROPERTY_KEYS: TemplatePropertyKey[] = ['title', 'author', 'year', 'company'];
 
+const DEFAULT_TAG_MAPPING: TagMapping = {
+  enabled: false,
+  prefix: '',
+  denyList: [],
+  slugify: false,
+};
+
 const DEFAULT_TEMPLATE_SETTINGS: TemplateSettings = {
   propertyOrder: [...TEMPLATE_PROPERTY_KEYS],
   colorHeadingOverrides: {},
+  blockIds: false,
+  frontmatterFields: [],
+  metadataOutput: 'frontmatter',
+  tagMapping: DEFAULT_TAG_MAPPING,
+  escapeMarkdown: false,
+  preserveMath: false,
+  sectionHeadingLevel: 3,
+  colorOrder: [],
+  otherColorsHeading: '',
+  grouping: 'color',
+  tableOfContents: false,
+  pdfLinks: false,
+  dateFormat: { pattern: '', locale: '' },
+  imageText: 'altText',
 };
 
 const DEFAULT_SETTINGS: AppSettings = {
+  outputTarget: 'markdown',
   markdownDir: '',
   attachmentBaseDir: '',
   zoteroApiKey: '',
   zoteroBaseUrl: 'http://127.0.0.1:23119',
+  zoteroUserId: '',
   templateSettings: DEFAULT_TEMPLATE_SETTINGS,
+  imageSettings: {
+    format: 'png',
+    quality: 85,
+    maxWidth: null,
+    maxHeight: null,
+    renderDpi: null,
+    darkMode: 'off',
+  },
+  assetPathTemplate: '',
+  imageLinkStyle: 'attachmentRelative',
+  ocr: {
+    enabled: false,
+    languages: 'eng',
+    tesseractPath: '',
+  },
+  joplin: {
+    baseUrl: 'http://127.0.0.1:41184',
+    token: '',
+    notebook: '',
+  },
+  changelogNote: '',
+  git: {
+    autoCommit: false,
+  },
+  collectionOverrides: [],
+  editorCommand: '',
+  obsidian: {
+    openAfterExport: false,
+    vault: '',
+  },
+  csvColumns: [],
+  autoSync: {
+    enabled: false,
+    intervalMinutes: 30,
+    onStartup: false,
+    startupDelaySeconds: 10,
+  },
+  apiServer: {
+    enabled: false,
+    port: 23121,
+    token: '',
+  },
+  conflictStrategy: 'conflictCopy',
+  deletedAnnotations: 'remove',
+  orphanArchiveDir: '_archive',
+  orphanTag: 'zotero/deleted',
+  excludeTag: '',
+  zoteroDataDir: '',
+  webdav: {
+    url: '',
+    username: '',
+    password: '',
+  },
+  searchLimit: 75,
+  searchFuzziness: 1,
+  fullTextSearch: true,
+  logLevel: 'info',
 };
 
 type ConnectionState = 'unknown' | 'checking' | 'connected' | 'disconnected';
@@ -134,6 +294,32 @@ function maskApiKey(key: string): string {
   return `${trimmed.slice(0, 4)}...${trimmed.slice(-2)}`;
 }
 
+/** Whether the item carries `tag`, compared like Zotero does, ignoring case. */
+function hasTag(item: ZoteroItemData, tag: string): boolean {
+  const wanted = tag.trim().toLowerCase();
+  if (!wanted || !Array.isArray(item.data.tags)) {
+    return false;
+  }
+  return item.data.tags.some(
+    (entry) => String((entry as { tag?: unknown }).tag ?? '').trim().toLowerCase() === wanted,
+  );
+}
+
+function countByStatus(report: ExportReport, status: WriteStatus): number {
+  return report.items.filter((item) => item.status === status).length;
+}
+
+function freshnessNote(freshness: ZoteroFreshness | undefined): string {
+  switch (freshness?.kind) {
+    case 'lagging':
+      return 'Zotero has changes it has not saved to zotero.sqlite yet; edits from the last few minutes may be missing.';
+    case 'snapshot':
+      return `zotero.sqlite could not be read directly, so a copy taken at ${new Date(freshness.takenAt * 1000).toLocaleTimeString()} was used.`;
+    default:
+      return '';
+  }
+}
+
 function shortPath(path: string): string {
   const trimmed = path.trim();
   if (!trimmed) {
@@ -160,6 +346,20 @@ function normalizeTemplateSettings(input: TemplateSettings | undefined): Templat
   return {
     propertyOrder: deduped,
     colorHeadingOverrides: { ...(input?.colorHeadingOverrides ?? {}) },
+    blockIds: input?.blockIds ?? false,
+    frontmatterFields: [...(input?.frontmatterFields ?? [])],
+    metadataOutput: input?.metadataOutput ?? 'frontmatter',
+    tagMapping: { ...DEFAULT_TAG_MAPPING, ...(input?.tagMapping ?? {}) },
+    escapeMarkdown: input?.escapeMarkdown ?? false,
+    preserveMath: input?.preserveMath ?? false,
+    sectionHeadingLevel: input?.sectionHeadingLevel ?? 3,
+    colorOrder: [...(input?.colorOrder ?? [])],
+    otherColorsHeading: input?.otherColorsHeading ?? '',
+    grouping: input?.grouping ?? 'color',
+    tableOfContents: input?.tableOfContents ?? false,
+    pdfLinks: input?.pdfLinks ?? false,
+    dateFormat: { pattern: '', locale: '', ...(input?.dateFormat ?? {}) },
+    imageText: input?.imageText ?? 'altText',
   };
 }
 
@@ -192,6 +392,16 @@ export default function App() {
   const [query, setQuery] = useState('');
   const [items, setItems] = useState<ItemSummary[]>([]);
   const [loadingItems, setLoadingItems] = useState(false);
+  const [quickList, setQuickList] = useState<QuickListItem[]>([]);
+  const [libraryRevision, setLibraryRevision] = useState(0);
+  const [autoSyncProgress, setAutoSyncProgress] = useState<AutoSyncProgress | null>(null);
+  const [activeOperationId, setActiveOperationId] = useState<string | null>(null);
+  const [operationProgress, setOperationProgress] = useState<OperationProgress | null>(null);
+  const [jobs, setJobs] = useState<Job[]>([]);
+  const [syncHistory, setSyncHistory] = useState<SyncRun[] | null>(null);
+  const [orphanedNotes, setOrphanedNotes] = useState<OrphanedNote[] | null>(null);
+  const [importPreview, setImportPreview] = useState<ImportReport | null>(null);
+  const [citationScan, setCitationScan] = useState<CitationScan | null>(null);
 
   const [selectedItemKeys, setSelectedItemKeys] = useState<string[]>([]);
   const [selectedItemMetaByKey, setSelectedItemMetaByKey] = useState<Record<string, SelectedItemMeta>>({});
@@ -199,6 +409,7 @@ export default function App() {
 
   const [isExporting, setIsExporting] = useState(false);
   const [dryRunOutput, setDryRunOutput] = useState('');
+  const [exportReport, setExportReport] = useState<ExportReport | null>(null);
 
   const [activeTab, setActiveTab] = useState<SidebarTab>('export');
   const [templateDraft, setTemplateDraft] = useState<TemplateSettings>(DEFAULT_TEMPLATE_SETTINGS);
@@ -222,6 +433,8 @@ export default function App() {
   };
 
   const client = useMemo(() => new ZoteroClient(settings), [settings]);
+  const activeProgress =
+    operationProgress && operationProgress.operationId === activeOperationId ? operationProgress : null;
   const canExport = settingsConfigured(settings) && connectionState === 'connected' && selectedItemKeys.length > 0 && !isExporting;
 
   useEffect(() => {
@@ -274,7 +487,7 @@ export default function App() {
           setSettingsOpen(true);
         }
       } catch (error) {
-        addToast('error', error instanceof Error ? error.message : String(error));
+        addToast('error', errorMessage(error));
       }
     })();
   }, []);
@@ -315,6 +528,121 @@ export default function App() {
     };
   }, [client, settings]);
 
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let disposed = false;
+
+    void onAutoSyncEvents({
+      onProgress: setAutoSyncProgress,
+      onCompleted: (report) => {
+        setAutoSyncProgress(null);
+        if (report.items.length > 0) {
+          setExportReport(report);
+          addToast('success', `Background sync updated ${report.items.length} item(s).`);
+        }
+        if (report.failed.length > 0) {
+          addToast('error', `Background sync failed for ${report.failed.length} item(s).`);
+        }
+      },
+      onFailed: (error) => {
+        setAutoSyncProgress(null);
+        addToast('error', `Background sync failed: ${errorMessage(error)}`);
+      },
+    }).then((dispose) => {
+      if (disposed) {
+        dispose();
+      } else {
+        unlisten = dispose;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let disposed = false;
+
+    const refreshJobs = () => {
+      void listJobs(10)
+        .then(setJobs)
+        .catch(() => undefined);
+    };
+    refreshJobs();
+    void onJobsChanged(refreshJobs).then((dispose) => {
+      if (disposed) {
+        dispose();
+      } else {
+        unlisten = dispose;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let disposed = false;
+
+    void onOperationProgress(setOperationProgress).then((dispose) => {
+      if (disposed) {
+        dispose();
+      } else {
+        unlisten = dispose;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let disposed = false;
+
+    void onSettingsChanged((saved) => onSettingsReplaced(saved)).then((dispose) => {
+      if (disposed) {
+        dispose();
+      } else {
+        unlisten = dispose;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let disposed = false;
+
+    void onZoteroLibraryChanged(() => {
+      setLibraryRevision((prev) => prev + 1);
+      addToast('info', 'Zotero library changed. Use "Sync new highlights" to update exported notes.');
+    }).then((dispose) => {
+      if (disposed) {
+        dispose();
+      } else {
+        unlisten = dispose;
+      }
+    });
+
+    return () => {
+      disposed = true;
+      unlisten?.();
+    };
+  }, []);
+
   useEffect(() => {
     if (!settingsConfigured(settings) || connectionState !== 'connected') {
       setItems([]);
@@ -348,7 +676,7 @@ export default function App() {
             if (!cancelled) {
               setConnectionState('disconnected');
               setItems([]);
-              addToast('error', error instanceof Error ? error.message : String(error));
+              addToast('error', errorMessage(error));
             }
           })
           .finally(() => {
@@ -363,7 +691,7 @@ export default function App() {
       cancelled = true;
       window.clearTimeout(timeout);
     };
-  }, [client, connectionState, query, settings]);
+  }, [client, connectionState, libraryRevision, query, settings]);
 
   const resolveItemCiteKey = async (item: ZoteroItemData): Promise<string> => {
     try {
@@ -389,6 +717,12 @@ export default function App() {
     setTemplateDraft(normalizedTemplate);
   };
 
+  const onSettingsReplaced = (loaded: AppSettings) => {
+    const normalizedTemplate = normalizeTemplateSettings(loaded.templateSettings);
+    setSettings({ ...loaded, templateSettings: normalizedTemplate });
+    setTemplateDraft(normalizedTemplate);
+  };
+
   const hydrateSelectedItemMeta = async (itemKey: string, fallbackTitle: string, fallbackYear: string) => {
     try {
       const full = await client.getItem(itemKey);
@@ -433,6 +767,24 @@ export default function App() {
     setActiveItemKey(itemKey);
   };
 
+  const refreshQuickList = () => {
+    void getQuickList()
+      .then(setQuickList)
+      .catch(() => setQuickList([]));
+  };
+
+  useEffect(() => {
+    if (connectionState === 'connected') {
+      refreshQuickList();
+    }
+  }, [connectionState]);
+
+  const togglePin = (item: ItemSummary, pinned: boolean) => {
+    void (pinned ? pinItem(item.key) : unpinItem(item.key))
+      .then(refreshQuickList)
+      .catch((error) => addToast('error', errorMessage(error)));
+  };
+
   const onSelectItem = async (item: ItemSummary) => {
     if (connectionState !== 'connected') {
       addToast('error', 'Start Zotero Desktop and wait for status to show Connected before selecting items.');
@@ -456,6 +808,9 @@ export default function App() {
 
     setDryRunOutput('');
     activateItem(item.key);
+    void recordItemOpened(item.key)
+      .then(refreshQuickList)
+      .catch(() => undefined);
   };
 
   const removeSelectedItem = (itemKey: string) => {
@@ -476,18 +831,14 @@ export default function App() {
   const saveTemplateChanges = async () => {
     setIsSavingTemplate(true);
     const normalizedTemplate = normalizeTemplateSettings(templateDraft);
-    const nextSettings = {
-      ...settings,
-      templateSettings: normalizedTemplate,
-    };
 
     try {
-      await saveSettings(nextSettings);
-      setSettings(nextSettings);
+      const saved = await updateSettings({ templateSettings: normalizedTemplate });
+      setSettings({ ...saved, templateSettings: normalizedTemplate });
       setTemplateDraft(normalizedTemplate);
       addToast('success', 'Template settings saved.');
     } catch (error) {
-      addToast('error', error instanceof Error ? error.message : String(error));
+      addToast('error', errorMessage(error));
     } finally {
       setIsSavingTemplate(false);
     }
@@ -557,25 +908,61 @@ export default function App() {
     });
   };
 
-  const runExport = async (dryRun: boolean) => {
+  const exportToJoplin = async (dryRun: boolean, itemKeys: string[]) => {
+    const operationId = newOperationId('export');
+    setIsExporting(true);
+    setActiveOperationId(operationId);
+    setDryRunOutput('');
+    setExportReport(null);
+    try {
+      const report = await exportItems(itemKeys, dryRun, operationId);
+      setExportReport(report);
+      if (report.items.length > 0) {
+        addToast(
+          dryRun ? 'info' : 'success',
+          dryRun
+            ? `Export would send ${report.items.length} note(s) to Joplin.`
+            : `Sent ${report.items.length} note(s) to Joplin.`,
+        );
+      }
+      if (report.failed.length > 0) {
+        addToast('error', `Failed to export ${report.failed.length} item(s) to Joplin.`);
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setIsExporting(false);
+      setActiveOperationId(null);
+    }
+  };
+
+  const runExport = async (dryRun: boolean, itemKeys: string[] = selectedItemKeys) => {
     if (connectionState !== 'connected') {
       addToast('error', 'Start Zotero Desktop and wait for status to show Connected before exporting.');
       return;
     }
 
-    if (selectedItemKeys.length === 0) {
+    if (itemKeys.length === 0) {
+      return;
+    }
+
+    if (settings.outputTarget === 'joplin') {
+      await exportToJoplin(dryRun, itemKeys);
       return;
     }
 
     setIsExporting(true);
     setDryRunOutput('');
+    setExportReport(null);
 
     try {
-      const keysToExport = [...selectedItemKeys];
+      const keysToExport = [...itemKeys];
       const dryRunSections: string[] = [];
       const failedItems: string[] = [];
       const missingImageWarnings: string[] = [];
       const itemsWithNoAnnotations: string[] = [];
+      const reportItems: ItemReport[] = [];
+      const excludedItems: string[] = [];
       let successCount = 0;
 
       if (!dryRun) {
@@ -586,8 +973,15 @@ export default function App() {
       for (const itemKey of keysToExport) {
         try {
           const freshItem = await client.getItem(itemKey);
+          // Same rule as backend exports: items with the exclusion tag are skipped.
+          if (hasTag(freshItem, settings.excludeTag)) {
+            excludedItems.push(itemKey);
+            continue;
+          }
           const citeKey = await resolveItemCiteKey(freshItem);
           const freshAnnotations = await client.getAnnotationsForItem(itemKey);
+          const childNotes = await client.getChildNotes(itemKey);
+          const target = await resolveExportTarget(freshItem, citeKey, templateDraft);
 
           let prepared = prepareExport({
             markdownDir: settings.markdownDir,
@@ -595,12 +989,32 @@ export default function App() {
             citeKey,
             item: freshItem,
             annotations: freshAnnotations,
+            imageExtension: settings.imageSettings.format === 'jpeg' ? 'jpg' : settings.imageSettings.format,
+            markdownPath: target?.markdownPath,
           });
+          const noteDir = prepared.markdownPath.slice(0, prepared.markdownPath.lastIndexOf('/'));
 
           const imageWarningsForItem: string[] = [];
 
+          if (prepared.imagePlans.length > 0) {
+            // Warms the image cache in parallel; the loop below then reads from it.
+            const operationId = newOperationId('prefetch');
+            setActiveOperationId(operationId);
+            try {
+              await prefetchAnnotationImages(itemKey, operationId);
+            } catch {
+              // Images are still fetched one by one below.
+            } finally {
+              setActiveOperationId(null);
+            }
+          }
+
           for (const imagePlan of prepared.imagePlans) {
-            const bytes = await client.getSelectedAreaImage(imagePlan.annotationKey, imagePlan.attachmentKey);
+            const bytes = await client.getSelectedAreaImage(
+              imagePlan.annotationKey,
+              imagePlan.attachmentKey,
+              imagePlan.dateModified,
+            );
             if (!bytes || bytes.length === 0) {
               const warning = `@${citeKey}: selected-area image missing for annotation ${imagePlan.annotationKey}.`;
               prepared = addMissingImageTodo(prepared, imagePlan.annotationKey, warning);
@@ -609,11 +1023,37 @@ export default function App() {
             }
 
             if (!dryRun) {
-              await savePngBytes(imagePlan.absolutePath, Array.from(bytes));
+              const saved = await saveItemAsset(
+                itemKey,
+                citeKey,
+                imagePlan.fileName,
+                Array.from(bytes),
+                noteDir,
+                imagePlan.annotationKey,
+              );
+              prepared = setImageLink(prepared, imagePlan.annotationKey, saved.link);
+            }
+
+            if (settings.ocr.enabled) {
+              try {
+                const text = await ocrAnnotationImage(bytes);
+                if (text) {
+                  prepared = setImageText(prepared, imagePlan.annotationKey, text);
+                }
+              } catch (error) {
+                imageWarningsForItem.push(`@${citeKey}: OCR failed for annotation ${imagePlan.annotationKey}: ${errorMessage(error)}`);
+              }
             }
           }
 
-          const markdown = renderPreparedExport(prepared, templateDraft);
+          // Outside Tauri there is no backend renderer; the browser one keeps
+          // dry runs working, without child notes.
+          const markdown = isTauriRuntime()
+            ? await renderNote(
+                { item: freshItem, citeKey, groupedAnnotations: prepared.groupedAnnotations, childNotes },
+                target?.templateSettings ?? templateDraft,
+              )
+            : renderPreparedExport(prepared, target?.templateSettings ?? templateDraft);
           if (freshAnnotations.length === 0) {
             itemsWithNoAnnotations.push(`@${citeKey}`);
           }
@@ -624,13 +1064,30 @@ export default function App() {
             const warningLines =
               imageWarningsForItem.length > 0 ? `\nWarnings:\n${imageWarningsForItem.map((w) => `- ${w}`).join('\n')}` : '';
 
+            const overrideLine = target?.collection ? `\nCollection override: ${target.collection}` : '';
+
             dryRunSections.push(
-              `Item: @${citeKey}\nAnnotations found: ${freshAnnotations.length}\nMarkdown path: ${prepared.markdownPath}\n\nImage plan:\n${
+              `Item: @${citeKey}\nAnnotations found: ${freshAnnotations.length}\nMarkdown path: ${prepared.markdownPath}${overrideLine}\n\nImage plan:\n${
                 imagePlanLines || '- (none)'
               }${warningLines}\n\n${markdown}`,
             );
           } else {
-            await saveMarkdownFile(prepared.markdownPath, markdown);
+            const stamps = freshAnnotations.map((annotation) => ({
+              key: annotation.key,
+              dateAdded: annotation.dateAdded,
+              dateModified: annotation.dateModified,
+            }));
+            const written = await saveMarkdownFile(prepared.markdownPath, markdown, stamps, itemKey);
+            await recordItemExport(itemKey, citeKey, written.path, freshItem.version, stamps, written.contentHash);
+            reportItems.push({
+              itemKey,
+              citeKey,
+              path: written.path,
+              status: written.status,
+              annotationCount: freshAnnotations.length,
+              newAnnotations: written.newAnnotationKeys.length,
+              conflictPath: written.conflictPath,
+            });
           }
 
           setSelectedItemMetaByKey((prev) => {
@@ -649,7 +1106,7 @@ export default function App() {
 
           successCount += 1;
         } catch (error) {
-          const message = error instanceof Error ? error.message : String(error);
+          const message = errorMessage(error);
           failedItems.push(`${itemKey}: ${message}`);
         }
       }
@@ -658,8 +1115,30 @@ export default function App() {
         const failureBlock = failedItems.length > 0 ? `\n\nFailed items:\n${failedItems.map((item) => `- ${item}`).join('\n')}` : '';
         setDryRunOutput(`${dryRunSections.join('\n\n----------------------------------------\n\n')}${failureBlock}`.trim());
         addToast('info', `Dry run complete for ${successCount} of ${keysToExport.length} selected item(s).`);
-      } else if (successCount > 0) {
-        addToast('success', `Exported ${successCount} item(s).`);
+      } else {
+        const report: ExportReport = { items: reportItems, failed: failedItems, excluded: excludedItems };
+        setExportReport(report);
+        if (successCount > 0) {
+          addToast('success', `Exported ${successCount} item(s).`);
+        }
+        if (settings.obsidian.openAfterExport && reportItems.length === 1) {
+          try {
+            await openInObsidian(reportItems[0].path);
+          } catch (error) {
+            addToast('error', `Could not open the note in Obsidian: ${errorMessage(error)}`);
+          }
+        }
+        if (reportItems.length > 0) {
+          try {
+            await writeExportChangelog(report);
+          } catch (error) {
+            addToast('error', `Changelog not written: ${errorMessage(error)}`);
+          }
+        }
+      }
+
+      if (excludedItems.length > 0) {
+        addToast('info', `Skipped ${excludedItems.length} item(s) tagged "${settings.excludeTag.trim()}".`);
       }
 
       if (itemsWithNoAnnotations.length > 0) {
@@ -681,7 +1160,325 @@ export default function App() {
       }
     } catch (error) {
       setConnectionState('disconnected');
-      addToast('error', error instanceof Error ? error.message : String(error));
+      addToast('error', errorMessage(error));
+    } finally {
+      setIsExporting(false);
+      refreshQuickList();
+    }
+  };
+
+  const openExportedNote = async (path: string) => {
+    try {
+      await openInEditor(path);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const revealExportedNote = async (path: string) => {
+    try {
+      await revealInFileManager(path);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const showInZotero = async (itemKey: string) => {
+    try {
+      await openInZotero(itemKey);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const exportMetadataCsv = async () => {
+    const itemKeys = items.length > 0 ? items.map((item) => item.key) : selectedItemKeys;
+    if (itemKeys.length === 0) {
+      return;
+    }
+
+    try {
+      const path = await selectSaveFileDialog('zotero-items.csv');
+      if (!path) {
+        return;
+      }
+      const operationId = newOperationId('csv-export');
+      setActiveOperationId(operationId);
+      const count = await exportItemsCsv(path, itemKeys, undefined, operationId);
+      addToast('success', `Wrote ${count} item(s) to ${shortPath(path)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setActiveOperationId(null);
+    }
+  };
+
+  const exportMetadataRis = async () => {
+    const itemKeys = items.length > 0 ? items.map((item) => item.key) : selectedItemKeys;
+    if (itemKeys.length === 0) {
+      return;
+    }
+
+    try {
+      const path = await selectSaveFileDialog('zotero-items.ris');
+      if (!path) {
+        return;
+      }
+      const operationId = newOperationId('ris-export');
+      setActiveOperationId(operationId);
+      const count = await exportItemsRis(path, itemKeys, undefined, operationId);
+      addToast('success', `Wrote ${count} RIS record(s) to ${shortPath(path)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setActiveOperationId(null);
+    }
+  };
+
+  const exportReadwiseHighlights = async () => {
+    const itemKeys = items.length > 0 ? items.map((item) => item.key) : selectedItemKeys;
+    if (itemKeys.length === 0) {
+      return;
+    }
+
+    try {
+      const path = await selectSaveFileDialog('readwise-highlights.csv');
+      if (!path) {
+        return;
+      }
+      const format = path.toLowerCase().endsWith('.json') ? 'json' : 'csv';
+      const operationId = newOperationId('readwise-export');
+      setActiveOperationId(operationId);
+      const count = await exportHighlightsReadwise(path, itemKeys, format, undefined, operationId);
+      addToast('success', `Wrote ${count} highlight(s) to ${shortPath(path)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setActiveOperationId(null);
+    }
+  };
+
+  const exportSelectedDocx = async () => {
+    if (selectedItemKeys.length === 0) {
+      return;
+    }
+
+    try {
+      const path = await selectSaveFileDialog(selectedItemKeys.length === 1 ? 'note.docx' : 'notes.docx');
+      if (!path) {
+        return;
+      }
+      const operationId = newOperationId('docx-export');
+      setActiveOperationId(operationId);
+      const count = await exportNotesDocx(path, selectedItemKeys, undefined, operationId);
+      addToast('success', `Wrote ${count} note(s) to ${shortPath(path)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setActiveOperationId(null);
+    }
+  };
+
+  const exportSelectedNotion = async () => {
+    if (selectedItemKeys.length === 0) {
+      return;
+    }
+
+    try {
+      const dir = await selectDirectoryDialog();
+      if (!dir) {
+        return;
+      }
+      const operationId = newOperationId('notion-export');
+      setActiveOperationId(operationId);
+      const count = await exportNotesNotion(dir, selectedItemKeys, undefined, operationId);
+      addToast('success', `Wrote ${count} Notion page(s) to ${shortPath(dir)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setActiveOperationId(null);
+    }
+  };
+
+  const exportActiveItemBundle = async () => {
+    if (!activeItemKey) {
+      return;
+    }
+
+    try {
+      const citeKey = selectedItemMetaByKey[activeItemKey]?.citeKey || activeItemKey;
+      const path = await selectSaveFileDialog(`@${citeKey}.json`);
+      if (!path) {
+        return;
+      }
+      const written = await exportItemBundle(activeItemKey, path);
+      addToast('success', `Wrote JSON bundle to ${shortPath(written)}.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const syncNewHighlights = async (dryRun = false) => {
+    if (connectionState !== 'connected') {
+      addToast('error', 'Start Zotero Desktop and wait for status to show Connected before exporting.');
+      return;
+    }
+
+    const operationId = newOperationId('sync');
+    setIsExporting(true);
+    setActiveOperationId(operationId);
+    setDryRunOutput('');
+    try {
+      const report = await syncChangedItems(operationId, dryRun);
+      if (report.items.length === 0 && report.failed.length === 0) {
+        addToast('info', 'No exported items have changed in Zotero.');
+        return;
+      }
+      setExportReport(report);
+      if (dryRun) {
+        const fileCount = report.items.reduce((count, item) => count + (item.changes?.length ?? 0), 0);
+        addToast('info', `Sync would write ${fileCount} file(s) for ${report.items.length} item(s).`);
+      } else if (report.items.length > 0) {
+        addToast('success', `Synced ${report.items.length} item(s).`);
+      }
+      if (report.failed.length > 0) {
+        addToast('error', `Failed to sync ${report.failed.length} item(s).`);
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    } finally {
+      setIsExporting(false);
+      setActiveOperationId(null);
+    }
+  };
+
+  const toggleSyncHistory = async () => {
+    if (syncHistory) {
+      setSyncHistory(null);
+      return;
+    }
+
+    try {
+      setSyncHistory(await getSyncHistory(20));
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const checkOrphanedNotes = async () => {
+    try {
+      const orphans = await findOrphanedNotes();
+      setOrphanedNotes(orphans);
+      if (orphans.length === 0) {
+        addToast('info', 'Every exported note still has its Zotero item.');
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const handleOrphanedNotes = async (action: OrphanAction) => {
+    if (!orphanedNotes || orphanedNotes.length === 0) {
+      return;
+    }
+
+    try {
+      const changed = await resolveOrphanedNotes(
+        orphanedNotes.map((orphan) => orphan.itemKey),
+        action,
+      );
+      addToast('success', `${action === 'archive' ? 'Archived' : 'Tagged'} ${changed.length} note(s).`);
+      setOrphanedNotes(await findOrphanedNotes());
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const previewNoteImport = async () => {
+    try {
+      const preview = await importNotes(undefined, true);
+      setImportPreview(preview);
+      if (preview.imported.length === 0) {
+        addToast('info', `No untracked notes matched a Zotero item (${preview.unmatched.length} unmatched).`);
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const runNoteImport = async () => {
+    try {
+      const report = await importNotes();
+      setImportPreview(null);
+      addToast('success', `Mapped ${report.imported.length} existing note(s) to Zotero items.`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const scanCitations = async () => {
+    try {
+      const scan = await findUnlinkedCitations();
+      setCitationScan(scan);
+      if (scan.unlinked.length === 0) {
+        addToast('info', 'Every cited item already has a note.');
+      }
+      if (scan.unknownKeys.length > 0) {
+        addToast('info', `Not in the library: ${scan.unknownKeys.map((key) => `@${key}`).join(', ')}`);
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const createCitedNotes = async () => {
+    if (!citationScan || citationScan.unlinked.length === 0) {
+      return;
+    }
+
+    const itemKeys = citationScan.unlinked.map((cited) => cited.itemKey);
+    setCitationScan(null);
+    await runExport(false, itemKeys);
+  };
+
+  const exportInBackground = async () => {
+    try {
+      const job = await enqueueJob({ kind: 'export', itemKeys: selectedItemKeys });
+      addToast('info', `Queued background export #${job.id} of ${selectedItemKeys.length} item(s).`);
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const updateJob = async (job: Job, action: 'cancel' | 'retry') => {
+    try {
+      const changed = action === 'cancel' ? await cancelJob(job.id) : await retryJob(job.id);
+      if (!changed) {
+        addToast('info', `Job #${job.id} is already ${job.status}.`);
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
+    }
+  };
+
+  const pushEditedComments = async () => {
+    setIsExporting(true);
+    try {
+      const report = await pushCommentEdits();
+      if (report.pushed.length > 0) {
+        addToast('success', `Pushed ${report.pushed.length} comment edit(s) to Zotero.`);
+      }
+      if (report.conflicts.length > 0) {
+        addToast('error', `${report.conflicts.length} comment(s) also changed in Zotero; re-export to review them.`);
+      }
+      if (report.failed.length > 0) {
+        addToast('error', `Failed to push ${report.failed.length} comment edit(s): ${report.failed[0]}`);
+      }
+      if (report.pushed.length + report.conflicts.length + report.failed.length === 0) {
+        addToast('info', 'No comment edits found in exported notes.');
+      }
+    } catch (error) {
+      addToast('error', errorMessage(error));
     } finally {
       setIsExporting(false);
     }
@@ -899,6 +1696,8 @@ export default function App() {
                   onSelect={(item) => void onSelectItem(item)}
                   dense={denseMode}
                   showHints={showHints}
+                  quickList={quickList}
+                  onTogglePin={togglePin}
                 />
 
                 <div className="flex flex-wrap gap-2">
@@ -916,10 +1715,135 @@ export default function App() {
                     )}
                   </Button>
 
+                  <Button type="button" variant="outline" disabled={!canExport} onClick={() => void exportInBackground()}>
+                    <ListTodo className="mr-2 h-4 w-4" />
+                    Export in background
+                  </Button>
+
                   <Button type="button" variant="outline" disabled={!canExport} onClick={() => void runExport(true)}>
                     <FlaskConical className="mr-2 h-4 w-4" />
                     Dry run selected
                   </Button>
+
+                  <Button
+                    type="button"
+                    variant="outline"
+                    disabled={!settingsConfigured(settings) || connectionState !== 'connected' || isExporting}
+                    onClick={() => void syncNewHighlights()}
+                  >
+                    <RefreshCw className="mr-2 h-4 w-4" />
+                    Sync new highlights
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={!settingsConfigured(settings) || connectionState !== 'connected' || isExporting}
+                    onClick={() => void syncNewHighlights(true)}
+                  >
+                    <FlaskConical className="mr-2 h-4 w-4" />
+                    Preview sync
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={!settings.zoteroUserId.trim() || isExporting}
+                    onClick={() => void pushEditedComments()}
+                  >
+                    <MessageSquareShare className="mr-2 h-4 w-4" />
+                    Push comment edits
+                  </Button>
+
+                  <Button type="button" variant="ghost" onClick={() => void toggleSyncHistory()}>
+                    <History className="mr-2 h-4 w-4" />
+                    Sync history
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={connectionState !== 'connected'}
+                    onClick={() => void checkOrphanedNotes()}
+                  >
+                    <Unlink className="mr-2 h-4 w-4" />
+                    Find orphaned notes
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={connectionState !== 'connected' || !settings.markdownDir}
+                    onClick={() => void previewNoteImport()}
+                  >
+                    <FileInput className="mr-2 h-4 w-4" />
+                    Import existing notes
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={connectionState !== 'connected' || !settings.markdownDir}
+                    onClick={() => void scanCitations()}
+                  >
+                    <AtSign className="mr-2 h-4 w-4" />
+                    Find unlinked citations
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={items.length === 0 && selectedItemKeys.length === 0}
+                    onClick={() => void exportMetadataCsv()}
+                  >
+                    <FileSpreadsheet className="mr-2 h-4 w-4" />
+                    Export CSV
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={items.length === 0 && selectedItemKeys.length === 0}
+                    onClick={() => void exportMetadataRis()}
+                  >
+                    <FileText className="mr-2 h-4 w-4" />
+                    Export RIS
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={items.length === 0 && selectedItemKeys.length === 0}
+                    onClick={() => void exportReadwiseHighlights()}
+                  >
+                    <Highlighter className="mr-2 h-4 w-4" />
+                    Export to Readwise
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={selectedItemKeys.length === 0 || connectionState !== 'connected'}
+                    onClick={() => void exportSelectedDocx()}
+                  >
+                    <FileType className="mr-2 h-4 w-4" />
+                    Export DOCX
+                  </Button>
+
+                  <Button
+                    type="button"
+                    variant="ghost"
+                    disabled={selectedItemKeys.length === 0 || connectionState !== 'connected'}
+                    onClick={() => void exportSelectedNotion()}
+                  >
+                    <BookOpenText className="mr-2 h-4 w-4" />
+                    Export for Notion
+                  </Button>
+
+                  <Button type="button" variant="ghost" disabled={!activeItemKey} onClick={() => void exportActiveItemBundle()}>
+                    <FileJson className="mr-2 h-4 w-4" />
+                    JSON bundle
+                  </Button>
                 </div>
 
                 <div className="rounded-md border border-border">
@@ -938,6 +1862,7 @@ export default function App() {
                         setSelectedItemMetaByKey({});
                         setActiveItemKey('');
                         setDryRunOutput('');
+                        setExportReport(null);
                       }}
                     >
                       <Trash2 className="mr-1.5 h-3.5 w-3.5" />
@@ -1001,6 +1926,268 @@ export default function App() {
                   </div>
                 </div>
 
+                {activeProgress && activeProgress.total > 0 && (
+                  <div className="space-y-1">
+                    <div className="h-1.5 overflow-hidden rounded-full bg-[rgba(255,255,255,0.08)]">
+                      <div
+                        className="h-full bg-primary transition-all"
+                        style={{ width: `${Math.min(100, (activeProgress.current / activeProgress.total) * 100)}%` }}
+                      />
+                    </div>
+                    <p className="truncate text-xs text-muted-foreground">
+                      {activeProgress.current}/{activeProgress.total} {activeProgress.message}
+                    </p>
+                  </div>
+                )}
+
+                {autoSyncProgress && (
+                  <p className="inline-flex items-center gap-1.5 text-xs text-muted-foreground">
+                    <RefreshCw className="h-3.5 w-3.5 animate-spin" />
+                    Background sync {autoSyncProgress.current}/{autoSyncProgress.total}: @{autoSyncProgress.citeKey}
+                  </p>
+                )}
+
+                {syncHistory && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <History className="h-4 w-4 text-primary" />
+                      Sync history
+                    </p>
+                    {syncHistory.length === 0 ? (
+                      <p className="text-xs text-muted-foreground">No syncs have run yet.</p>
+                    ) : (
+                      <ul className="max-h-40 space-y-1 overflow-auto text-xs text-muted-foreground">
+                        {syncHistory.map((run) => (
+                          <li
+                            key={run.id}
+                            className="flex justify-between gap-2"
+                            title={[run.error, ...run.failures].filter(Boolean).join('\n') || undefined}
+                          >
+                            <span className="truncate">
+                              {new Date(run.startedAt * 1000).toLocaleString()} · {run.trigger}
+                            </span>
+                            <span className="shrink-0">
+                              {run.error
+                                ? 'failed'
+                                : `${run.created + run.updated} written${run.conflicts > 0 ? ` · ${run.conflicts} conflicts` : ''}${
+                                    run.failures.length > 0 ? ` · ${run.failures.length} errors` : ''
+                                  }`}
+                            </span>
+                          </li>
+                        ))}
+                      </ul>
+                    )}
+                  </div>
+                )}
+
+                {orphanedNotes && orphanedNotes.length > 0 && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <Unlink className="h-4 w-4 text-primary" />
+                      Orphaned notes
+                    </p>
+                    <ul className="max-h-40 space-y-1 overflow-auto text-xs text-muted-foreground">
+                      {orphanedNotes.map((orphan) => (
+                        <li key={orphan.itemKey} className="flex justify-between gap-2">
+                          <span className="truncate" title={orphan.markdownPath}>
+                            @{orphan.citeKey}
+                          </span>
+                          <span className="shrink-0">
+                            {orphan.reason}
+                            {orphan.noteExists ? '' : ' · note missing'}
+                          </span>
+                        </li>
+                      ))}
+                    </ul>
+                    <div className="mt-2 flex gap-2">
+                      <Button type="button" size="sm" variant="outline" onClick={() => void handleOrphanedNotes('tag')}>
+                        Tag all
+                      </Button>
+                      <Button type="button" size="sm" variant="outline" onClick={() => void handleOrphanedNotes('archive')}>
+                        Archive all
+                      </Button>
+                    </div>
+                  </div>
+                )}
+
+                {importPreview && importPreview.imported.length > 0 && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <FileInput className="h-4 w-4 text-primary" />
+                      Existing notes to import
+                    </p>
+                    <ul className="max-h-40 space-y-1 overflow-auto text-xs text-muted-foreground">
+                      {importPreview.imported.map((note) => (
+                        <li key={note.path} className="flex justify-between gap-2">
+                          <span className="truncate" title={note.path}>
+                            {shortPath(note.path)}
+                          </span>
+                          <span className="shrink-0">
+                            @{note.citeKey || note.itemKey} · by {note.matchedBy === 'doi' ? 'DOI' : 'cite key'}
+                          </span>
+                        </li>
+                      ))}
+                      {importPreview.skipped.map((note) => (
+                        <li key={note.path} className="truncate" title={note.path}>
+                          Skipped: {note.reason}
+                        </li>
+                      ))}
+                    </ul>
+                    <p className="mt-2 text-xs text-muted-foreground">
+                      {importPreview.unmatched.length} note(s) matched no item.
+                    </p>
+                    <div className="mt-2 flex gap-2">
+                      <Button type="button" size="sm" variant="outline" onClick={() => void runNoteImport()}>
+                        Import {importPreview.imported.length}
+                      </Button>
+                      <Button type="button" size="sm" variant="ghost" onClick={() => setImportPreview(null)}>
+                        Cancel
+                      </Button>
+                    </div>
+                  </div>
+                )}
+
+                {citationScan && citationScan.unlinked.length > 0 && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <AtSign className="h-4 w-4 text-primary" />
+                      Cited without a note
+                    </p>
+                    <ul className="max-h-40 space-y-1 overflow-auto text-xs text-muted-foreground">
+                      {citationScan.unlinked.map((cited) => (
+                        <li key={cited.itemKey} className="flex justify-between gap-2">
+                          <span className="truncate" title={cited.title}>
+                            @{cited.citeKey}
+                          </span>
+                          <span
+                            className="shrink-0"
+                            title={cited.mentions.map((mention) => `${mention.path}:${mention.line}`).join('\n')}
+                          >
+                            {cited.mentions.length} mention(s)
+                          </span>
+                        </li>
+                      ))}
+                    </ul>
+                    <div className="mt-2 flex gap-2">
+                      <Button type="button" size="sm" variant="outline" disabled={isExporting} onClick={() => void createCitedNotes()}>
+                        Create {citationScan.unlinked.length} note(s)
+                      </Button>
+                      <Button type="button" size="sm" variant="ghost" onClick={() => setCitationScan(null)}>
+                        Dismiss
+                      </Button>
+                    </div>
+                  </div>
+                )}
+
+                {jobs.length > 0 && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <ListTodo className="h-4 w-4 text-primary" />
+                      Background jobs
+                    </p>
+                    <ul className="max-h-40 space-y-1 overflow-auto text-xs text-muted-foreground">
+                      {jobs.map((job) => (
+                        <li key={job.id} className="flex justify-between gap-2">
+                          <span className="truncate" title={job.error ?? undefined}>
+                            #{job.id} {job.task.kind}
+                            {job.task.kind !== 'download' && job.task.itemKeys
+                              ? ` · ${job.completed.length}/${job.task.itemKeys.length}`
+                              : ''}
+                            {job.failures.length > 0 ? ` · ${job.failures.length} failed` : ''}
+                          </span>
+                          <span className="inline-flex shrink-0 items-center gap-2">
+                            {job.status}
+                            {(job.status === 'queued' || job.status === 'running') && (
+                              <button
+                                type="button"
+                                className="text-primary hover:underline"
+                                onClick={() => void updateJob(job, 'cancel')}
+                              >
+                                Cancel
+                              </button>
+                            )}
+                            {(job.status === 'failed' || job.status === 'cancelled') && (
+                              <button
+                                type="button"
+                                className="text-primary hover:underline"
+                                onClick={() => void updateJob(job, 'retry')}
+                              >
+                                Retry
+                              </button>
+                            )}
+                          </span>
+                        </li>
+                      ))}
+                    </ul>
+                  </div>
+                )}
+
+                {exportReport && (
+                  <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
+                    <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
+                      <TerminalSquare className="h-4 w-4 text-primary" />
+                      {exportReport.dryRun ? 'Export preview (nothing written)' : 'Export report'}
+                    </p>
+                    <p className="mb-2 text-xs text-muted-foreground">
+                      {countByStatus(exportReport, 'created')} created, {countByStatus(exportReport, 'updated')} updated,{' '}
+                      {countByStatus(exportReport, 'unchanged')} unchanged
+                      {countByStatus(exportReport, 'conflict') > 0 ? `, ${countByStatus(exportReport, 'conflict')} conflicts` : ''}
+                      {exportReport.failed.length > 0 ? `, ${exportReport.failed.length} failed` : ''}
+                    </p>
+                    {freshnessNote(exportReport.zoteroDatabase) && (
+                      <p className="mb-2 text-xs text-muted-foreground">{freshnessNote(exportReport.zoteroDatabase)}</p>
+                    )}
+                    <ul className="max-h-56 space-y-1 overflow-auto text-xs text-muted-foreground">
+                      {exportReport.items.map((item) => (
+                        <li key={item.itemKey} className="flex justify-between gap-2">
+                          <span className="truncate">@{item.citeKey}</span>
+                          <span className="inline-flex shrink-0 items-center gap-2">
+                            {item.status}
+                            {item.newAnnotations > 0 ? ` · ${item.newAnnotations} new` : ''}
+                            {exportReport.dryRun && item.changes && item.changes.length > 0 && (
+                              <span
+                                title={item.changes.map((change) => `${change.action}: ${change.path}`).join('\n')}
+                              >
+                                · {item.changes.length} file(s)
+                              </span>
+                            )}
+                            <button
+                              type="button"
+                              className="text-primary hover:underline"
+                              onClick={() => void openExportedNote(item.path)}
+                            >
+                              Open
+                            </button>
+                            <button
+                              type="button"
+                              className="text-primary hover:underline"
+                              onClick={() => void revealExportedNote(item.path)}
+                            >
+                              Show
+                            </button>
+                            <button
+                              type="button"
+                              className="text-primary hover:underline"
+                              onClick={() => void showInZotero(item.itemKey)}
+                            >
+                              Zotero
+                            </button>
+                            {item.conflictPath && (
+                              <button
+                                type="button"
+                                className="text-primary hover:underline"
+                                onClick={() => void openExportedNote(item.conflictPath ?? '')}
+                              >
+                                Zotero version
+                              </button>
+                            )}
+                          </span>
+                        </li>
+                      ))}
+                    </ul>
+                  </div>
+                )}
+
                 {dryRunOutput && (
                   <div className="rounded-md border border-border bg-[rgba(16,16,16,0.95)] p-3">
                     <p className="mb-2 inline-flex items-center gap-1.5 text-sm font-medium">
@@ -1102,6 +2289,65 @@ export default function App() {
                   </div>
                 </div>
 
+                <label className="flex items-center gap-2 rounded-md border border-border px-3 py-2 text-sm">
+                  <input
+                    type="checkbox"
+                    checked={Boolean(templateDraft.blockIds)}
+                    onChange={(event) => setTemplateDraft((prev) => ({ ...prev, blockIds: event.target.checked }))}
+                  />
+                  Append block IDs (<code>^zot-&lt;annotationKey&gt;</code>) to each annotation
+                </label>
+
+                <label className="flex items-center gap-2 rounded-md border border-border px-3 py-2 text-sm">
+                  <input
+                    type="checkbox"
+                    checked={Boolean(templateDraft.pdfLinks)}
+                    onChange={(event) => setTemplateDraft((prev) => ({ ...prev, pdfLinks: event.target.checked }))}
+                  />
+                  Link page references to the annotation in the PDF reader
+                </label>
+
+                <div className="flex items-center gap-3 rounded-md border border-border px-3 py-2 text-sm">
+                  <span className="shrink-0">Dates</span>
+                  <input
+                    value={templateDraft.dateFormat?.pattern ?? ''}
+                    placeholder="As stored in Zotero, or short, long, iso, D MMMM YYYY"
+                    onChange={(event) =>
+                      setTemplateDraft((prev) => ({
+                        ...prev,
+                        dateFormat: { locale: '', ...prev.dateFormat, pattern: event.target.value },
+                      }))
+                    }
+                    className="h-9 flex-1 rounded-md border border-input bg-background px-3 text-sm"
+                  />
+                  <input
+                    value={templateDraft.dateFormat?.locale ?? ''}
+                    placeholder="en-US"
+                    aria-label="Date locale"
+                    onChange={(event) =>
+                      setTemplateDraft((prev) => ({
+                        ...prev,
+                        dateFormat: { pattern: '', ...prev.dateFormat, locale: event.target.value },
+                      }))
+                    }
+                    className="h-9 w-24 rounded-md border border-input bg-background px-3 text-sm"
+                  />
+                </div>
+
+                <div className="flex items-center gap-3 rounded-md border border-border px-3 py-2 text-sm">
+                  <span className="shrink-0">Image text (OCR)</span>
+                  <select
+                    value={templateDraft.imageText ?? 'altText'}
+                    onChange={(event) =>
+                      setTemplateDraft((prev) => ({ ...prev, imageText: event.target.value as ImageTextStyle }))
+                    }
+                    className="h-9 flex-1 rounded-md border border-input bg-background px-3 text-sm"
+                  >
+                    <option value="altText">As the image link text</option>
+                    <option value="quote">As a quote below the image</option>
+                  </select>
+                </div>
+
                 <div className="flex justify-end">
                   <Button type="button" onClick={() => void saveTemplateChanges()} disabled={isSavingTemplate}>
                     {isSavingTemplate ? (
@@ -1129,6 +2375,7 @@ export default function App() {
         settings={settings}
         onClose={() => setSettingsOpen(false)}
         onSave={onSaveSettings}
+        onSettingsReplaced={onSettingsReplaced}
         onToast={addToast}
       />
 
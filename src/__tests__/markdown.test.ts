@@ -149,4 +149,32 @@ describe('generateMarkdown', () => {
     expect(markdown).toContain('### Disagree with author');
     expect(markdown).not.toContain('### Yellow');
   });
+
+  it('appends stable block IDs to each annotation when enabled', () => {
+    const markdown = generateMarkdown({
+      title: '',
+      author: '',
+      year: '',
+      company: '',
+      citeKey: 'example',
+      abstractText: '',
+      groupedAnnotations: [
+        {
+          colorName: 'Yellow',
+          annotations: [
+            { key: 'A1', text: 'First', comment: 'Why it matters', pageLabel: '1' },
+            { key: 'A2', text: 'Second', comment: '', pageLabel: '' },
+          ],
+        },
+      ],
+      templateSettings: {
+        propertyOrder: ['title', 'author', 'year', 'company'],
+        colorHeadingOverrides: {},
+        blockIds: true,
+      },
+    });
+
+    expect(markdown).toContain('> Comment: Why it matters ^zot-A1');
+    expect(markdown).toContain('> Second ^zot-A2');
+  });
 });
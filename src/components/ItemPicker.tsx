@@ -1,8 +1,9 @@
-import { BookOpenText, CheckCircle2, LoaderCircle, Search } from 'lucide-react';
+import type { ReactNode } from 'react';
+import { BookOpenText, CheckCircle2, Highlighter, LoaderCircle, Pin, PinOff, Search } from 'lucide-react';
 import { Command, CommandItem, CommandList } from '@/components/ui/command';
 import { Input } from '@/components/ui/input';
 import { ScrollArea } from '@/components/ui/scroll-area';
-import type { ItemSummary } from '@/lib/types';
+import type { ItemSummary, QuickListItem } from '@/lib/types';
 import { cn } from '@/lib/utils';
 
 interface ItemPickerProps {
@@ -15,6 +16,9 @@ interface ItemPickerProps {
   onSelect: (item: ItemSummary) => void;
   dense?: boolean;
   showHints?: boolean;
+  /** Pinned and recent items, shown while the search box is empty. */
+  quickList?: QuickListItem[];
+  onTogglePin?: (item: ItemSummary, pinned: boolean) => void;
 }
 
 function itemMetadataLine(item: ItemSummary): string {
@@ -32,6 +36,47 @@ function itemMetadataLine(item: ItemSummary): string {
   return '';
 }
 
+/** Highlight count or a missing-attachment hint, when the search reports them. */
+function annotationBadge(item: ItemSummary): ReactNode {
+  if (item.hasAttachment === false) {
+    return <span className="ml-2 shrink-0 pt-0.5 text-[11px] text-muted-foreground">No attachment</span>;
+  }
+  if (!item.annotationCount) {
+    return null;
+  }
+  return (
+    <span
+      title={`${item.annotationCount} annotation${item.annotationCount === 1 ? '' : 's'}`}
+      className="ml-2 inline-flex shrink-0 items-center gap-1 pt-0.5 text-[11px] text-muted-foreground"
+    >
+      <Highlighter className="h-3 w-3" />
+      {item.annotationCount}
+    </span>
+  );
+}
+
+/** Wraps the matched ranges of `field` in `<mark>`. */
+function highlightMatches(item: ItemSummary, field: string, text: string): ReactNode {
+  const ranges = item.matches?.find((match) => match.field === field)?.ranges ?? [];
+  if (ranges.length === 0) {
+    return text;
+  }
+
+  const parts: ReactNode[] = [];
+  let cursor = 0;
+  for (const [start, end] of ranges) {
+    parts.push(text.slice(cursor, start));
+    parts.push(
+      <mark key={start} className="rounded-sm bg-primary/20 text-foreground">
+        {text.slice(start, end)}
+      </mark>,
+    );
+    cursor = end;
+  }
+  parts.push(text.slice(cursor));
+  return parts;
+}
+
 export function ItemPicker({
   query,
   onQueryChange,
@@ -42,7 +87,53 @@ export function ItemPicker({
   onSelect,
   dense = false,
   showHints = true,
+  quickList = [],
+  onTogglePin,
 }: ItemPickerProps) {
+  const pinnedKeys = new Set(quickList.filter((item) => item.pinned).map((item) => item.key));
+  const showQuickList = !query.trim() && quickList.length > 0;
+
+  const renderItem = (item: ItemSummary, keyPrefix: string) => {
+    const pinned = pinnedKeys.has(item.key);
+    return (
+      <CommandItem
+        key={`${keyPrefix}${item.key}`}
+        onClick={() => onSelect(item)}
+        className={cn(
+          item.key === activeItemKey && 'bg-muted',
+          selectedItemKeys.includes(item.key) && 'border-primary/40',
+          dense && 'py-1.5',
+        )}
+      >
+        <BookOpenText className="mr-2 h-3.5 w-3.5 shrink-0 text-muted-foreground" />
+        <div className="min-w-0 flex-1">
+          <p className="font-medium">{highlightMatches(item, 'title', item.title)}</p>
+          {itemMetadataLine(item) ? <p className="text-xs text-muted-foreground">{itemMetadataLine(item)}</p> : null}
+        </div>
+        {annotationBadge(item)}
+        {selectedItemKeys.includes(item.key) ? (
+          <span className="inline-flex items-center gap-1 pt-0.5 text-[11px] font-medium text-primary">
+            <CheckCircle2 className="h-3.5 w-3.5" />
+            Added
+          </span>
+        ) : null}
+        {onTogglePin ? (
+          <button
+            type="button"
+            title={pinned ? 'Unpin' : 'Pin to quick list'}
+            className={cn('ml-2 shrink-0 text-muted-foreground hover:text-primary', pinned && 'text-primary')}
+            onClick={(event) => {
+              event.stopPropagation();
+              onTogglePin(item, !pinned);
+            }}
+          >
+            {pinned ? <PinOff className="h-3.5 w-3.5" /> : <Pin className="h-3.5 w-3.5" />}
+          </button>
+        ) : null}
+      </CommandItem>
+    );
+  };
+
   return (
     <div className={cn('rounded-md border border-border p-3', dense ? 'space-y-2.5' : 'space-y-3')}>
       <label className="inline-flex items-center gap-1.5 text-sm font-medium">
@@ -64,6 +155,13 @@ export function ItemPicker({
       <Command>
         <ScrollArea className={cn(dense ? 'max-h-64' : 'max-h-80')}>
           <CommandList>
+            {showQuickList && (
+              <>
+                <p className="px-3 pb-1 pt-2 text-xs font-medium text-muted-foreground">Pinned and recent</p>
+                {quickList.map((item) => renderItem(item, 'quick-'))}
+                <p className="px-3 pb-1 pt-3 text-xs font-medium text-muted-foreground">Library</p>
+              </>
+            )}
             {isLoading && (
               <p className="inline-flex items-center gap-1.5 px-3 py-2 text-sm text-muted-foreground">
                 <LoaderCircle className="h-3.5 w-3.5 animate-spin" />
@@ -76,30 +174,7 @@ export function ItemPicker({
                 No items found.
               </p>
             )}
-            {!isLoading &&
-              items.map((item) => (
-                <CommandItem
-                  key={item.key}
-                  onClick={() => onSelect(item)}
-                  className={cn(
-                    item.key === activeItemKey && 'bg-muted',
-                    selectedItemKeys.includes(item.key) && 'border-primary/40',
-                    dense && 'py-1.5',
-                  )}
-                >
-                  <BookOpenText className="mr-2 h-3.5 w-3.5 shrink-0 text-muted-foreground" />
-                  <div className="min-w-0 flex-1">
-                    <p className="font-medium">{item.title}</p>
-                    {itemMetadataLine(item) ? <p className="text-xs text-muted-foreground">{itemMetadataLine(item)}</p> : null}
-                  </div>
-                  {selectedItemKeys.includes(item.key) ? (
-                    <span className="inline-flex items-center gap-1 pt-0.5 text-[11px] font-medium text-primary">
-                      <CheckCircle2 className="h-3.5 w-3.5" />
-                      Added
-                    </span>
-                  ) : null}
-                </CommandItem>
-              ))}
+            {!isLoading && items.map((item) => renderItem(item, ''))}
           </CommandList>
         </ScrollArea>
       </Command>
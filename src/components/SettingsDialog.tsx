@@ -1,10 +1,72 @@
 import { useEffect, useState } from 'react';
-import { FolderSearch, KeyRound, Link2, Pencil, RefreshCw, Save } from 'lucide-react';
+import {
+  Archive,
+  Ban,
+  CloudDownload,
+  Crop,
+  Download,
+  Eraser,
+  FileClock,
+  FileText,
+  FolderSearch,
+  Gem,
+  GitCommitHorizontal,
+  GitMerge,
+  KeyRound,
+  Layers,
+  LifeBuoy,
+  Link2,
+  MessageSquareShare,
+  Moon,
+  NotebookPen,
+  Pencil,
+  Plus,
+  RefreshCw,
+  RotateCcw,
+  Save,
+  ScanText,
+  Search,
+  Server,
+  SpellCheck,
+  SquarePen,
+  Timer,
+  Trash2,
+  Upload,
+} from 'lucide-react';
 import { Dialog } from '@/components/ui/dialog';
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
-import type { AppSettings } from '@/lib/types';
-import { selectDirectoryDialog } from '@/lib/tauri';
+import type {
+  ApiServerStatus,
+  AppSettings,
+  ConflictStrategy,
+  ConnectionReport,
+  DarkImageMode,
+  DeletedAnnotations,
+  LogLevel,
+  OutputTarget,
+  ProfileList,
+  SettingsSection,
+} from '@/lib/types';
+import {
+  clearImageCache,
+  CommandError,
+  createDiagnosticsBundle,
+  createProfile,
+  deleteProfile,
+  exportSettingsBundle,
+  getApiServerStatus,
+  getRecentLogs,
+  importSettingsBundle,
+  listProfiles,
+  resetSettings,
+  selectDirectoryDialog,
+  selectOpenFileDialog,
+  selectSaveFileDialog,
+  switchProfile,
+  testConnections,
+} from '@/lib/tauri';
+import { cn, errorMessage } from '@/lib/utils';
 import { ZoteroClient } from '@/lib/zotero';
 
 interface SettingsDialogProps {
@@ -13,10 +75,35 @@ interface SettingsDialogProps {
   settings: AppSettings;
   onClose: () => void;
   onSave: (settings: AppSettings) => Promise<void>;
+  onSettingsReplaced: (settings: AppSettings) => void;
   onToast: (level: 'info' | 'success' | 'error', message: string) => void;
 }
 
-type SettingsTextField = 'markdownDir' | 'attachmentBaseDir' | 'zoteroApiKey' | 'zoteroBaseUrl';
+type SettingsTextField = 'markdownDir' | 'attachmentBaseDir' | 'changelogNote' | 'editorCommand' | 'zoteroApiKey' | 'zoteroBaseUrl' | 'zoteroUserId' | 'orphanArchiveDir' | 'orphanTag' | 'excludeTag' | 'zoteroDataDir';
+
+const RESET_SECTIONS: { section: SettingsSection; label: string }[] = [
+  { section: 'templates', label: 'Templates' },
+  { section: 'images', label: 'Images' },
+  { section: 'export', label: 'Export' },
+  { section: 'sync', label: 'Sync' },
+  { section: 'search', label: 'Search' },
+  { section: 'orphans', label: 'Orphaned notes' },
+  { section: 'webdav', label: 'WebDAV login' },
+  { section: 'logging', label: 'Logging' },
+];
+
+/** Fields whose validation errors are shown below their input; others go to the toast. */
+const INLINE_ERROR_FIELDS: string[] = ['markdownDir', 'attachmentBaseDir', 'zoteroDataDir', 'searchLimit', 'searchFuzziness', 'imageSettings.renderDpi', 'ocr.languages', 'orphanArchiveDir', 'zoteroBaseUrl', 'zoteroUserId', 'joplin.baseUrl', 'joplin.token', 'apiServer.port', 'apiServer.token', 'webdav.url'];
+
+function FieldErrorText({ message }: { message?: string }) {
+  return message ? <p className="text-xs text-destructive">{message}</p> : null;
+}
+
+/** 32 random hex characters for the local API server. */
+function generateApiToken(): string {
+  const bytes = crypto.getRandomValues(new Uint8Array(16));
+  return Array.from(bytes, (byte) => byte.toString(16).padStart(2, '0')).join('');
+}
 
 function maskApiKey(key: string): string {
   const trimmed = key.trim();
@@ -32,31 +119,165 @@ function maskApiKey(key: string): string {
   return `${trimmed.slice(0, 4)}${'.'.repeat(middleLength)}${trimmed.slice(-2)}`;
 }
 
-export function SettingsDialog({ open, isConnected, settings, onClose, onSave, onToast }: SettingsDialogProps) {
+export function SettingsDialog({ open, isConnected, settings, onClose, onSave, onSettingsReplaced, onToast }: SettingsDialogProps) {
   const [draft, setDraft] = useState<AppSettings>(settings);
   const [isTesting, setIsTesting] = useState(false);
+  const [connectionReport, setConnectionReport] = useState<ConnectionReport | null>(null);
+  const [logLines, setLogLines] = useState<string[] | null>(null);
+  const [fieldErrors, setFieldErrors] = useState<Record<string, string>>({});
   const [isSaving, setIsSaving] = useState(false);
   const [apiKeyUnlocked, setApiKeyUnlocked] = useState(false);
   const [apiKeyActivated, setApiKeyActivated] = useState(false);
+  const [profiles, setProfiles] = useState<ProfileList>({ active: 'default', profiles: ['default'] });
+  const [newProfileName, setNewProfileName] = useState('');
+  const [resetSections, setResetSections] = useState<SettingsSection[]>([]);
+  const [apiServerStatus, setApiServerStatus] = useState<ApiServerStatus | null>(null);
 
   useEffect(() => {
     if (open) {
       setDraft(settings);
       setApiKeyUnlocked(false);
       setApiKeyActivated(isConnected);
+      setConnectionReport(null);
+      setLogLines(null);
+      setFieldErrors({});
     }
   }, [isConnected, open, settings]);
 
+  useEffect(() => {
+    if (open) {
+      void listProfiles()
+        .then(setProfiles)
+        .catch((error) => onToast('error', errorMessage(error)));
+      void getApiServerStatus()
+        .then(setApiServerStatus)
+        .catch(() => setApiServerStatus(null));
+    }
+  }, [open, settings]);
+
+  const changeProfile = async (name: string) => {
+    try {
+      const loaded = await switchProfile(name);
+      setProfiles((prev) => ({ ...prev, active: name }));
+      onSettingsReplaced(loaded);
+      onToast('success', `Switched to profile "${name}".`);
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const toggleLog = async () => {
+    if (logLines) {
+      setLogLines(null);
+      return;
+    }
+
+    try {
+      setLogLines(await getRecentLogs(100));
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const clearCachedImages = async () => {
+    try {
+      const removed = await clearImageCache();
+      onToast('success', `Removed ${removed} cached image${removed === 1 ? '' : 's'}.`);
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const saveDiagnostics = async () => {
+    const path = await selectSaveFileDialog('zotnotes-diagnostics.zip');
+    if (!path) {
+      return;
+    }
+
+    try {
+      await createDiagnosticsBundle(path);
+      onToast('success', `Saved diagnostics to ${path}. Attach it to your bug report.`);
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const exportBundle = async () => {
+    const path = await selectSaveFileDialog('zotnotes-settings.json');
+    if (!path) {
+      return;
+    }
+
+    try {
+      await exportSettingsBundle(path);
+      onToast('success', 'Exported the saved settings (without the API key).');
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const importBundle = async () => {
+    const path = await selectOpenFileDialog();
+    if (!path) {
+      return;
+    }
+
+    try {
+      onSettingsReplaced(await importSettingsBundle(path));
+      onToast('success', `Imported settings into profile "${profiles.active}".`);
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const toggleResetSection = (section: SettingsSection, checked: boolean) => {
+    setResetSections((prev) => (checked ? [...prev, section] : prev.filter((entry) => entry !== section)));
+  };
+
+  const resetToDefaults = async () => {
+    try {
+      const { settings: reset, backup } = await resetSettings(resetSections);
+      onSettingsReplaced(reset);
+      setResetSections([]);
+      onToast('success', backup ? `Restored defaults. The previous settings were saved to ${backup}.` : 'Restored defaults.');
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const addProfile = async () => {
+    try {
+      setProfiles(await createProfile(newProfileName, true));
+      setNewProfileName('');
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
+  const removeProfile = async (name: string) => {
+    try {
+      onSettingsReplaced(await switchProfile('default'));
+      setProfiles(await deleteProfile(name));
+      onToast('success', `Deleted profile "${name}" and switched to the default profile.`);
+    } catch (error) {
+      onToast('error', errorMessage(error));
+    }
+  };
+
   const shouldMaskApiKey = Boolean(draft.zoteroApiKey.trim()) && (isConnected || apiKeyActivated) && !apiKeyUnlocked;
 
   const setField = (field: SettingsTextField, value: string) => {
     setDraft((prev) => ({ ...prev, [field]: value }));
+    setFieldErrors((prev) => {
+      const { [field]: _cleared, ...rest } = prev;
+      return rest;
+    });
     if (field === 'zoteroApiKey') {
       setApiKeyActivated(false);
     }
   };
 
-  const chooseDirectory = async (field: keyof Pick<AppSettings, 'markdownDir' | 'attachmentBaseDir'>) => {
+  const chooseDirectory = async (field: keyof Pick<AppSettings, 'markdownDir' | 'attachmentBaseDir' | 'zoteroDataDir'>) => {
     const selected = await selectDirectoryDialog();
     if (selected) {
       setField(field, selected);
@@ -66,6 +287,16 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
   const testConnection = async () => {
     setIsTesting(true);
     try {
+      const report = await testConnections(draft);
+      if (report) {
+        setConnectionReport(report);
+        if (report.localApi.ok || report.webApi.ok) {
+          setApiKeyActivated(true);
+          setApiKeyUnlocked(false);
+        }
+        return;
+      }
+
       const client = new ZoteroClient(draft);
       const ok = await client.ping();
       if (ok) {
@@ -76,7 +307,7 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
         onToast('error', 'Zotero ping failed. Verify Zotero is running and API is enabled.');
       }
     } catch (error) {
-      onToast('error', error instanceof Error ? error.message : String(error));
+      onToast('error', errorMessage(error));
     } finally {
       setIsTesting(false);
     }
@@ -89,7 +320,13 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
       onToast('success', 'Settings saved.');
       onClose();
     } catch (error) {
-      onToast('error', error instanceof Error ? error.message : String(error));
+      if (error instanceof CommandError && error.fields.length > 0) {
+        setFieldErrors(Object.fromEntries(error.fields.map(({ field, message }) => [field, message])));
+        const hidden = error.fields.filter(({ field }) => !INLINE_ERROR_FIELDS.includes(field));
+        onToast('error', hidden.length > 0 ? hidden.map(({ message }) => message).join(' ') : error.message);
+        return;
+      }
+      onToast('error', errorMessage(error));
     } finally {
       setIsSaving(false);
     }
@@ -98,6 +335,126 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
   return (
     <Dialog open={open} onClose={onClose} title="Settings">
       <div className="space-y-4">
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Layers className="h-4 w-4 text-primary" />
+            Profile
+          </label>
+          <div className="flex gap-2">
+            <select
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+              value={profiles.active}
+              onChange={(event) => void changeProfile(event.target.value)}
+            >
+              {profiles.profiles.map((name) => (
+                <option key={name} value={name}>
+                  {name}
+                </option>
+              ))}
+            </select>
+            <Button
+              type="button"
+              variant="outline"
+              disabled={profiles.active === 'default'}
+              title="Delete this profile and switch to the default one"
+              onClick={() => void removeProfile(profiles.active)}
+            >
+              <Trash2 className="h-4 w-4" />
+            </Button>
+          </div>
+          <div className="flex gap-2">
+            <Input
+              placeholder="New profile name, e.g. work"
+              value={newProfileName}
+              onChange={(event) => setNewProfileName(event.target.value)}
+            />
+            <Button type="button" variant="outline" disabled={!newProfileName.trim()} onClick={() => void addProfile()}>
+              <Plus className="mr-2 h-4 w-4" />
+              Copy current
+            </Button>
+          </div>
+          <div className="flex gap-2">
+            <Button type="button" variant="ghost" onClick={() => void exportBundle()}>
+              <Download className="mr-2 h-4 w-4" />
+              Export settings
+            </Button>
+            <Button type="button" variant="ghost" onClick={() => void importBundle()}>
+              <Upload className="mr-2 h-4 w-4" />
+              Import settings
+            </Button>
+          </div>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <RotateCcw className="h-4 w-4 text-primary" />
+            Reset to defaults
+          </label>
+          <div className="flex flex-wrap gap-x-4 gap-y-1">
+            {RESET_SECTIONS.map(({ section, label }) => (
+              <label key={section} className="inline-flex items-center gap-2 text-sm">
+                <input
+                  type="checkbox"
+                  checked={resetSections.includes(section)}
+                  onChange={(event) => toggleResetSection(section, event.target.checked)}
+                />
+                {label}
+              </label>
+            ))}
+          </div>
+          <Button type="button" variant="outline" disabled={resetSections.length === 0} onClick={() => void resetToDefaults()}>
+            <RotateCcw className="mr-2 h-4 w-4" />
+            Reset selected
+          </Button>
+          <p className="text-xs text-muted-foreground">Folders, the Zotero data directory, URLs and keys are kept.</p>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <NotebookPen className="h-4 w-4 text-primary" />
+            Export to
+          </label>
+          <select
+            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+            value={draft.outputTarget}
+            onChange={(event) => setDraft((prev) => ({ ...prev, outputTarget: event.target.value as OutputTarget }))}
+          >
+            <option value="markdown">Markdown files</option>
+            <option value="joplin">Joplin (Web Clipper service)</option>
+          </select>
+          {draft.outputTarget === 'joplin' && (
+            <>
+              <div className="flex gap-2">
+                <Input
+                  className={cn(fieldErrors['joplin.baseUrl'] && 'border-destructive')}
+                  placeholder="http://127.0.0.1:41184"
+                  aria-label="Joplin Web Clipper URL"
+                  value={draft.joplin.baseUrl}
+                  onChange={(event) => setDraft((prev) => ({ ...prev, joplin: { ...prev.joplin, baseUrl: event.target.value } }))}
+                />
+                <Input
+                  type="password"
+                  className={cn(fieldErrors['joplin.token'] && 'border-destructive')}
+                  placeholder="Web Clipper token"
+                  aria-label="Joplin Web Clipper token"
+                  value={draft.joplin.token}
+                  onChange={(event) => setDraft((prev) => ({ ...prev, joplin: { ...prev.joplin, token: event.target.value } }))}
+                />
+                <Input
+                  placeholder="Notebook (default notebook)"
+                  aria-label="Joplin notebook"
+                  value={draft.joplin.notebook}
+                  onChange={(event) => setDraft((prev) => ({ ...prev, joplin: { ...prev.joplin, notebook: event.target.value } }))}
+                />
+              </div>
+              <FieldErrorText message={fieldErrors['joplin.baseUrl'] ?? fieldErrors['joplin.token']} />
+              <p className="text-xs text-muted-foreground">
+                Find the token in Joplin under Tools → Options → Web Clipper. Images are uploaded as Joplin resources.
+              </p>
+            </>
+          )}
+        </div>
+
         <div className="space-y-2">
           <label className="inline-flex items-center gap-1.5 text-sm font-medium">
             <FolderSearch className="h-4 w-4 text-primary" />
@@ -107,6 +464,7 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
             <Input
               placeholder="/path/to/markdown"
               value={draft.markdownDir}
+              className={fieldErrors.markdownDir ? 'border-destructive' : undefined}
               onChange={(event) => setField('markdownDir', event.target.value)}
             />
             <Button type="button" variant="outline" onClick={() => void chooseDirectory('markdownDir')}>
@@ -114,6 +472,7 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
               Browse
             </Button>
           </div>
+          <FieldErrorText message={fieldErrors.markdownDir} />
         </div>
 
         <div className="space-y-2">
@@ -125,6 +484,7 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
             <Input
               placeholder="/path/to/attachments"
               value={draft.attachmentBaseDir}
+              className={fieldErrors.attachmentBaseDir ? 'border-destructive' : undefined}
               onChange={(event) => setField('attachmentBaseDir', event.target.value)}
             />
             <Button type="button" variant="outline" onClick={() => void chooseDirectory('attachmentBaseDir')}>
@@ -132,6 +492,296 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
               Browse
             </Button>
           </div>
+          <FieldErrorText message={fieldErrors.attachmentBaseDir} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <FolderSearch className="h-4 w-4 text-primary" />
+            Zotero data directory
+          </label>
+          <div className="flex gap-2">
+            <Input
+              placeholder="Default locations (folder containing zotero.sqlite)"
+              value={draft.zoteroDataDir}
+              className={fieldErrors.zoteroDataDir ? 'border-destructive' : undefined}
+              onChange={(event) => setField('zoteroDataDir', event.target.value)}
+            />
+            <Button type="button" variant="outline" onClick={() => void chooseDirectory('zoteroDataDir')}>
+              <FolderSearch className="mr-2 h-4 w-4" />
+              Browse
+            </Button>
+          </div>
+          <FieldErrorText message={fieldErrors.zoteroDataDir} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <CloudDownload className="h-4 w-4 text-primary" />
+            WebDAV file sync
+          </label>
+          <div className="flex gap-2">
+            <Input
+              className={cn(fieldErrors['webdav.url'] && 'border-destructive')}
+              placeholder="https://dav.example.com/dav (off)"
+              aria-label="WebDAV URL"
+              value={draft.webdav.url}
+              onChange={(event) => setDraft((prev) => ({ ...prev, webdav: { ...prev.webdav, url: event.target.value } }))}
+            />
+            <Input
+              placeholder="Username"
+              aria-label="WebDAV username"
+              value={draft.webdav.username}
+              onChange={(event) => setDraft((prev) => ({ ...prev, webdav: { ...prev.webdav, username: event.target.value } }))}
+            />
+            <Input
+              type="password"
+              placeholder="Password"
+              aria-label="WebDAV password"
+              value={draft.webdav.password}
+              onChange={(event) => setDraft((prev) => ({ ...prev, webdav: { ...prev.webdav, password: event.target.value } }))}
+            />
+          </div>
+          <FieldErrorText message={fieldErrors['webdav.url']} />
+          <p className="text-xs text-muted-foreground">
+            Use the same server as Zotero's Sync settings. Attachments missing from the Zotero storage folder are downloaded from it to render annotation images.
+          </p>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Search className="h-4 w-4 text-primary" />
+            Search results
+          </label>
+          <div className="flex items-center gap-3">
+            <Input
+              type="number"
+              min={1}
+              max={1000}
+              className={cn('w-24', fieldErrors.searchLimit && 'border-destructive')}
+              value={draft.searchLimit}
+              onChange={(event) =>
+                setDraft((prev) => ({ ...prev, searchLimit: Math.min(1000, Math.max(1, Number(event.target.value) || 1)) }))
+              }
+            />
+            <span className="text-sm text-muted-foreground">items at most (the Zotero API caps requests at 100)</span>
+          </div>
+          <FieldErrorText message={fieldErrors.searchLimit} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <SpellCheck className="h-4 w-4 text-primary" />
+            Search typo tolerance
+          </label>
+          <div className="flex items-center gap-3">
+            <select
+              className={cn(
+                'flex h-10 w-44 rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60',
+                fieldErrors.searchFuzziness && 'border-destructive',
+              )}
+              value={draft.searchFuzziness}
+              onChange={(event) => setDraft((prev) => ({ ...prev, searchFuzziness: Number(event.target.value) }))}
+            >
+              <option value={0}>Exact words only</option>
+              <option value={1}>1 typo per word</option>
+              <option value={2}>2 typos per word</option>
+            </select>
+            <span className="text-sm text-muted-foreground">words shorter than 4 letters always match exactly</span>
+          </div>
+          <FieldErrorText message={fieldErrors.searchFuzziness} />
+          <label className="inline-flex items-center gap-2 text-sm">
+            <input
+              type="checkbox"
+              checked={draft.fullTextSearch}
+              onChange={(event) => setDraft((prev) => ({ ...prev, fullTextSearch: event.target.checked }))}
+            />
+            Search abstracts and annotations with the full-text index
+          </label>
+          <p className="text-xs text-muted-foreground">
+            When off, searches match parts of titles, creators and years only.
+          </p>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Crop className="h-4 w-4 text-primary" />
+            Area annotation resolution
+          </label>
+          <div className="flex items-center gap-3">
+            <Input
+              type="number"
+              min={36}
+              max={600}
+              placeholder="Zotero"
+              className={cn('w-24', fieldErrors['imageSettings.renderDpi'] && 'border-destructive')}
+              value={draft.imageSettings.renderDpi ?? ''}
+              onChange={(event) => {
+                const value = event.target.value.trim();
+                setDraft((prev) => ({
+                  ...prev,
+                  imageSettings: {
+                    ...prev.imageSettings,
+                    renderDpi: value === '' ? null : Math.min(600, Math.max(36, Number(value) || 150)),
+                  },
+                }));
+              }}
+            />
+            <span className="text-sm text-muted-foreground">DPI when rendering from the PDF (leave empty to use the image Zotero cached)</span>
+            <Button type="button" variant="ghost" title="Fetch or render every image again on the next export" onClick={() => void clearCachedImages()}>
+              <Eraser className="h-4 w-4" />
+            </Button>
+          </div>
+          <FieldErrorText message={fieldErrors['imageSettings.renderDpi']} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Moon className="h-4 w-4 text-primary" />
+            Images in dark themes
+          </label>
+          <select
+            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+            value={draft.imageSettings.darkMode}
+            onChange={(event) =>
+              setDraft((prev) => ({
+                ...prev,
+                imageSettings: { ...prev.imageSettings, darkMode: event.target.value as DarkImageMode },
+              }))
+            }
+          >
+            <option value="off">Keep images as they are</option>
+            <option value="invert">Invert images (white pages turn dark)</option>
+            <option value="variant">Also save an inverted -dark copy</option>
+          </select>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <ScanText className="h-4 w-4 text-primary" />
+            Text in area annotations (OCR)
+          </label>
+          <div className="flex items-center gap-3">
+            <label className="inline-flex shrink-0 items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                checked={draft.ocr.enabled}
+                onChange={(event) => setDraft((prev) => ({ ...prev, ocr: { ...prev.ocr, enabled: event.target.checked } }))}
+              />
+              Recognize with Tesseract in
+            </label>
+            <Input
+              className={cn('w-28', fieldErrors['ocr.languages'] && 'border-destructive')}
+              placeholder="eng"
+              aria-label="OCR languages"
+              value={draft.ocr.languages}
+              onChange={(event) => setDraft((prev) => ({ ...prev, ocr: { ...prev.ocr, languages: event.target.value } }))}
+            />
+            <Input
+              placeholder="tesseract (on PATH)"
+              aria-label="Tesseract path"
+              value={draft.ocr.tesseractPath}
+              onChange={(event) => setDraft((prev) => ({ ...prev, ocr: { ...prev.ocr, tesseractPath: event.target.value } }))}
+            />
+          </div>
+          <FieldErrorText message={fieldErrors['ocr.languages']} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <FileClock className="h-4 w-4 text-primary" />
+            Export changelog note
+          </label>
+          <Input
+            placeholder="ZotNotes export log.md (leave empty to disable)"
+            value={draft.changelogNote}
+            onChange={(event) => setField('changelogNote', event.target.value)}
+          />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <GitCommitHorizontal className="h-4 w-4 text-primary" />
+            Git history
+          </label>
+          <label className="inline-flex items-center gap-2 text-sm">
+            <input
+              type="checkbox"
+              checked={draft.git.autoCommit}
+              onChange={(event) => setDraft((prev) => ({ ...prev, git: { ...prev.git, autoCommit: event.target.checked } }))}
+            />
+            Commit changed notes in the Markdown folder after each export
+          </label>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <SquarePen className="h-4 w-4 text-primary" />
+            Editor command
+          </label>
+          <Input
+            placeholder="System default, obsidian, or e.g. code {path}"
+            value={draft.editorCommand}
+            onChange={(event) => setField('editorCommand', event.target.value)}
+          />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Gem className="h-4 w-4 text-primary" />
+            Obsidian
+          </label>
+          <div className="flex items-center gap-3">
+            <label className="inline-flex shrink-0 items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                checked={draft.obsidian.openAfterExport}
+                onChange={(event) =>
+                  setDraft((prev) => ({ ...prev, obsidian: { ...prev.obsidian, openAfterExport: event.target.checked } }))
+                }
+              />
+              Open the note after exporting one item, in vault
+            </label>
+            <Input
+              placeholder="Detected from the .obsidian folder"
+              aria-label="Obsidian vault name"
+              value={draft.obsidian.vault}
+              onChange={(event) => setDraft((prev) => ({ ...prev, obsidian: { ...prev.obsidian, vault: event.target.value } }))}
+            />
+          </div>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Archive className="h-4 w-4 text-primary" />
+            Orphaned notes
+          </label>
+          <div className="flex gap-2">
+            <Input
+              placeholder="Archive folder, e.g. _archive"
+              value={draft.orphanArchiveDir}
+              className={fieldErrors.orphanArchiveDir ? 'border-destructive' : undefined}
+              onChange={(event) => setField('orphanArchiveDir', event.target.value)}
+            />
+            <Input
+              placeholder="Tag, e.g. zotero/deleted"
+              value={draft.orphanTag}
+              onChange={(event) => setField('orphanTag', event.target.value)}
+            />
+          </div>
+          <FieldErrorText message={fieldErrors.orphanArchiveDir} />
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Ban className="h-4 w-4 text-primary" />
+            Exclusion tag
+          </label>
+          <Input
+            placeholder="Zotero tag to skip in batch exports and sync, e.g. #no-export"
+            value={draft.excludeTag}
+            onChange={(event) => setField('excludeTag', event.target.value)}
+          />
         </div>
 
         <div className="space-y-2">
@@ -163,10 +813,237 @@ export function SettingsDialog({ open, isConnected, settings, onClose, onSave, o
           <Input
             placeholder="http://127.0.0.1:23119"
             value={draft.zoteroBaseUrl}
+            className={fieldErrors.zoteroBaseUrl ? 'border-destructive' : undefined}
             onChange={(event) => setField('zoteroBaseUrl', event.target.value)}
           />
+          <FieldErrorText message={fieldErrors.zoteroBaseUrl} />
         </div>
 
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <MessageSquareShare className="h-4 w-4 text-primary" />
+            Zotero user ID
+          </label>
+          <Input
+            placeholder="Numeric user ID from zotero.org/settings/keys"
+            value={draft.zoteroUserId}
+            className={fieldErrors.zoteroUserId ? 'border-destructive' : undefined}
+            onChange={(event) => setField('zoteroUserId', event.target.value)}
+          />
+          <FieldErrorText message={fieldErrors.zoteroUserId} />
+          <p className="text-xs text-muted-foreground">
+            Needed to push comment edits back to Zotero; the API key above must allow write access.
+          </p>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Timer className="h-4 w-4 text-primary" />
+            Background sync
+          </label>
+          <div className="flex items-center gap-3">
+            <label className="inline-flex items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                checked={draft.autoSync.enabled}
+                onChange={(event) =>
+                  setDraft((prev) => ({ ...prev, autoSync: { ...prev.autoSync, enabled: event.target.checked } }))
+                }
+              />
+              Sync changed items every
+            </label>
+            <Input
+              type="number"
+              min={1}
+              className="w-20"
+              value={draft.autoSync.intervalMinutes}
+              onChange={(event) =>
+                setDraft((prev) => ({
+                  ...prev,
+                  autoSync: { ...prev.autoSync, intervalMinutes: Math.max(1, Number(event.target.value) || 1) },
+                }))
+              }
+            />
+            <span className="text-sm text-muted-foreground">minutes</span>
+          </div>
+          <div className="flex items-center gap-3">
+            <label className="inline-flex items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                checked={draft.autoSync.onStartup}
+                onChange={(event) =>
+                  setDraft((prev) => ({ ...prev, autoSync: { ...prev.autoSync, onStartup: event.target.checked } }))
+                }
+              />
+              Sync when the app starts, after
+            </label>
+            <Input
+              type="number"
+              min={0}
+              className="w-20"
+              value={draft.autoSync.startupDelaySeconds}
+              onChange={(event) =>
+                setDraft((prev) => ({
+                  ...prev,
+                  autoSync: { ...prev.autoSync, startupDelaySeconds: Math.max(0, Number(event.target.value) || 0) },
+                }))
+              }
+            />
+            <span className="text-sm text-muted-foreground">seconds</span>
+          </div>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Server className="h-4 w-4 text-primary" />
+            Local API server
+          </label>
+          <div className="flex items-center gap-3">
+            <label className="inline-flex shrink-0 items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                checked={draft.apiServer.enabled}
+                onChange={(event) =>
+                  setDraft((prev) => ({
+                    ...prev,
+                    apiServer: {
+                      ...prev.apiServer,
+                      enabled: event.target.checked,
+                      token: prev.apiServer.token || generateApiToken(),
+                    },
+                  }))
+                }
+              />
+              Listen on 127.0.0.1 port
+            </label>
+            <Input
+              type="number"
+              min={1024}
+              max={65535}
+              className={cn('w-24', fieldErrors['apiServer.port'] && 'border-destructive')}
+              value={draft.apiServer.port}
+              onChange={(event) =>
+                setDraft((prev) => ({
+                  ...prev,
+                  apiServer: { ...prev.apiServer, port: Number(event.target.value) || 0 },
+                }))
+              }
+            />
+          </div>
+          <div className="flex gap-2">
+            <Input
+              readOnly
+              className={cn('font-mono', fieldErrors['apiServer.token'] && 'border-destructive')}
+              aria-label="API token"
+              placeholder="No token yet"
+              value={draft.apiServer.token}
+            />
+            <Button
+              type="button"
+              variant="outline"
+              title="Create a new token; tools using the old one must be updated"
+              onClick={() => setDraft((prev) => ({ ...prev, apiServer: { ...prev.apiServer, token: generateApiToken() } }))}
+            >
+              <RefreshCw className="mr-2 h-4 w-4" />
+              New token
+            </Button>
+          </div>
+          <FieldErrorText message={fieldErrors['apiServer.port'] ?? fieldErrors['apiServer.token']} />
+          <p className="text-xs text-muted-foreground">
+            {apiServerStatus?.running
+              ? `Running at ${apiServerStatus.address}. `
+              : apiServerStatus?.error
+                ? `Not running: ${apiServerStatus.error}. `
+                : ''}
+            Send the token as <code>Authorization: Bearer &lt;token&gt;</code>.
+          </p>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <GitMerge className="h-4 w-4 text-primary" />
+            When a note was edited and Zotero changed too
+          </label>
+          <select
+            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+            value={draft.conflictStrategy}
+            onChange={(event) =>
+              setDraft((prev) => ({ ...prev, conflictStrategy: event.target.value as ConflictStrategy }))
+            }
+          >
+            <option value="conflictCopy">Keep my note and write a .conflict copy</option>
+            <option value="keepLocal">Keep my note</option>
+            <option value="keepZotero">Overwrite with the Zotero version</option>
+          </select>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <Eraser className="h-4 w-4 text-primary" />
+            Highlights deleted in Zotero
+          </label>
+          <select
+            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+            value={draft.deletedAnnotations}
+            onChange={(event) =>
+              setDraft((prev) => ({ ...prev, deletedAnnotations: event.target.value as DeletedAnnotations }))
+            }
+          >
+            <option value="remove">Remove them from the note</option>
+            <option value="mark">Keep them in a "Deleted in Zotero" callout</option>
+          </select>
+        </div>
+
+        <div className="space-y-2">
+          <label className="inline-flex items-center gap-1.5 text-sm font-medium">
+            <FileText className="h-4 w-4 text-primary" />
+            Log level
+          </label>
+          <div className="flex gap-2">
+            <select
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-primary/60"
+              value={draft.logLevel}
+              onChange={(event) => setDraft((prev) => ({ ...prev, logLevel: event.target.value as LogLevel }))}
+            >
+              <option value="error">Errors only</option>
+              <option value="warn">Warnings</option>
+              <option value="info">Info</option>
+              <option value="debug">Debug</option>
+              <option value="trace">Trace</option>
+            </select>
+            <Button type="button" variant="outline" onClick={() => void toggleLog()}>
+              {logLines ? 'Hide log' : 'View log'}
+            </Button>
+            <Button type="button" variant="outline" title="Save a zip to attach to bug reports" onClick={() => void saveDiagnostics()}>
+              <LifeBuoy className="h-4 w-4" />
+            </Button>
+          </div>
+          {logLines && (
+            <pre className="max-h-48 overflow-auto rounded-md border border-border p-2 text-xs text-muted-foreground">
+              {logLines.length > 0 ? logLines.join('\n') : 'The log is empty.'}
+            </pre>
+          )}
+        </div>
+
+        {connectionReport && (
+          <ul className="space-y-1 rounded-md border border-border p-3 text-xs">
+            {(
+              [
+                ['zotero.sqlite', connectionReport.zoteroDatabase],
+                ['Better BibTeX', connectionReport.betterBibtex],
+                ['Local API', connectionReport.localApi],
+                ['Web API key', connectionReport.webApi],
+              ] as const
+            ).map(([label, status]) => (
+              <li key={label} className="flex gap-2">
+                <span className={status.ok ? 'text-primary' : 'text-destructive'}>{status.ok ? '✓' : '✗'}</span>
+                <span className="font-medium">{label}</span>
+                <span className="text-muted-foreground">{status.message}</span>
+              </li>
+            ))}
+          </ul>
+        )}
+
         <div className="flex items-center justify-between gap-2 pt-2">
           <Button type="button" variant="outline" onClick={() => void testConnection()} disabled={isTesting}>
             <RefreshCw className={`mr-1.5 h-4 w-4 ${isTesting ? 'animate-spin' : ''}`} />
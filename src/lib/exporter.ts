@@ -27,6 +27,8 @@ export interface ExportPreparationInput {
   citeKey: string;
   item: ZoteroItemData;
   annotations: AnnotationModel[];
+  imageExtension?: string;
+  markdownPath?: string;
 }
 
 export interface PreparedExport {
@@ -80,8 +82,9 @@ function groupAnnotations(annotations: Array<RenderAnnotation & { __colorName: s
 
 export function prepareExport(input: ExportPreparationInput): PreparedExport {
   const imageDir = normalizePath(input.attachmentBaseDir);
-  const markdownPath = normalizePath(`${input.markdownDir}/@${input.citeKey}.md`);
+  const markdownPath = normalizePath(input.markdownPath ?? `${input.markdownDir}/@${input.citeKey}.md`);
 
+  const imageExtension = input.imageExtension ?? 'png';
   let imageCounter = 0;
   const imagePlans: AnnotationImagePlan[] = [];
 
@@ -93,18 +96,23 @@ export function prepareExport(input: ExportPreparationInput): PreparedExport {
         text: annotation.text.trim(),
         comment: annotation.comment.trim(),
         pageLabel: annotation.pageLabel.trim(),
+        attachmentKey: annotation.attachmentKey,
+        attachmentTitle: annotation.attachmentTitle,
+        sortIndex: annotation.sortIndex,
+        dateAdded: annotation.dateAdded,
         __colorName: annotation.colorName,
       };
 
       if (annotation.isImageSelection) {
         imageCounter += 1;
-        const fileName = `@${input.citeKey}_${imageCounter}.png`;
+        const fileName = `@${input.citeKey}_${imageCounter}.${imageExtension}`;
         const absolutePath = normalizePath(`${imageDir}/${fileName}`);
         const relativePathFromMarkdown = fileName;
 
         imagePlans.push({
           annotationKey: annotation.key,
           attachmentKey: annotation.attachmentKey,
+          dateModified: annotation.dateModified,
           fileName,
           absolutePath,
           relativePathFromMarkdown,
@@ -162,6 +170,34 @@ export function addMissingImageTodo(prepared: PreparedExport, annotationKey: str
   };
 }
 
+export function setImageText(prepared: PreparedExport, annotationKey: string, text: string): PreparedExport {
+  const groupedAnnotations = prepared.groupedAnnotations.map((group) => ({
+    ...group,
+    annotations: group.annotations.map((annotation) =>
+      annotation.key === annotationKey ? { ...annotation, imageText: text } : annotation,
+    ),
+  }));
+
+  return {
+    ...prepared,
+    groupedAnnotations,
+  };
+}
+
+export function setImageLink(prepared: PreparedExport, annotationKey: string, link: string): PreparedExport {
+  const groupedAnnotations = prepared.groupedAnnotations.map((group) => ({
+    ...group,
+    annotations: group.annotations.map((annotation) =>
+      annotation.key === annotationKey ? { ...annotation, imageMarkdownPath: link } : annotation,
+    ),
+  }));
+
+  return {
+    ...prepared,
+    groupedAnnotations,
+  };
+}
+
 export function normalizeAnnotation(raw: ZoteroItemData, attachmentKey: string, sortIndex: number): AnnotationModel {
   const data = raw.data ?? {};
   return {
@@ -173,6 +209,8 @@ export function normalizeAnnotation(raw: ZoteroItemData, attachmentKey: string,
     comment: ((data.annotationComment as string) ?? '').trim(),
     pageLabel: ((data.annotationPageLabel as string) ?? '').trim(),
     sortIndex,
-    isImageSelection: ((data.annotationType as string) ?? '').toLowerCase() === 'image',
+    isImageSelection: ['image', 'ink'].includes(((data.annotationType as string) ?? '').toLowerCase()),
+    dateAdded: ((data.dateAdded as string) ?? '').trim(),
+    dateModified: ((data.dateModified as string) ?? '').trim(),
   };
 }
@@ -7,7 +7,15 @@ import type {
 } from "./types";
 import { escapeSingleQuote } from "./utils";
 
-function annotationQuoteLines(annotation: RenderAnnotation): string[] {
+export function blockIdForAnnotation(annotationKey: string): string {
+  // Obsidian block IDs only allow letters, digits and dashes.
+  return `^zot-${annotationKey.replace(/[^A-Za-z0-9-]/g, "")}`;
+}
+
+function annotationQuoteLines(
+  annotation: RenderAnnotation,
+  withBlockId: boolean,
+): string[] {
   const lines: string[] = [];
 
   const pageSuffix = annotation.pageLabel
@@ -35,6 +43,11 @@ function annotationQuoteLines(annotation: RenderAnnotation): string[] {
     lines.push(`TODO: ${annotation.missingImageMessage}`);
   }
 
+  if (withBlockId) {
+    const last = lines.length - 1;
+    lines[last] = `${lines[last]} ${blockIdForAnnotation(annotation.key)}`;
+  }
+
   return lines;
 }
 
@@ -139,7 +152,10 @@ export function generateMarkdown(input: ExportInput): string {
     lines.push(`### ${override || group.colorName}`);
 
     group.annotations.forEach((annotation, index) => {
-      const quoteLines = annotationQuoteLines(annotation);
+      const quoteLines = annotationQuoteLines(
+        annotation,
+        Boolean(input.templateSettings?.blockIds),
+      );
       quoteLines.forEach((quoteLine) => lines.push(`> ${quoteLine}`));
       if (index < group.annotations.length - 1) {
         lines.push("");
@@ -1,5 +1,99 @@
-import { invoke } from '@tauri-apps/api/core';
-import type { AppSettings, ItemSummary, ZoteroItemData } from './types';
+import { convertFileSrc, invoke as tauriInvoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type {
+  AnnotationStamp,
+  ApiServerStatus,
+  AppErrorPayload,
+  AppSettings,
+  AutoSyncProgress,
+  CitationScan,
+  ConnectionReport,
+  ErrorCode,
+  ExportReport,
+  ExportTarget,
+  FieldError,
+  FieldMatch,
+  ImageOutputFormat,
+  ImageSettings,
+  ImportReport,
+  IndexHit,
+  IndexRefresh,
+  ItemExportRecord,
+  ItemSummary,
+  Job,
+  JobTask,
+  NoteInput,
+  NoteMatch,
+  NoteWrite,
+  OperationProgress,
+  OrphanAction,
+  OrphanedNote,
+  PrefetchReport,
+  ProfileList,
+  QuickListItem,
+  ReadwiseFormat,
+  SavedAsset,
+  SearchResultsEvent,
+  SettingsReset,
+  SettingsSection,
+  SyncRun,
+  TemplateSettings,
+  UnifiedSearch,
+  WritebackReport,
+  ZoteroItemData,
+} from './types';
+
+/** Thrown by every command wrapper; `code` tells failures apart, `fields` lists rejected settings. */
+export class CommandError extends Error {
+  code: ErrorCode;
+  context?: string;
+  fields: FieldError[];
+
+  constructor(payload: AppErrorPayload) {
+    super(payload.message);
+    this.name = 'CommandError';
+    this.code = payload.code;
+    this.context = payload.context;
+    this.fields = payload.fields ?? [];
+  }
+}
+
+export function toCommandError(error: unknown): CommandError {
+  if (error instanceof CommandError) {
+    return error;
+  }
+  const payload = error as Partial<AppErrorPayload> | null;
+  if (payload && typeof payload === 'object' && typeof payload.code === 'string') {
+    return new CommandError(payload as AppErrorPayload);
+  }
+  const message = error instanceof Error ? error.message : String(error);
+  return new CommandError({ code: 'other', message });
+}
+
+async function invoke<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  try {
+    return await tauriInvoke<T>(command, args);
+  } catch (error) {
+    throw toCommandError(error);
+  }
+}
+
+/** Whether the webview can gunzip, so large JSON results may be requested compressed. */
+const canDecompress = typeof DecompressionStream !== 'undefined';
+
+/**
+ * Invokes a command that returns raw JSON bytes, asking for gzip when the webview can decompress it.
+ * Compressed payloads are recognized by the gzip magic bytes; small ones arrive as plain JSON.
+ */
+async function invokeJson<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  const buffer = await invoke<ArrayBuffer>(command, { ...args, compress: canDecompress });
+  let bytes = new Uint8Array(buffer);
+  if (bytes[0] === 0x1f && bytes[1] === 0x8b) {
+    const stream = new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip'));
+    bytes = new Uint8Array(await new Response(stream).arrayBuffer());
+  }
+  return JSON.parse(new TextDecoder().decode(bytes)) as T;
+}
 
 export interface SqliteAnnotationPayload {
   key: string;
@@ -10,27 +104,213 @@ export interface SqliteAnnotationPayload {
   pageLabel: string;
   sortIndex: number;
   isImageSelection: boolean;
+  attachmentTitle: string;
+  dateAdded: string;
+  dateModified: string;
+}
+
+/** Annotations matching a library-wide search, under their item. */
+export interface AnnotationSearchGroup extends ItemSummary {
+  annotations: Array<{
+    annotation: SqliteAnnotationPayload;
+    /** Where the query matched in the annotation's `text` and `comment`. */
+    matches: FieldMatch[];
+  }>;
+}
+
+export interface SqliteChildNotePayload {
+  key: string;
+  note: string;
 }
 
 const LOCAL_STORAGE_KEY = 'zotnotes-settings';
 
-function isTauriRuntime(): boolean {
+export function isTauriRuntime(): boolean {
   return typeof window !== 'undefined' && '__TAURI_INTERNALS__' in window;
 }
 
 function defaultSettings(): AppSettings {
   return {
+    outputTarget: 'markdown',
     markdownDir: '',
     attachmentBaseDir: '',
     zoteroApiKey: '',
     zoteroBaseUrl: 'http://127.0.0.1:23119',
+    zoteroUserId: '',
     templateSettings: {
       propertyOrder: ['title', 'author', 'year', 'company'],
       colorHeadingOverrides: {},
+      blockIds: false,
+      frontmatterFields: [],
+      metadataOutput: 'frontmatter',
+      tagMapping: {
+        enabled: false,
+        prefix: '',
+        denyList: [],
+        slugify: false,
+      },
+      escapeMarkdown: false,
+      preserveMath: false,
+      sectionHeadingLevel: 3,
+      colorOrder: [],
+      otherColorsHeading: '',
+      grouping: 'color',
+      tableOfContents: false,
+      pdfLinks: false,
+      dateFormat: { pattern: '', locale: '' },
+      imageText: 'altText',
+    },
+    imageSettings: {
+      format: 'png',
+      quality: 85,
+      maxWidth: null,
+      maxHeight: null,
+      renderDpi: null,
+      darkMode: 'off',
+    },
+    assetPathTemplate: '',
+    imageLinkStyle: 'attachmentRelative',
+    ocr: {
+      enabled: false,
+      languages: 'eng',
+      tesseractPath: '',
+    },
+    joplin: {
+      baseUrl: 'http://127.0.0.1:41184',
+      token: '',
+      notebook: '',
+    },
+    changelogNote: '',
+    git: {
+      autoCommit: false,
+    },
+    collectionOverrides: [],
+    editorCommand: '',
+    obsidian: {
+      openAfterExport: false,
+      vault: '',
+    },
+    csvColumns: [],
+    autoSync: {
+      enabled: false,
+      intervalMinutes: 30,
+      onStartup: false,
+      startupDelaySeconds: 10,
     },
+    apiServer: {
+      enabled: false,
+      port: 23121,
+      token: '',
+    },
+    conflictStrategy: 'conflictCopy',
+    deletedAnnotations: 'remove',
+    orphanArchiveDir: '_archive',
+    orphanTag: 'zotero/deleted',
+    excludeTag: '',
+    zoteroDataDir: '',
+    webdav: {
+      url: '',
+      username: '',
+      password: '',
+    },
+    searchLimit: 75,
+    searchFuzziness: 1,
+    fullTextSearch: true,
+    logLevel: 'info',
   };
 }
 
+export interface LibraryChangedPayload {
+  changedAt: number;
+}
+
+export async function onZoteroLibraryChanged(handler: (payload: LibraryChangedPayload) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<LibraryChangedPayload>('zotero-library-changed', (event) => handler(event.payload));
+}
+
+export interface AutoSyncHandlers {
+  onProgress: (progress: AutoSyncProgress) => void;
+  onCompleted: (report: ExportReport) => void;
+  onFailed: (error: CommandError) => void;
+}
+
+export async function onAutoSyncEvents(handlers: AutoSyncHandlers): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  const unlisteners = await Promise.all([
+    listen<AutoSyncProgress>('auto-sync-progress', (event) => handlers.onProgress(event.payload)),
+    listen<ExportReport>('auto-sync-completed', (event) => handlers.onCompleted(event.payload)),
+    listen<AppErrorPayload>('auto-sync-failed', (event) => handlers.onFailed(toCommandError(event.payload))),
+  ]);
+  return () => unlisteners.forEach((unlisten) => unlisten());
+}
+
+export function newOperationId(kind: string): string {
+  return `${kind}-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
+}
+
+export async function onOperationProgress(handler: (progress: OperationProgress) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<OperationProgress>('operation-progress', (event) => handler(event.payload));
+}
+
+/** Pages of results from `streamSearch`; ignore pages whose `searchId` isn't the latest. */
+export async function onSearchResults(handler: (results: SearchResultsEvent) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<SearchResultsEvent>('search-results', (event) => handler(event.payload));
+}
+
+/** Fires with the new settings whenever any window or background task saves them. */
+export async function onSettingsChanged(handler: (settings: AppSettings) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<AppSettings>('settings-changed', (event) => handler(event.payload));
+}
+
+export async function onJobsChanged(handler: (jobId: number) => void): Promise<() => void> {
+  if (!isTauriRuntime()) {
+    return () => {};
+  }
+  return listen<number>('jobs-changed', (event) => handler(event.payload));
+}
+
+export async function enqueueJob(task: JobTask): Promise<Job> {
+  if (!isTauriRuntime()) {
+    throw new Error('Background jobs are only available in Tauri runtime.');
+  }
+  return invoke<Job>('enqueue_job', { task });
+}
+
+export async function listJobs(limit?: number): Promise<Job[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return invoke<Job[]>('list_jobs', { limit });
+}
+
+export async function cancelJob(id: number): Promise<boolean> {
+  if (!isTauriRuntime()) {
+    return false;
+  }
+  return invoke<boolean>('cancel_job', { id });
+}
+
+export async function retryJob(id: number): Promise<boolean> {
+  if (!isTauriRuntime()) {
+    return false;
+  }
+  return invoke<boolean>('retry_job', { id });
+}
+
 export async function selectDirectoryDialog(): Promise<string | null> {
   if (!isTauriRuntime()) {
     return null;
@@ -38,11 +318,121 @@ export async function selectDirectoryDialog(): Promise<string | null> {
   return invoke<string | null>('select_directory_dialog');
 }
 
-export async function saveMarkdownFile(path: string, content: string): Promise<void> {
+export async function selectSaveFileDialog(defaultName: string): Promise<string | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<string | null>('select_save_file_dialog', { defaultName });
+}
+
+export async function selectOpenFileDialog(): Promise<string | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<string | null>('select_open_file_dialog');
+}
+
+export async function saveMarkdownFile(
+  path: string,
+  content: string,
+  annotations?: AnnotationStamp[],
+  itemKey?: string,
+): Promise<NoteWrite> {
   if (!isTauriRuntime()) {
     throw new Error('Filesystem write is only available in Tauri runtime.');
   }
-  await invoke('save_markdown_file', { path, content });
+  return invoke<NoteWrite>('save_markdown_file', { path, content, annotations, itemKey });
+}
+
+export async function recordItemExport(
+  itemKey: string,
+  citeKey: string,
+  markdownPath: string,
+  itemVersion: number | undefined,
+  annotations: AnnotationStamp[],
+  contentHash?: string,
+): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+  await invoke('record_item_export', { itemKey, citeKey, markdownPath, itemVersion, annotations, contentHash });
+}
+
+export async function getItemExportRecord(itemKey: string): Promise<ItemExportRecord | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<ItemExportRecord | null>('get_item_export_record', { itemKey });
+}
+
+export async function writeExportChangelog(report: ExportReport): Promise<string | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<string | null>('write_export_changelog', { report });
+}
+
+export async function openInEditor(path: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('Opening files is only available in Tauri runtime.');
+  }
+  await invoke('open_in_editor', { path });
+}
+
+/** Opens an exported note in Obsidian; returns the `obsidian://` URI used. */
+export async function openInObsidian(path: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Opening files is only available in Tauri runtime.');
+  }
+  return invoke<string>('open_in_obsidian', { path });
+}
+
+/** Selects the item in the Zotero desktop app. */
+export async function openInZotero(itemKey: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('Opening Zotero is only available in Tauri runtime.');
+  }
+  await invoke('open_in_zotero', { itemKey });
+}
+
+/** Opens the annotation's PDF in Zotero's reader at the annotation. */
+export async function openAnnotationInZotero(annotationKey: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('Opening Zotero is only available in Tauri runtime.');
+  }
+  await invoke('open_annotation_in_zotero', { annotationKey });
+}
+
+/** Shows the file or folder in the system file manager. */
+export async function revealInFileManager(path: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('Revealing files is only available in Tauri runtime.');
+  }
+  await invoke('reveal_in_file_manager', { path });
+}
+
+/** Copies a plain-text reference for the item; returns the copied text. */
+export async function copyCitation(itemKey: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Clipboard commands are only available in Tauri runtime.');
+  }
+  return invoke<string>('copy_citation', { itemKey });
+}
+
+/** Copies `[@citekey]` for the item; returns the copied text. */
+export async function copyPandocCitation(itemKey: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Clipboard commands are only available in Tauri runtime.');
+  }
+  return invoke<string>('copy_pandoc_citation', { itemKey });
+}
+
+/** Copies the item's rendered note Markdown; returns the copied text. */
+export async function copyNoteMarkdown(itemKey: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Clipboard commands are only available in Tauri runtime.');
+  }
+  return invoke<string>('copy_note_markdown', { itemKey });
 }
 
 export async function ensureDir(path: string): Promise<void> {
@@ -52,11 +442,50 @@ export async function ensureDir(path: string): Promise<void> {
   await invoke('ensure_dir', { path });
 }
 
-export async function savePngBytes(path: string, bytes: number[]): Promise<void> {
+export async function saveAnnotationImage(path: string, bytes: number[], imageSettings: ImageSettings): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Binary write is only available in Tauri runtime.');
+  }
+  return invoke<string>('save_annotation_image', { path, bytes, imageSettings });
+}
+
+export async function saveItemAsset(
+  itemKey: string,
+  citeKey: string,
+  fileName: string,
+  bytes: number[],
+  noteDir?: string,
+  annotationKey?: string,
+): Promise<SavedAsset> {
   if (!isTauriRuntime()) {
     throw new Error('Binary write is only available in Tauri runtime.');
   }
-  await invoke('save_png_bytes', { path, bytes });
+  return invoke<SavedAsset>('save_item_asset', { itemKey, citeKey, fileName, bytes, noteDir, annotationKey });
+}
+
+export async function resolveExportTarget(
+  item: ZoteroItemData,
+  citeKey: string,
+  templateSettings: TemplateSettings,
+): Promise<ExportTarget | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<ExportTarget>('resolve_export_target', { item, citeKey, templateSettings });
+}
+
+export async function renderNote(input: NoteInput, templateSettings: TemplateSettings): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Note rendering is only available in Tauri runtime.');
+  }
+  return invoke<string>('render_note', { input, templateSettings });
+}
+
+export async function htmlToMarkdown(html: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('HTML conversion is only available in Tauri runtime.');
+  }
+  return invoke<string>('html_to_markdown', { html });
 }
 
 export async function loadSettings(): Promise<AppSettings> {
@@ -80,6 +509,38 @@ export async function loadSettings(): Promise<AppSettings> {
             ...(parsed.templateSettings?.colorHeadingOverrides ?? {}),
           },
         },
+        imageSettings: {
+          ...defaults.imageSettings,
+          ...(parsed.imageSettings ?? {}),
+        },
+        ocr: {
+          ...defaults.ocr,
+          ...(parsed.ocr ?? {}),
+        },
+        joplin: {
+          ...defaults.joplin,
+          ...(parsed.joplin ?? {}),
+        },
+        git: {
+          ...defaults.git,
+          ...(parsed.git ?? {}),
+        },
+        obsidian: {
+          ...defaults.obsidian,
+          ...(parsed.obsidian ?? {}),
+        },
+        autoSync: {
+          ...defaults.autoSync,
+          ...(parsed.autoSync ?? {}),
+        },
+        apiServer: {
+          ...defaults.apiServer,
+          ...(parsed.apiServer ?? {}),
+        },
+        webdav: {
+          ...defaults.webdav,
+          ...(parsed.webdav ?? {}),
+        },
       };
     } catch {
       return defaultSettings();
@@ -97,6 +558,88 @@ export async function saveSettings(settings: AppSettings): Promise<void> {
   await invoke('save_settings', { settings });
 }
 
+/** Changes only the given top-level settings, merged into the saved ones by the backend; returns the result. */
+export async function updateSettings(patch: Partial<AppSettings>): Promise<AppSettings> {
+  if (!isTauriRuntime()) {
+    const next = { ...(await loadSettings()), ...patch };
+    window.localStorage.setItem(LOCAL_STORAGE_KEY, JSON.stringify(next));
+    return next;
+  }
+  return invoke<AppSettings>('update_settings', { patch });
+}
+
+/** Restores the defaults for the given sections, keeping folders, URLs and keys. */
+export async function resetSettings(sections: SettingsSection[]): Promise<SettingsReset> {
+  if (!isTauriRuntime()) {
+    throw new Error('Resetting settings is only available in Tauri runtime.');
+  }
+  return invoke<SettingsReset>('reset_settings', { sections });
+}
+
+/** Checks every data source with the given (possibly unsaved) settings; `null` outside Tauri. */
+export async function testConnections(settings: AppSettings): Promise<ConnectionReport | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+  return invoke<ConnectionReport>('test_connections', { settings });
+}
+
+export async function getRecentLogs(lines?: number): Promise<string[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+  return invoke<string[]>('get_recent_logs', { lines });
+}
+
+export async function createDiagnosticsBundle(path: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('Diagnostics bundles are only available in Tauri runtime.');
+  }
+  return invoke<string>('create_diagnostics_bundle', { path });
+}
+
+export async function exportSettingsBundle(path: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('Settings bundles are only available in Tauri runtime.');
+  }
+  await invoke('export_settings_bundle', { path });
+}
+
+export async function importSettingsBundle(path: string): Promise<AppSettings> {
+  if (!isTauriRuntime()) {
+    throw new Error('Settings bundles are only available in Tauri runtime.');
+  }
+  return invoke<AppSettings>('import_settings_bundle', { path });
+}
+
+export async function listProfiles(): Promise<ProfileList> {
+  if (!isTauriRuntime()) {
+    return { active: 'default', profiles: ['default'] };
+  }
+  return invoke<ProfileList>('list_profiles');
+}
+
+export async function createProfile(name: string, copyCurrent: boolean): Promise<ProfileList> {
+  if (!isTauriRuntime()) {
+    throw new Error('Profiles are only available in Tauri runtime.');
+  }
+  return invoke<ProfileList>('create_profile', { name, copyCurrent });
+}
+
+export async function switchProfile(name: string): Promise<AppSettings> {
+  if (!isTauriRuntime()) {
+    throw new Error('Profiles are only available in Tauri runtime.');
+  }
+  return invoke<AppSettings>('switch_profile', { name });
+}
+
+export async function deleteProfile(name: string): Promise<ProfileList> {
+  if (!isTauriRuntime()) {
+    throw new Error('Profiles are only available in Tauri runtime.');
+  }
+  return invoke<ProfileList>('delete_profile', { name });
+}
+
 export async function writeTempDebugDump(prefix: string, content: string): Promise<string> {
   if (!isTauriRuntime()) {
     throw new Error('Debug dump is only available in Tauri runtime.');
@@ -115,25 +658,124 @@ export async function zoteroProxyGetJson(url: string, zoteroApiKey: string): Pro
   });
 }
 
-export async function zoteroProxyGetBytes(url: string, zoteroApiKey: string): Promise<Uint8Array> {
+export async function zoteroProxyGetBytes(url: string, zoteroApiKey: string, operationId?: string): Promise<Uint8Array> {
   if (!isTauriRuntime()) {
     throw new Error('Zotero proxy is only available in Tauri runtime.');
   }
 
-  const values = await invoke<number[]>('zotero_proxy_get_bytes', {
+  const buffer = await invoke<ArrayBuffer>('zotero_proxy_get_bytes', {
     url,
     zoteroApiKey: zoteroApiKey || null,
+    operationId: operationId ?? null,
   });
 
-  return new Uint8Array(values);
+  return new Uint8Array(buffer);
+}
+
+/** Searches the local database; `limit` defaults to the `searchLimit` setting. */
+export async function zoteroSqliteSearchItems(query: string, limit?: number): Promise<ItemSummary[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<ItemSummary[]>('zotero_sqlite_search_items', { query, limit });
+}
+
+/** Full-text search over the app's index of the library, refreshed before each search. */
+export async function searchIndex(query: string, limit?: number): Promise<IndexHit[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<IndexHit[]>('search_index', { query, limit });
+}
+
+/**
+ * Search-as-you-type: results arrive as `search-results` events, and any search
+ * still running is cancelled. Resolves to false when this search was cancelled.
+ */
+export async function streamSearch(query: string, searchId: string, limit?: number): Promise<boolean> {
+  if (!isTauriRuntime()) {
+    return false;
+  }
+
+  return invoke<boolean>('stream_search', { query, searchId, limit });
+}
+
+export async function cancelSearch(): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+
+  await invoke('cancel_search');
 }
 
-export async function zoteroSqliteSearchItems(query: string): Promise<ItemSummary[]> {
+/** Highlights and comments containing every word of `query`, grouped by item. */
+export async function searchAnnotations(query: string, limit?: number): Promise<AnnotationSearchGroup[]> {
   if (!isTauriRuntime()) {
     throw new Error('SQLite access is only available in Tauri runtime.');
   }
 
-  return invoke<ItemSummary[]>('zotero_sqlite_search_items', { query });
+  return invoke<AnnotationSearchGroup[]>('search_annotations', { query, limit });
+}
+
+/** Lines of the notes in `markdownDir` that contain every word of `query`. */
+export async function searchNotes(query: string, limit?: number): Promise<NoteMatch[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+
+  return invoke<NoteMatch[]>('search_notes', { query, limit });
+}
+
+/** Searches the local database and unsynced group libraries on api.zotero.org. */
+export async function unifiedSearch(query: string, limit?: number): Promise<UnifiedSearch> {
+  if (!isTauriRuntime()) {
+    return { items: [], warnings: [] };
+  }
+
+  return invoke<UnifiedSearch>('unified_search', { query, limit });
+}
+
+/** Pinned items, then the most recently opened or exported ones. */
+export async function getQuickList(limit?: number): Promise<QuickListItem[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+
+  return invoke<QuickListItem[]>('get_quick_list', { limit });
+}
+
+export async function recordItemOpened(itemKey: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+
+  await invoke('record_item_opened', { itemKey });
+}
+
+export async function pinItem(itemKey: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+
+  await invoke('pin_item', { itemKey });
+}
+
+export async function unpinItem(itemKey: string): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+
+  await invoke('unpin_item', { itemKey });
+}
+
+export async function rebuildSearchIndex(): Promise<IndexRefresh> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<IndexRefresh>('rebuild_search_index');
 }
 
 export async function zoteroSqliteGetItem(itemKey: string): Promise<ZoteroItemData> {
@@ -141,24 +783,285 @@ export async function zoteroSqliteGetItem(itemKey: string): Promise<ZoteroItemDa
     throw new Error('SQLite access is only available in Tauri runtime.');
   }
 
-  return invoke<ZoteroItemData>('zotero_sqlite_get_item', { itemKey });
+  return invokeJson<ZoteroItemData>('zotero_sqlite_get_item', { itemKey });
+}
+
+/** An item's annotations in reading order; with `limit`, one page starting at `offset`. */
+export async function zoteroSqliteGetAnnotations(
+  itemKey: string,
+  offset?: number,
+  limit?: number,
+): Promise<SqliteAnnotationPayload[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invokeJson<SqliteAnnotationPayload[]>('zotero_sqlite_get_annotations', {
+    itemKey,
+    offset: offset ?? null,
+    limit: limit ?? null,
+  });
+}
+
+export async function zoteroSqliteCountAnnotations(itemKey: string): Promise<number> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<number>('zotero_sqlite_count_annotations', { itemKey });
+}
+
+export async function zoteroSqliteGetChildNotes(itemKey: string): Promise<SqliteChildNotePayload[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invokeJson<SqliteChildNotePayload[]>('zotero_sqlite_get_child_notes', { itemKey });
 }
 
-export async function zoteroSqliteGetAnnotations(itemKey: string): Promise<SqliteAnnotationPayload[]> {
+export async function zoteroSqliteChangedExportedItems(): Promise<string[]> {
   if (!isTauriRuntime()) {
     throw new Error('SQLite access is only available in Tauri runtime.');
   }
 
-  return invoke<SqliteAnnotationPayload[]>('zotero_sqlite_get_annotations', { itemKey });
+  return invoke<string[]>('zotero_sqlite_changed_exported_items');
 }
 
-export async function zoteroSqliteGetCachedAnnotationImage(annotationKey: string): Promise<Uint8Array> {
+export async function exportItemsCsv(
+  path: string,
+  itemKeys: string[],
+  collection?: string,
+  operationId?: string,
+): Promise<number> {
   if (!isTauriRuntime()) {
     throw new Error('SQLite access is only available in Tauri runtime.');
   }
 
-  const values = await invoke<number[]>('zotero_sqlite_get_cached_annotation_image', { annotationKey });
-  return new Uint8Array(values);
+  return invoke<number>('export_items_csv', { path, itemKeys, collection, operationId });
+}
+
+export async function exportItemsRis(
+  path: string,
+  itemKeys: string[],
+  collection?: string,
+  operationId?: string,
+): Promise<number> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<number>('export_items_ris', { path, itemKeys, collection, operationId });
+}
+
+export async function exportHighlightsReadwise(
+  path: string,
+  itemKeys: string[],
+  format: ReadwiseFormat,
+  collection?: string,
+  operationId?: string,
+): Promise<number> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<number>('export_highlights_readwise', { path, itemKeys, collection, format, operationId });
+}
+
+export async function exportNotesDocx(
+  path: string,
+  itemKeys: string[],
+  collection?: string,
+  operationId?: string,
+): Promise<number> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<number>('export_notes_docx', { path, itemKeys, collection, operationId });
+}
+
+export async function exportNotesNotion(
+  dir: string,
+  itemKeys: string[],
+  collection?: string,
+  operationId?: string,
+): Promise<number> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<number>('export_notes_notion', { dir, itemKeys, collection, operationId });
+}
+
+export async function exportItemBundle(itemKey: string, path: string): Promise<string> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<string>('export_item_bundle', { itemKey, path });
+}
+
+export async function syncChangedItems(operationId?: string, dryRun = false): Promise<ExportReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<ExportReport>('sync_changed_items', { operationId, dryRun });
+}
+
+export async function getSyncHistory(limit?: number): Promise<SyncRun[]> {
+  if (!isTauriRuntime()) {
+    return [];
+  }
+
+  return invoke<SyncRun[]>('get_sync_history', { limit });
+}
+
+export async function getApiServerStatus(): Promise<ApiServerStatus> {
+  if (!isTauriRuntime()) {
+    return { running: false };
+  }
+
+  return invoke<ApiServerStatus>('get_api_server_status');
+}
+
+export async function findOrphanedNotes(): Promise<OrphanedNote[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<OrphanedNote[]>('find_orphaned_notes');
+}
+
+export async function resolveOrphanedNotes(itemKeys: string[], action: OrphanAction): Promise<string[]> {
+  if (!isTauriRuntime()) {
+    throw new Error('Filesystem write is only available in Tauri runtime.');
+  }
+
+  return invoke<string[]>('resolve_orphaned_notes', { itemKeys, action });
+}
+
+export async function importNotes(dir?: string, dryRun = false): Promise<ImportReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('Note import is only available in Tauri runtime.');
+  }
+
+  return invoke<ImportReport>('import_notes', { dir: dir ?? null, dryRun });
+}
+
+export async function findUnlinkedCitations(dir?: string): Promise<CitationScan> {
+  if (!isTauriRuntime()) {
+    throw new Error('Note scanning is only available in Tauri runtime.');
+  }
+
+  return invoke<CitationScan>('find_unlinked_citations', { dir: dir ?? null });
+}
+
+export async function exportItems(itemKeys: string[], dryRun = false, operationId?: string): Promise<ExportReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  return invoke<ExportReport>('export_items', { itemKeys, dryRun, operationId });
+}
+
+export async function pushCommentEdits(markdownPath?: string): Promise<WritebackReport> {
+  if (!isTauriRuntime()) {
+    throw new Error('Comment write-back is only available in Tauri runtime.');
+  }
+
+  return invoke<WritebackReport>('push_comment_edits', { markdownPath: markdownPath ?? null });
+}
+
+/** Zotero's image for an annotation; `format` re-encodes it before it crosses IPC. */
+export async function zoteroSqliteGetCachedAnnotationImage(
+  annotationKey: string,
+  format?: ImageOutputFormat,
+): Promise<Uint8Array> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  const buffer = await invoke<ArrayBuffer>('zotero_sqlite_get_cached_annotation_image', {
+    annotationKey,
+    format: format ?? null,
+  });
+  return new Uint8Array(buffer);
+}
+
+/**
+ * URL that loads an annotation image through the `annotation://` protocol, for use as an `<img>` source.
+ * With `dateModified` the image comes from the app's image cache and the webview revalidates it by ETag.
+ * Null outside the Tauri runtime.
+ */
+export function annotationImageUrl(annotationKey: string, dateModified?: string): string | null {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+
+  const url = convertFileSrc(annotationKey, 'annotation');
+  return dateModified ? `${url}?modified=${encodeURIComponent(dateModified)}` : url;
+}
+
+/** Image cached by the app for this annotation version, or null when there is none. */
+export async function readImageCache(annotationKey: string, dateModified: string): Promise<Uint8Array | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+
+  const buffer = await invoke<ArrayBuffer>('read_image_cache', { annotationKey, dateModified });
+  return buffer.byteLength > 0 ? new Uint8Array(buffer) : null;
+}
+
+export async function writeImageCache(annotationKey: string, dateModified: string, bytes: Uint8Array): Promise<void> {
+  if (!isTauriRuntime()) {
+    return;
+  }
+
+  await invoke('write_image_cache', { annotationKey, dateModified, bytes: Array.from(bytes) });
+}
+
+/** Text recognized in an annotation image; null when OCR is off or found nothing. */
+export async function ocrAnnotationImage(bytes: Uint8Array): Promise<string | null> {
+  if (!isTauriRuntime()) {
+    return null;
+  }
+
+  return invoke<string | null>('ocr_annotation_image', { bytes: Array.from(bytes) });
+}
+
+export async function prefetchAnnotationImages(itemKey: string, operationId?: string): Promise<PrefetchReport> {
+  if (!isTauriRuntime()) {
+    return { cached: 0, failed: [] };
+  }
+
+  return invoke<PrefetchReport>('prefetch_annotation_images', { itemKey, operationId });
+}
+
+export async function clearImageCache(): Promise<number> {
+  if (!isTauriRuntime()) {
+    return 0;
+  }
+
+  return invoke<number>('clear_image_cache');
+}
+
+/** Renders an area or ink annotation from the PDF at `dpi`, bypassing Zotero's cached image. */
+export async function zoteroSqliteRenderAnnotationImage(
+  annotationKey: string,
+  dpi?: number,
+  format?: ImageOutputFormat,
+): Promise<Uint8Array> {
+  if (!isTauriRuntime()) {
+    throw new Error('SQLite access is only available in Tauri runtime.');
+  }
+
+  const buffer = await invoke<ArrayBuffer>('zotero_sqlite_render_annotation_image', {
+    annotationKey,
+    dpi: dpi ?? null,
+    format: format ?? null,
+  });
+  return new Uint8Array(buffer);
 }
 
 export async function zoteroSqliteGetCitationKey(itemKey: string): Promise<string | null> {
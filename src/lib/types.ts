@@ -1,20 +1,145 @@
 export type TemplatePropertyKey = 'title' | 'author' | 'year' | 'company';
 
+export type AnnotationGrouping = 'color' | 'page' | 'chronological' | 'attachment';
+
+export type MetadataOutput = 'frontmatter' | 'inline' | 'both';
+
+export interface TagMapping {
+  enabled: boolean;
+  prefix: string;
+  denyList: string[];
+  slugify: boolean;
+}
+
+export interface FrontmatterField {
+  field: string;
+  key: string;
+  omit?: boolean;
+  constant?: string | null;
+  list?: boolean;
+}
+
 export interface TemplateSettings {
   propertyOrder: TemplatePropertyKey[];
   colorHeadingOverrides: Record<string, string>;
+  blockIds?: boolean;
+  frontmatterFields?: FrontmatterField[];
+  metadataOutput?: MetadataOutput;
+  tagMapping?: TagMapping;
+  escapeMarkdown?: boolean;
+  preserveMath?: boolean;
+  sectionHeadingLevel?: number;
+  colorOrder?: string[];
+  otherColorsHeading?: string;
+  grouping?: AnnotationGrouping;
+  tableOfContents?: boolean;
+  pdfLinks?: boolean;
+  dateFormat?: DateFormat;
+  /** Where OCR text of image annotations is written. */
+  imageText?: ImageTextStyle;
+}
+
+export type ImageTextStyle = 'altText' | 'quote';
+
+/** `pattern` is `short`, `long`, `iso` or tokens like `D MMMM YYYY`; empty keeps Zotero's value. */
+export interface DateFormat {
+  pattern: string;
+  /** BCP 47 tag for month names and presets, e.g. `de`; empty means `en-US`. */
+  locale: string;
+}
+
+export type ImageOutputFormat = 'png' | 'jpeg' | 'webp';
+
+export type DarkImageMode = 'off' | 'invert' | 'variant';
+
+export interface ImageSettings {
+  format: ImageOutputFormat;
+  quality: number;
+  maxWidth: number | null;
+  maxHeight: number | null;
+  /** DPI for area annotations rendered from the PDF; null keeps Zotero's cached image. */
+  renderDpi: number | null;
+  darkMode: DarkImageMode;
+}
+
+export type ImageLinkStyle = 'attachmentRelative' | 'noteRelative' | 'absolute';
+
+export type OutputTarget = 'markdown' | 'joplin';
+
+/** Readwise import format: the CSV upload or the highlight API's JSON body. */
+export type ReadwiseFormat = 'csv' | 'json';
+
+export interface JoplinSettings {
+  /** Web Clipper service address. */
+  baseUrl: string;
+  /** Authorization token from Joplin's Web Clipper options. */
+  token: string;
+  /** Notebook the notes go to; empty uses Joplin's default notebook. */
+  notebook: string;
+}
+
+export interface ObsidianSettings {
+  /** Open the note in Obsidian after exporting a single item. */
+  openAfterExport: boolean;
+  /** Vault name as Obsidian shows it; empty uses the vault folder's name. */
+  vault: string;
+}
+
+export interface GitSettings {
+  /** Commit the changed notes in the Markdown folder after each export. */
+  autoCommit: boolean;
+}
+
+export interface WebDavSettings {
+  /** WebDAV URL from Zotero's Sync settings; empty disables attachment downloads. */
+  url: string;
+  username: string;
+  password: string;
 }
 
 export interface AppSettings {
+  outputTarget: OutputTarget;
   markdownDir: string;
   attachmentBaseDir: string;
   zoteroApiKey: string;
   zoteroBaseUrl: string;
+  zoteroUserId: string;
   templateSettings: TemplateSettings;
+  imageSettings: ImageSettings;
+  assetPathTemplate: string;
+  imageLinkStyle: ImageLinkStyle;
+  ocr: OcrSettings;
+  joplin: JoplinSettings;
+  changelogNote: string;
+  git: GitSettings;
+  collectionOverrides: CollectionOverride[];
+  editorCommand: string;
+  obsidian: ObsidianSettings;
+  csvColumns: string[];
+  autoSync: AutoSyncSettings;
+  apiServer: ApiServerSettings;
+  conflictStrategy: ConflictStrategy;
+  deletedAnnotations: DeletedAnnotations;
+  orphanArchiveDir: string;
+  orphanTag: string;
+  /** Zotero tag that keeps items out of batch exports and sync; empty disables it. */
+  excludeTag: string;
+  /** Folder holding zotero.sqlite; empty to look in the default locations. */
+  zoteroDataDir: string;
+  /** Server missing attachment files are downloaded from, for WebDAV file sync. */
+  webdav: WebDavSettings;
+  /** Most items a library search returns (1–1000). */
+  searchLimit: number;
+  /** Typos tolerated per word by the full-text search (0–2); 0 matches exactly. */
+  searchFuzziness: number;
+  /** Search through the full-text index; when off, searches match substrings of titles, creators and years. */
+  fullTextSearch: boolean;
+  logLevel: LogLevel;
 }
 
 export interface ZoteroItemData {
   key: string;
+  version?: number;
   data: Record<string, unknown>;
   meta?: Record<string, unknown>;
 }
@@ -25,11 +150,71 @@ export interface ZoteroCreator {
   name?: string;
 }
 
+/** Where a search matched in one result field; ranges are `[start, end)` string indices. */
+export interface FieldMatch {
+  field: string;
+  ranges: Array<[number, number]>;
+}
+
 export interface ItemSummary {
   key: string;
   title: string;
   creators: string;
   year: string;
+  /** Annotations on the item's attachments; missing for Zotero API results. */
+  annotationCount?: number;
+  /** Whether the item has an attachment; missing for Zotero API results. */
+  hasAttachment?: boolean;
+  /** Where the search query matched, when the search reports it. */
+  matches?: FieldMatch[];
+}
+
+export interface IndexHit extends ItemSummary {
+  /** Best-matching fragment of the indexed text. */
+  snippet: string;
+  matches: FieldMatch[];
+}
+
+/** Result of a search across the local database and the Zotero web API. */
+export interface UnifiedHit extends ItemSummary {
+  source: 'local' | 'web';
+  /** Library the item is in, e.g. "My Library" or a group name. */
+  library: string;
+}
+
+export interface UnifiedSearch {
+  items: UnifiedHit[];
+  /** Libraries that could not be searched, with the reason. */
+  warnings: string[];
+}
+
+/** One page of a streamed search; pages of the same `searchId` append. */
+export interface SearchResultsEvent {
+  searchId: string;
+  items: IndexHit[];
+  done: boolean;
+}
+
+export interface IndexRefresh {
+  indexed: number;
+  removed: number;
+  total: number;
+}
+
+export interface NoteMatch {
+  path: string;
+  /** 1-based line number. */
+  line: number;
+  snippet: string;
+  /** Zotero item the note was exported from, when it is an exported note. */
+  itemKey: string | null;
+  matches: FieldMatch[];
+}
+
+export interface QuickListItem extends ItemSummary {
+  pinned: boolean;
+  /** When the item was pinned, or last opened or exported (unix seconds). */
+  lastUsedAt: number;
 }
 
 export interface ItemPreview {
@@ -49,12 +234,17 @@ export interface AnnotationModel {
   comment: string;
   pageLabel: string;
   sortIndex: number;
+  /** Area and ink annotations, which are exported as images. */
   isImageSelection: boolean;
+  attachmentTitle?: string;
+  dateAdded?: string;
+  dateModified?: string;
 }
 
 export interface AnnotationImagePlan {
   annotationKey: string;
   attachmentKey: string;
+  dateModified?: string;
   fileName: string;
   absolutePath: string;
   relativePathFromMarkdown: string;
@@ -83,6 +273,248 @@ export interface RenderAnnotation {
   pageLabel: string;
   imageMarkdownPath?: string;
   missingImageMessage?: string;
+  /** Text recognized in the image by OCR. */
+  imageText?: string;
+  attachmentKey?: string;
+  attachmentTitle?: string;
+  sortIndex?: number;
+  dateAdded?: string;
+}
+
+export interface SavedAsset {
+  path: string;
+  link: string;
+  /** The inverted `-dark` copy, when `imageSettings.darkMode` is `variant`. */
+  darkPath?: string;
+  darkLink?: string;
+}
+
+/** Text recognition for area annotations with the Tesseract command-line tool. */
+export interface OcrSettings {
+  enabled: boolean;
+  /** Tesseract language codes joined by `+`, e.g. `eng+deu`. */
+  languages: string;
+  /** Path to the `tesseract` executable; empty looks it up on PATH. */
+  tesseractPath: string;
+}
+
+export interface AutoSyncSettings {
+  enabled: boolean;
+  intervalMinutes: number;
+  onStartup: boolean;
+  startupDelaySeconds: number;
+}
+
+export interface ApiServerSettings {
+  enabled: boolean;
+  port: number;
+  /** Bearer token every request must send. */
+  token: string;
+}
+
+export interface ApiServerStatus {
+  running: boolean;
+  /** `http://127.0.0.1:<port>` while running. */
+  address?: string;
+  /** Why the server could not start, e.g. the port is taken. */
+  error?: string;
+}
+
+export interface AutoSyncProgress {
+  current: number;
+  total: number;
+  citeKey: string;
+}
+
+export interface CollectionOverride {
+  collection: string;
+  templateSettings?: TemplateSettings | null;
+  filenamePattern: string;
+  outputSubfolder: string;
+}
+
+export interface ExportTarget {
+  markdownPath: string;
+  templateSettings: TemplateSettings;
+  collection: string | null;
+}
+
+export interface ItemExportRecord {
+  itemKey: string;
+  citeKey: string;
+  markdownPath: string;
+  itemVersion: number;
+  contentHash: string;
+  annotationsModified: number;
+  annotationCount: number;
+  firstExportedAt: number;
+  lastExportedAt: number;
+}
+
+export type SyncTrigger = 'manual' | 'scheduled' | 'startup' | 'job' | 'cli';
+
+export interface SyncRun {
+  id: number;
+  trigger: SyncTrigger;
+  startedAt: number;
+  finishedAt: number;
+  created: number;
+  updated: number;
+  unchanged: number;
+  conflicts: number;
+  failures: string[];
+  error: string | null;
+}
+
+export type OrphanReason = 'deleted' | 'trashed';
+
+export interface OrphanedNote {
+  itemKey: string;
+  citeKey: string;
+  markdownPath: string;
+  reason: OrphanReason;
+  noteExists: boolean;
+}
+
+export type OrphanAction = 'archive' | 'tag';
+
+export interface UnlinkedCitation {
+  citeKey: string;
+  itemKey: string;
+  title: string;
+  mentions: { path: string; line: number }[];
+}
+
+export interface CitationScan {
+  unlinked: UnlinkedCitation[];
+  unknownKeys: string[];
+}
+
+export interface ImportedNote {
+  path: string;
+  itemKey: string;
+  citeKey: string;
+  matchedBy: 'citeKey' | 'doi';
+}
+
+export interface ImportReport {
+  dryRun: boolean;
+  imported: ImportedNote[];
+  skipped: { path: string; reason: string }[];
+  unmatched: string[];
+}
+
+export type JobTask =
+  | { kind: 'export'; itemKeys: string[] }
+  | { kind: 'sync'; itemKeys?: string[] | null }
+  | { kind: 'download'; url: string; path: string };
+
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface Job {
+  id: number;
+  task: JobTask;
+  status: JobStatus;
+  completed: string[];
+  failures: string[];
+  error: string | null;
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface OperationProgress {
+  operationId: string;
+  current: number;
+  /** Zero when the total is unknown. */
+  total: number;
+  message: string;
+}
+
+export type WriteStatus = 'created' | 'updated' | 'unchanged' | 'conflict';
+
+export type DeletedAnnotations = 'remove' | 'mark';
+
+export type ConflictStrategy = 'keepLocal' | 'keepZotero' | 'conflictCopy';
+
+export type FileAction = 'create' | 'update';
+
+export interface FileChange {
+  path: string;
+  action: FileAction;
+}
+
+export interface AnnotationStamp {
+  key: string;
+  dateAdded?: string;
+  dateModified?: string;
+}
+
+export interface NoteWrite {
+  path: string;
+  status: WriteStatus;
+  newAnnotationKeys: string[];
+  contentHash: string;
+  conflictPath: string | null;
+  changes: FileChange[];
+}
+
+export interface ItemReport {
+  itemKey: string;
+  citeKey: string;
+  path: string;
+  status: WriteStatus;
+  annotationCount: number;
+  newAnnotations: number;
+  conflictPath?: string | null;
+  changes?: FileChange[];
+}
+
+export interface ExportReport {
+  items: ItemReport[];
+  failed: string[];
+  /** Set when nothing was written and the report only previews the export. */
+  dryRun?: boolean;
+  /** Keys of requested items skipped because they carry the exclusion tag. */
+  excluded?: string[];
+  /** How current the Zotero data behind the report was. */
+  zoteroDatabase?: ZoteroFreshness;
+}
+
+export type ZoteroFreshness =
+  | { kind: 'live' }
+  | { kind: 'lagging'; walModifiedAt: number }
+  | { kind: 'snapshot'; takenAt: number; reason: string };
+
+export interface CommentEdit {
+  itemKey: string;
+  annotationKey: string;
+  comment: string;
+}
+
+export interface WritebackReport {
+  pushed: CommentEdit[];
+  conflicts: string[];
+  skipped: string[];
+  failed: string[];
+}
+
+export interface PrefetchFailure {
+  annotationKey: string;
+  message: string;
+}
+
+export interface PrefetchReport {
+  cached: number;
+  /** Images without a `dateModified` to cache them by; exports render them each time. */
+  skipped: number;
+  failed: PrefetchFailure[];
+}
+
+export interface NoteInput {
+  item: ZoteroItemData;
+  citeKey: string;
+  groupedAnnotations: ColorGroup[];
+  childNotes?: string[];
 }
 
 export interface ExportPlan {
@@ -90,3 +522,56 @@ export interface ExportPlan {
   markdownContent: string;
   imagePlans: AnnotationImagePlan[];
 }
+
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface ProfileList {
+  active: string;
+  /** Profile names, the default profile first. */
+  profiles: string[];
+}
+
+export type SettingsSection = 'templates' | 'images' | 'export' | 'sync' | 'search' | 'orphans' | 'webdav' | 'logging';
+
+export interface SettingsReset {
+  settings: AppSettings;
+  /** Copy of the previous settings file, if there was one. */
+  backup: string | null;
+}
+
+/** A setting rejected by `save_settings` or `update_settings`; `field` is its JSON path, e.g. `collectionOverrides.0.filenamePattern`. */
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+/** Machine-readable reason a command failed; see `CommandError`. */
+export type ErrorCode =
+  | 'zoteroNotFound'
+  | 'databaseLocked'
+  | 'database'
+  | 'network'
+  | 'zoteroApi'
+  | 'invalidSettings'
+  | 'pathNotAllowed'
+  | 'other';
+
+/** Error payload returned by every backend command. */
+export interface AppErrorPayload {
+  code: ErrorCode;
+  message: string;
+  context?: string;
+  fields?: FieldError[];
+}
+
+export interface ConnectionStatus {
+  ok: boolean;
+  message: string;
+}
+
+export interface ConnectionReport {
+  zoteroDatabase: ConnectionStatus;
+  betterBibtex: ConnectionStatus;
+  localApi: ConnectionStatus;
+  webApi: ConnectionStatus;
+}
@@ -1,10 +1,24 @@
 import { type ClassValue, clsx } from 'clsx';
 import { twMerge } from 'tailwind-merge';
+import type { ErrorCode } from './types';
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
 }
 
+const ERROR_HINTS: Partial<Record<ErrorCode, string>> = {
+  databaseLocked: 'Zotero is busy with its database; try again in a moment.',
+  network: 'Check that Zotero is running and the URL in Settings is reachable.',
+};
+
+/** Message for a failed command, with a hint on what to do for known error codes. */
+export function errorMessage(error: unknown): string {
+  const message = error instanceof Error ? error.message : String(error);
+  const code = (error as { code?: ErrorCode } | null)?.code;
+  const hint = code ? ERROR_HINTS[code] : undefined;
+  return hint ? `${message} — ${hint}` : message;
+}
+
 export function ensureTrailingSlash(value: string): string {
   return value.endsWith('/') ? value : `${value}/`;
 }
@@ -1,9 +1,14 @@
 import {
   type SqliteAnnotationPayload,
+  readImageCache,
+  searchIndex,
+  writeImageCache,
   zoteroProxyGetBytes,
   zoteroProxyGetJson,
+  zoteroSqliteCountAnnotations,
   zoteroSqliteGetAnnotations,
   zoteroSqliteGetCachedAnnotationImage,
+  zoteroSqliteGetChildNotes,
   zoteroSqliteGetCitationKey,
   zoteroSqliteGetItem,
   zoteroSqliteSearchItems,
@@ -73,6 +78,31 @@ function creatorsSummary(item: ZoteroItemData): string {
     .join('; ');
 }
 
+/** Annotations per `zotero_sqlite_get_annotations` call for items with many of them. */
+const ANNOTATION_PAGE_SIZE = 250;
+
+/**
+ * Loads an item's annotations from SQLite. Items with more than one page of
+ * them are loaded a page at a time, so no single IPC payload is huge and the
+ * UI can paint between pages.
+ */
+async function loadSqliteAnnotationPages(itemKey: string): Promise<SqliteAnnotationPayload[]> {
+  const total = await zoteroSqliteCountAnnotations(itemKey);
+  if (total <= ANNOTATION_PAGE_SIZE) {
+    return zoteroSqliteGetAnnotations(itemKey);
+  }
+
+  const annotations: SqliteAnnotationPayload[] = [];
+  for (let offset = 0; offset < total; offset += ANNOTATION_PAGE_SIZE) {
+    const page = await zoteroSqliteGetAnnotations(itemKey, offset, ANNOTATION_PAGE_SIZE);
+    annotations.push(...page);
+    if (page.length < ANNOTATION_PAGE_SIZE) {
+      break;
+    }
+  }
+  return annotations;
+}
+
 function normalizeSqliteAnnotation(annotation: SqliteAnnotationPayload): AnnotationModel {
   const colorHex = annotation.colorHex.trim().toLowerCase();
   return {
@@ -85,6 +115,9 @@ function normalizeSqliteAnnotation(annotation: SqliteAnnotationPayload): Annotat
     pageLabel: annotation.pageLabel.trim(),
     sortIndex: annotation.sortIndex,
     isImageSelection: annotation.isImageSelection,
+    attachmentTitle: annotation.attachmentTitle,
+    dateAdded: annotation.dateAdded,
+    dateModified: annotation.dateModified,
   };
 }
 
@@ -94,12 +127,16 @@ function isUsableTitle(title: string): boolean {
 }
 
 function sanitizeSearchResults(items: ItemSummary[]): ItemSummary[] {
-  return items.filter((item) => isUsableTitle(item.title)).map((item) => ({
-    ...item,
-    title: item.title.trim(),
-    creators: item.creators.trim(),
-    year: item.year.trim(),
-  }));
+  return items.filter((item) => isUsableTitle(item.title)).map((item) => {
+    const trimmed = {
+      title: item.title.trim(),
+      creators: item.creators.trim(),
+      year: item.year.trim(),
+    };
+    // Match ranges index the untrimmed fields.
+    const shifted = trimmed.title !== item.title || trimmed.creators !== item.creators || trimmed.year !== item.year;
+    return { ...item, ...trimmed, matches: shifted ? undefined : item.matches };
+  });
 }
 
 export class ZoteroClient {
@@ -107,9 +144,15 @@ export class ZoteroClient {
 
   private readonly apiKey: string;
 
+  private readonly searchLimit: number;
+
+  private readonly fullTextSearch: boolean;
+
   constructor(settings: AppSettings) {
     this.baseUrl = ensureTrailingSlash(settings.zoteroBaseUrl.trim() || 'http://127.0.0.1:23119').replace(/\/+$/, '');
     this.apiKey = settings.zoteroApiKey;
+    this.searchLimit = settings.searchLimit;
+    this.fullTextSearch = settings.fullTextSearch;
   }
 
   private async getItemsByParent(parentKey: string, itemType?: string): Promise<ZoteroItemData[]> {
@@ -210,7 +253,7 @@ export class ZoteroClient {
       return Array.isArray(raw) || isRecord(raw);
     } catch {
       try {
-        await zoteroSqliteSearchItems('');
+        await zoteroSqliteSearchItems('', 1);
         return true;
       } catch {
         return false;
@@ -224,7 +267,9 @@ export class ZoteroClient {
 
     try {
       // Endpoint observed in Zotero docs for local API parity: /api/users/0/items
-      const raw = await this.requestJson(`/api/users/0/items?limit=75&sort=title&direction=asc${qPart}`);
+      // The Zotero API returns at most 100 items per request.
+      const limit = Math.min(this.searchLimit, 100);
+      const raw = await this.requestJson(`/api/users/0/items?limit=${limit}&sort=title&direction=asc${qPart}`);
       const items = asArray<unknown>(raw)
         .map((entry) => shapeItem(entry))
         .filter((entry) => {
@@ -241,7 +286,9 @@ export class ZoteroClient {
       return sanitizeSearchResults(mapped);
     } catch (apiError) {
       try {
-        const sqliteResults = await zoteroSqliteSearchItems(query);
+        const sqliteResults = query.trim() && this.fullTextSearch
+          ? await searchIndex(query, this.searchLimit)
+          : await zoteroSqliteSearchItems(query, this.searchLimit);
         return sanitizeSearchResults(sqliteResults);
       } catch (sqliteError) {
         const apiMessage = apiError instanceof Error ? apiError.message : String(apiError);
@@ -276,9 +323,25 @@ export class ZoteroClient {
     }
   }
 
+  /** Returns child note bodies as HTML; the backend renderer converts them to markdown. */
+  async getChildNotes(itemKey: string): Promise<string[]> {
+    try {
+      const notes = await zoteroSqliteGetChildNotes(itemKey);
+      return notes.map((entry) => entry.note);
+    } catch {
+      // Fall through to HTTP API strategy for environments where direct DB access is unavailable.
+    }
+
+    const children = await this.getItemChildren(itemKey).catch(() => []);
+    return children
+      .filter((child) => String(child.data.itemType ?? '') === 'note')
+      .map((child) => String(child.data.note ?? ''))
+      .filter((note) => note.trim().length > 0);
+  }
+
   async getAnnotationsForItem(itemKey: string): Promise<AnnotationModel[]> {
     try {
-      const sqliteAnnotations = await zoteroSqliteGetAnnotations(itemKey);
+      const sqliteAnnotations = await loadSqliteAnnotationPages(itemKey);
       return sqliteAnnotations.map((entry) => normalizeSqliteAnnotation(entry));
     } catch {
       // Fall through to HTTP API strategy for environments where direct DB access is unavailable.
@@ -329,7 +392,30 @@ export class ZoteroClient {
     );
   }
 
-  async getSelectedAreaImage(annotationKey: string, attachmentKey?: string): Promise<Uint8Array | null> {
+  /**
+   * Image bytes for an area or ink annotation. With `dateModified`, the app's image cache is checked first
+   * and updated after a fetch; a newer `dateModified` invalidates the cached image.
+   */
+  async getSelectedAreaImage(
+    annotationKey: string,
+    attachmentKey?: string,
+    dateModified?: string,
+  ): Promise<Uint8Array | null> {
+    if (dateModified) {
+      const cached = await readImageCache(annotationKey, dateModified).catch(() => null);
+      if (cached) {
+        return cached;
+      }
+    }
+
+    const bytes = await this.fetchSelectedAreaImage(annotationKey, attachmentKey);
+    if (bytes && dateModified) {
+      await writeImageCache(annotationKey, dateModified, bytes).catch(() => undefined);
+    }
+    return bytes;
+  }
+
+  private async fetchSelectedAreaImage(annotationKey: string, attachmentKey?: string): Promise<Uint8Array | null> {
     const candidates = [
       `/api/users/0/items/${encodeURIComponent(annotationKey)}/file`,
       `/api/users/0/items/${encodeURIComponent(annotationKey)}/file/view`,